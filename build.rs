@@ -2,6 +2,7 @@ fn main() {
     set_git_revision_hash();
     set_windows_exe_options();
     set_mrshv2_linking();
+    set_vectorscan_linking();
 }
 
 /// Embed a Windows manifest and set some linker options.
@@ -84,3 +85,32 @@ fn set_mrshv2_linking() {
         .unwrap_or_else(|| "precursor_mrshv2".to_string());
     println!("cargo:rustc-link-lib=dylib={}", lib_name);
 }
+
+/// Link the Vectorscan (or Hyperscan) runtime when `regex-vectorscan` is enabled.
+///
+/// Only the block-mode API is used: `hs_compile_multi`, `hs_alloc_scratch`,
+/// `hs_clone_scratch`, `hs_scan`, and the matching free functions.
+///
+/// The library name defaults to `hs` (as installed by `libvectorscan-dev` and
+/// `libhyperscan-dev`) and can be overridden with
+/// `PRECURSOR_VECTORSCAN_LIB_NAME`. An extra search directory can be provided
+/// via `PRECURSOR_VECTORSCAN_LIB_DIR`.
+fn set_vectorscan_linking() {
+    if std::env::var_os("CARGO_FEATURE_REGEX_VECTORSCAN").is_none() {
+        return;
+    }
+    println!("cargo:rerun-if-env-changed=PRECURSOR_VECTORSCAN_LIB_DIR");
+    println!("cargo:rerun-if-env-changed=PRECURSOR_VECTORSCAN_LIB_NAME");
+
+    if let Ok(lib_dir) = std::env::var("PRECURSOR_VECTORSCAN_LIB_DIR") {
+        if !lib_dir.is_empty() {
+            println!("cargo:rustc-link-search=native={}", lib_dir);
+        }
+    }
+
+    let lib_name = std::env::var("PRECURSOR_VECTORSCAN_LIB_NAME")
+        .ok()
+        .filter(|value| !value.is_empty())
+        .unwrap_or_else(|| "hs".to_string());
+    println!("cargo:rustc-link-lib=dylib={}", lib_name);
+}
@@ -0,0 +1,213 @@
+/*
+ * Minimal stand-in for the Vectorscan/Hyperscan block-mode API used by
+ * `--features regex-vectorscan`, built on POSIX extended regexes so CI can
+ * exercise the prefilter path without the real library.
+ *
+ * Named and non-capturing groups are rewritten to plain groups; any other
+ * `(?` construct is rejected, like the lookarounds Vectorscan refuses.
+ * Expressions must be compiled with HS_FLAG_PREFILTER, which the prefilter
+ * relies on to never miss a PCRE2 match.
+ */
+#define _GNU_SOURCE
+#include <regex.h>
+#include <stdlib.h>
+#include <string.h>
+
+#define HS_SUCCESS 0
+#define HS_INVALID (-1)
+#define HS_NOMEM (-2)
+#define HS_COMPILER_ERROR (-4)
+#define HS_FLAG_PREFILTER 128
+
+typedef int hs_error_t;
+typedef int (*match_event_handler)(
+    unsigned int id,
+    unsigned long long from,
+    unsigned long long to,
+    unsigned int flags,
+    void *context
+);
+
+typedef struct hs_compile_error {
+    char *message;
+    int expression;
+} hs_compile_error_t;
+
+typedef struct hs_database {
+    unsigned int count;
+    unsigned int *ids;
+    regex_t *regexes;
+} hs_database_t;
+
+typedef struct hs_scratch {
+    int unused;
+} hs_scratch_t;
+
+static hs_error_t compile_error(hs_compile_error_t **error, const char *message, int expression) {
+    hs_compile_error_t *value = (hs_compile_error_t *)malloc(sizeof(hs_compile_error_t));
+    if (value == NULL) {
+        return HS_NOMEM;
+    }
+    value->message = strdup(message);
+    value->expression = expression;
+    *error = value;
+    return HS_COMPILER_ERROR;
+}
+
+/* Returns a heap copy of the expression in POSIX ERE syntax, or NULL if unsupported. */
+static char *translate(const char *expression) {
+    size_t length = strlen(expression);
+    char *out = (char *)malloc(length + 1);
+    if (out == NULL) {
+        return NULL;
+    }
+    size_t j = 0;
+    for (size_t i = 0; i < length; i++) {
+        if (expression[i] == '\\' && i + 1 < length) {
+            out[j++] = expression[i++];
+            out[j++] = expression[i];
+            continue;
+        }
+        if (expression[i] == '(' && expression[i + 1] == '?') {
+            if (expression[i + 2] == ':') {
+                out[j++] = '(';
+                i += 2;
+                continue;
+            }
+            if (expression[i + 2] == '<' && expression[i + 3] != '=' && expression[i + 3] != '!') {
+                const char *close = strchr(expression + i + 3, '>');
+                if (close != NULL) {
+                    out[j++] = '(';
+                    i = (size_t)(close - expression);
+                    continue;
+                }
+            }
+            free(out);
+            return NULL;
+        }
+        out[j++] = expression[i];
+    }
+    out[j] = '\0';
+    return out;
+}
+
+hs_error_t hs_compile_multi(
+    const char *const *expressions,
+    const unsigned int *flags,
+    const unsigned int *ids,
+    unsigned int elements,
+    unsigned int mode,
+    const void *platform,
+    hs_database_t **db,
+    hs_compile_error_t **error
+) {
+    (void)mode;
+    (void)platform;
+    if (expressions == NULL || flags == NULL || db == NULL || error == NULL || elements == 0) {
+        return compile_error(error, "invalid compile arguments", -1);
+    }
+    for (unsigned int i = 0; i < elements; i++) {
+        if ((flags[i] & HS_FLAG_PREFILTER) == 0) {
+            return compile_error(error, "expression is not compiled with HS_FLAG_PREFILTER", (int)i);
+        }
+    }
+    hs_database_t *database = (hs_database_t *)calloc(1, sizeof(hs_database_t));
+    if (database == NULL) {
+        return HS_NOMEM;
+    }
+    database->ids = (unsigned int *)calloc(elements, sizeof(unsigned int));
+    database->regexes = (regex_t *)calloc(elements, sizeof(regex_t));
+    if (database->ids == NULL || database->regexes == NULL) {
+        free(database->ids);
+        free(database->regexes);
+        free(database);
+        return HS_NOMEM;
+    }
+    for (unsigned int i = 0; i < elements; i++) {
+        char *translated = translate(expressions[i]);
+        int rc = translated == NULL
+            ? -1
+            : regcomp(&database->regexes[i], translated, REG_EXTENDED | REG_NEWLINE | REG_NOSUB);
+        free(translated);
+        if (rc != 0) {
+            for (unsigned int k = 0; k < database->count; k++) {
+                regfree(&database->regexes[k]);
+            }
+            free(database->ids);
+            free(database->regexes);
+            free(database);
+            return compile_error(error, "Unsupported construct in mock expression", (int)i);
+        }
+        database->ids[i] = ids == NULL ? 0 : ids[i];
+        database->count++;
+    }
+    *db = database;
+    return HS_SUCCESS;
+}
+
+hs_error_t hs_free_compile_error(hs_compile_error_t *error) {
+    if (error != NULL) {
+        free(error->message);
+        free(error);
+    }
+    return HS_SUCCESS;
+}
+
+hs_error_t hs_free_database(hs_database_t *db) {
+    if (db != NULL) {
+        for (unsigned int i = 0; i < db->count; i++) {
+            regfree(&db->regexes[i]);
+        }
+        free(db->ids);
+        free(db->regexes);
+        free(db);
+    }
+    return HS_SUCCESS;
+}
+
+hs_error_t hs_alloc_scratch(const hs_database_t *db, hs_scratch_t **scratch) {
+    if (db == NULL || scratch == NULL) {
+        return HS_INVALID;
+    }
+    *scratch = (hs_scratch_t *)calloc(1, sizeof(hs_scratch_t));
+    return *scratch == NULL ? HS_NOMEM : HS_SUCCESS;
+}
+
+hs_error_t hs_clone_scratch(const hs_scratch_t *src, hs_scratch_t **dest) {
+    if (src == NULL || dest == NULL) {
+        return HS_INVALID;
+    }
+    *dest = (hs_scratch_t *)calloc(1, sizeof(hs_scratch_t));
+    return *dest == NULL ? HS_NOMEM : HS_SUCCESS;
+}
+
+hs_error_t hs_free_scratch(hs_scratch_t *scratch) {
+    free(scratch);
+    return HS_SUCCESS;
+}
+
+hs_error_t hs_scan(
+    const hs_database_t *db,
+    const char *data,
+    unsigned int length,
+    unsigned int flags,
+    hs_scratch_t *scratch,
+    match_event_handler on_event,
+    void *context
+) {
+    (void)flags;
+    if (db == NULL || scratch == NULL || (data == NULL && length > 0)) {
+        return HS_INVALID;
+    }
+    for (unsigned int i = 0; i < db->count; i++) {
+        regmatch_t range[1];
+        range[0].rm_so = 0;
+        range[0].rm_eo = (regoff_t)length;
+        if (regexec(&db->regexes[i], data == NULL ? "" : data, 1, range, REG_STARTEND) == 0
+            && on_event != NULL
+            && on_event(db->ids[i], 0, (unsigned long long)range[0].rm_eo, 0, context) != 0) {
+            return -3;
+        }
+    }
+    return HS_SUCCESS;
+}
@@ -73,6 +73,16 @@ echo "== fox-it log4shell pcap replay triage (fbhash) =="
   --protocol-hints \
   < "$root_dir/public-log4shell-foxit-pcap/payloads.string"
 
+echo
+echo "== fox-it log4shell native pcap ingest =="
+"$bin_path" \
+  -p "$root_dir/public-log4shell-foxit-pcap/patterns.pcre" \
+  --input-mode pcap \
+  -t -d \
+  --similarity-mode fbhash \
+  -P \
+  < "$root_dir/public-log4shell-foxit-pcap/ldap-uri-params-ev0.pcap"
+
 echo
 echo "== public firmware blob triage (binary folder mode) =="
 "$bin_path" \
@@ -94,3 +104,11 @@ echo "== public zeek dns log triage (lzjd + json extract) =="
   --similarity-mode lzjd \
   --protocol-hints \
   < "$root_dir/public-zeek-dns-log-triage/payloads.jsonl"
+
+echo
+echo "== public zeek dns log triage (native TSV reader) =="
+"$bin_path" \
+  -p "$root_dir/public-zeek-dns-log-triage/patterns.pcre" \
+  -m zeek \
+  --zeek-field query \
+  < "$root_dir/public-zeek-dns-log-triage/dns.log"
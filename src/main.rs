@@ -1,10 +1,12 @@
 mod precursor;
 
-use std::collections::HashSet;
+use std::collections::{HashMap, HashSet};
 use std::io::{self, BufRead, Read, Write};
-use std::path::PathBuf;
+use std::net::UdpSocket;
+use std::sync::atomic::{AtomicBool, AtomicU64, AtomicUsize, Ordering};
+use std::sync::mpsc::{channel, sync_channel, RecvTimeoutError, SyncSender, TrySendError};
 use std::sync::{Arc, Mutex};
-use std::time::Instant;
+use std::time::{Duration, Instant};
 
 extern crate atomic_counter;
 extern crate base64;
@@ -17,11 +19,38 @@ extern crate rayon;
 extern crate serde_json;
 extern crate xxhash_rust;
 
+use crate::precursor::archive::{visit_members, ContainerKind, MemberInfo};
+use crate::precursor::carve::carve_objects;
+use crate::precursor::chunking::{chunk_ranges, ChunkConfig, ChunkMode};
+use crate::precursor::defines::{define_group_names, DefineLibrary};
+use crate::precursor::defrag::{DefragConfig, Defragmenter, FragmentPolicy};
+use crate::precursor::delimited::{
+    ColumnSelector, DelimitedConfig, DelimitedReader, Dialect, HeaderMode,
+};
+use crate::precursor::eve::{eve_context, eve_payloads, parse_eve_record};
+use crate::precursor::follow::{FollowConfig, FollowEvent, FollowStart, LogFollower};
+use crate::precursor::hexdump::parse_hexdumps;
 use crate::precursor::inference::infer_protocol_candidates;
-use crate::precursor::regex_engine::{vectorscan_compatibility_issues, RegexEngine};
+use crate::precursor::layers::peel_layers;
+use crate::precursor::limits::{match_error_kind, ErrorOutcome, PatternHealth, PatternLimits};
+use crate::precursor::listen::{
+    read_length_frame, Connection, Framing, LineLimit, ListenAddress, Listener, MAX_FRAME_BYTES,
+    MAX_LINE_BYTES,
+};
+use crate::precursor::packet::{decode_datagram_transport, decode_ip_datagram};
+use crate::precursor::pattern_pack::load_pattern_pack;
+use crate::precursor::pcap::CaptureReader;
+use crate::precursor::prefilter::Prefilter;
+use crate::precursor::reassembly::{
+    OverlapPolicy, ReassemblyConfig, ReassemblyMode, StreamChunk, TcpReassembler,
+};
+use crate::precursor::regex_engine::{vectorscan_compatibility_issues, RegexEngine, VectorscanSet};
 use crate::precursor::sigma::{load_sigma_rule_plan, matching_sigma_rules, SigmaRulePlan};
 use crate::precursor::similarity::*;
+use crate::precursor::syslog::parse_syslog;
 use crate::precursor::util::*;
+use crate::precursor::walk::{walk_folder, WalkConfig, WalkedFile};
+use crate::precursor::zeek::{zeek_context, zeek_payloads, ZeekLogReader};
 
 use atomic_counter::{AtomicCounter, ConsistentCounter};
 use clap::{
@@ -42,6 +71,23 @@ const TLSH_LENGTH: &str = "tlsh-length";
 const TLSH_DISTANCE: &str = "tlsh-distance";
 const TLSH_SIM_ONLY: &str = "tlsh-sim-only";
 const INPUT_FOLDER: &str = "input-folder";
+const RECURSIVE: &str = "recursive";
+const MAX_DEPTH: &str = "max-depth";
+const INCLUDE: &str = "include";
+const EXCLUDE: &str = "exclude";
+const FOLLOW_SYMLINKS: &str = "follow-symlinks";
+const MAX_FILE_SIZE: &str = "max-file-size";
+const PARALLEL_FILES: &str = "parallel-files";
+const DECOMPRESS: &str = "decompress";
+const DECOMPRESS_MAX_BYTES: &str = "decompress-max-bytes";
+const CHUNK_MODE: &str = "chunk-mode";
+const CHUNK_SIZE: &str = "chunk-size";
+const CHUNK_OVERLAP: &str = "chunk-overlap";
+const MAX_SUMMARY_CHUNKS: usize = 1024;
+const CARVE: &str = "carve";
+const CARVE_MAX_OBJECTS: &str = "carve-max-objects";
+const AUTO_DECODE: &str = "auto-decode";
+const AUTO_DECODE_DEPTH: &str = "auto-decode-depth";
 const INPUT_MODE: &str = "input-mode";
 const INPUT_BINARY: &str = "input-binary";
 const INPUT_BLOB: &str = "input-blob";
@@ -49,12 +95,65 @@ const INPUT_MODE_BASE64: &str = "base64";
 const INPUT_MODE_STRING: &str = "string";
 const INPUT_MODE_HEX: &str = "hex";
 const INPUT_MODE_BINARY: &str = "binary";
+const INPUT_MODE_PCAP: &str = "pcap";
+const INPUT_MODE_PCAPNG: &str = "pcapng";
+const INPUT_MODE_EVE: &str = "eve";
+const INPUT_MODE_ZEEK: &str = "zeek";
+const INPUT_MODE_SYSLOG: &str = "syslog";
+const INPUT_MODE_HEXDUMP: &str = "hexdump";
+const ZEEK_FIELD: &str = "zeek-field";
+const DELIMITED: &str = "delimited";
+const DELIMITED_HEADER: &str = "delimited-header";
+const PAYLOAD_COLUMN: &str = "payload-column";
 const INPUT_JSON_KEY: &str = "input-json-key";
+const JSON_SLOT: &str = "json-slot";
+const TCP_REASSEMBLY: &str = "tcp-reassembly";
+const TCP_REASSEMBLY_STREAM: &str = "stream";
+const TCP_REASSEMBLY_MESSAGE: &str = "message";
+const TCP_OVERLAP_POLICY: &str = "tcp-overlap-policy";
+const TCP_OVERLAP_FIRST: &str = "first";
+const TCP_OVERLAP_LAST: &str = "last";
+const TCP_STREAM_MAX_BYTES: &str = "tcp-stream-max-bytes";
+const IP_FRAG_POLICY: &str = "ip-frag-policy";
+const IP_FRAG_POLICY_FIRST: &str = "first";
+const IP_FRAG_POLICY_LAST: &str = "last";
+const IP_FRAG_POLICY_BSD: &str = "bsd";
+const IP_FRAG_POLICY_LINUX: &str = "linux";
+const IP_FRAG_TIMEOUT: &str = "ip-frag-timeout";
+const STREAM: &str = "stream";
+const PER_RECORD: &str = "per-record";
+const STDIN_SOURCE_PATH: &str = "-";
+const MAX_CAPTURE_SPANS: usize = 1024;
+const MAX_CAPTURE_VALUE_BYTES: usize = 4096;
+const CAPTURES: &str = "captures";
+const CAPTURE_ENCODING: &str = "capture-encoding";
+const CAPTURE_ENCODING_BASE64: &str = "base64";
+const CAPTURE_ENCODING_HEX: &str = "hex";
+const MAX_OCCURRENCE_REFS: &str = "max-occurrence-refs";
+const STREAM_BUFFER: &str = "stream-buffer";
+const FOLLOW: &str = "follow";
+const FOLLOW_STATE: &str = "follow-state";
+const FOLLOW_FROM: &str = "follow-from";
+const FOLLOW_INTERVAL: &str = "follow-interval";
+const FOLLOW_IDLE_EXIT: &str = "follow-idle-exit";
+const LISTEN: &str = "listen";
+const LISTEN_FRAMING: &str = "listen-framing";
+const LISTEN_FRAMING_LINE: &str = "line";
+const LISTEN_FRAMING_LENGTH: &str = "length";
+const LISTEN_REPLY: &str = "listen-reply";
+const LISTEN_MAX_CLIENTS: &str = "listen-max-clients";
+const STATS_INTERVAL: &str = "stats-interval";
 const PATTERN_FILE: &str = "pattern-file";
+const DEFINE_LIBRARY: &str = "define-library";
 const SIGMA_RULE: &str = "sigma-rule";
 const REGEX_ENGINE: &str = "regex-engine";
 const REGEX_ENGINE_PCRE2: &str = "pcre2";
 const REGEX_ENGINE_VECTORSCAN: &str = "vectorscan";
+const NO_PREFILTER: &str = "no-prefilter";
+const MATCH_LIMIT: &str = "match-limit";
+const DEPTH_LIMIT: &str = "depth-limit";
+const PAYLOAD_BUDGET_MS: &str = "payload-budget-ms";
+const MAX_PATTERN_ERRORS: &str = "max-pattern-errors";
 const PATTERN: &str = "pattern";
 const SIMILARITY_MODE: &str = "similarity-mode";
 const SIMILARITY_MODE_TLSH: &str = "tlsh";
@@ -77,6 +176,91 @@ enum PatternOrigin {
 struct CompiledPattern {
     regex: pcre2::bytes::Regex,
     origin: PatternOrigin,
+    // Part of the Vectorscan database, so PCRE2 only runs when Vectorscan flagged it.
+    accelerated: bool,
+    // The pattern as written, without the definitions and limit items prepended for PCRE2.
+    source: String,
+    // Capture names reported as tags; DEFINE helper groups are left out.
+    tags: Vec<String>,
+}
+
+// A labelled --json-slot filter.
+struct JsonSlot {
+    label: String,
+    filter: String,
+}
+
+impl JsonSlot {
+    fn parse(spec: &str) -> Result<Self, String> {
+        match spec.split_once('=') {
+            Some((label, filter)) if !label.trim().is_empty() && !filter.trim().is_empty() => {
+                Ok(Self {
+                    label: label.trim().to_string(),
+                    filter: filter.to_string(),
+                })
+            }
+            _ => Err(format!(
+                "Invalid --{} {:?}: expected LABEL=FILTER",
+                JSON_SLOT, spec
+            )),
+        }
+    }
+}
+
+struct PipelineContext<'a> {
+    patterns: &'a [CompiledPattern],
+    vectorscan: Option<&'a VectorscanSet>,
+    prefilter: Option<&'a Prefilter>,
+    pattern_health: &'a PatternHealth,
+    payload_budget: Option<Duration>,
+    tag_metadata: &'a HashMap<String, Value>,
+    sigma_rule_plans: &'a [SigmaRulePlan],
+    args: &'a ArgMatches,
+    input_mode: &'a str,
+    similarity_mode: &'a SimilarityMode,
+    counter_inputs: &'a ConsistentCounter,
+    tlsh_list: &'a Mutex<Vec<SimilarityHash>>,
+    payload_reports: &'a Mutex<Map<String, Value>>,
+    counter_pcre_matches: &'a DashMap<String, i64>,
+    counter_tlsh_hashes: &'a ConsistentCounter,
+    vec_payload_size: &'a Mutex<Vec<i64>>,
+    vec_payload_size_matched: &'a Mutex<Vec<i64>>,
+    counter_unique_payloads: &'a Mutex<HashSet<u64>>,
+    counter_pcre_matches_total: &'a ConsistentCounter,
+    tcp_reassembly: Option<ReassemblyConfig>,
+    ip_defrag: DefragConfig,
+    chunking: Option<ChunkConfig>,
+    delimited: Option<DelimitedConfig>,
+    json_slots: &'a [JsonSlot],
+    stats: &'a StatsSources<'a>,
+    report_sink: Option<SyncSender<Value>>,
+    stream_progress: Option<Arc<StreamProgress>>,
+}
+
+// Records handed to the stream matchers and reports handed to the stream writer, so
+// --follow only checkpoints input whose reports have been written and --listen stops
+// accepting clients once the matchers are gone.
+#[derive(Default)]
+struct StreamProgress {
+    matchers_stopped: AtomicBool,
+    records_submitted: AtomicU64,
+    records_handled: AtomicU64,
+    reports_sent: AtomicU64,
+    reports_written: AtomicU64,
+}
+
+impl StreamProgress {
+    // Only the thread submitting records may wait: once every submitted record has been
+    // handled, no more reports can be sent for them.
+    fn wait_until_written(&self) {
+        while self.records_handled.load(Ordering::SeqCst)
+            < self.records_submitted.load(Ordering::SeqCst)
+            || self.reports_written.load(Ordering::SeqCst)
+                < self.reports_sent.load(Ordering::SeqCst)
+        {
+            std::thread::sleep(Duration::from_millis(1));
+        }
+    }
 }
 
 fn compact_pattern(pattern: &str) -> String {
@@ -90,7 +274,7 @@ fn compact_pattern(pattern: &str) -> String {
     }
 }
 
-fn resolved_input_mode<'a>(args: &'a ArgMatches) -> &'a str {
+fn resolved_input_mode(args: &ArgMatches) -> &str {
     if args.get_flag(INPUT_BINARY) {
         INPUT_MODE_BINARY
     } else {
@@ -100,7 +284,11 @@ fn resolved_input_mode<'a>(args: &'a ArgMatches) -> &'a str {
 }
 
 fn blob_mode_enabled(args: &ArgMatches, input_mode: &str) -> bool {
-    args.get_flag(INPUT_BLOB) || input_mode == INPUT_MODE_BINARY
+    args.get_flag(INPUT_BLOB) || input_mode == INPUT_MODE_BINARY || input_mode == INPUT_MODE_HEXDUMP
+}
+
+fn capture_mode_enabled(input_mode: &str) -> bool {
+    input_mode == INPUT_MODE_PCAP || input_mode == INPUT_MODE_PCAPNG
 }
 
 fn main() {
@@ -157,6 +345,54 @@ fn main() {
         .value_parser(PathBufValueParser::new())
         .help("Specify the path to the input folder.")
         .action(ArgAction::Set))
+    .arg(Arg::new(RECURSIVE)
+        .short('r')
+        .long(RECURSIVE)
+        .help("Descend into subdirectories of the input folder.")
+        .requires(INPUT_FOLDER)
+        .action(ArgAction::SetTrue))
+    .arg(Arg::new(MAX_DEPTH)
+        .long(MAX_DEPTH)
+        .help("Maximum folder depth to descend with --recursive (files directly in the input folder are depth 1).")
+        .value_parser(value_parser!(usize))
+        .requires(RECURSIVE)
+        .action(ArgAction::Set))
+    .arg(Arg::new(INCLUDE)
+        .long(INCLUDE)
+        .help("Only ingest files whose relative path matches this glob (repeatable; globs without `/` match file names).")
+        .requires(INPUT_FOLDER)
+        .action(ArgAction::Append))
+    .arg(Arg::new(EXCLUDE)
+        .long(EXCLUDE)
+        .help("Skip files and folders whose relative path matches this glob (repeatable; globs without `/` match entry names).")
+        .requires(INPUT_FOLDER)
+        .action(ArgAction::Append))
+    .arg(Arg::new(FOLLOW_SYMLINKS)
+        .long(FOLLOW_SYMLINKS)
+        .help("Follow symbolic links in the input folder instead of skipping them.")
+        .requires(INPUT_FOLDER)
+        .action(ArgAction::SetTrue))
+    .arg(Arg::new(MAX_FILE_SIZE)
+        .long(MAX_FILE_SIZE)
+        .help("Skip input folder files larger than this many bytes.")
+        .value_parser(value_parser!(u64))
+        .requires(INPUT_FOLDER)
+        .action(ArgAction::Set))
+    .arg(Arg::new(PARALLEL_FILES)
+        .long(PARALLEL_FILES)
+        .help("Read input folder files in parallel; input indexes then follow completion order rather than path order.")
+        .requires(INPUT_FOLDER)
+        .action(ArgAction::SetTrue))
+    .arg(Arg::new(DECOMPRESS)
+        .long(DECOMPRESS)
+        .help("Detect gzip/zstd/xz/zip/tar inputs by magic bytes and ingest their decompressed members instead of the container bytes.")
+        .action(ArgAction::SetTrue))
+    .arg(Arg::new(DECOMPRESS_MAX_BYTES)
+        .long(DECOMPRESS_MAX_BYTES)
+        .help("Maximum decompressed bytes per archive member with --decompress; larger members are abandoned.")
+        .value_parser(value_parser!(u64))
+        .default_value("268435456")
+        .action(ArgAction::Set))
     .arg(Arg::new(INPUT_BLOB)
         .short('z')
         .long(INPUT_BLOB)
@@ -171,8 +407,14 @@ fn main() {
         .short('p')
         .long(PATTERN_FILE)
         .value_parser(PathBufValueParser::new())
-        .help("Specify the path to the file containing PCRE2 patterns, one per line, each must contain a single named capture group.")
+        .help("Specify the path to a pattern pack: PCRE2 patterns one per line, each with a named capture group, plus `#` comments, `@id`/`@description`/`@severity`/`@tags`/`@attack`/`@reference`/`@owner`/`@enabled` metadata lines for the next pattern, and `@include PATH` lines.")
         .action(ArgAction::Set))
+    .arg(Arg::new(DEFINE_LIBRARY)
+        .long(DEFINE_LIBRARY)
+        .value_name("PATH")
+        .value_parser(PathBufValueParser::new())
+        .help("Load a library of `(?(DEFINE)(?<NAME>...))` lines; patterns calling `(?&NAME)` get the definitions they use prepended. Repeatable.")
+        .action(ArgAction::Append))
     .arg(Arg::new(SIGMA_RULE)
         .long(SIGMA_RULE)
         .value_parser(PathBufValueParser::new())
@@ -225,10 +467,39 @@ fn main() {
         .default_value(SIMILARITY_MODE_TLSH))
     .arg(Arg::new(REGEX_ENGINE)
         .long(REGEX_ENGINE)
-        .help("Regex execution engine. `vectorscan` currently runs compatibility checks and falls back to PCRE2 in this build.")
+        .help("Regex execution engine. `vectorscan` prefilters all compatible patterns in one Vectorscan database (builds with the `regex-vectorscan` feature) and runs PCRE2 only for flagged or unsupported patterns.")
         .value_parser([REGEX_ENGINE_PCRE2, REGEX_ENGINE_VECTORSCAN])
         .action(ArgAction::Set)
         .default_value(REGEX_ENGINE_PCRE2))
+    .arg(Arg::new(NO_PREFILTER)
+        .long(NO_PREFILTER)
+        .help("Disable the literal prefilter that skips PCRE2 for patterns whose required literals do not occur in a payload.")
+        .action(ArgAction::SetTrue))
+    .arg(Arg::new(MATCH_LIMIT)
+        .long(MATCH_LIMIT)
+        .value_name("N")
+        .help("Per-pattern PCRE2 match limit (backtracking steps per match attempt); payloads that exceed it are reported under `match_errors`.")
+        .value_parser(value_parser!(u32).range(1..))
+        .action(ArgAction::Set))
+    .arg(Arg::new(DEPTH_LIMIT)
+        .long(DEPTH_LIMIT)
+        .value_name("N")
+        .help("Per-pattern PCRE2 backtracking depth limit; payloads that exceed it are reported under `match_errors`.")
+        .value_parser(value_parser!(u32).range(1..))
+        .action(ArgAction::Set))
+    .arg(Arg::new(PAYLOAD_BUDGET_MS)
+        .long(PAYLOAD_BUDGET_MS)
+        .value_name("MILLISECONDS")
+        .help("Wall-clock budget for matching one payload; patterns not yet run when it is spent are skipped and reported under `match_errors`.")
+        .value_parser(value_parser!(u64).range(1..))
+        .action(ArgAction::Set))
+    .arg(Arg::new(MAX_PATTERN_ERRORS)
+        .long(MAX_PATTERN_ERRORS)
+        .value_name("N")
+        .help("Disable a pattern for the rest of the run after this many match errors (0 never disables).")
+        .value_parser(value_parser!(u64))
+        .default_value("10")
+        .action(ArgAction::Set))
     .arg(Arg::new(PROTOCOL_HINTS)
         .long(PROTOCOL_HINTS)
         .help("Emit protocol-discovery hint JSON to STDERR for LLM-guided analysis loops.")
@@ -261,19 +532,218 @@ fn main() {
     .arg(Arg::new(INPUT_MODE)
         .short('m')
         .long(INPUT_MODE)
-        .help("Specify the payload mode as base64, string, hex, or binary.")
+        .help("Specify the payload mode as base64, string, hex, binary, pcap/pcapng (capture format is auto-detected and each TCP/UDP payload becomes one record), eve (Suricata EVE JSON lines; payload fields are decoded per event type), zeek (Zeek TSV or JSON logs; payloads are the --zeek-field columns), syslog (RFC 3164/5424 lines; patterns match the MSG body and the parsed header is reported), or hexdump (xxd, hexdump -C, tcpdump -X, Wireshark, or bare hex dumps; each dump in a source becomes one payload).")
         .value_parser([
             INPUT_MODE_BASE64,
             INPUT_MODE_STRING,
             INPUT_MODE_HEX,
             INPUT_MODE_BINARY,
+            INPUT_MODE_PCAP,
+            INPUT_MODE_PCAPNG,
+            INPUT_MODE_EVE,
+            INPUT_MODE_ZEEK,
+            INPUT_MODE_SYSLOG,
+            INPUT_MODE_HEXDUMP,
         ])
         .action(ArgAction::Set)
         .default_value("base64"))
+    .arg(Arg::new(ZEEK_FIELD)
+        .long(ZEEK_FIELD)
+        .help("Zeek log field used as a payload in zeek mode (repeatable); the remaining columns are kept as `zeek` report context.")
+        .action(ArgAction::Append))
+    .arg(Arg::new(DELIMITED)
+        .long(DELIMITED)
+        .help("Read CSV (RFC 4180 quoting) or TSV (backslash escapes) records; the --payload-column cell is decoded with the base64, string, or hex input mode and the other columns are copied into the report.")
+        .value_parser(["csv", "tsv"])
+        .requires(PAYLOAD_COLUMN)
+        .action(ArgAction::Set))
+    .arg(Arg::new(PAYLOAD_COLUMN)
+        .long(PAYLOAD_COLUMN)
+        .value_name("NAME|NUMBER")
+        .help("Column holding the payload in --delimited input, by header name or 1-based column number.")
+        .requires(DELIMITED)
+        .action(ArgAction::Set))
+    .arg(Arg::new(DELIMITED_HEADER)
+        .long(DELIMITED_HEADER)
+        .help("Whether the first --delimited row is a header; `auto` treats it as one when it contains the named payload column, or when every cell is a short, distinct label that starts with a letter.")
+        .value_parser(["auto", "present", "absent"])
+        .default_value("auto")
+        .action(ArgAction::Set))
+    .arg(Arg::new(TCP_REASSEMBLY)
+        .long(TCP_REASSEMBLY)
+        .help("Reassemble TCP flows in pcap/pcapng mode and emit one record per direction stream or per request/response message instead of per segment.")
+        .value_parser([TCP_REASSEMBLY_STREAM, TCP_REASSEMBLY_MESSAGE])
+        .action(ArgAction::Set))
+    .arg(Arg::new(TCP_OVERLAP_POLICY)
+        .long(TCP_OVERLAP_POLICY)
+        .help("Which bytes win when reassembled TCP segments overlap with different content.")
+        .value_parser([TCP_OVERLAP_FIRST, TCP_OVERLAP_LAST])
+        .default_value(TCP_OVERLAP_FIRST)
+        .action(ArgAction::Set))
+    .arg(Arg::new(TCP_STREAM_MAX_BYTES)
+        .long(TCP_STREAM_MAX_BYTES)
+        .help("Maximum bytes buffered per TCP flow direction during reassembly; later bytes are dropped and the stream is marked truncated.")
+        .value_parser(value_parser!(u64))
+        .default_value("16777216")
+        .action(ArgAction::Set))
+    .arg(Arg::new(IP_FRAG_POLICY)
+        .long(IP_FRAG_POLICY)
+        .help("Overlap policy used when reassembling IPv4/IPv6 fragments in pcap/pcapng mode.")
+        .value_parser([
+            IP_FRAG_POLICY_FIRST,
+            IP_FRAG_POLICY_LAST,
+            IP_FRAG_POLICY_BSD,
+            IP_FRAG_POLICY_LINUX,
+        ])
+        .default_value(IP_FRAG_POLICY_BSD)
+        .action(ArgAction::Set))
+    .arg(Arg::new(IP_FRAG_TIMEOUT)
+        .long(IP_FRAG_TIMEOUT)
+        .help("Seconds of capture time to wait for missing IP fragments before dropping a datagram.")
+        .value_parser(value_parser!(u64))
+        .default_value("30")
+        .action(ArgAction::Set))
+    .arg(Arg::new(AUTO_DECODE)
+        .long(AUTO_DECODE)
+        .help("After -m decoding, detect and peel nested gzip/zlib/url/hex/base64 layers and match patterns at every layer.")
+        .action(ArgAction::SetTrue))
+    .arg(Arg::new(AUTO_DECODE_DEPTH)
+        .long(AUTO_DECODE_DEPTH)
+        .help("Maximum number of nested encodings peeled by --auto-decode.")
+        .value_parser(value_parser!(usize))
+        .default_value("3")
+        .action(ArgAction::Set))
+    .arg(Arg::new(CHUNK_MODE)
+        .long(CHUNK_MODE)
+        .help("Split each blob into fixed windows or content-defined chunks and match/hash every chunk as its own report, plus a per-blob `chunk_summary`.")
+        .value_parser(["fixed", "cdc"])
+        .action(ArgAction::Set))
+    .arg(Arg::new(CHUNK_SIZE)
+        .long(CHUNK_SIZE)
+        .help("Window length in bytes for `fixed` chunking, or average chunk length for `cdc`.")
+        .value_parser(value_parser!(usize))
+        .default_value("4096")
+        .action(ArgAction::Set))
+    .arg(Arg::new(CHUNK_OVERLAP)
+        .long(CHUNK_OVERLAP)
+        .help("Bytes shared by consecutive `fixed` chunks.")
+        .value_parser(value_parser!(usize))
+        .default_value("0")
+        .action(ArgAction::Set))
+    .arg(Arg::new(CARVE)
+        .long(CARVE)
+        .help("Scan each payload for embedded objects (ELF, PE, gzip, zip, xz, zstd, uImage, squashfs, cramfs, romfs) and match each carved region as a child report.")
+        .action(ArgAction::SetTrue))
+    .arg(Arg::new(CARVE_MAX_OBJECTS)
+        .long(CARVE_MAX_OBJECTS)
+        .help("Maximum embedded objects carved from a single payload.")
+        .value_parser(value_parser!(usize))
+        .default_value("64")
+        .action(ArgAction::Set))
     .arg(Arg::new(INPUT_JSON_KEY)
         .short('j')
         .long(INPUT_JSON_KEY)
-        .help("Specify the JQ-like pattern for parsing the input from the JSON input.")
+        .help("Specify the JQ-like pattern for parsing the input from the JSON input; every output of the filter becomes its own payload.")
+        .action(ArgAction::Set))
+    .arg(Arg::new(JSON_SLOT)
+        .long(JSON_SLOT)
+        .value_name("LABEL=FILTER")
+        .help("Extract a labelled payload slot with a JQ-like filter (repeatable); all slots of a record are matched separately and reported together; not combinable with --chunk-mode or --carve, which split payloads into separate reports.")
+        .conflicts_with_all([INPUT_JSON_KEY, CHUNK_MODE, CARVE])
+        .action(ArgAction::Append))
+    .arg(Arg::new(CAPTURES)
+        .long(CAPTURES)
+        .help("Add a `captures` object mapping each tag to its captured values, byte offsets, and pattern index.")
+        .action(ArgAction::SetTrue))
+    .arg(Arg::new(CAPTURE_ENCODING)
+        .long(CAPTURE_ENCODING)
+        .help("Encoding used for capture values that are not valid UTF-8.")
+        .value_parser([CAPTURE_ENCODING_BASE64, CAPTURE_ENCODING_HEX])
+        .default_value(CAPTURE_ENCODING_BASE64)
+        .action(ArgAction::Set))
+    .arg(Arg::new(PER_RECORD)
+        .long(PER_RECORD)
+        .help("Emit one report per matching input record instead of one canonical report per unique payload.")
+        .action(ArgAction::SetTrue))
+    .arg(Arg::new(MAX_OCCURRENCE_REFS)
+        .long(MAX_OCCURRENCE_REFS)
+        .help("Maximum number of source references kept in `occurrences` on each canonical report.")
+        .value_parser(value_parser!(usize))
+        .default_value("10")
+        .action(ArgAction::Set))
+    .arg(Arg::new(STREAM)
+        .long(STREAM)
+        .help("Stream NDJSON reports as soon as each record is matched, using a bounded reader -> matcher -> writer pipeline. Similarity neighbours (-d) are emitted afterwards as separate `similarity_cluster` records.")
+        .action(ArgAction::SetTrue))
+    .arg(Arg::new(STREAM_BUFFER)
+        .long(STREAM_BUFFER)
+        .help("Capacity, in records, of each bounded channel in --stream mode.")
+        .value_parser(value_parser!(usize))
+        .default_value("1024")
+        .action(ArgAction::Set))
+    .arg(Arg::new(FOLLOW)
+        .long(FOLLOW)
+        .value_name("PATH")
+        .help("Tail a growing log file or FIFO like `tail -F`, surviving rotation and truncation, and stream reports as lines arrive (implies --stream).")
+        .value_parser(value_parser!(std::path::PathBuf))
+        .conflicts_with_all([INPUT_FOLDER, INPUT_BLOB, DECOMPRESS])
+        .action(ArgAction::Set))
+    .arg(Arg::new(FOLLOW_STATE)
+        .long(FOLLOW_STATE)
+        .value_name("FILE")
+        .help("Persist the --follow read offset to FILE and resume from it on restart when it still names the same file.")
+        .value_parser(value_parser!(std::path::PathBuf))
+        .requires(FOLLOW)
+        .action(ArgAction::Set))
+    .arg(Arg::new(FOLLOW_FROM)
+        .long(FOLLOW_FROM)
+        .help("Where --follow starts reading a file without a saved offset.")
+        .value_parser(["start", "end"])
+        .default_value("start")
+        .action(ArgAction::Set))
+    .arg(Arg::new(FOLLOW_INTERVAL)
+        .long(FOLLOW_INTERVAL)
+        .value_name("MILLISECONDS")
+        .help("How often --follow polls for new data, rotation, and truncation once it reaches the end of the file.")
+        .value_parser(value_parser!(u64))
+        .default_value("500")
+        .action(ArgAction::Set))
+    .arg(Arg::new(FOLLOW_IDLE_EXIT)
+        .long(FOLLOW_IDLE_EXIT)
+        .value_name("SECONDS")
+        .help("Stop --follow after no new data has arrived for this long (default: run until interrupted).")
+        .value_parser(value_parser!(u64))
+        .requires(FOLLOW)
+        .action(ArgAction::Set))
+    .arg(Arg::new(LISTEN)
+        .long(LISTEN)
+        .value_name("ADDRESS")
+        .help("Serve payloads pushed over tcp://HOST:PORT, udp://HOST:PORT, or unix:PATH from many concurrent clients against one set of compiled patterns (implies --stream).")
+        .conflicts_with_all([INPUT_FOLDER, FOLLOW, DECOMPRESS])
+        .action(ArgAction::Set))
+    .arg(Arg::new(LISTEN_FRAMING)
+        .long(LISTEN_FRAMING)
+        .help("How --listen splits a TCP or Unix socket stream into records: newline-delimited lines, or frames prefixed with a 4-byte big-endian length.")
+        .value_parser([LISTEN_FRAMING_LINE, LISTEN_FRAMING_LENGTH])
+        .default_value(LISTEN_FRAMING_LINE)
+        .action(ArgAction::Set))
+    .arg(Arg::new(LISTEN_REPLY)
+        .long(LISTEN_REPLY)
+        .help("Write each client's NDJSON reports back over its own connection instead of to STDOUT.")
+        .requires(LISTEN)
+        .action(ArgAction::SetTrue))
+    .arg(Arg::new(LISTEN_MAX_CLIENTS)
+        .long(LISTEN_MAX_CLIENTS)
+        .help("Maximum number of --listen clients served at once; further connections are closed immediately.")
+        .value_parser(value_parser!(usize))
+        .default_value("64")
+        .action(ArgAction::Set))
+    .arg(Arg::new(STATS_INTERVAL)
+        .long(STATS_INTERVAL)
+        .value_name("SECONDS")
+        .help("With --follow or --listen and --stats, emit a statistics snapshot to STDERR this often.")
+        .value_parser(value_parser!(u64))
+        .default_value("60")
         .action(ArgAction::Set))
     .arg(Arg::new(STATS)
         .short('s')
@@ -321,46 +791,275 @@ fn main() {
     }
 
     let input_mode = resolved_input_mode(&args);
-    if input_mode == INPUT_MODE_BINARY && args.get_one::<String>(INPUT_JSON_KEY).is_some() {
+    let json_flag = [INPUT_JSON_KEY, JSON_SLOT]
+        .into_iter()
+        .find(|flag| args.contains_id(flag));
+    if let Some(json_flag) = json_flag.filter(|_| input_mode == INPUT_MODE_BINARY) {
         eprintln!(
             "--{} cannot be combined with --{} because JSON extraction requires UTF-8 text input.",
-            INPUT_BINARY, INPUT_JSON_KEY
+            INPUT_BINARY, json_flag
+        );
+        std::process::exit(2);
+    }
+    if let Some(json_flag) = json_flag.filter(|_| input_mode == INPUT_MODE_HEXDUMP) {
+        eprintln!(
+            "--{} {} cannot be combined with --{} because payloads are reassembled from hex dumps.",
+            INPUT_MODE, input_mode, json_flag
+        );
+        std::process::exit(2);
+    }
+    let capture_mode = capture_mode_enabled(input_mode);
+    if let Some(json_flag) = json_flag.filter(|_| capture_mode) {
+        eprintln!(
+            "--{} {} cannot be combined with --{} because payloads are decoded from packet captures.",
+            INPUT_MODE, input_mode, json_flag
+        );
+        std::process::exit(2);
+    }
+    if [INPUT_MODE_EVE, INPUT_MODE_ZEEK, INPUT_MODE_SYSLOG].contains(&input_mode) {
+        for (conflicting_flag, enabled) in [
+            (json_flag.unwrap_or(INPUT_JSON_KEY), json_flag.is_some()),
+            (INPUT_BLOB, args.get_flag(INPUT_BLOB)),
+        ] {
+            if enabled {
+                eprintln!(
+                    "--{} {} cannot be combined with --{} because payload fields are selected from each log record.",
+                    INPUT_MODE, input_mode, conflicting_flag
+                );
+                std::process::exit(2);
+            }
+        }
+    }
+    if (input_mode == INPUT_MODE_ZEEK) != args.contains_id(ZEEK_FIELD) {
+        eprintln!(
+            "--{} {} and --{} must be used together.",
+            INPUT_MODE, INPUT_MODE_ZEEK, ZEEK_FIELD
+        );
+        std::process::exit(2);
+    }
+    let tcp_reassembly = match args.get_one::<String>(TCP_REASSEMBLY) {
+        Some(mode) if capture_mode => {
+            let config = ReassemblyMode::from_str(mode).and_then(|mode| {
+                let overlap_policy = OverlapPolicy::from_str(
+                    args.get_one::<String>(TCP_OVERLAP_POLICY)
+                        .map_or(TCP_OVERLAP_FIRST, String::as_str),
+                )?;
+                Ok(ReassemblyConfig {
+                    mode,
+                    overlap_policy,
+                    max_stream_bytes: *args.get_one::<u64>(TCP_STREAM_MAX_BYTES).unwrap_or(&0),
+                })
+            });
+            match config {
+                Ok(config) => Some(config),
+                Err(err) => {
+                    eprintln!("Unable to configure TCP reassembly: {}", err);
+                    std::process::exit(2);
+                }
+            }
+        }
+        Some(_) => {
+            eprintln!(
+                "--{} requires --{} {} or {}.",
+                TCP_REASSEMBLY, INPUT_MODE, INPUT_MODE_PCAP, INPUT_MODE_PCAPNG
+            );
+            std::process::exit(2);
+        }
+        None => None,
+    };
+    let stream_flag = if args.contains_id(FOLLOW) {
+        Some(FOLLOW)
+    } else if args.contains_id(LISTEN) {
+        Some(LISTEN)
+    } else {
+        args.get_flag(STREAM).then_some(STREAM)
+    };
+    if let Some(stream_flag) = stream_flag {
+        for (conflicting_flag, enabled) in [
+            (TLSH_SIM_ONLY, args.get_flag(TLSH_SIM_ONLY)),
+            (PROTOCOL_HINTS, args.get_flag(PROTOCOL_HINTS)),
+        ] {
+            if enabled {
+                eprintln!(
+                    "--{} cannot be combined with --{} because streamed reports are written before similarity results exist.",
+                    stream_flag, conflicting_flag
+                );
+                std::process::exit(2);
+            }
+        }
+    }
+    if args.contains_id(FOLLOW) && (capture_mode || blob_mode_enabled(&args, input_mode)) {
+        eprintln!(
+            "--{} reads newline-delimited records and cannot be used with --{} {}.",
+            FOLLOW, INPUT_MODE, input_mode
         );
         std::process::exit(2);
     }
-    let blob_mode = blob_mode_enabled(&args, input_mode);
+    if let Some(spec) = args.get_one::<String>(LISTEN) {
+        let address = match ListenAddress::parse(spec) {
+            Ok(address) => address,
+            Err(err) => {
+                eprintln!("{}", err);
+                std::process::exit(2);
+            }
+        };
+        let length_framing = args.get_one::<String>(LISTEN_FRAMING).map(String::as_str)
+            == Some(LISTEN_FRAMING_LENGTH);
+        let misuse = if capture_mode {
+            Some(format!(
+                "--{} cannot be used with --{} {}.",
+                LISTEN, INPUT_MODE, input_mode
+            ))
+        } else if address.is_datagram() && args.get_flag(LISTEN_REPLY) {
+            Some(format!(
+                "--{} is only supported for tcp:// and unix: listeners.",
+                LISTEN_REPLY
+            ))
+        } else if address.is_datagram() && length_framing {
+            Some(format!(
+                "--{} {} does not apply to udp:// listeners, where each datagram is already one record.",
+                LISTEN_FRAMING, LISTEN_FRAMING_LENGTH
+            ))
+        } else if blob_mode_enabled(&args, input_mode) && !address.is_datagram() && !length_framing
+        {
+            Some(format!(
+                "Blob input over --{} needs --{} {} so each payload's boundaries are known.",
+                LISTEN, LISTEN_FRAMING, LISTEN_FRAMING_LENGTH
+            ))
+        } else {
+            None
+        };
+        if let Some(misuse) = misuse {
+            eprintln!("{}", misuse);
+            std::process::exit(2);
+        }
+    }
+    let ip_defrag = match FragmentPolicy::from_str(
+        args.get_one::<String>(IP_FRAG_POLICY)
+            .map_or(IP_FRAG_POLICY_BSD, String::as_str),
+    ) {
+        Ok(policy) => DefragConfig {
+            policy,
+            timeout_nanos: args
+                .get_one::<u64>(IP_FRAG_TIMEOUT)
+                .copied()
+                .unwrap_or(30)
+                .saturating_mul(1_000_000_000),
+        },
+        Err(err) => {
+            eprintln!("Unable to configure IP defragmentation: {}", err);
+            std::process::exit(2);
+        }
+    };
+    let json_slots: Vec<JsonSlot> = match args
+        .get_many::<String>(JSON_SLOT)
+        .map(|specs| specs.map(|spec| JsonSlot::parse(spec)).collect())
+        .unwrap_or_else(|| Ok(Vec::new()))
+    {
+        Ok(json_slots) => json_slots,
+        Err(err) => {
+            eprintln!("{}", err);
+            std::process::exit(2);
+        }
+    };
+    let chunking = match args.get_one::<String>(CHUNK_MODE) {
+        Some(mode) if blob_mode_enabled(&args, input_mode) => {
+            let config = ChunkMode::from_str(mode)
+                .map(|mode| ChunkConfig {
+                    mode,
+                    size: args.get_one::<usize>(CHUNK_SIZE).copied().unwrap_or(4096),
+                    overlap: args.get_one::<usize>(CHUNK_OVERLAP).copied().unwrap_or(0),
+                })
+                .and_then(|config| config.validate().map(|_| config));
+            match config {
+                Ok(config) => Some(config),
+                Err(err) => {
+                    eprintln!("Unable to configure chunking: {}", err);
+                    std::process::exit(2);
+                }
+            }
+        }
+        Some(_) => {
+            eprintln!(
+                "--{} requires blob input (--{}, --{}, or --{} {}).",
+                CHUNK_MODE, INPUT_BLOB, INPUT_BINARY, INPUT_MODE, INPUT_MODE_BINARY
+            );
+            std::process::exit(2);
+        }
+        None => None,
+    };
+    let delimited = match args.get_one::<String>(DELIMITED) {
+        Some(dialect) => {
+            let blocked_flag =
+                json_flag.or_else(|| args.get_flag(INPUT_BLOB).then_some(INPUT_BLOB));
+            if let Some(flag) = blocked_flag {
+                eprintln!(
+                    "--{} cannot be combined with --{} because payloads are selected with --{}.",
+                    DELIMITED, flag, PAYLOAD_COLUMN
+                );
+                std::process::exit(2);
+            }
+            if ![INPUT_MODE_BASE64, INPUT_MODE_STRING, INPUT_MODE_HEX].contains(&input_mode) {
+                eprintln!(
+                    "--{} requires --{} {}, {}, or {}.",
+                    DELIMITED, INPUT_MODE, INPUT_MODE_BASE64, INPUT_MODE_STRING, INPUT_MODE_HEX
+                );
+                std::process::exit(2);
+            }
+            let config = Dialect::from_str(dialect)
+                .and_then(|dialect| {
+                    Ok(DelimitedConfig {
+                        dialect,
+                        header: HeaderMode::from_str(
+                            args.get_one::<String>(DELIMITED_HEADER)
+                                .map_or("auto", String::as_str),
+                        )?,
+                        payload_column: ColumnSelector::parse(
+                            args.get_one::<String>(PAYLOAD_COLUMN)
+                                .map_or("", String::as_str),
+                        )?,
+                    })
+                })
+                .and_then(|config| config.validate().map(|_| config));
+            match config {
+                Ok(config) => Some(config),
+                Err(err) => {
+                    eprintln!("Unable to configure --{} input: {}", DELIMITED, err);
+                    std::process::exit(2);
+                }
+            }
+        }
+        None => None,
+    };
 
     let tlsh_list = Mutex::new(tlsh_list);
     let payload_reports = Mutex::new(payload_reports);
 
     let mut sigma_rule_plans: Vec<SigmaRulePlan> = Vec::new();
-    let mut pattern_specs: Vec<(String, PatternOrigin)> = Vec::new();
+    // Each pattern with its origin and any pattern-pack metadata.
+    let mut pattern_specs: Vec<(String, PatternOrigin, Option<Value>)> = Vec::new();
     if let Some(pattern_file) = args.get_one::<std::path::PathBuf>(PATTERN_FILE) {
-        match read_patterns(Some(pattern_file)) {
-            Ok(loaded_patterns) => {
-                for loaded_pattern in loaded_patterns {
-                    pattern_specs.push((loaded_pattern, PatternOrigin::Standard));
+        match load_pattern_pack(pattern_file) {
+            Ok(entries) => {
+                for entry in entries {
+                    pattern_specs.push((entry.pattern, PatternOrigin::Standard, entry.metadata));
                 }
             }
             Err(err) => {
-                eprintln!(
-                    "Unable to read pattern file {}: {}",
-                    pattern_file.display(),
-                    err
-                );
+                eprintln!("{}", err);
                 std::process::exit(2);
             }
         }
     }
     if let Some(pattern) = args.get_one::<String>(PATTERN) {
-        pattern_specs.push((pattern.to_string(), PatternOrigin::Standard));
+        pattern_specs.push((pattern.to_string(), PatternOrigin::Standard, None));
     }
     if let Some(sigma_rules) = args.get_many::<std::path::PathBuf>(SIGMA_RULE) {
         for sigma_rule in sigma_rules {
             match load_sigma_rule_plan(sigma_rule.as_path()) {
                 Ok(plan) => {
                     for spec in &plan.pattern_specs {
-                        pattern_specs.push((spec.regex.to_string(), PatternOrigin::Sigma));
+                        pattern_specs.push((spec.regex.to_string(), PatternOrigin::Sigma, None));
                     }
                     sigma_rule_plans.push(plan);
                 }
@@ -379,19 +1078,31 @@ fn main() {
         std::process::exit(2);
     }
 
-    if regex_engine == RegexEngine::Vectorscan {
-        eprintln!(
-            "Regex engine '{}' is currently a compatibility scaffold; executing with '{}' runtime in this build.",
-            regex_engine.as_str(),
-            RegexEngine::Pcre2.as_str()
-        );
-    }
-
+    let define_library = match args.get_many::<std::path::PathBuf>(DEFINE_LIBRARY) {
+        Some(paths) => {
+            let paths: Vec<std::path::PathBuf> = paths.cloned().collect();
+            match DefineLibrary::load(&paths) {
+                Ok(library) => library,
+                Err(err) => {
+                    eprintln!("{}", err);
+                    std::process::exit(2);
+                }
+            }
+        }
+        None => DefineLibrary::default(),
+    };
+    let pattern_limits = PatternLimits {
+        match_limit: args.get_one::<u32>(MATCH_LIMIT).copied(),
+        depth_limit: args.get_one::<u32>(DEPTH_LIMIT).copied(),
+    };
     let mut compiled_patterns = Vec::with_capacity(pattern_specs.len());
-    for (pattern, origin) in &pattern_specs {
+    let mut vectorscan_expressions = Vec::new();
+    for (pattern, origin, _) in &pattern_specs {
         if regex_engine == RegexEngine::Vectorscan {
             let issues = vectorscan_compatibility_issues(pattern);
-            if !issues.is_empty() {
+            if issues.is_empty() {
+                vectorscan_expressions.push((compiled_patterns.len(), pattern.as_str()));
+            } else {
                 eprintln!(
                     "Pattern '{}' requires PCRE2 fallback semantics under '{}': {}",
                     compact_pattern(pattern),
@@ -400,11 +1111,25 @@ fn main() {
                 );
             }
         }
-        match build_regex(pattern) {
-            Ok(re) => compiled_patterns.push(CompiledPattern {
-                regex: re,
-                origin: *origin,
-            }),
+        let composed = define_library.compose(pattern);
+        match build_regex(&pattern_limits.apply(&composed)) {
+            Ok(re) => {
+                let helpers = define_group_names(&composed);
+                let tags = re
+                    .capture_names()
+                    .iter()
+                    .flatten()
+                    .filter(|name| !helpers.contains(*name))
+                    .cloned()
+                    .collect();
+                compiled_patterns.push(CompiledPattern {
+                    regex: re,
+                    origin: *origin,
+                    accelerated: false,
+                    source: pattern.clone(),
+                    tags,
+                });
+            }
             Err(err) => {
                 eprintln!("Invalid PCRE2 pattern '{}': {}", pattern, err);
                 std::process::exit(2);
@@ -412,156 +1137,191 @@ fn main() {
         }
     }
     counter_pcre_patterns.add(compiled_patterns.len());
-
-    if let Some(path) = args.get_one::<std::path::PathBuf>(INPUT_FOLDER) {
-        if !path.is_dir() {
-            eprintln!("-f path must be a folder: {}", path.display());
-            return;
+    let vectorscan = if vectorscan_expressions.is_empty() {
+        None
+    } else {
+        match VectorscanSet::compile(&vectorscan_expressions) {
+            Ok((set, rejected)) => {
+                for (index, message) in &rejected {
+                    eprintln!(
+                        "Pattern '{}' requires PCRE2 fallback semantics under '{}': {}",
+                        compact_pattern(&pattern_specs[*index].0),
+                        regex_engine.as_str(),
+                        message
+                    );
+                }
+                if set.is_some() {
+                    for (index, _) in &vectorscan_expressions {
+                        compiled_patterns[*index].accelerated = !rejected
+                            .iter()
+                            .any(|(rejected_index, _)| rejected_index == index);
+                    }
+                }
+                set
+            }
+            Err(err) => {
+                eprintln!(
+                    "Regex engine '{}' is unavailable ({}); executing with '{}' runtime.",
+                    regex_engine.as_str(),
+                    err,
+                    RegexEngine::Pcre2.as_str()
+                );
+                None
+            }
         }
-        let entries = match std::fs::read_dir(path) {
-            Ok(entries) => entries,
+    };
+    let accelerated_patterns = compiled_patterns
+        .iter()
+        .filter(|compiled| compiled.accelerated)
+        .count();
+    // Patterns Vectorscan already prefilters are left out of the literal prefilter.
+    let prefilter = if args.get_flag(NO_PREFILTER) {
+        None
+    } else {
+        let filterable: Vec<(usize, &str)> = pattern_specs
+            .iter()
+            .enumerate()
+            .filter(|(index, _)| !compiled_patterns[*index].accelerated)
+            .map(|(index, (pattern, _, _))| (index, pattern.as_str()))
+            .collect();
+        match Prefilter::build(&filterable, compiled_patterns.len()) {
+            Ok(prefilter) => prefilter,
             Err(err) => {
-                eprintln!("Unable to read directory {}: {}", path.display(), err);
-                return;
+                eprintln!("{}; running every pattern through PCRE2", err);
+                None
             }
+        }
+    };
+    let pattern_health = PatternHealth::new(
+        compiled_patterns.len(),
+        args.get_one::<u64>(MAX_PATTERN_ERRORS)
+            .copied()
+            .unwrap_or(10),
+    );
+    // Pattern-pack metadata keyed by the tags of the pattern it describes.
+    let mut tag_metadata: HashMap<String, Value> = HashMap::new();
+    for (compiled, (_, _, metadata)) in compiled_patterns.iter().zip(&pattern_specs) {
+        let Some(metadata) = metadata else {
+            continue;
         };
-
-        for entry_result in entries {
-            let entry = match entry_result {
-                Ok(entry) => entry,
-                Err(err) => {
-                    eprintln!("Unable to read directory entry: {}", err);
-                    continue;
+        for name in &compiled.tags {
+            match tag_metadata.get(name.as_str()) {
+                Some(existing) if existing != metadata => eprintln!(
+                    "Tag '{}' is described by more than one pattern; reporting the first description",
+                    name
+                ),
+                Some(_) => {}
+                None => {
+                    tag_metadata.insert(name.clone(), metadata.clone());
                 }
-            };
-            let file_path: PathBuf = entry.path();
-            if !file_path.is_file() {
-                continue;
             }
+        }
+    }
 
-            if blob_mode {
-                let blob = match std::fs::read(&file_path) {
-                    Ok(blob) => blob,
-                    Err(err) => {
-                        eprintln!("Unable to read blob file {}: {}", file_path.display(), err);
-                        continue;
-                    }
-                };
-                counter_inputs.inc();
-                handle_blob(
-                    blob.as_slice(),
-                    &compiled_patterns,
-                    &sigma_rule_plans,
-                    &args,
-                    input_mode,
-                    &similarity_mode,
-                    &tlsh_list,
-                    &payload_reports,
-                    &counter_pcre_matches,
-                    &counter_tlsh_hashes,
-                    &vec_payload_size,
-                    &vec_payload_size_matched,
-                    &counter_unique_payloads,
-                    &counter_pcre_matches_total,
-                );
-                continue;
-            }
+    let stats_sources = StatsSources {
+        start,
+        args: &args,
+        similarity_mode: &similarity_mode,
+        regex_engine: &regex_engine,
+        accelerated_patterns,
+        library_definitions: define_library.len(),
+        prefilter: prefilter.as_ref(),
+        patterns: &compiled_patterns,
+        pattern_health: &pattern_health,
+        sigma_rule_plans: &sigma_rule_plans,
+        counter_inputs: &counter_inputs,
+        counter_pcre_patterns: &counter_pcre_patterns,
+        counter_pcre_matches: &counter_pcre_matches,
+        counter_pcre_matches_total: &counter_pcre_matches_total,
+        counter_tlsh_hashes: &counter_tlsh_hashes,
+        counter_tlsh_similarites: &counter_tlsh_similarites,
+        counter_unique_payloads: &counter_unique_payloads,
+        vec_payload_size: &vec_payload_size,
+        vec_payload_size_matched: &vec_payload_size_matched,
+        vec_tlsh_disance: &vec_tlsh_disance,
+    };
+    let pipeline = PipelineContext {
+        patterns: &compiled_patterns,
+        vectorscan: vectorscan.as_ref(),
+        prefilter: prefilter.as_ref(),
+        pattern_health: &pattern_health,
+        payload_budget: args
+            .get_one::<u64>(PAYLOAD_BUDGET_MS)
+            .map(|millis| Duration::from_millis(*millis)),
+        tag_metadata: &tag_metadata,
+        sigma_rule_plans: &sigma_rule_plans,
+        args: &args,
+        input_mode,
+        similarity_mode: &similarity_mode,
+        counter_inputs: &counter_inputs,
+        tlsh_list: &tlsh_list,
+        payload_reports: &payload_reports,
+        counter_pcre_matches: &counter_pcre_matches,
+        counter_tlsh_hashes: &counter_tlsh_hashes,
+        vec_payload_size: &vec_payload_size,
+        vec_payload_size_matched: &vec_payload_size_matched,
+        counter_unique_payloads: &counter_unique_payloads,
+        counter_pcre_matches_total: &counter_pcre_matches_total,
+        tcp_reassembly,
+        ip_defrag,
+        chunking,
+        delimited,
+        json_slots: &json_slots,
+        stats: &stats_sources,
+        report_sink: None,
+        stream_progress: None,
+    };
 
-            let file = match std::fs::File::open(&file_path) {
-                Ok(file) => file,
-                Err(err) => {
-                    eprintln!("Unable to open file {}: {}", file_path.display(), err);
-                    continue;
-                }
-            };
-            let reader = std::io::BufReader::new(file);
-            for line_result in reader.lines() {
-                let line = match line_result {
-                    Ok(line) => line,
-                    Err(err) => {
-                        eprintln!("Unable to read line from {}: {}", file_path.display(), err);
-                        continue;
-                    }
-                };
-                counter_inputs.inc();
-                handle_line(
-                    &line,
-                    &compiled_patterns,
-                    &sigma_rule_plans,
-                    &args,
-                    input_mode,
-                    &similarity_mode,
-                    &tlsh_list,
-                    &payload_reports,
-                    &counter_pcre_matches,
-                    &counter_tlsh_hashes,
-                    &vec_payload_size,
-                    &vec_payload_size_matched,
-                    &counter_unique_payloads,
-                    &counter_pcre_matches_total,
-                );
-            }
-        }
-    } else {
-        let stdin = io::stdin();
-        if blob_mode {
-            let mut blob = Vec::new();
-            let mut lock = stdin.lock();
-            if let Err(err) = lock.read_to_end(&mut blob) {
-                eprintln!("Unable to read blob from STDIN: {}", err);
-                return;
+    let stream_mode = stream_flag.is_some();
+    let ingest_result = if stream_mode {
+        let stream_buffer = args
+            .get_one::<usize>(STREAM_BUFFER)
+            .copied()
+            .unwrap_or(1024)
+            .max(1);
+        let (report_sender, report_receiver) = sync_channel::<Value>(stream_buffer);
+        let progress = Arc::new(StreamProgress::default());
+        let writer_progress = Arc::clone(&progress);
+        let writer = std::thread::spawn(move || {
+            for report in report_receiver {
+                emit_report(&report);
+                writer_progress
+                    .reports_written
+                    .fetch_add(1, Ordering::SeqCst);
             }
-            counter_inputs.inc();
-            handle_blob(
-                blob.as_slice(),
-                &compiled_patterns,
-                &sigma_rule_plans,
-                &args,
-                input_mode,
-                &similarity_mode,
-                &tlsh_list,
-                &payload_reports,
-                &counter_pcre_matches,
-                &counter_tlsh_hashes,
-                &vec_payload_size,
-                &vec_payload_size_matched,
-                &counter_unique_payloads,
-                &counter_pcre_matches_total,
-            );
-        } else {
-            stdin
-                .lock()
-                .lines()
-                .filter_map(|line| match line {
-                    Ok(value) => Some(value),
-                    Err(err) => {
-                        eprintln!("Unable to read line from STDIN: {}", err);
-                        None
+        });
+        let pipeline = PipelineContext {
+            report_sink: Some(report_sender),
+            stream_progress: Some(progress),
+            ..pipeline
+        };
+        let (record_sender, record_receiver) = sync_channel::<InputRecord>(stream_buffer);
+        let ingest_result = std::thread::scope(|scope| {
+            let pipeline_ref = &pipeline;
+            scope.spawn(move || {
+                record_receiver.into_iter().par_bridge().for_each(|record| {
+                    handle_record(record, pipeline_ref);
+                    if let Some(progress) = &pipeline_ref.stream_progress {
+                        progress.records_handled.fetch_add(1, Ordering::SeqCst);
                     }
-                })
-                .collect::<Vec<String>>()
-                .par_iter()
-                .for_each(|line| {
-                    counter_inputs.inc();
-                    handle_line(
-                        line,
-                        &compiled_patterns,
-                        &sigma_rule_plans,
-                        &args,
-                        input_mode,
-                        &similarity_mode,
-                        &tlsh_list,
-                        &payload_reports,
-                        &counter_pcre_matches,
-                        &counter_tlsh_hashes,
-                        &vec_payload_size,
-                        &vec_payload_size_matched,
-                        &counter_unique_payloads,
-                        &counter_pcre_matches_total,
-                    );
                 });
+            });
+            let ingest_result = ingest_inputs(&args, &pipeline, Some(&record_sender));
+            drop(record_sender);
+            ingest_result
+        });
+        drop(pipeline);
+        if writer.join().is_err() {
+            eprintln!("Report writer thread panicked");
         }
-    }
+        ingest_result
+    } else {
+        ingest_inputs(&args, &pipeline, None)
+    };
+    if let Err(err) = ingest_result {
+        eprintln!("{}", err);
+        return;
+    }
 
     if args.get_flag(TLSH_DIFF) {
         run_hash_diffs(
@@ -574,239 +1334,1314 @@ fn main() {
         );
     }
 
-    generate_reports(&tlsh_reports, &payload_reports, &args);
+    if stream_mode {
+        emit_similarity_clusters(&tlsh_reports);
+    } else {
+        generate_reports(&tlsh_reports, &payload_reports, &args);
+    }
     if args.get_flag(PROTOCOL_HINTS) {
         emit_protocol_hints(&payload_reports, &tlsh_reports, &args, &similarity_mode);
     }
 
     if args.get_flag(STATS) {
-        // TODO: Potentially optimize so that we don't waist CPU on creation of stats (counter, incrementers, etc.) unless this flag is passed.
-        let default_empty = 0;
-        let end = Instant::now();
-        let duration = end.duration_since(start);
-        let duration_in_seconds = duration.as_secs_f32();
-        let formated_duration: String = format!("{:.2}", duration_in_seconds);
-
-        // Payloads Matched
-        let (
-            avg_payload_size_matched,
-            min_payload_size_matched,
-            max_payload_size_matched,
-            p95_payload_size_matched,
-            total_payload_size_matched,
-        ) = match vec_payload_size_matched.lock() {
-            Ok(payload_sizes_matched) => {
-                let payload_sizes_matched_len = payload_sizes_matched.len();
-                let avg_payload_size_matched = if payload_sizes_matched_len == 0 {
-                    0.0
-                } else {
-                    payload_sizes_matched.iter().sum::<i64>() as f64
-                        / payload_sizes_matched_len as f64
-                };
-                let min_payload_size_matched =
-                    *payload_sizes_matched.iter().min().unwrap_or(&default_empty);
-                let max_payload_size_matched =
-                    *payload_sizes_matched.iter().max().unwrap_or(&default_empty);
-                let mut sorted_payload_sizes_matched = payload_sizes_matched.clone();
-                sorted_payload_sizes_matched.sort();
-                let p95_payload_size_matched = if payload_sizes_matched_len > 1 {
-                    sorted_payload_sizes_matched[(payload_sizes_matched_len * 95 / 100) - 1]
-                } else if payload_sizes_matched_len == 1 {
-                    sorted_payload_sizes_matched[0]
-                } else {
-                    default_empty
-                };
-                let total_payload_size_matched = payload_sizes_matched.iter().sum::<i64>();
-                (
-                    avg_payload_size_matched,
-                    min_payload_size_matched,
-                    max_payload_size_matched,
-                    p95_payload_size_matched,
-                    total_payload_size_matched,
-                )
+        emit_stats(&build_stats(&stats_sources));
+    }
+}
+
+// Everything the `--stats` report reads, so it can be built at the end of a run or,
+// with --follow, periodically while input keeps arriving.
+struct StatsSources<'a> {
+    start: Instant,
+    args: &'a ArgMatches,
+    similarity_mode: &'a SimilarityMode,
+    regex_engine: &'a RegexEngine,
+    // Patterns prefiltered by Vectorscan; the rest always run through PCRE2.
+    accelerated_patterns: usize,
+    // Definitions loaded with --define-library.
+    library_definitions: usize,
+    prefilter: Option<&'a Prefilter>,
+    patterns: &'a [CompiledPattern],
+    pattern_health: &'a PatternHealth,
+    sigma_rule_plans: &'a [SigmaRulePlan],
+    counter_inputs: &'a ConsistentCounter,
+    counter_pcre_patterns: &'a ConsistentCounter,
+    counter_pcre_matches: &'a DashMap<String, i64>,
+    counter_pcre_matches_total: &'a ConsistentCounter,
+    counter_tlsh_hashes: &'a ConsistentCounter,
+    counter_tlsh_similarites: &'a ConsistentCounter,
+    counter_unique_payloads: &'a Mutex<HashSet<u64>>,
+    vec_payload_size: &'a Mutex<Vec<i64>>,
+    vec_payload_size_matched: &'a Mutex<Vec<i64>>,
+    vec_tlsh_disance: &'a Mutex<Vec<i32>>,
+}
+
+fn build_stats(sources: &StatsSources) -> Value {
+    let StatsSources {
+        start,
+        args,
+        similarity_mode,
+        regex_engine,
+        accelerated_patterns,
+        library_definitions,
+        prefilter,
+        patterns,
+        pattern_health,
+        sigma_rule_plans,
+        counter_inputs,
+        counter_pcre_patterns,
+        counter_pcre_matches,
+        counter_pcre_matches_total,
+        counter_tlsh_hashes,
+        counter_tlsh_similarites,
+        counter_unique_payloads,
+        vec_payload_size,
+        vec_payload_size_matched,
+        vec_tlsh_disance,
+    } = *sources;
+    // TODO: Potentially optimize so that we don't waist CPU on creation of stats (counter, incrementers, etc.) unless this flag is passed.
+    let default_empty = 0;
+    let end = Instant::now();
+    let duration = end.duration_since(start);
+    let duration_in_seconds = duration.as_secs_f32();
+    let formated_duration: String = format!("{:.2}", duration_in_seconds);
+
+    // Payloads Matched
+    let (
+        avg_payload_size_matched,
+        min_payload_size_matched,
+        max_payload_size_matched,
+        p95_payload_size_matched,
+        total_payload_size_matched,
+    ) = match vec_payload_size_matched.lock() {
+        Ok(payload_sizes_matched) => {
+            let payload_sizes_matched_len = payload_sizes_matched.len();
+            let avg_payload_size_matched = if payload_sizes_matched_len == 0 {
+                0.0
+            } else {
+                payload_sizes_matched.iter().sum::<i64>() as f64 / payload_sizes_matched_len as f64
+            };
+            let min_payload_size_matched =
+                *payload_sizes_matched.iter().min().unwrap_or(&default_empty);
+            let max_payload_size_matched =
+                *payload_sizes_matched.iter().max().unwrap_or(&default_empty);
+            let mut sorted_payload_sizes_matched = payload_sizes_matched.clone();
+            sorted_payload_sizes_matched.sort();
+            let p95_payload_size_matched = match payload_sizes_matched_len {
+                0 => default_empty,
+                1 => sorted_payload_sizes_matched[0],
+                len => sorted_payload_sizes_matched[(len * 95 / 100) - 1],
+            };
+            let total_payload_size_matched = payload_sizes_matched.iter().sum::<i64>();
+            (
+                avg_payload_size_matched,
+                min_payload_size_matched,
+                max_payload_size_matched,
+                p95_payload_size_matched,
+                total_payload_size_matched,
+            )
+        }
+        Err(err) => {
+            eprintln!(
+                "Unable to read matched payload sizes due to poisoned lock: {}",
+                err
+            );
+            (0.0, default_empty, default_empty, default_empty, 0)
+        }
+    };
+
+    // Raw Payloads
+    let (
+        avg_payload_size,
+        min_payload_size,
+        max_payload_size,
+        p95_payload_size,
+        total_payload_size,
+    ) = match vec_payload_size.lock() {
+        Ok(payload_sizes) => {
+            let payload_sizes_len = payload_sizes.len();
+            let avg_payload_size = if payload_sizes_len == 0 {
+                0.0
+            } else {
+                payload_sizes.iter().sum::<i64>() as f64 / payload_sizes_len as f64
+            };
+            let min_payload_size = *payload_sizes.iter().min().unwrap_or(&default_empty);
+            let max_payload_size = *payload_sizes.iter().max().unwrap_or(&default_empty);
+            let mut sorted_payload_sizes = payload_sizes.clone();
+            sorted_payload_sizes.sort();
+            let p95_payload_size = match payload_sizes_len {
+                0 => default_empty,
+                1 => sorted_payload_sizes[0],
+                len => sorted_payload_sizes[(len * 95 / 100) - 1],
+            };
+            let total_payload_size = payload_sizes.iter().sum::<i64>();
+            (
+                avg_payload_size,
+                min_payload_size,
+                max_payload_size,
+                p95_payload_size,
+                total_payload_size,
+            )
+        }
+        Err(err) => {
+            eprintln!("Unable to read payload sizes due to poisoned lock: {}", err);
+            (0.0, default_empty, default_empty, default_empty, 0)
+        }
+    };
+
+    let processing_rate = if duration.as_secs() < 1 {
+        let elapsed_millis = std::cmp::max(duration.as_millis() as i64, 1);
+        format!("{}/ms", format_size(total_payload_size / elapsed_millis))
+    } else {
+        let elapsed_seconds = std::cmp::max(duration.as_secs() as i64, 1);
+        format!("{}/s", format_size(total_payload_size / elapsed_seconds))
+    };
+    let default_empty_32 = 0_i32;
+    // TLSH Hashes
+    let mut compare_json: Value = Value::Null;
+    let mut matches_json_array = Vec::new();
+    for entry in counter_pcre_matches.iter() {
+        let key = entry.key();
+        let value = entry.value();
+        let json_object: Value = json!({
+            "Name": key,
+            "Matches": *value
+        });
+        matches_json_array.push(json_object);
+    }
+    let matches_json = Value::Array(matches_json_array);
+    if let Ok(tlsh_distances) = vec_tlsh_disance.lock() {
+        if tlsh_distances.len() > 2 {
+            let avg_tlsh_distance =
+                tlsh_distances.iter().sum::<i32>() as f32 / tlsh_distances.len() as f32;
+            let min_tlsh_distance = tlsh_distances.iter().min().unwrap_or(&default_empty_32);
+            let max_tlsh_distance = tlsh_distances.iter().max().unwrap_or(&default_empty_32);
+            let mut sorted_tlsh_distances = tlsh_distances.clone();
+            sorted_tlsh_distances.sort();
+            let tlsh_distances_len = tlsh_distances.len();
+            let p95_tlsh_distance = if tlsh_distances_len > 1 {
+                sorted_tlsh_distances[(tlsh_distances_len * 95 / 100) - 1]
+            } else {
+                sorted_tlsh_distances[0]
+            };
+            compare_json = json!({
+                "Similarities": counter_tlsh_similarites.get(),
+                "AvgDistance": format!("{:.0}", avg_tlsh_distance),
+                "MinDistance": *min_tlsh_distance,
+                "MaxDistance": *max_tlsh_distance,
+                "P95Distance": p95_tlsh_distance,
+            });
+        }
+    } else {
+        eprintln!("Unable to read TLSH distances due to poisoned lock");
+    }
+
+    let unique_payload_count = match counter_unique_payloads.lock() {
+        Ok(unique_payloads) => unique_payloads.len(),
+        Err(err) => {
+            eprintln!(
+                "Unable to read unique payload count due to poisoned lock: {}",
+                err
+            );
+            0
+        }
+    };
+    let input_mode = resolved_input_mode(args);
+    let hash_function = args
+        .get_one::<String>(TLSH_ALGORITHM)
+        .map_or("48_1", String::as_str);
+    let distance_threshold = args.get_one::<i32>(TLSH_DISTANCE).copied().unwrap_or(100);
+    let input_json_key = args
+        .get_one::<String>(INPUT_JSON_KEY)
+        .map_or("", String::as_str);
+
+    // Create a JSON object for the stats
+    let stats = json!({
+        "---PRECURSOR_STATISTICS---": "This JSON is output to STDERR so that you can parse stats separate from the primary output.",
+        "Input": {
+                    "Count": counter_inputs.get(),
+                    "Unique": unique_payload_count,
+                    "AvgSize": format!("{:.0}", avg_payload_size),
+                    "MinSize": min_payload_size,
+                    "MaxSize": max_payload_size,
+                    "P95Size": p95_payload_size,
+                    "TotalSize": format_size(total_payload_size),},
+        "Match": {
+                    "Patterns": counter_pcre_patterns.get(),
+                    "LibraryDefinitions": library_definitions,
+                    "TotalMatches": counter_pcre_matches_total.get(),
+                    "Matches": matches_json,
+                    "HashesGenerated": counter_tlsh_hashes.get(),
+                    "Prefilter": prefilter.map(Prefilter::stats),
+                    "Errors": pattern_health.stats(|index| compact_pattern(&patterns[index].source)),
+                    "AvgSize": format!("{:.0}", avg_payload_size_matched),
+                    "MinSize": min_payload_size_matched,
+                    "MaxSize": max_payload_size_matched,
+                    "P95Size": p95_payload_size_matched,
+                    "TotalSize": format_size(total_payload_size_matched),},
+        "Compare": compare_json,
+        "Environment": {
+                    "Version": env!("CARGO_PKG_VERSION"),
+                    "DurationSeconds": formated_duration,
+                    "ProcessingRate": processing_rate,
+                    "SimilarityMode": similarity_mode.as_str(),
+                    "RegexEngine": regex_engine.as_str(),
+                    "AcceleratedPatterns": accelerated_patterns,
+                    "InputMode": input_mode,
+                    "HashFunction": hash_function,
+                    "DistanceThreshold": distance_threshold,
+                    "DiffEnabled": args.get_flag(TLSH_DIFF),
+                    "OnlyOutputSimilar": args.get_flag(TLSH_SIM_ONLY),
+                    "LengthEnabled": args.get_flag(TLSH_LENGTH),
+                    "InputJSONKey": input_json_key,
+                    "SinglePacketInference": args.get_flag(SINGLE_PACKET),
+                    "AbstainThreshold": args.get_one::<f64>(ABSTAIN_THRESHOLD).copied().unwrap_or(0.65),
+                    "ProtocolTopK": args.get_one::<usize>(PROTOCOL_TOP_K).copied().unwrap_or(3),
+                    "SigmaRulesLoaded": sigma_rule_plans.len(),
+                    },
+        }
+    );
+    stats
+}
+
+fn emit_stats(stats: &Value) {
+    // Serialize the JSON object as a pretty-printed String
+    match serde_json::to_string_pretty(stats) {
+        Ok(pretty_json) => {
+            let mut stderr = std::io::stderr();
+            if let Err(err) = writeln!(&mut stderr, "{}", pretty_json) {
+                eprintln!("Error printing JSON to STDERR: {}", err);
+                return;
+            }
+            if let Err(err) = stderr.flush() {
+                eprintln!("Error flushing STDERR buffer: {}", err);
             }
+        }
+        Err(err) => {
+            eprintln!(
+                "Error converting JSON object to pretty-printed String: {}",
+                err
+            );
+        }
+    }
+}
+
+#[derive(Clone, Debug)]
+struct InputSource {
+    path: Arc<str>,
+    // Path below the -f folder, when the record came from a folder walk.
+    relative_path: Option<Arc<str>>,
+    // Set when the record was unpacked from a compressed or archived input.
+    member_path: Option<Arc<str>>,
+    containers: Option<Arc<[&'static str]>>,
+    // With --listen-reply, reports go back to the client connection the record came from.
+    reply: Option<ReplySink>,
+}
+
+// The reply channel of one --listen-reply client. Reports are never waited on: a client
+// that stops reading its replies is disconnected instead of stalling the matcher workers.
+#[derive(Clone, Debug)]
+struct ReplySink {
+    sender: SyncSender<Value>,
+    connection: Arc<Connection>,
+    peer: Arc<str>,
+    disconnected: Arc<AtomicBool>,
+}
+
+impl ReplySink {
+    fn send(&self, report: Value) {
+        match self.sender.try_send(report) {
+            Ok(()) => {}
+            Err(TrySendError::Full(_)) => {
+                if !self.disconnected.swap(true, Ordering::SeqCst) {
+                    eprintln!("Disconnecting {}: it is not reading its replies", self.peer);
+                    self.connection.shutdown();
+                }
+            }
+            // The reply writer already reported why it stopped.
+            Err(TrySendError::Disconnected(_)) => {}
+        }
+    }
+}
+
+impl InputSource {
+    fn new(path: &str) -> Self {
+        InputSource {
+            path: Arc::from(path),
+            relative_path: None,
+            member_path: None,
+            containers: None,
+            reply: None,
+        }
+    }
+
+    fn with_member(&self, member: &MemberInfo) -> Self {
+        let mut source = self.clone();
+        source.member_path = member.path.as_deref().map(Arc::from);
+        if !member.containers.is_empty() {
+            source.containers = Some(
+                member
+                    .containers
+                    .iter()
+                    .map(ContainerKind::as_str)
+                    .collect(),
+            );
+        }
+        source
+    }
+
+    fn label(&self) -> &str {
+        source_label(&self.path)
+    }
+}
+
+#[derive(Clone, Debug)]
+struct RecordOrigin {
+    input_index: u64,
+    source: InputSource,
+    source_line: Option<u64>,
+    source_offset: Option<u64>,
+}
+
+impl RecordOrigin {
+    fn insert_provenance(&self, report: &mut Map<String, Value>) {
+        report.insert("source_path".to_string(), json!(self.source.path.as_ref()));
+        if let Some(relative_path) = &self.source.relative_path {
+            report.insert(
+                "source_relative_path".to_string(),
+                json!(relative_path.as_ref()),
+            );
+        }
+        if let Some(member_path) = &self.source.member_path {
+            report.insert(
+                "source_member_path".to_string(),
+                json!(member_path.as_ref()),
+            );
+        }
+        if let Some(containers) = &self.source.containers {
+            report.insert("source_containers".to_string(), json!(containers.as_ref()));
+        }
+        if let Some(source_line) = self.source_line {
+            report.insert("source_line".to_string(), json!(source_line));
+        }
+        if let Some(source_offset) = self.source_offset {
+            report.insert("source_offset".to_string(), json!(source_offset));
+        }
+    }
+}
+
+// Input indexes are 1-based and assigned in read order, before records fan out to workers.
+fn next_record_origin(
+    ctx: &PipelineContext,
+    source: &InputSource,
+    source_line: Option<u64>,
+    source_offset: Option<u64>,
+) -> RecordOrigin {
+    RecordOrigin {
+        input_index: ctx.counter_inputs.inc() as u64 + 1,
+        source: source.clone(),
+        source_line,
+        source_offset,
+    }
+}
+
+fn source_label(source_path: &str) -> &str {
+    if source_path == STDIN_SOURCE_PATH {
+        "STDIN"
+    } else {
+        source_path
+    }
+}
+
+// Like BufRead::lines, but also yields each line's 1-based number and the byte
+// offset of its first byte so reports can point back into the source.
+fn read_lines_with_offsets<R: BufRead>(
+    mut reader: R,
+    source_path: &str,
+    mut handle: impl FnMut(String, u64, u64) -> Result<(), String>,
+) -> Result<(), String> {
+    let mut buffer = Vec::new();
+    let mut offset = 0u64;
+    let mut line_number = 0u64;
+    loop {
+        buffer.clear();
+        let read = match reader.read_until(b'\n', &mut buffer) {
+            Ok(0) => return Ok(()),
+            Ok(read) => read,
             Err(err) => {
                 eprintln!(
-                    "Unable to read matched payload sizes due to poisoned lock: {}",
+                    "Unable to read line from {}: {}",
+                    source_label(source_path),
                     err
                 );
-                (0.0, default_empty, default_empty, default_empty, 0)
+                return Ok(());
             }
         };
-
-        // Raw Payloads
-        let (
-            avg_payload_size,
-            min_payload_size,
-            max_payload_size,
-            p95_payload_size,
-            total_payload_size,
-        ) = match vec_payload_size.lock() {
-            Ok(payload_sizes) => {
-                let payload_sizes_len = payload_sizes.len();
-                let avg_payload_size = if payload_sizes_len == 0 {
-                    0.0
-                } else {
-                    payload_sizes.iter().sum::<i64>() as f64 / payload_sizes_len as f64
-                };
-                let min_payload_size = *payload_sizes.iter().min().unwrap_or(&default_empty);
-                let max_payload_size = *payload_sizes.iter().max().unwrap_or(&default_empty);
-                let mut sorted_payload_sizes = payload_sizes.clone();
-                sorted_payload_sizes.sort();
-                let p95_payload_size = if payload_sizes_len > 1 {
-                    sorted_payload_sizes[(payload_sizes_len * 95 / 100) - 1]
-                } else if payload_sizes_len == 1 {
-                    sorted_payload_sizes[0]
-                } else {
-                    default_empty
-                };
-                let total_payload_size = payload_sizes.iter().sum::<i64>();
-                (
-                    avg_payload_size,
-                    min_payload_size,
-                    max_payload_size,
-                    p95_payload_size,
-                    total_payload_size,
-                )
-            }
-            Err(err) => {
-                eprintln!("Unable to read payload sizes due to poisoned lock: {}", err);
-                (0.0, default_empty, default_empty, default_empty, 0)
+        line_number += 1;
+        let line_offset = offset;
+        offset += read as u64;
+        if buffer.last() == Some(&b'\n') {
+            buffer.pop();
+            if buffer.last() == Some(&b'\r') {
+                buffer.pop();
             }
-        };
+        }
+        match String::from_utf8(std::mem::take(&mut buffer)) {
+            Ok(line) => handle(line, line_number, line_offset)?,
+            Err(err) => eprintln!(
+                "Unable to read line {} from {}: {}",
+                line_number,
+                source_label(source_path),
+                err.utf8_error()
+            ),
+        }
+    }
+}
 
-        let processing_rate: String;
-        if duration.as_secs() < 1 {
-            let elapsed_millis = std::cmp::max(duration.as_millis() as i64, 1);
-            processing_rate = format!("{}/ms", format_size(total_payload_size / elapsed_millis));
-        } else {
-            let elapsed_seconds = std::cmp::max(duration.as_secs() as i64, 1);
-            processing_rate = format!("{}/s", format_size(total_payload_size / elapsed_seconds));
-        }
-        let default_empty_32 = 0_i32;
-        // TLSH Hashes
-        let mut compare_json: Value = Value::Null;
-        let mut matches_json_array = Vec::new();
-        for entry in counter_pcre_matches.iter() {
-            let key = entry.key();
-            let value = entry.value();
-            let json_object: Value = json!({
-                "Name": key,
-                "Matches": *value
-            });
-            matches_json_array.push(json_object);
-        }
-        let matches_json = Value::Array(matches_json_array);
-        if let Ok(tlsh_distances) = vec_tlsh_disance.lock() {
-            if tlsh_distances.len() > 2 {
-                let avg_tlsh_distance =
-                    tlsh_distances.iter().sum::<i32>() as f32 / tlsh_distances.len() as f32;
-                let min_tlsh_distance = tlsh_distances.iter().min().unwrap_or(&default_empty_32);
-                let max_tlsh_distance = tlsh_distances.iter().max().unwrap_or(&default_empty_32);
-                let mut sorted_tlsh_distances = tlsh_distances.clone();
-                sorted_tlsh_distances.sort();
-                let tlsh_distances_len = tlsh_distances.len();
-                let p95_tlsh_distance = if tlsh_distances_len > 1 {
-                    sorted_tlsh_distances[(tlsh_distances_len * 95 / 100) - 1]
-                } else {
-                    sorted_tlsh_distances[0]
-                };
-                compare_json = json!({
-                    "Similarities": counter_tlsh_similarites.get(),
-                    "AvgDistance": format!("{:.0}", avg_tlsh_distance),
-                    "MinDistance": *min_tlsh_distance,
-                    "MaxDistance": *max_tlsh_distance,
-                    "P95Distance": p95_tlsh_distance,
-                });
+// Zeek TSV and delimited headers are stateful, so they are resolved here, while the
+// source is read in order, and only data records (as JSON) reach the matcher workers.
+fn read_input_lines<R: BufRead>(
+    reader: R,
+    source_path: &str,
+    ctx: &PipelineContext,
+    mut handle: impl FnMut(String, u64, u64) -> Result<(), String>,
+) -> Result<(), String> {
+    let mut normalizer = LineNormalizer::new(ctx);
+    read_lines_with_offsets(reader, source_path, |line, line_number, offset| {
+        normalizer.push_line(line, line_number, offset, source_path, &mut handle)
+    })?;
+    normalizer.finish(source_path);
+    Ok(())
+}
+
+// Per-source line state; one is kept for each file (and rebuilt when --follow reopens it).
+enum LineNormalizer {
+    Lines,
+    Zeek(ZeekLogReader),
+    // Records spanning several lines (quoted CSV cells) are reported at their first line.
+    Delimited {
+        reader: DelimitedReader,
+        record_start: Option<(u64, u64)>,
+    },
+}
+
+impl LineNormalizer {
+    fn new(ctx: &PipelineContext) -> Self {
+        if let Some(config) = &ctx.delimited {
+            return LineNormalizer::Delimited {
+                reader: DelimitedReader::new(config.clone()),
+                record_start: None,
+            };
+        }
+        if ctx.input_mode == INPUT_MODE_ZEEK {
+            return LineNormalizer::Zeek(ZeekLogReader::default());
+        }
+        LineNormalizer::Lines
+    }
+
+    fn push_line(
+        &mut self,
+        line: String,
+        line_number: u64,
+        offset: u64,
+        source_path: &str,
+        handle: &mut impl FnMut(String, u64, u64) -> Result<(), String>,
+    ) -> Result<(), String> {
+        match self {
+            LineNormalizer::Lines => handle(line, line_number, offset),
+            LineNormalizer::Zeek(zeek) => match zeek.normalize_line(&line) {
+                Ok(Some(record)) => handle(record, line_number, offset),
+                Ok(None) => Ok(()),
+                Err(err) => {
+                    eprintln!(
+                        "Unable to read Zeek log line {} from {}: {}",
+                        line_number,
+                        source_label(source_path),
+                        err
+                    );
+                    Ok(())
+                }
+            },
+            LineNormalizer::Delimited {
+                reader,
+                record_start,
+            } => {
+                let (first_line, first_offset) = *record_start.get_or_insert((line_number, offset));
+                let normalized = reader.normalize_line(&line);
+                if !reader.in_quoted_cell() {
+                    *record_start = None;
+                }
+                match normalized {
+                    Ok(Some(record)) => handle(record, first_line, first_offset),
+                    Ok(None) => Ok(()),
+                    Err(err) => {
+                        eprintln!(
+                            "Unable to read delimited record at line {} from {}: {}",
+                            first_line,
+                            source_label(source_path),
+                            err
+                        );
+                        Ok(())
+                    }
+                }
             }
-        } else {
-            eprintln!("Unable to read TLSH distances due to poisoned lock");
         }
+    }
 
-        let unique_payload_count = match counter_unique_payloads.lock() {
-            Ok(unique_payloads) => unique_payloads.len(),
-            Err(err) => {
+    fn finish(&mut self, source_path: &str) {
+        if let LineNormalizer::Delimited {
+            reader,
+            record_start,
+        } = self
+        {
+            if let Err(err) = reader.finish() {
                 eprintln!(
-                    "Unable to read unique payload count due to poisoned lock: {}",
+                    "Unable to read delimited record at line {} from {}: {}",
+                    record_start.map_or(0, |(line, _)| line),
+                    source_label(source_path),
                     err
                 );
-                0
             }
+        }
+    }
+}
+
+enum InputRecord {
+    Line(String, RecordOrigin),
+    Blob(Vec<u8>, RecordOrigin),
+    // A capture packet or reassembled stream, already decoded, with its report fields.
+    Packet(Vec<u8>, Value, RecordOrigin),
+}
+
+fn handle_record(record: InputRecord, ctx: &PipelineContext) {
+    match record {
+        InputRecord::Line(line, origin) => handle_line(&line, &origin, ctx),
+        InputRecord::Blob(blob, origin) => handle_blob(blob.as_slice(), &origin, ctx),
+        InputRecord::Packet(payload, json_clone, origin) => {
+            process_decoded_payload(payload, json_clone, &origin, ctx)
+        }
+    }
+}
+
+// With a stream sender, records are pushed through the bounded channel to the
+// matcher workers; otherwise they are handled inline (stdin lines in parallel).
+fn submit_record(
+    record: InputRecord,
+    ctx: &PipelineContext,
+    stream: Option<&SyncSender<InputRecord>>,
+) -> Result<(), String> {
+    match stream {
+        Some(sender) => {
+            sender.send(record).map_err(|_| {
+                if let Some(progress) = &ctx.stream_progress {
+                    progress.matchers_stopped.store(true, Ordering::SeqCst);
+                }
+                "Stream matcher workers stopped unexpectedly".to_string()
+            })?;
+            if let Some(progress) = &ctx.stream_progress {
+                progress.records_submitted.fetch_add(1, Ordering::SeqCst);
+            }
+            Ok(())
+        }
+        None => {
+            handle_record(record, ctx);
+            Ok(())
+        }
+    }
+}
+
+fn ingest_inputs(
+    args: &ArgMatches,
+    ctx: &PipelineContext,
+    stream: Option<&SyncSender<InputRecord>>,
+) -> Result<(), String> {
+    if let Some(path) = args.get_one::<std::path::PathBuf>(FOLLOW) {
+        return follow_input(path, args, ctx, stream);
+    }
+    if let Some(spec) = args.get_one::<String>(LISTEN) {
+        return listen_input(spec, args, ctx, stream);
+    }
+    let capture_mode = capture_mode_enabled(ctx.input_mode);
+    let blob_mode = blob_mode_enabled(args, ctx.input_mode);
+    if let Some(path) = args.get_one::<std::path::PathBuf>(INPUT_FOLDER) {
+        let walk_config = WalkConfig {
+            recursive: args.get_flag(RECURSIVE),
+            max_depth: args.get_one::<usize>(MAX_DEPTH).copied(),
+            include: args
+                .get_many::<String>(INCLUDE)
+                .map(|globs| globs.cloned().collect())
+                .unwrap_or_default(),
+            exclude: args
+                .get_many::<String>(EXCLUDE)
+                .map(|globs| globs.cloned().collect())
+                .unwrap_or_default(),
+            follow_symlinks: args.get_flag(FOLLOW_SYMLINKS),
+            max_file_size: args.get_one::<u64>(MAX_FILE_SIZE).copied(),
         };
-        let input_mode = resolved_input_mode(&args);
-        let hash_function = args
-            .get_one::<String>(TLSH_ALGORITHM)
-            .map_or("48_1", String::as_str);
-        let distance_threshold = args.get_one::<i32>(TLSH_DISTANCE).copied().unwrap_or(100);
-        let input_json_key = args
-            .get_one::<String>(INPUT_JSON_KEY)
-            .map_or("", String::as_str);
-
-        // Create a JSON object for the stats
-        let stats = json!({
-            "---PRECURSOR_STATISTICS---": "This JSON is output to STDERR so that you can parse stats separate from the primary output.",
-            "Input": {
-                        "Count": counter_inputs.get(),
-                        "Unique": unique_payload_count,
-                        "AvgSize": format!("{:.0}", avg_payload_size),
-                        "MinSize": min_payload_size,
-                        "MaxSize": max_payload_size,
-                        "P95Size": p95_payload_size,
-                        "TotalSize": format_size(total_payload_size),},
-            "Match": {
-                        "Patterns": counter_pcre_patterns.get(),
-                        "TotalMatches": counter_pcre_matches_total.get(),
-                        "Matches": matches_json,
-                        "HashesGenerated": counter_tlsh_hashes.get(),
-                        "AvgSize": format!("{:.0}", avg_payload_size_matched),
-                        "MinSize": min_payload_size_matched,
-                        "MaxSize": max_payload_size_matched,
-                        "P95Size": p95_payload_size_matched,
-                        "TotalSize": format_size(total_payload_size_matched),},
-            "Compare": compare_json,
-            "Environment": {
-                        "Version": env!("CARGO_PKG_VERSION"),
-                        "DurationSeconds": formated_duration,
-                        "ProcessingRate": processing_rate,
-                        "SimilarityMode": similarity_mode.as_str(),
-                        "RegexEngine": regex_engine.as_str(),
-                        "InputMode": input_mode,
-                        "HashFunction": hash_function,
-                        "DistanceThreshold": distance_threshold,
-                        "DiffEnabled": args.get_flag(TLSH_DIFF),
-                        "OnlyOutputSimilar": args.get_flag(TLSH_SIM_ONLY),
-                        "LengthEnabled": args.get_flag(TLSH_LENGTH),
-                        "InputJSONKey": input_json_key,
-                        "SinglePacketInference": args.get_flag(SINGLE_PACKET),
-                        "AbstainThreshold": args.get_one::<f64>(ABSTAIN_THRESHOLD).copied().unwrap_or(0.65),
-                        "ProtocolTopK": args.get_one::<usize>(PROTOCOL_TOP_K).copied().unwrap_or(3),
-                        "SigmaRulesLoaded": sigma_rule_plans.len(),
-                        },
+        let walk = walk_folder(path, &walk_config)?;
+        for err in &walk.errors {
+            eprintln!("{}", err);
+        }
+        if walk.skipped_oversize > 0 {
+            eprintln!(
+                "Skipped {} files larger than --max-file-size",
+                walk.skipped_oversize
+            );
+        }
+        if walk.skipped_symlinks > 0 {
+            eprintln!(
+                "Skipped {} symbolic links (use --follow-symlinks to ingest them)",
+                walk.skipped_symlinks
+            );
+        }
+        if args.get_flag(PARALLEL_FILES) && stream.is_some() {
+            ingest_files_on_threads(&walk.files, capture_mode, blob_mode, ctx, stream)?;
+        } else if args.get_flag(PARALLEL_FILES) {
+            walk.files
+                .par_iter()
+                .try_for_each(|file| ingest_file(file, capture_mode, blob_mode, ctx, stream))?;
+        } else {
+            for file in &walk.files {
+                ingest_file(file, capture_mode, blob_mode, ctx, stream)?;
             }
-        );
+        }
+    } else {
+        let stdin = io::stdin();
+        let source = InputSource::new(STDIN_SOURCE_PATH);
+        if args.get_flag(DECOMPRESS) {
+            ingest_unpacked(
+                &mut stdin.lock(),
+                &source,
+                capture_mode,
+                blob_mode,
+                ctx,
+                stream,
+            )?;
+        } else if capture_mode {
+            handle_capture(stdin.lock(), &source, ctx, stream)?;
+        } else if blob_mode {
+            let mut blob = Vec::new();
+            let mut lock = stdin.lock();
+            lock.read_to_end(&mut blob)
+                .map_err(|err| format!("Unable to read blob from STDIN: {}", err))?;
+            let origin = next_record_origin(ctx, &source, None, Some(0));
+            submit_record(InputRecord::Blob(blob, origin), ctx, stream)?;
+        } else if stream.is_some() {
+            read_input_lines(
+                stdin.lock(),
+                &source.path,
+                ctx,
+                |line, line_number, offset| {
+                    let origin = next_record_origin(ctx, &source, Some(line_number), Some(offset));
+                    submit_record(InputRecord::Line(line, origin), ctx, stream)
+                },
+            )?;
+        } else {
+            let mut lines: Vec<(String, u64, u64)> = Vec::new();
+            read_input_lines(
+                stdin.lock(),
+                &source.path,
+                ctx,
+                |line, line_number, offset| {
+                    lines.push((line, line_number, offset));
+                    Ok(())
+                },
+            )?;
+            let first_index = ctx.counter_inputs.add(lines.len()) as u64 + 1;
+            lines
+                .par_iter()
+                .enumerate()
+                .for_each(|(position, (line, line_number, offset))| {
+                    let origin = RecordOrigin {
+                        input_index: first_index + position as u64,
+                        source: source.clone(),
+                        source_line: Some(*line_number),
+                        source_offset: Some(*offset),
+                    };
+                    handle_line(line, &origin, ctx);
+                });
+        }
+    }
+    Ok(())
+}
 
-        // Serialize the JSON object as a pretty-printed String
-        match serde_json::to_string_pretty(&stats) {
-            Ok(pretty_json) => {
-                let mut stderr = std::io::stderr();
-                if let Err(err) = writeln!(&mut stderr, "{}", pretty_json) {
-                    eprintln!("Error printing JSON to STDERR: {}", err);
-                    return;
+// Tails one growing file or FIFO through the streaming pipeline, checkpointing the read
+// offset after every batch and emitting --stats snapshots every --stats-interval.
+fn follow_input(
+    path: &std::path::Path,
+    args: &ArgMatches,
+    ctx: &PipelineContext,
+    stream: Option<&SyncSender<InputRecord>>,
+) -> Result<(), String> {
+    let mut follower = LogFollower::new(FollowConfig {
+        path: path.to_path_buf(),
+        state_path: args.get_one::<std::path::PathBuf>(FOLLOW_STATE).cloned(),
+        start: FollowStart::from_str(
+            args.get_one::<String>(FOLLOW_FROM)
+                .map_or("start", String::as_str),
+        )?,
+    })?;
+    let source = InputSource::new(&path.to_string_lossy());
+    let poll_interval = Duration::from_millis(
+        args.get_one::<u64>(FOLLOW_INTERVAL)
+            .copied()
+            .unwrap_or(500)
+            .max(1),
+    );
+    let idle_exit = args
+        .get_one::<u64>(FOLLOW_IDLE_EXIT)
+        .map(|seconds| Duration::from_secs(*seconds));
+    let stats_interval = args
+        .get_flag(STATS)
+        .then(|| Duration::from_secs(args.get_one::<u64>(STATS_INTERVAL).copied().unwrap_or(60)));
+    let mut normalizer = LineNormalizer::new(ctx);
+    let mut submit = |line: String, line_number: u64, offset: u64| {
+        let origin = next_record_origin(ctx, &source, Some(line_number), Some(offset));
+        submit_record(InputRecord::Line(line, origin), ctx, stream)
+    };
+    let mut last_data = Instant::now();
+    let mut last_snapshot = Instant::now();
+    let mut snapshot_index = 0u64;
+    loop {
+        let progressed = follower.poll(|event| match event {
+            FollowEvent::Line {
+                line,
+                line_number,
+                offset,
+            } => normalizer.push_line(line, line_number, offset, &source.path, &mut submit),
+            FollowEvent::Reopened { reason } => {
+                eprintln!("Reopening {} ({})", source.label(), reason);
+                normalizer.finish(&source.path);
+                normalizer = LineNormalizer::new(ctx);
+                Ok(())
+            }
+        })?;
+        if progressed {
+            wait_for_stream_writes(ctx);
+            follower.save_state()?;
+            last_data = Instant::now();
+        }
+        if stats_interval.is_some_and(|interval| last_snapshot.elapsed() >= interval) {
+            snapshot_index += 1;
+            let mut stats = build_stats(ctx.stats);
+            stats["Snapshot"] = json!(snapshot_index);
+            stats["Follow"] = follower.status();
+            emit_stats(&stats);
+            last_snapshot = Instant::now();
+        }
+        if idle_exit.is_some_and(|idle| last_data.elapsed() >= idle) {
+            break;
+        }
+        if !progressed {
+            std::thread::sleep(poll_interval);
+        }
+    }
+    normalizer.finish(&source.path);
+    wait_for_stream_writes(ctx);
+    follower.save_state()
+}
+
+// The follow offset covers every line read so far, so it is only saved once the
+// reports for those lines are out.
+fn wait_for_stream_writes(ctx: &PipelineContext) {
+    if let Some(progress) = &ctx.stream_progress {
+        progress.wait_until_written();
+    }
+}
+
+// Serves --listen clients until the process is stopped. Each TCP or Unix client is read
+// on its own thread and feeds the shared matcher workers; UDP datagrams are read here.
+fn listen_input(
+    spec: &str,
+    args: &ArgMatches,
+    ctx: &PipelineContext,
+    stream: Option<&SyncSender<InputRecord>>,
+) -> Result<(), String> {
+    let mut listener = Listener::bind(&ListenAddress::parse(spec)?)?;
+    let label = listener.label();
+    eprintln!("Listening on {}", label);
+    let framing = Framing::from_str(
+        args.get_one::<String>(LISTEN_FRAMING)
+            .map_or(LISTEN_FRAMING_LINE, String::as_str),
+    )?;
+    let reply = args.get_flag(LISTEN_REPLY);
+    let max_clients = args
+        .get_one::<usize>(LISTEN_MAX_CLIENTS)
+        .copied()
+        .unwrap_or(64)
+        .max(1);
+    // Snapshots are taken on a timer here, so they are rate-limited to once a second.
+    let stats_interval = args.get_flag(STATS).then(|| {
+        Duration::from_secs(
+            args.get_one::<u64>(STATS_INTERVAL)
+                .copied()
+                .unwrap_or(60)
+                .max(1),
+        )
+    });
+    let connections = ConsistentCounter::new(0);
+    let active = AtomicUsize::new(0);
+    // Clones of the open client connections, shut down when listening ends with an error
+    // so their reader threads do not keep the scope alive.
+    let clients: Mutex<HashMap<usize, Connection>> = Mutex::new(HashMap::new());
+    let close_clients = || {
+        if let Ok(clients) = clients.lock() {
+            for client in clients.values() {
+                client.shutdown();
+            }
+        }
+    };
+    std::thread::scope(|scope| {
+        // Never sent on: dropping it when this closure returns, on any path, stops the
+        // snapshot thread.
+        let (_stop_snapshots, snapshots_stopped) = channel::<()>();
+        if let Some(interval) = stats_interval {
+            let (label, connections, active) = (&label, &connections, &active);
+            scope.spawn(move || {
+                let mut snapshot_index = 0u64;
+                while let Err(RecvTimeoutError::Timeout) = snapshots_stopped.recv_timeout(interval)
+                {
+                    snapshot_index += 1;
+                    let mut stats = build_stats(ctx.stats);
+                    stats["Snapshot"] = json!(snapshot_index);
+                    stats["Listen"] = json!({
+                        "Address": label,
+                        "Connections": connections.get(),
+                        "ActiveConnections": active.load(Ordering::SeqCst),
+                    });
+                    emit_stats(&stats);
                 }
-                if let Err(err) = stderr.flush() {
-                    eprintln!("Error flushing STDERR buffer: {}", err);
+            });
+        }
+        if let Listener::Udp(socket) = &listener {
+            return serve_datagrams(socket, ctx, stream);
+        }
+        loop {
+            let (connection, peer) = match listener.accept() {
+                Ok(accepted) => accepted,
+                Err(err) => {
+                    eprintln!("{}", err);
+                    continue;
                 }
+            };
+            if ctx
+                .stream_progress
+                .as_ref()
+                .is_some_and(|progress| progress.matchers_stopped.load(Ordering::SeqCst))
+            {
+                close_clients();
+                return Err("Stream matcher workers stopped unexpectedly".to_string());
             }
-            Err(err) => {
+            let client = connections.inc();
+            if active.load(Ordering::SeqCst) >= max_clients {
                 eprintln!(
-                    "Error converting JSON object to pretty-printed String: {}",
-                    err
+                    "Rejecting {}: --{} {} reached",
+                    peer, LISTEN_MAX_CLIENTS, max_clients
                 );
+                continue;
+            }
+            active.fetch_add(1, Ordering::SeqCst);
+            if let (Ok(clone), Ok(mut clients)) = (connection.try_clone(), clients.lock()) {
+                clients.insert(client, clone);
             }
+            let (active, clients) = (&active, &clients);
+            scope.spawn(move || {
+                if let Err(err) = serve_connection(connection, &peer, framing, reply, ctx, stream) {
+                    eprintln!("Closing {}: {}", peer, err);
+                }
+                if let Ok(mut clients) = clients.lock() {
+                    clients.remove(&client);
+                }
+                active.fetch_sub(1, Ordering::SeqCst);
+            });
+        }
+    })
+}
+
+// Reads one client's records until it closes its side. With --listen-reply, the client's
+// reports are written back on the same connection, which is shut down for writing once
+// every record it sent has been matched.
+fn serve_connection(
+    connection: Connection,
+    peer: &str,
+    framing: Framing,
+    reply: bool,
+    ctx: &PipelineContext,
+    stream: Option<&SyncSender<InputRecord>>,
+) -> Result<(), String> {
+    let mut source = InputSource::new(peer);
+    let writer = if reply {
+        let mut reply_connection = connection
+            .try_clone()
+            .map_err(|err| format!("Unable to open reply channel: {}", err))?;
+        let buffer = ctx
+            .args
+            .get_one::<usize>(STREAM_BUFFER)
+            .copied()
+            .unwrap_or(1024)
+            .max(1);
+        let (sender, receiver) = sync_channel::<Value>(buffer);
+        source.reply = Some(ReplySink {
+            sender,
+            connection: Arc::new(
+                connection
+                    .try_clone()
+                    .map_err(|err| format!("Unable to open reply channel: {}", err))?,
+            ),
+            peer: Arc::from(peer),
+            disconnected: Arc::new(AtomicBool::new(false)),
+        });
+        let peer = peer.to_string();
+        Some(std::thread::spawn(move || {
+            for report in receiver {
+                let written =
+                    to_string(&report)
+                        .map_err(|err| err.to_string())
+                        .and_then(|serialized| {
+                            writeln!(reply_connection, "{}", serialized)
+                                .map_err(|err| err.to_string())
+                        });
+                if let Err(err) = written {
+                    eprintln!("Unable to write report to {}: {}", peer, err);
+                    break;
+                }
+            }
+            reply_connection.shutdown_write();
+        }))
+    } else {
+        None
+    };
+    let result = match framing {
+        Framing::Line => read_input_lines(
+            std::io::BufReader::new(LineLimit::new(connection, MAX_LINE_BYTES)),
+            peer,
+            ctx,
+            |line, line_number, offset| {
+                let origin = next_record_origin(ctx, &source, Some(line_number), Some(offset));
+                submit_record(InputRecord::Line(line, origin), ctx, stream)
+            },
+        ),
+        Framing::Length => read_length_frames(
+            &mut std::io::BufReader::new(connection),
+            &source,
+            ctx,
+            stream,
+        ),
+    };
+    // The reply writer stops once the records still in flight have dropped their senders.
+    drop(source);
+    if let Some(writer) = writer {
+        if writer.join().is_err() {
+            eprintln!("Reply writer for {} panicked", peer);
+        }
+    }
+    result
+}
+
+// Length-prefixed frames are numbered like lines, and offsets point at each frame's prefix.
+fn read_length_frames<R: Read>(
+    reader: &mut R,
+    source: &InputSource,
+    ctx: &PipelineContext,
+    stream: Option<&SyncSender<InputRecord>>,
+) -> Result<(), String> {
+    let blob_mode = blob_mode_enabled(ctx.args, ctx.input_mode);
+    let mut normalizer = LineNormalizer::new(ctx);
+    let mut submit = |line: String, frame_number: u64, offset: u64| {
+        let origin = next_record_origin(ctx, source, Some(frame_number), Some(offset));
+        submit_record(InputRecord::Line(line, origin), ctx, stream)
+    };
+    let mut frame_number = 0u64;
+    let mut offset = 0u64;
+    while let Some(frame) = read_length_frame(reader, MAX_FRAME_BYTES)? {
+        frame_number += 1;
+        let frame_offset = offset;
+        offset += 4 + frame.len() as u64;
+        if blob_mode {
+            let origin = next_record_origin(ctx, source, Some(frame_number), Some(frame_offset));
+            submit_record(InputRecord::Blob(frame, origin), ctx, stream)?;
+        } else {
+            normalizer.push_line(
+                String::from_utf8_lossy(&frame).into_owned(),
+                frame_number,
+                frame_offset,
+                &source.path,
+                &mut submit,
+            )?;
+        }
+    }
+    normalizer.finish(&source.path);
+    Ok(())
+}
+
+// Each datagram is one blob, or a small newline-delimited source of its own.
+fn serve_datagrams(
+    socket: &UdpSocket,
+    ctx: &PipelineContext,
+    stream: Option<&SyncSender<InputRecord>>,
+) -> Result<(), String> {
+    let blob_mode = blob_mode_enabled(ctx.args, ctx.input_mode);
+    let mut buffer = vec![0u8; 65536];
+    loop {
+        let (length, peer) = match socket.recv_from(&mut buffer) {
+            Ok(received) => received,
+            Err(err) => {
+                eprintln!("Unable to receive UDP datagram: {}", err);
+                continue;
+            }
+        };
+        let source = InputSource::new(&format!("udp://{}", peer));
+        let datagram = &buffer[..length];
+        if blob_mode {
+            let origin = next_record_origin(ctx, &source, None, None);
+            submit_record(InputRecord::Blob(datagram.to_vec(), origin), ctx, stream)?;
+        } else {
+            read_input_lines(datagram, &source.path, ctx, |line, line_number, offset| {
+                let origin = next_record_origin(ctx, &source, Some(line_number), Some(offset));
+                submit_record(InputRecord::Line(line, origin), ctx, stream)
+            })?;
         }
     }
 }
 
+// In stream mode the matchers run on the rayon pool behind the bounded record channel,
+// so readers that block on a full channel get their own threads instead of rayon workers.
+fn ingest_files_on_threads(
+    files: &[WalkedFile],
+    capture_mode: bool,
+    blob_mode: bool,
+    ctx: &PipelineContext,
+    stream: Option<&SyncSender<InputRecord>>,
+) -> Result<(), String> {
+    let next_file = AtomicUsize::new(0);
+    let readers = std::thread::available_parallelism()
+        .map_or(1, |readers| readers.get())
+        .min(files.len());
+    std::thread::scope(|scope| {
+        let handles: Vec<_> = (0..readers)
+            .map(|_| {
+                scope.spawn(|| loop {
+                    let Some(file) = files.get(next_file.fetch_add(1, Ordering::Relaxed)) else {
+                        return Ok(());
+                    };
+                    if let Err(err) = ingest_file(file, capture_mode, blob_mode, ctx, stream) {
+                        next_file.store(files.len(), Ordering::Relaxed);
+                        return Err(err);
+                    }
+                })
+            })
+            .collect();
+        handles.into_iter().try_for_each(|handle| {
+            handle
+                .join()
+                .unwrap_or_else(|_| Err("File reader thread panicked".to_string()))
+        })
+    })
+}
+
+fn ingest_file(
+    file: &WalkedFile,
+    capture_mode: bool,
+    blob_mode: bool,
+    ctx: &PipelineContext,
+    stream: Option<&SyncSender<InputRecord>>,
+) -> Result<(), String> {
+    let mut source = InputSource::new(&file.path.display().to_string());
+    source.relative_path = Some(Arc::from(file.relative_path.as_str()));
+    let mut reader = match std::fs::File::open(&file.path) {
+        Ok(reader) => reader,
+        Err(err) => {
+            eprintln!("Unable to open file {}: {}", file.path.display(), err);
+            return Ok(());
+        }
+    };
+    if ctx.args.get_flag(DECOMPRESS) {
+        ingest_unpacked(&mut reader, &source, capture_mode, blob_mode, ctx, stream)
+    } else {
+        ingest_reader(&mut reader, &source, capture_mode, blob_mode, ctx, stream)
+    }
+}
+
+// Feeds every member of a compressed or archived input through `ingest_reader`,
+// tagging its records with the member path and the containers that were peeled.
+fn ingest_unpacked(
+    reader: &mut dyn Read,
+    source: &InputSource,
+    capture_mode: bool,
+    blob_mode: bool,
+    ctx: &PipelineContext,
+    stream: Option<&SyncSender<InputRecord>>,
+) -> Result<(), String> {
+    let max_member_bytes = *ctx.args.get_one::<u64>(DECOMPRESS_MAX_BYTES).unwrap_or(&0);
+    let mut ingest_error = None;
+    let unpacked = visit_members(reader, max_member_bytes, &mut |member, member_reader| {
+        let member_source = source.with_member(member);
+        ingest_reader(
+            member_reader,
+            &member_source,
+            capture_mode,
+            blob_mode,
+            ctx,
+            stream,
+        )
+        .inspect_err(|err| ingest_error = Some(err.clone()))
+    });
+    if let Some(err) = ingest_error {
+        return Err(err);
+    }
+    if let Err(err) = unpacked {
+        eprintln!("Unable to unpack {}: {}", source.label(), err);
+    }
+    Ok(())
+}
+
+// Keeps the per-source semantics of flat folder ingest: captures are decoded frame by
+// frame, blob modes submit the whole source, and everything else is read line by line.
+fn ingest_reader(
+    reader: &mut dyn Read,
+    source: &InputSource,
+    capture_mode: bool,
+    blob_mode: bool,
+    ctx: &PipelineContext,
+    stream: Option<&SyncSender<InputRecord>>,
+) -> Result<(), String> {
+    if capture_mode {
+        return handle_capture(std::io::BufReader::new(reader), source, ctx, stream);
+    }
+
+    if blob_mode {
+        let mut blob = Vec::new();
+        if let Err(err) = reader.read_to_end(&mut blob) {
+            eprintln!("Unable to read blob from {}: {}", source.label(), err);
+            return Ok(());
+        }
+        let origin = next_record_origin(ctx, source, None, Some(0));
+        return submit_record(InputRecord::Blob(blob, origin), ctx, stream);
+    }
+
+    read_input_lines(
+        std::io::BufReader::new(reader),
+        &source.path,
+        ctx,
+        |line, line_number, offset| {
+            let origin = next_record_origin(ctx, source, Some(line_number), Some(offset));
+            submit_record(InputRecord::Line(line, origin), ctx, stream)
+        },
+    )
+}
+
+struct CaptureSpan<'a> {
+    tag: &'a str,
+    pattern_index: usize,
+    start: usize,
+    end: usize,
+}
+
+fn match_offsets(spans: &[CaptureSpan]) -> Value {
+    Value::Array(
+        spans
+            .iter()
+            .map(|span| {
+                json!({
+                    "tag": span.tag,
+                    "start": span.start,
+                    "end": span.end,
+                })
+            })
+            .collect(),
+    )
+}
+
+// Annotations such as `decode_depth` or `slot` name the auto-decoded layer or JSON slot
+// the span offsets index into.
+fn insert_captures(
+    captures: &mut Map<String, Value>,
+    spans: &[CaptureSpan],
+    payload: &[u8],
+    binary_encoding: &str,
+    annotations: &[(&str, Value)],
+) {
+    for span in spans {
+        let end = span.end.min(span.start + MAX_CAPTURE_VALUE_BYTES);
+        let (value, encoding) = encode_capture_value(&payload[span.start..end], binary_encoding);
+        let mut capture = json!({
+            "value": value,
+            "encoding": encoding,
+            "start": span.start,
+            "end": span.end,
+            "pattern_index": span.pattern_index,
+        });
+        if end < span.end {
+            capture["truncated"] = Value::Bool(true);
+        }
+        for (key, value) in annotations {
+            capture[*key] = value.clone();
+        }
+        if let Value::Array(values) = captures
+            .entry(span.tag.to_string())
+            .or_insert_with(|| Value::Array(Vec::new()))
+        {
+            values.push(capture);
+        }
+    }
+}
+
+fn with_single_occurrence(
+    mut report: Value,
+    source_record: Value,
+    origin: &RecordOrigin,
+    max_refs: usize,
+) -> Value {
+    let mut source_ref = Map::new();
+    source_ref.insert("input_index".to_string(), json!(origin.input_index));
+    origin.insert_provenance(&mut source_ref);
+    if source_record
+        .as_object()
+        .is_some_and(|record| !record.is_empty())
+    {
+        source_ref.insert("record".to_string(), source_record);
+    }
+    report["occurrence_count"] = json!(1);
+    report["first_seen_input"] = json!(origin.input_index);
+    report["last_seen_input"] = json!(origin.input_index);
+    report["occurrences"] = if max_refs > 0 {
+        json!([source_ref])
+    } else {
+        json!([])
+    };
+    report
+}
+
+// Folds another occurrence of the same payload into its canonical report. The
+// earliest input wins as the canonical body and only the earliest refs are kept,
+// so the result does not depend on worker scheduling.
+fn merge_occurrences(existing: &mut Value, incoming: Value, max_refs: usize) {
+    let existing_first = existing["first_seen_input"].as_u64().unwrap_or(u64::MAX);
+    let incoming_first = incoming["first_seen_input"].as_u64().unwrap_or(u64::MAX);
+    let occurrence_count = existing["occurrence_count"].as_u64().unwrap_or(1)
+        + incoming["occurrence_count"].as_u64().unwrap_or(1);
+    let last_seen = existing["last_seen_input"]
+        .as_u64()
+        .unwrap_or(0)
+        .max(incoming["last_seen_input"].as_u64().unwrap_or(0));
+    let mut occurrences: Vec<Value> = existing["occurrences"]
+        .as_array()
+        .into_iter()
+        .chain(incoming["occurrences"].as_array())
+        .flatten()
+        .cloned()
+        .collect();
+    occurrences.sort_by_key(|source_ref| source_ref["input_index"].as_u64().unwrap_or(u64::MAX));
+    occurrences.truncate(max_refs);
+
+    if incoming_first < existing_first {
+        *existing = incoming;
+    }
+    existing["occurrence_count"] = json!(occurrence_count);
+    existing["first_seen_input"] = json!(existing_first.min(incoming_first));
+    existing["last_seen_input"] = json!(last_seen);
+    existing["occurrences"] = Value::Array(occurrences);
+}
+
 // Unpacks the reports from the shared mutex
 // and performs TLSH hash lookups for the matches from the tlsh in the payload report./
 fn emit_report(report: &Value) {
@@ -827,6 +2662,59 @@ fn emit_report(report: &Value) {
     }
 }
 
+fn transport_evidence(report: &Value) -> Vec<String> {
+    let mut evidence = Vec::new();
+    let packet = &report["packet"];
+    if packet["fragmented"].as_bool() == Some(true) {
+        evidence.push(format!(
+            "reassembled from {} IP fragments",
+            packet["fragment_count"].as_u64().unwrap_or(0)
+        ));
+        let overlap_bytes = packet["fragment_overlap_bytes"].as_u64().unwrap_or(0);
+        if overlap_bytes > 0 {
+            evidence.push(format!(
+                "IP fragment overlap anomaly: {} overlapping bytes, {} conflicting",
+                overlap_bytes,
+                packet["fragment_conflicting_bytes"].as_u64().unwrap_or(0)
+            ));
+        }
+    }
+    let stream = &report["stream"];
+    let fragmented_segments = stream["fragmented_segments"].as_u64().unwrap_or(0);
+    if fragmented_segments > 0 {
+        evidence.push(format!(
+            "{} TCP segments reassembled from IP fragments",
+            fragmented_segments
+        ));
+    }
+    let overlap_conflicts = stream["overlap_conflicts"].as_u64().unwrap_or(0);
+    if overlap_conflicts > 0 {
+        evidence.push(format!(
+            "TCP segment overlap anomaly: {} conflicting overlaps",
+            overlap_conflicts
+        ));
+    }
+    evidence
+}
+
+// Streaming mode cannot attach neighbours to records that were already written,
+// so each similarity hash with neighbours gets its own cluster record instead.
+fn emit_similarity_clusters(tlsh_reports: &DashMap<String, Value>) {
+    for entry in tlsh_reports.iter() {
+        let neighbor_count = entry.value().as_object().map_or(0, Map::len);
+        if neighbor_count == 0 {
+            continue;
+        }
+        emit_report(&json!({
+            "similarity_cluster": {
+                "similarity_hash": entry.key(),
+                "neighbor_count": neighbor_count,
+                "tlsh_similarities": entry.value(),
+            }
+        }));
+    }
+}
+
 fn apply_similarity_neighbor_boost(
     report: &mut Value,
     neighbor_count: usize,
@@ -907,14 +2795,13 @@ fn generate_reports(
             return;
         }
     };
-    for (xxh3_64_sum, report) in payload_reports_guard.iter() {
+    for report in payload_reports_guard.values() {
         let similarity_hash = report["similarity_hash"]
             .as_str()
             .or_else(|| report["tlsh"].as_str())
             .unwrap_or("");
         if !similarity_hash.is_empty() && args.get_flag(TLSH_DIFF) {
             let mut report_clone = report.clone();
-            report_clone["xxh3_64_sum"] = json!(xxh3_64_sum.as_str());
             if let Some(tlsh_similarities) = tlsh_reports.get(similarity_hash) {
                 report_clone["tlsh_similarities"] = tlsh_similarities.value().clone();
                 if args.get_flag(SINGLE_PACKET) {
@@ -941,9 +2828,7 @@ fn generate_reports(
             }
         } else if !args.get_flag(TLSH_SIM_ONLY) {
             // Print reports empty TLSH hashes
-            let mut report_clone = report.clone();
-            report_clone["xxh3_64_sum"] = json!(xxh3_64_sum.as_str());
-            emit_report(&report_clone);
+            emit_report(report);
         }
     }
 }
@@ -970,7 +2855,7 @@ fn emit_protocol_hints(
         }
     };
 
-    for (xxh3_64_sum, report) in payload_reports_guard.iter() {
+    for report in payload_reports_guard.values() {
         let similarity_hash = report["similarity_hash"]
             .as_str()
             .or_else(|| report["tlsh"].as_str())
@@ -998,7 +2883,7 @@ fn emit_protocol_hints(
         candidates.push((
             neighbor_count,
             json!({
-                "xxh3_64_sum": xxh3_64_sum.as_str(),
+                "xxh3_64_sum": report["xxh3_64_sum"],
                 "similarity_hash": similarity_hash,
                 "neighbor_count": neighbor_count,
                 "tags": tags,
@@ -1105,11 +2990,9 @@ fn run_hash_diffs(
         });
 }
 
-fn decode_payload_from_json_expression(
-    raw_json: &str,
-    payload_key: &str,
-    input_mode: &str,
-) -> Result<(Vec<u8>, Value), String> {
+// Parses one JSON input record, returning it alongside the report wrapper it seeds
+// (non-object records are wrapped as `{"input": ...}`).
+fn parse_json_record(raw_json: &str) -> Result<(Value, Value), String> {
     let line_json: Value =
         from_str(raw_json).map_err(|err| format!("Unable to parse input as JSON: {}", err))?;
 
@@ -1120,153 +3003,606 @@ fn decode_payload_from_json_expression(
         wrapped.insert("input".to_string(), line_json.clone());
         Value::Object(wrapped)
     };
+    Ok((line_json, json_clone))
+}
+
+// Runs a jaq filter over a parsed record and decodes every output with the -m mode.
+// A decoded filter output and its position among the filter's outputs.
+type FilterPayload = (usize, Vec<u8>);
+
+fn run_json_filter(
+    record: &Value,
+    payload_key: &str,
+    input_mode: &str,
+) -> Result<Vec<FilterPayload>, String> {
+    let defs = Definitions::core();
+    let mut errs = Vec::new();
+    let Some(parsed_filter) = parse::parse(payload_key, parse::main()).0 else {
+        return Err(format!(
+            "Unable to parse JSON key expression: {:?}",
+            payload_key
+        ));
+    };
+    let f = defs.finish(parsed_filter, Vec::new(), &mut errs);
+    if !errs.is_empty() {
+        return Err(format!(
+            "Unable to compile JSON key expression {:?}: {:?}",
+            payload_key, errs
+        ));
+    }
+
+    let inputs = RcIter::new(core::iter::empty());
+    let out = f.run(Ctx::new([], &inputs), Val::from(record.clone()));
+    // Outputs keep their position in the filter output, so a null or undecodable one
+    // is skipped without renumbering or dropping the others.
+    let mut payloads = Vec::new();
+    for (index, output) in out.enumerate() {
+        match output {
+            Ok(Val::Null) => {}
+            Ok(v) => match get_payload(&v.to_string(), input_mode) {
+                Ok(payload) => payloads.push((index, payload)),
+                Err(err) => eprintln!(
+                    "Unable to decode payload {} from JSON key {:?}: {}",
+                    index, payload_key, err
+                ),
+            },
+            Err(e) => {
+                return Err(format!(
+                    "Unable to parse JSON pattern: {:?} with error: {:?}",
+                    payload_key, e
+                ));
+            }
+        }
+    }
+    Ok(payloads)
+}
+
+fn decode_payload_from_json_expression(
+    raw_json: &str,
+    payload_key: &str,
+    input_mode: &str,
+) -> Result<(Vec<FilterPayload>, Value), String> {
+    let (line_json, json_clone) = parse_json_record(raw_json)?;
+    let payloads = run_json_filter(&line_json, payload_key, input_mode)?;
+    if payloads.is_empty() {
+        return Err(format!(
+            "No valid JSON was found for pattern: {:?}",
+            payload_key
+        ));
+    }
+    Ok((payloads, json_clone))
+}
+
+struct PayloadMatches<'a> {
+    tags: Vec<String>,
+    standard: bool,
+    sigma_pattern: bool,
+    // `match_errors` entries: PCRE2 errors (usually tripped limits) and budget overruns.
+    errors: Vec<Value>,
+    // Every named capture of the patterns that matched, when spans were asked for.
+    spans: Vec<CaptureSpan<'a>>,
+    // `(pattern_index, tag)` for every tag that fired, counted once per payload.
+    pattern_tags: Vec<(usize, String)>,
+}
+
+impl PayloadMatches<'_> {
+    fn merge_pattern_tags(&mut self, pattern_tags: Vec<(usize, String)>) {
+        for pattern_tag in pattern_tags {
+            if !self.pattern_tags.contains(&pattern_tag) {
+                self.pattern_tags.push(pattern_tag);
+            }
+        }
+    }
+}
+
+// Records a PCRE2 error for `match_errors` and the per-pattern tally, warning once per
+// pattern and again when it gets disabled.
+fn pattern_error(index: usize, err: &pcre2::Error, ctx: &PipelineContext) -> Value {
+    let kind = match_error_kind(err.code());
+    let pattern = compact_pattern(&ctx.patterns[index].source);
+    match ctx.pattern_health.record_error(index) {
+        ErrorOutcome::First => eprintln!(
+            "Pattern {} '{}' failed with {}: {}",
+            index, pattern, kind, err
+        ),
+        ErrorOutcome::Disabled => eprintln!(
+            "Disabling pattern {} '{}' after repeated match errors (--{})",
+            index, pattern, MAX_PATTERN_ERRORS
+        ),
+        ErrorOutcome::Repeated => {}
+    }
+    json!({
+        "pattern_index": index,
+        "error": kind,
+        "message": err.to_string(),
+    })
+}
+
+// Runs every pattern over one payload (or one slot or decoded layer of it); the match
+// counters are left to record_match_stats, once the whole payload is matched. With `record_spans`, matched patterns keep iterating to
+// collect every capture span (capped so a pathological blob cannot produce an unbounded
+// report) in the same pass, under the same budget.
+fn match_payload<'a>(
+    payload: &[u8],
+    ctx: &PipelineContext<'a>,
+    record_spans: bool,
+) -> PayloadMatches<'a> {
+    let started = Instant::now();
+    let over_budget = || {
+        ctx.payload_budget
+            .is_some_and(|budget| started.elapsed() > budget)
+    };
+    let mut matched_tag_names: Vec<String> = Vec::new();
+    let mut standard_match_exists = false;
+    let mut sigma_pattern_match_exists = false;
+    let mut errors = Vec::new();
+    let mut spans = Vec::new();
+    let mut pattern_tags = Vec::new();
+
+    // Vectorscan compiles in prefilter mode and never misses a match of a pattern it
+    // compiled, so unflagged accelerated patterns are skipped; if the scan fails every
+    // pattern runs through PCRE2.
+    let candidates = ctx.vectorscan.and_then(|set| {
+        let mut candidates = vec![false; ctx.patterns.len()];
+        match set.scan(payload, &mut candidates) {
+            Ok(()) => Some(candidates),
+            Err(err) => {
+                eprintln!("Vectorscan prefilter skipped: {}", err);
+                None
+            }
+        }
+    });
+    let literal_candidates = ctx.prefilter.map(|prefilter| prefilter.candidates(payload));
+    let budget_error = |skipped_from: usize| {
+        ctx.pattern_health.record_budget_exceeded();
+        json!({
+            "error": "payload_budget",
+            "budget_ms": ctx.payload_budget.map_or(0, |budget| budget.as_millis()),
+            "skipped_patterns": (skipped_from..ctx.patterns.len())
+                .filter(|index| !ctx.pattern_health.is_disabled(*index))
+                .count(),
+        })
+    };
+    'patterns: for (index, compiled) in ctx.patterns.iter().enumerate() {
+        if ctx.pattern_health.is_disabled(index) {
+            continue;
+        }
+        if over_budget() {
+            errors.push(budget_error(index));
+            break;
+        }
+        if compiled.accelerated
+            && candidates
+                .as_ref()
+                .is_some_and(|candidates| !candidates[index])
+        {
+            continue;
+        }
+        if literal_candidates
+            .as_ref()
+            .is_some_and(|candidates| !candidates[index])
+        {
+            continue;
+        }
+        let mut result = false;
+        for caps in compiled.regex.captures_iter(payload) {
+            // Only span collection continues past the first match; cut it off, and the
+            // patterns not run yet, once the budget is spent.
+            if result && over_budget() {
+                errors.push(budget_error(index + 1));
+                match compiled.origin {
+                    PatternOrigin::Standard => standard_match_exists = true,
+                    PatternOrigin::Sigma => sigma_pattern_match_exists = true,
+                }
+                break 'patterns;
+            }
+            let caps = match caps {
+                Ok(caps) => caps,
+                Err(err) => {
+                    errors.push(pattern_error(index, &err, ctx));
+                    break;
+                }
+            };
+            if record_spans {
+                for name in &compiled.tags {
+                    if let Some(capture) = caps.name(name) {
+                        if spans.len() < MAX_CAPTURE_SPANS {
+                            spans.push(CaptureSpan {
+                                tag: name.as_str(),
+                                pattern_index: index,
+                                start: capture.start(),
+                                end: capture.end(),
+                            });
+                        }
+                    }
+                }
+            }
+            if result {
+                if spans.len() == MAX_CAPTURE_SPANS {
+                    break;
+                }
+                continue;
+            }
+            let mut found_match = false;
+            for name in &compiled.tags {
+                if caps.name(name).is_some() {
+                    matched_tag_names.push(name.clone());
+                    pattern_tags.push((index, name.clone()));
+                    found_match = true;
+                }
+            }
+            if found_match {
+                result = true;
+                if !record_spans {
+                    break;
+                }
+            }
+        }
+        if result {
+            match compiled.origin {
+                PatternOrigin::Standard => standard_match_exists = true,
+                PatternOrigin::Sigma => sigma_pattern_match_exists = true,
+            }
+        }
+    }
+    PayloadMatches {
+        tags: matched_tag_names,
+        standard: standard_match_exists,
+        sigma_pattern: sigma_pattern_match_exists,
+        errors,
+        spans,
+        pattern_tags,
+    }
+}
+
+// One count per pattern and per tag that fired anywhere in the payload, however many
+// of its slots or decoded layers matched.
+fn record_match_stats(payload: &[u8], pattern_tags: &[(usize, String)], ctx: &PipelineContext) {
+    let mut patterns: Vec<usize> = pattern_tags.iter().map(|(index, _)| *index).collect();
+    patterns.sort_unstable();
+    patterns.dedup();
+    for _ in &patterns {
+        if ctx.args.get_flag(STATS) {
+            if let Ok(mut payload_sizes_matched) = ctx.vec_payload_size_matched.lock() {
+                payload_sizes_matched.push(payload.len() as i64);
+            } else {
+                eprintln!("Unable to record matched payload size due to poisoned lock");
+            }
+        }
+        ctx.counter_pcre_matches_total.inc();
+    }
+    for (_, tag) in pattern_tags {
+        // Here we increment a counter for each of the capture group names from the PCRE2 patterns.
+        *ctx.counter_pcre_matches.entry(tag.clone()).or_insert(0) += 1;
+    }
+}
+
+// With --carve, embedded objects found in the payload are matched and reported as
+// children of the payload after it has been reported itself.
+fn process_decoded_payload(
+    payload: Vec<u8>,
+    json_clone: Value,
+    origin: &RecordOrigin,
+    ctx: &PipelineContext,
+) {
+    if !ctx.args.get_flag(CARVE) {
+        report_payload(payload, json_clone, origin, ctx);
+        return;
+    }
+    let max_objects = ctx
+        .args
+        .get_one::<usize>(CARVE_MAX_OBJECTS)
+        .copied()
+        .unwrap_or(64);
+    let carved = carve_objects(&payload, max_objects);
+    if carved.is_empty() {
+        report_payload(payload, json_clone, origin, ctx);
+        return;
+    }
+    let (_, parent_xxh3_64_sum) = xxh3_64_hex(payload.clone());
+    let children: Vec<(Vec<u8>, Value)> = carved
+        .iter()
+        .map(|object| {
+            let mut child_json = json_clone.clone();
+            child_json["carved"] = json!({
+                "parent_xxh3_64_sum": parent_xxh3_64_sum,
+                "signature": object.signature,
+                "offset": object.offset,
+                "length": object.length,
+            });
+            (
+                payload[object.offset..object.offset + object.length].to_vec(),
+                child_json,
+            )
+        })
+        .collect();
+    report_payload(payload, json_clone, origin, ctx);
+    for (child_payload, child_json) in children {
+        report_payload(child_payload, child_json, origin, ctx);
+    }
+}
+
+// Each chunk is matched and hashed as its own payload so similarity clustering and -d
+// work at region granularity; matching chunks roll up into one summary per blob.
+fn process_chunks(
+    payload: Vec<u8>,
+    json_clone: Value,
+    config: &ChunkConfig,
+    origin: &RecordOrigin,
+    ctx: &PipelineContext,
+) {
+    let (_, parent_xxh3_64_sum) = xxh3_64_hex(payload.clone());
+    let ranges = chunk_ranges(&payload, config);
+    let chunk_results: Vec<Option<Value>> = ranges
+        .par_iter()
+        .enumerate()
+        .map(|(index, (start, end))| {
+            let chunk = payload[*start..*end].to_vec();
+            let (_, chunk_xxh3_64_sum) = xxh3_64_hex(chunk.clone());
+            let mut chunk_json = json_clone.clone();
+            chunk_json["chunk"] = json!({
+                "parent_xxh3_64_sum": parent_xxh3_64_sum,
+                "mode": config.mode.as_str(),
+                "index": index,
+                "offset": start,
+                "length": end - start,
+            });
+            report_payload(chunk, chunk_json, origin, ctx).map(|tags| {
+                json!({
+                    "index": index,
+                    "offset": start,
+                    "length": end - start,
+                    "xxh3_64_sum": chunk_xxh3_64_sum,
+                    "tags": tags,
+                })
+            })
+        })
+        .collect();
+
+    let matched_chunks: Vec<Value> = chunk_results.into_iter().flatten().collect();
+    if matched_chunks.is_empty() {
+        return;
+    }
+    let mut tags: Vec<Value> = Vec::new();
+    for tag in matched_chunks
+        .iter()
+        .filter_map(|chunk| chunk["tags"].as_array())
+        .flatten()
+    {
+        if !tags.contains(tag) {
+            tags.push(tag.clone());
+        }
+    }
+    let mut summary = Map::new();
+    summary.insert("xxh3_64_sum".to_string(), json!(parent_xxh3_64_sum));
+    origin.insert_provenance(&mut summary);
+    summary.insert("chunk_mode".to_string(), json!(config.mode.as_str()));
+    summary.insert("chunk_size".to_string(), json!(config.size));
+    if config.mode == ChunkMode::Fixed {
+        summary.insert("chunk_overlap".to_string(), json!(config.overlap));
+    }
+    summary.insert("chunk_count".to_string(), json!(ranges.len()));
+    summary.insert(
+        "matched_chunk_count".to_string(),
+        json!(matched_chunks.len()),
+    );
+    summary.insert("tags".to_string(), Value::Array(tags));
+    summary.insert(
+        "matched_chunks".to_string(),
+        Value::Array(
+            matched_chunks
+                .into_iter()
+                .take(MAX_SUMMARY_CHUNKS)
+                .collect(),
+        ),
+    );
+    let summary = json!({ "chunk_summary": summary });
+
+    if streams_reports(ctx, origin) {
+        stream_report(summary, "chunk summary", origin, ctx);
+    } else if let Ok(mut reports) = ctx.payload_reports.lock() {
+        reports.insert(
+            format!(
+                "chunk_summary:{}:{}",
+                parent_xxh3_64_sum, origin.input_index
+            ),
+            summary,
+        );
+    } else {
+        eprintln!("Unable to record chunk summary due to poisoned lock");
+    }
+}
 
-    let defs = Definitions::core();
-    let mut errs = Vec::new();
-    let Some(parsed_filter) = parse::parse(payload_key, parse::main()).0 else {
-        return Err(format!(
-            "Unable to parse JSON key expression: {:?}",
-            payload_key
-        ));
+// Whether reports for this record are streamed rather than collected for the end of the run.
+fn streams_reports(ctx: &PipelineContext, origin: &RecordOrigin) -> bool {
+    origin.source.reply.is_some() || ctx.report_sink.is_some()
+}
+
+// Sends a streamed report back to the --listen-reply client it came from, or to the
+// stream writer, where it is counted for --follow checkpoints.
+fn stream_report(report: Value, kind: &str, origin: &RecordOrigin, ctx: &PipelineContext) {
+    if let Some(reply) = &origin.source.reply {
+        reply.send(report);
+        return;
+    }
+    let Some(sink) = &ctx.report_sink else {
+        return;
     };
-    let f = defs.finish(parsed_filter, Vec::new(), &mut errs);
-    if !errs.is_empty() {
-        return Err(format!(
-            "Unable to compile JSON key expression {:?}: {:?}",
-            payload_key, errs
-        ));
+    if sink.send(report).is_err() {
+        eprintln!("Unable to stream {} because the writer stopped", kind);
+    } else if let Some(progress) = &ctx.stream_progress {
+        progress.reports_sent.fetch_add(1, Ordering::SeqCst);
     }
+}
 
-    let inputs = RcIter::new(core::iter::empty());
-    let mut out = f.run(Ctx::new([], &inputs), Val::from(line_json));
-    let payload = match out.next() {
-        Some(Ok(v)) => {
-            let v_str = v.to_string();
-            get_payload(&v_str, input_mode).map_err(|err| {
-                format!(
-                    "Unable to decode payload from JSON key {:?}: {}",
-                    payload_key, err
-                )
-            })?
-        }
-        Some(Err(e)) => {
-            return Err(format!(
-                "Unable to parse JSON pattern: {:?} with error: {:?}",
-                payload_key, e
-            ));
-        }
-        None => {
-            return Err(format!(
-                "No valid JSON was found for pattern: {:?}",
-                payload_key
-            ));
-        }
-    };
+struct PayloadSlot<'a> {
+    label: &'a str,
+    // Position among the outputs of the slot's filter.
+    index: usize,
+    payload: Vec<u8>,
+}
 
-    Ok((payload, json_clone))
+fn report_payload(
+    payload: Vec<u8>,
+    json_clone: Value,
+    origin: &RecordOrigin,
+    ctx: &PipelineContext,
+) -> Option<Vec<String>> {
+    report_payload_with_slots(payload, &[], json_clone, origin, ctx)
 }
 
-fn process_decoded_payload(
+// Returns the matched tags when the payload passed the match gate, or tripped a match
+// limit or the budget, and was reported. With slots, patterns run over each slot instead of the combined payload.
+fn report_payload_with_slots(
     payload: Vec<u8>,
+    slots: &[PayloadSlot],
     mut json_clone: Value,
-    patterns: &[CompiledPattern],
-    sigma_rule_plans: &[SigmaRulePlan],
-    args: &ArgMatches,
-    similarity_mode: &SimilarityMode,
-    tlsh_list: &Mutex<Vec<SimilarityHash>>,
-    payload_reports: &Mutex<Map<String, Value>>,
-    counter_pcre_matches: &Arc<DashMap<String, i64>>,
-    counter_tlsh_hashes: &Arc<ConsistentCounter>,
-    vec_payload_size: &std::sync::Mutex<Vec<i64>>,
-    vec_payload_size_matched: &std::sync::Mutex<Vec<i64>>,
-    counter_unique_payloads: &Arc<Mutex<HashSet<u64>>>,
-    counter_pcre_matches_total: &Arc<ConsistentCounter>,
-) {
-    if let Ok(mut payload_sizes) = vec_payload_size.lock() {
-        payload_sizes.push(payload.len() as i64);
-    } else {
-        eprintln!("Unable to record payload size due to poisoned lock");
-        return;
+    origin: &RecordOrigin,
+    ctx: &PipelineContext,
+) -> Option<Vec<String>> {
+    // Per-payload stats bookkeeping grows with the input, so only keep it when asked for.
+    let collect_stats = ctx.args.get_flag(STATS);
+    if collect_stats {
+        if let Ok(mut payload_sizes) = ctx.vec_payload_size.lock() {
+            payload_sizes.push(payload.len() as i64);
+        } else {
+            eprintln!("Unable to record payload size due to poisoned lock");
+            return None;
+        }
     }
 
     let (xxh3_64_sum, xxh3_64_sum_string) = xxh3_64_hex(payload.clone());
-    if let Ok(mut unique_payloads) = counter_unique_payloads.lock() {
-        unique_payloads.insert(xxh3_64_sum);
-    } else {
-        eprintln!("Unable to record unique payload due to poisoned lock");
-        return;
+    if collect_stats {
+        if let Ok(mut unique_payloads) = ctx.counter_unique_payloads.lock() {
+            unique_payloads.insert(xxh3_64_sum);
+        } else {
+            eprintln!("Unable to record unique payload due to poisoned lock");
+            return None;
+        }
     }
 
-    let mut matched_capture_groups: Vec<Value> = Vec::new();
-    let mut matched_tag_names: Vec<String> = Vec::new();
-    let mut standard_match_exists = false;
-    let mut sigma_pattern_match_exists = false;
-
-    for compiled in patterns.iter() {
-        let result = compiled
-            .regex
-            .captures_iter(payload.as_slice())
-            .filter_map(|res| res.ok())
-            .any(|caps| {
-                if let Ok(mut payload_sizes_matched) = vec_payload_size_matched.lock() {
-                    payload_sizes_matched.push(payload.len() as i64);
-                } else {
-                    eprintln!("Unable to record matched payload size due to poisoned lock");
+    // Slot offsets only make sense per slot, so they are left to `captures`.
+    let emit_match_offsets = slots.is_empty() && blob_mode_enabled(ctx.args, ctx.input_mode);
+    let emit_captures = ctx.args.get_flag(CAPTURES);
+    let mut slot_reports = Vec::new();
+    let mut slot_spans = Vec::new();
+    let mut payload_matches = if slots.is_empty() {
+        match_payload(&payload, ctx, emit_match_offsets || emit_captures)
+    } else {
+        let mut combined = PayloadMatches {
+            tags: Vec::new(),
+            standard: false,
+            sigma_pattern: false,
+            errors: Vec::new(),
+            spans: Vec::new(),
+            pattern_tags: Vec::new(),
+        };
+        for slot in slots {
+            let mut slot_matches = match_payload(&slot.payload, ctx, emit_captures);
+            slot_spans.push(std::mem::take(&mut slot_matches.spans));
+            combined.merge_pattern_tags(std::mem::take(&mut slot_matches.pattern_tags));
+            combined.standard |= slot_matches.standard;
+            combined.sigma_pattern |= slot_matches.sigma_pattern;
+            combined
+                .errors
+                .extend(slot_matches.errors.into_iter().map(|mut error| {
+                    error["slot"] = json!(slot.label);
+                    error
+                }));
+            for tag in slot_matches.tags.iter() {
+                if !combined.tags.contains(tag) {
+                    combined.tags.push(tag.clone());
                 }
-                counter_pcre_matches_total.inc();
-                let mut found_match = false;
-                for name in compiled.regex.capture_names() {
-                    if let Some(name) = name {
-                        if caps.name(name).is_some() {
-                            // Here we increment a counter for each of the capture group names from the PCRE2 patterns.
-                            let tag_name = name.to_string();
-                            let mut count =
-                                counter_pcre_matches.entry(tag_name.clone()).or_insert(0);
-                            *count += 1;
-                            matched_capture_groups.push(Value::String(tag_name.clone()));
-                            matched_tag_names.push(tag_name);
-                            found_match = true;
-                        }
-                    }
+            }
+            slot_reports.push(json!({
+                "label": slot.label,
+                "index": slot.index,
+                "tags": slot_matches.tags,
+            }));
+        }
+        combined
+    };
+    let mut standard_match_exists = payload_matches.standard;
+    let mut sigma_pattern_match_exists = payload_matches.sigma_pattern;
+    let mut matched_tag_names = std::mem::take(&mut payload_matches.tags);
+    let mut match_errors = std::mem::take(&mut payload_matches.errors);
+    let spans = std::mem::take(&mut payload_matches.spans);
+    let mut decoded_layers = Vec::new();
+    let mut layer_spans = Vec::new();
+    let mut decode_layers = Vec::new();
+    if ctx.args.get_flag(AUTO_DECODE) {
+        let max_depth = ctx
+            .args
+            .get_one::<usize>(AUTO_DECODE_DEPTH)
+            .copied()
+            .unwrap_or(3);
+        decoded_layers = peel_layers(&payload, max_depth);
+        if !decoded_layers.is_empty() {
+            decode_layers.push(json!({ "decode_chain": [], "tags": matched_tag_names }));
+        }
+        for (index, layer) in decoded_layers.iter().enumerate() {
+            let mut layer_matches = match_payload(&layer.payload, ctx, emit_captures);
+            layer_spans.push(std::mem::take(&mut layer_matches.spans));
+            payload_matches.merge_pattern_tags(std::mem::take(&mut layer_matches.pattern_tags));
+            standard_match_exists |= layer_matches.standard;
+            sigma_pattern_match_exists |= layer_matches.sigma_pattern;
+            match_errors.extend(layer_matches.errors.into_iter().map(|mut error| {
+                error["decode_depth"] = json!(index + 1);
+                error
+            }));
+            for tag in layer_matches.tags.iter() {
+                if !matched_tag_names.contains(tag) {
+                    matched_tag_names.push(tag.clone());
                 }
-                found_match
-            });
-        if result {
-            match compiled.origin {
-                PatternOrigin::Standard => standard_match_exists = true,
-                PatternOrigin::Sigma => sigma_pattern_match_exists = true,
             }
+            decode_layers.push(json!({ "decode_chain": layer.chain, "tags": layer_matches.tags }));
         }
     }
+    record_match_stats(&payload, &payload_matches.pattern_tags, ctx);
+    let matched_capture_groups: Vec<Value> = matched_tag_names
+        .iter()
+        .map(|tag| Value::String(tag.clone()))
+        .collect();
 
-    let sigma_rule_matches = matching_sigma_rules(sigma_rule_plans, &matched_tag_names);
+    let sigma_rule_matches = matching_sigma_rules(ctx.sigma_rule_plans, &matched_tag_names);
     let sigma_condition_match_exists = !sigma_rule_matches.is_empty();
     let match_exists = standard_match_exists
         || sigma_condition_match_exists
-        || (sigma_rule_plans.is_empty() && sigma_pattern_match_exists);
+        || (ctx.sigma_rule_plans.is_empty() && sigma_pattern_match_exists);
 
     let mut json_tlsh_hash: Value = Value::String(String::new());
-    let tlsh_algorithm = match args.get_one::<String>(TLSH_ALGORITHM) {
+    let tlsh_algorithm = match ctx.args.get_one::<String>(TLSH_ALGORITHM) {
         Some(algorithm) => algorithm,
         None => {
             eprintln!("Unable to read TLSH algorithm argument");
-            return;
+            return None;
         }
     };
-    if match_exists {
+    // Payloads that only tripped a match limit or the budget are still reported, so their
+    // `match_errors` are not lost.
+    if match_exists || !match_errors.is_empty() {
+        let source_record = json_clone.clone();
         // We only calculate TLSH hashes and push to the global TLSH list
         // If the payload passes the pattern_match gate
         // This helps us acchieve a massive reduction in work for TLSH computation
-        if args.get_flag(TLSH) || args.get_flag(TLSH_DIFF) || args.get_flag(TLSH_LENGTH) {
-            match calculate_similarity_hash(payload.as_slice(), similarity_mode, tlsh_algorithm) {
+        if match_exists
+            && (ctx.args.get_flag(TLSH)
+                || ctx.args.get_flag(TLSH_DIFF)
+                || ctx.args.get_flag(TLSH_LENGTH))
+        {
+            match calculate_similarity_hash(payload.as_slice(), ctx.similarity_mode, tlsh_algorithm)
+            {
                 Ok(hash) => {
-                    counter_tlsh_hashes.inc();
+                    ctx.counter_tlsh_hashes.inc();
                     let hash_as_string = hash.as_string();
-                    if let Ok(mut tlsh_hashes) = tlsh_list.lock() {
-                        tlsh_hashes.push(hash);
-                    } else {
-                        eprintln!("Unable to record TLSH hash due to poisoned lock");
-                        return;
+                    // Streamed reports are already written, so hashes are only retained for -d.
+                    if ctx.report_sink.is_none() || ctx.args.get_flag(TLSH_DIFF) {
+                        if let Ok(mut tlsh_hashes) = ctx.tlsh_list.lock() {
+                            tlsh_hashes.push(hash);
+                        } else {
+                            eprintln!("Unable to record TLSH hash due to poisoned lock");
+                            return None;
+                        }
                     }
                     if let Ok(tlsh_hash_string) = hash_as_string {
                         json_tlsh_hash = Value::String(tlsh_hash_string);
@@ -1277,7 +3613,7 @@ fn process_decoded_payload(
                 Err(err) => {
                     eprintln!(
                         "Unable to calculate similarity hash using mode {}: {}",
-                        similarity_mode.as_str(),
+                        ctx.similarity_mode.as_str(),
                         err
                     );
                 }
@@ -1296,6 +3632,27 @@ fn process_decoded_payload(
             json_clone["similarity_hash"] = json_tlsh_hash.clone();
         }
         json_clone["tags"] = Value::Array(matched_capture_groups);
+        let matched_tag_metadata: Map<String, Value> = matched_tag_names
+            .iter()
+            .filter_map(|tag| {
+                ctx.tag_metadata
+                    .get(tag)
+                    .map(|metadata| (tag.clone(), metadata.clone()))
+            })
+            .collect();
+        if !matched_tag_metadata.is_empty() {
+            json_clone["tag_metadata"] = Value::Object(matched_tag_metadata);
+        }
+        if let Some(deepest) = decoded_layers.last() {
+            json_clone["decode_chain"] = json!(deepest.chain);
+            json_clone["decode_layers"] = Value::Array(decode_layers);
+        }
+        if !slot_reports.is_empty() {
+            json_clone["slots"] = Value::Array(slot_reports);
+        }
+        if !match_errors.is_empty() {
+            json_clone["match_errors"] = Value::Array(match_errors);
+        }
         if !sigma_rule_matches.is_empty() {
             json_clone["sigma_rule_matches"] = Value::Array(
                 sigma_rule_matches
@@ -1310,18 +3667,25 @@ fn process_decoded_payload(
                     .collect(),
             );
         }
-        if args.get_flag(SINGLE_PACKET) {
-            let abstain_threshold = args
+        if ctx.args.get_flag(SINGLE_PACKET) {
+            let abstain_threshold = ctx
+                .args
                 .get_one::<f64>(ABSTAIN_THRESHOLD)
                 .copied()
                 .unwrap_or(0.65);
-            let protocol_top_k = args.get_one::<usize>(PROTOCOL_TOP_K).copied().unwrap_or(3);
+            let protocol_top_k = ctx
+                .args
+                .get_one::<usize>(PROTOCOL_TOP_K)
+                .copied()
+                .unwrap_or(3);
             let inference = infer_protocol_candidates(
                 payload.as_slice(),
                 &matched_tag_names,
                 0,
                 protocol_top_k,
                 abstain_threshold,
+                &transport_evidence(&json_clone),
+                json_clone["eve"]["app_proto"].as_str(),
             );
 
             json_clone["protocol_label"] = Value::String(inference.label);
@@ -1345,32 +3709,91 @@ fn process_decoded_payload(
                 .collect::<Vec<Value>>();
             json_clone["protocol_candidates"] = Value::Array(protocol_candidates);
         }
+        json_clone["xxh3_64_sum"] = json!(xxh3_64_sum_string);
+        if let Some(report) = json_clone.as_object_mut() {
+            origin.insert_provenance(report);
+        }
+        if emit_match_offsets || emit_captures {
+            if emit_match_offsets {
+                json_clone["match_offsets"] = match_offsets(&spans);
+            }
+            if emit_captures {
+                let binary_encoding = ctx
+                    .args
+                    .get_one::<String>(CAPTURE_ENCODING)
+                    .map_or(CAPTURE_ENCODING_BASE64, String::as_str);
+                let mut captures = Map::new();
+                if slots.is_empty() {
+                    insert_captures(&mut captures, &spans, &payload, binary_encoding, &[]);
+                }
+                for (slot, slot_spans) in slots.iter().zip(&slot_spans) {
+                    insert_captures(
+                        &mut captures,
+                        slot_spans,
+                        &slot.payload,
+                        binary_encoding,
+                        &[
+                            ("slot", json!(slot.label)),
+                            ("slot_index", json!(slot.index)),
+                        ],
+                    );
+                }
+                for (index, (layer, layer_spans)) in
+                    decoded_layers.iter().zip(&layer_spans).enumerate()
+                {
+                    insert_captures(
+                        &mut captures,
+                        layer_spans,
+                        &layer.payload,
+                        binary_encoding,
+                        &[("decode_depth", json!(index + 1))],
+                    );
+                }
+                json_clone["captures"] = Value::Object(captures);
+            }
+        }
+        if streams_reports(ctx, origin) {
+            json_clone["input_index"] = json!(origin.input_index);
+            stream_report(json_clone, "report", origin, ctx);
+            return Some(matched_tag_names);
+        }
         // This is where we insert the finished per-payload report
-        if let Ok(mut reports) = payload_reports.lock() {
-            reports.insert(xxh3_64_sum_string, json_clone);
+        if let Ok(mut reports) = ctx.payload_reports.lock() {
+            if ctx.args.get_flag(PER_RECORD) {
+                json_clone["input_index"] = json!(origin.input_index);
+                reports.insert(
+                    format!("{}:{}", xxh3_64_sum_string, origin.input_index),
+                    json_clone,
+                );
+            } else {
+                let max_refs = ctx
+                    .args
+                    .get_one::<usize>(MAX_OCCURRENCE_REFS)
+                    .copied()
+                    .unwrap_or(10);
+                let report = with_single_occurrence(json_clone, source_record, origin, max_refs);
+                match reports.get_mut(&xxh3_64_sum_string) {
+                    Some(existing) => merge_occurrences(existing, report, max_refs),
+                    None => {
+                        reports.insert(xxh3_64_sum_string, report);
+                    }
+                }
+            }
         } else {
             eprintln!("Unable to record payload report due to poisoned lock");
         }
+        Some(matched_tag_names)
+    } else {
+        None
     }
 }
 
-fn handle_blob(
-    blob: &[u8],
-    patterns: &[CompiledPattern],
-    sigma_rule_plans: &[SigmaRulePlan],
-    args: &ArgMatches,
-    input_mode: &str,
-    similarity_mode: &SimilarityMode,
-    tlsh_list: &Mutex<Vec<SimilarityHash>>,
-    payload_reports: &Mutex<Map<String, Value>>,
-    counter_pcre_matches: &Arc<DashMap<String, i64>>,
-    counter_tlsh_hashes: &Arc<ConsistentCounter>,
-    vec_payload_size: &std::sync::Mutex<Vec<i64>>,
-    vec_payload_size_matched: &std::sync::Mutex<Vec<i64>>,
-    counter_unique_payloads: &Arc<Mutex<HashSet<u64>>>,
-    counter_pcre_matches_total: &Arc<ConsistentCounter>,
-) {
-    let (payload, json_clone) = if let Some(payload_key) = args.get_one::<String>(INPUT_JSON_KEY) {
+fn handle_blob(blob: &[u8], origin: &RecordOrigin, ctx: &PipelineContext) {
+    if ctx.input_mode == INPUT_MODE_HEXDUMP {
+        handle_hexdump_blob(blob, origin, ctx);
+        return;
+    }
+    if json_extraction_enabled(ctx) {
         let blob_as_utf8 = match std::str::from_utf8(blob) {
             Ok(text) => text,
             Err(err) => {
@@ -1381,105 +3804,396 @@ fn handle_blob(
                 return;
             }
         };
-        match decode_payload_from_json_expression(blob_as_utf8, payload_key, input_mode) {
-            Ok(decoded) => decoded,
-            Err(err) => {
-                eprintln!("{}", err);
-                return;
-            }
+        process_json_record(blob_as_utf8, origin, ctx, &|payload, json_clone| {
+            process_blob_payload(payload, json_clone, origin, ctx)
+        });
+        return;
+    }
+    let payload = match get_payload_from_blob(blob, ctx.input_mode) {
+        Ok(decoded) => decoded,
+        Err(err) => {
+            eprintln!(
+                "Unable to decode blob using input mode {}: {}",
+                ctx.input_mode, err
+            );
+            return;
         }
-    } else {
-        let payload = match get_payload_from_blob(blob, input_mode) {
-            Ok(decoded) => decoded,
-            Err(err) => {
-                eprintln!(
-                    "Unable to decode blob using input mode {}: {}",
-                    input_mode, err
-                );
-                return;
-            }
-        };
-        (payload, Value::Object(Map::new()))
     };
+    process_blob_payload(payload, Value::Object(Map::new()), origin, ctx);
+}
 
-    process_decoded_payload(
-        payload,
-        json_clone,
-        patterns,
-        sigma_rule_plans,
-        args,
-        similarity_mode,
-        tlsh_list,
-        payload_reports,
-        counter_pcre_matches,
-        counter_tlsh_hashes,
-        vec_payload_size,
-        vec_payload_size_matched,
-        counter_unique_payloads,
-        counter_pcre_matches_total,
-    );
+// Every dump found in the source is matched as its own blob, with the detected layout
+// and source lines under `hexdump`.
+fn handle_hexdump_blob(blob: &[u8], origin: &RecordOrigin, ctx: &PipelineContext) {
+    let dumps = parse_hexdumps(&String::from_utf8_lossy(blob));
+    if dumps.is_empty() {
+        eprintln!("No hex dump found in {}", origin.source.label());
+        return;
+    }
+    for (dump_index, dump) in dumps.into_iter().enumerate() {
+        let hexdump = dump.metadata(dump_index);
+        process_blob_payload(dump.bytes, json!({ "hexdump": hexdump }), origin, ctx);
+    }
 }
 
-fn handle_line(
-    line: &str,
-    patterns: &[CompiledPattern],
-    sigma_rule_plans: &[SigmaRulePlan],
-    args: &ArgMatches,
-    input_mode: &str,
-    similarity_mode: &SimilarityMode,
-    tlsh_list: &Mutex<Vec<SimilarityHash>>,
-    payload_reports: &Mutex<Map<String, Value>>,
-    counter_pcre_matches: &Arc<DashMap<String, i64>>,
-    counter_tlsh_hashes: &Arc<ConsistentCounter>,
-    vec_payload_size: &std::sync::Mutex<Vec<i64>>,
-    vec_payload_size_matched: &std::sync::Mutex<Vec<i64>>,
-    counter_unique_payloads: &Arc<Mutex<HashSet<u64>>>,
-    counter_pcre_matches_total: &Arc<ConsistentCounter>,
+fn process_blob_payload(
+    payload: Vec<u8>,
+    json_clone: Value,
+    origin: &RecordOrigin,
+    ctx: &PipelineContext,
+) {
+    match ctx.chunking {
+        Some(config) => process_chunks(payload, json_clone, &config, origin, ctx),
+        None => process_decoded_payload(payload, json_clone, origin, ctx),
+    }
+}
+
+fn json_extraction_enabled(ctx: &PipelineContext) -> bool {
+    ctx.args.contains_id(INPUT_JSON_KEY) || !ctx.json_slots.is_empty()
+}
+
+// Every output of the -j filter becomes its own payload, numbered by
+// `json_output_index`. With --json-slot, the outputs of all slot filters are
+// matched separately but reported together as one record.
+fn process_json_record(
+    raw_json: &str,
+    origin: &RecordOrigin,
+    ctx: &PipelineContext,
+    dispatch: &dyn Fn(Vec<u8>, Value),
 ) {
-    let (payload, json_clone) = if let Some(payload_key) = args.get_one::<String>(INPUT_JSON_KEY) {
-        match decode_payload_from_json_expression(line, payload_key, input_mode) {
-            Ok(decoded) => decoded,
+    if !ctx.json_slots.is_empty() {
+        report_json_slots(raw_json, origin, ctx);
+        return;
+    }
+    let Some(payload_key) = ctx.args.get_one::<String>(INPUT_JSON_KEY) else {
+        return;
+    };
+    match decode_payload_from_json_expression(raw_json, payload_key, ctx.input_mode) {
+        Ok((payloads, json_clone)) => {
+            for (index, payload) in payloads {
+                let mut output_json = json_clone.clone();
+                output_json["json_output_index"] = json!(index);
+                dispatch(payload, output_json);
+            }
+        }
+        Err(err) => eprintln!("{}", err),
+    }
+}
+
+fn report_json_slots(raw_json: &str, origin: &RecordOrigin, ctx: &PipelineContext) {
+    let (record, json_clone) = match parse_json_record(raw_json) {
+        Ok(parsed) => parsed,
+        Err(err) => {
+            eprintln!("{}", err);
+            return;
+        }
+    };
+    let mut slots = Vec::new();
+    for slot in ctx.json_slots {
+        match run_json_filter(&record, &slot.filter, ctx.input_mode) {
+            Ok(payloads) => {
+                slots.extend(payloads.into_iter().map(|(index, payload)| PayloadSlot {
+                    label: &slot.label,
+                    index,
+                    payload,
+                }));
+            }
             Err(err) => {
-                eprintln!("{}", err);
+                eprintln!("Unable to extract JSON slot {:?}: {}", slot.label, err);
                 return;
             }
         }
-    } else {
-        let payload = match get_payload(line, input_mode) {
-            Ok(payload) => payload,
+    }
+    if slots.is_empty() {
+        return;
+    }
+    // Hashing and inference see the slots joined by newlines; matching stays per slot.
+    let payload = slots
+        .iter()
+        .map(|slot| slot.payload.as_slice())
+        .collect::<Vec<&[u8]>>()
+        .join(&b'\n');
+    report_payload_with_slots(payload, &slots, json_clone, origin, ctx);
+}
+
+// Frames are decoded, defragmented and reassembled here, in capture order; the
+// resulting payloads are submitted like any other record.
+fn handle_capture<R: Read>(
+    reader: R,
+    source: &InputSource,
+    ctx: &PipelineContext,
+    stream: Option<&SyncSender<InputRecord>>,
+) -> Result<(), String> {
+    let source_name = source.label();
+    let capture = match CaptureReader::new(reader) {
+        Ok(capture) => capture,
+        Err(err) => {
+            eprintln!("Unable to read capture from {}: {}", source_name, err);
+            return Ok(());
+        }
+    };
+    let capture_format = capture.format();
+    let mut reassembler = ctx.tcp_reassembly.map(TcpReassembler::new);
+    let mut defragmenter = Defragmenter::new(ctx.ip_defrag);
+    for frame_result in capture {
+        let frame = match frame_result {
+            Ok(frame) => frame,
+            Err(err) => {
+                eprintln!("Unable to read capture frame from {}: {}", source_name, err);
+                break;
+            }
+        };
+        let packet = match decode_ip_datagram(frame.link_type, &frame.data)
+            .and_then(|datagram| match datagram {
+                Some(datagram) => defragmenter.push(datagram, frame.timestamp_nanos),
+                None => Ok(None),
+            })
+            .and_then(|datagram| match datagram {
+                Some(datagram) => decode_datagram_transport(datagram),
+                None => Ok(None),
+            }) {
+            Ok(Some(packet)) => packet,
+            Ok(None) => continue,
             Err(err) => {
                 eprintln!(
-                    "Unable to decode payload using input mode {}: {}",
-                    input_mode, err
+                    "Skipping frame {} from {}: {}",
+                    frame.frame_number, source_name, err
                 );
-                return;
+                continue;
             }
         };
-        (payload, Value::Object(Map::new()))
+        if let (Some(reassembler), Some(_)) = (reassembler.as_mut(), packet.tcp_seq) {
+            for chunk in reassembler.push(packet, frame.frame_number, frame.timestamp_nanos) {
+                submit_stream_chunk(chunk, capture_format.as_str(), source, ctx, stream)?;
+            }
+            continue;
+        }
+        if packet.payload.is_empty() {
+            continue;
+        }
+        let origin = next_record_origin(ctx, source, None, None);
+
+        let mut packet_json = packet.metadata();
+        packet_json.insert("capture_format".to_string(), json!(capture_format.as_str()));
+        packet_json.insert("frame".to_string(), json!(frame.frame_number));
+        packet_json.insert("frame_len".to_string(), json!(frame.original_len));
+        packet_json.insert(
+            "timestamp".to_string(),
+            Number::from_f64(frame.timestamp_seconds())
+                .map(Value::Number)
+                .unwrap_or(Value::Null),
+        );
+        let mut json_clone = Map::new();
+        json_clone.insert("packet".to_string(), Value::Object(packet_json));
+        submit_record(
+            InputRecord::Packet(packet.payload, Value::Object(json_clone), origin),
+            ctx,
+            stream,
+        )?;
+    }
+    if let Some(reassembler) = reassembler.as_mut() {
+        for chunk in reassembler.flush() {
+            submit_stream_chunk(chunk, capture_format.as_str(), source, ctx, stream)?;
+        }
+    }
+    let incomplete = defragmenter.incomplete();
+    if incomplete > 0 {
+        eprintln!(
+            "Dropped {} incomplete fragmented datagrams from {}",
+            incomplete, source_name
+        );
+    }
+    Ok(())
+}
+
+fn submit_stream_chunk(
+    chunk: StreamChunk,
+    capture_format: &str,
+    source: &InputSource,
+    ctx: &PipelineContext,
+    stream: Option<&SyncSender<InputRecord>>,
+) -> Result<(), String> {
+    let origin = next_record_origin(ctx, source, None, None);
+    let mut stream_json = chunk.metadata();
+    stream_json.insert("capture_format".to_string(), json!(capture_format));
+    let mut json_clone = Map::new();
+    json_clone.insert("stream".to_string(), Value::Object(stream_json));
+    submit_record(
+        InputRecord::Packet(chunk.payload, Value::Object(json_clone), origin),
+        ctx,
+        stream,
+    )
+}
+
+fn handle_line(line: &str, origin: &RecordOrigin, ctx: &PipelineContext) {
+    if ctx.input_mode == INPUT_MODE_EVE {
+        handle_eve_line(line, origin, ctx);
+        return;
+    }
+    if ctx.input_mode == INPUT_MODE_ZEEK {
+        handle_zeek_line(line, origin, ctx);
+        return;
+    }
+    if ctx.input_mode == INPUT_MODE_SYSLOG {
+        handle_syslog_line(line, origin, ctx);
+        return;
+    }
+    if ctx.delimited.is_some() {
+        handle_delimited_record(line, origin, ctx);
+        return;
+    }
+    if json_extraction_enabled(ctx) {
+        process_json_record(line, origin, ctx, &|payload, json_clone| {
+            process_decoded_payload(payload, json_clone, origin, ctx)
+        });
+        return;
+    }
+    let payload = match get_payload(line, ctx.input_mode) {
+        Ok(payload) => payload,
+        Err(err) => {
+            eprintln!(
+                "Unable to decode payload using input mode {}: {}",
+                ctx.input_mode, err
+            );
+            return;
+        }
     };
+    process_decoded_payload(payload, Value::Object(Map::new()), origin, ctx);
+}
 
-    process_decoded_payload(
-        payload,
-        json_clone,
-        patterns,
-        sigma_rule_plans,
-        args,
-        similarity_mode,
-        tlsh_list,
-        payload_reports,
-        counter_pcre_matches,
-        counter_tlsh_hashes,
-        vec_payload_size,
-        vec_payload_size_matched,
-        counter_unique_payloads,
-        counter_pcre_matches_total,
-    );
+// Each payload field of the event is reported on its own, carrying the flow and
+// alert metadata under `eve`.
+fn handle_eve_line(line: &str, origin: &RecordOrigin, ctx: &PipelineContext) {
+    let (context, payloads) = match parse_eve_record(line).and_then(|record| {
+        let payloads = eve_payloads(&record)?;
+        Ok((eve_context(&record), payloads))
+    }) {
+        Ok(parsed) => parsed,
+        Err(err) => {
+            eprintln!("{}", err);
+            return;
+        }
+    };
+    for eve_payload in payloads {
+        let mut eve = context.clone();
+        eve.insert("payload_field".to_string(), json!(eve_payload.field));
+        process_decoded_payload(eve_payload.payload, json!({ "eve": eve }), origin, ctx);
+    }
+}
+
+// Every selected field is reported on its own with the record's other columns under `zeek`.
+fn handle_zeek_line(line: &str, origin: &RecordOrigin, ctx: &PipelineContext) {
+    let record: Map<String, Value> = match from_str(line) {
+        Ok(record) => record,
+        Err(err) => {
+            eprintln!("Unable to parse Zeek log record as JSON: {}", err);
+            return;
+        }
+    };
+    let fields: Vec<String> = ctx
+        .args
+        .get_many::<String>(ZEEK_FIELD)
+        .map(|fields| fields.cloned().collect())
+        .unwrap_or_default();
+    let context = zeek_context(&record, &fields);
+    for zeek_payload in zeek_payloads(&record, &fields) {
+        let mut zeek = context.clone();
+        zeek.insert("payload_field".to_string(), json!(zeek_payload.field));
+        process_decoded_payload(zeek_payload.payload, json!({ "zeek": zeek }), origin, ctx);
+    }
+}
+
+// The payload cell is decoded with the -m mode; the other columns become report fields,
+// as the source record does in JSON mode.
+fn handle_delimited_record(record: &str, origin: &RecordOrigin, ctx: &PipelineContext) {
+    let mut record: Map<String, Value> = match from_str(record) {
+        Ok(record) => record,
+        Err(err) => {
+            eprintln!("Unable to parse delimited record: {}", err);
+            return;
+        }
+    };
+    let cell = match record.remove("payload") {
+        Some(Value::String(cell)) if !cell.is_empty() => cell,
+        _ => return,
+    };
+    let payload = match get_payload(&cell, ctx.input_mode) {
+        Ok(payload) => payload,
+        Err(err) => {
+            eprintln!(
+                "Unable to decode payload using input mode {}: {}",
+                ctx.input_mode, err
+            );
+            return;
+        }
+    };
+    let columns = record.remove("columns").unwrap_or_else(|| json!({}));
+    process_decoded_payload(payload, columns, origin, ctx);
+}
+
+// Patterns run against the MSG body only; the parsed header is reported under `syslog`.
+fn handle_syslog_line(line: &str, origin: &RecordOrigin, ctx: &PipelineContext) {
+    let line = line.trim_end_matches(['\r', '\n']);
+    if line.trim().is_empty() {
+        return;
+    }
+    match parse_syslog(line) {
+        Ok((header, msg)) => process_decoded_payload(
+            msg.as_bytes().to_vec(),
+            json!({ "syslog": header }),
+            origin,
+            ctx,
+        ),
+        Err(err) => eprintln!("Unable to parse syslog line: {}", err),
+    }
 }
 
 #[cfg(test)]
 mod tests {
     use super::*;
 
+    #[test]
+    fn test_merge_occurrences_keeps_earliest_canonical_and_bounded_refs() {
+        let report = |host: &str, index: u64| {
+            with_single_occurrence(
+                json!({ "host": host, "tags": ["t"] }),
+                json!({ "host": host }),
+                &RecordOrigin {
+                    input_index: index,
+                    source: InputSource::new("-"),
+                    source_line: Some(index),
+                    source_offset: None,
+                },
+                2,
+            )
+        };
+        let mut canonical = report("c", 7);
+        merge_occurrences(&mut canonical, report("a", 3), 2);
+        merge_occurrences(&mut canonical, report("b", 5), 2);
+
+        assert_eq!(canonical["host"], json!("a"));
+        assert_eq!(canonical["occurrence_count"], json!(3));
+        assert_eq!(canonical["first_seen_input"], json!(3));
+        assert_eq!(canonical["last_seen_input"], json!(7));
+        assert_eq!(
+            canonical["occurrences"],
+            json!([
+                {
+                    "input_index": 3,
+                    "source_path": "-",
+                    "source_line": 3,
+                    "record": { "host": "a" }
+                },
+                {
+                    "input_index": 5,
+                    "source_path": "-",
+                    "source_line": 5,
+                    "record": { "host": "b" }
+                }
+            ])
+        );
+    }
+
     #[test]
     fn test_apply_similarity_neighbor_boost_updates_top_candidate() {
         let mut report = json!({
@@ -1522,4 +4236,28 @@ mod tests {
         let confidence = report["protocol_confidence"].as_f64().unwrap_or(0.0);
         assert!((confidence - 0.80).abs() < f64::EPSILON);
     }
+
+    #[test]
+    fn test_stream_progress_waits_for_in_flight_records_and_reports() {
+        let progress = Arc::new(StreamProgress::default());
+        progress.records_submitted.fetch_add(2, Ordering::SeqCst);
+        let worker_progress = Arc::clone(&progress);
+        let worker = std::thread::spawn(move || {
+            for _ in 0..2 {
+                std::thread::sleep(Duration::from_millis(20));
+                worker_progress.reports_sent.fetch_add(1, Ordering::SeqCst);
+                worker_progress
+                    .records_handled
+                    .fetch_add(1, Ordering::SeqCst);
+            }
+            std::thread::sleep(Duration::from_millis(20));
+            worker_progress
+                .reports_written
+                .fetch_add(2, Ordering::SeqCst);
+        });
+        progress.wait_until_written();
+        assert_eq!(progress.records_handled.load(Ordering::SeqCst), 2);
+        assert_eq!(progress.reports_written.load(Ordering::SeqCst), 2);
+        worker.join().expect("worker");
+    }
 }
@@ -0,0 +1,361 @@
+use std::io::{Cursor, Read, Write};
+
+// Nested containers deeper than this are handed to the pipeline as opaque bytes.
+const MAX_CONTAINER_DEPTH: usize = 4;
+// Enough to see the `ustar` magic at offset 257 of a tar header.
+const SNIFF_LEN: usize = 512;
+
+#[derive(Clone, Copy, Debug, Eq, PartialEq)]
+pub enum ContainerKind {
+    Gzip,
+    Zstd,
+    Xz,
+    Zip,
+    Tar,
+}
+
+impl ContainerKind {
+    pub fn as_str(&self) -> &'static str {
+        match self {
+            Self::Gzip => "gzip",
+            Self::Zstd => "zstd",
+            Self::Xz => "xz",
+            Self::Zip => "zip",
+            Self::Tar => "tar",
+        }
+    }
+}
+
+pub fn detect_container(header: &[u8]) -> Option<ContainerKind> {
+    if header.starts_with(&[0x1f, 0x8b]) {
+        Some(ContainerKind::Gzip)
+    } else if header.starts_with(&[0x28, 0xb5, 0x2f, 0xfd]) {
+        Some(ContainerKind::Zstd)
+    } else if header.starts_with(&[0xfd, b'7', b'z', b'X', b'Z', 0x00]) {
+        Some(ContainerKind::Xz)
+    } else if header.starts_with(b"PK\x03\x04") || header.starts_with(b"PK\x05\x06") {
+        Some(ContainerKind::Zip)
+    } else if header.get(257..262) == Some(b"ustar") {
+        Some(ContainerKind::Tar)
+    } else {
+        None
+    }
+}
+
+// Where a decoded member sits: the containers peeled to reach it (outermost first)
+// and its path inside them, with nested archive members joined by `!/`.
+#[derive(Clone, Debug, Default, Eq, PartialEq)]
+pub struct MemberInfo {
+    pub containers: Vec<ContainerKind>,
+    pub path: Option<String>,
+}
+
+impl MemberInfo {
+    fn unwrapped(&self, kind: ContainerKind) -> Self {
+        let mut containers = self.containers.clone();
+        containers.push(kind);
+        MemberInfo {
+            containers,
+            path: self.path.clone(),
+        }
+    }
+
+    fn child(&self, kind: ContainerKind, name: &str) -> Self {
+        let mut member = self.unwrapped(kind);
+        member.path = Some(match &self.path {
+            Some(parent) => format!("{}!/{}", parent, name),
+            None => name.to_string(),
+        });
+        member
+    }
+}
+
+// Peels compressed and archived layers off `reader` and hands every leaf member to
+// `visit` as a stream capped at `max_member_bytes`. Input that is not a recognised
+// container is passed through unchanged with an empty `MemberInfo`.
+pub fn visit_members(
+    reader: &mut dyn Read,
+    max_member_bytes: u64,
+    visit: &mut dyn FnMut(&MemberInfo, &mut dyn Read) -> Result<(), String>,
+) -> Result<(), String> {
+    let mut walker = MemberWalker {
+        max_member_bytes,
+        visit,
+    };
+    walker.walk_member(reader, &MemberInfo::default(), 0)
+}
+
+struct MemberWalker<'v> {
+    max_member_bytes: u64,
+    visit: &'v mut dyn FnMut(&MemberInfo, &mut dyn Read) -> Result<(), String>,
+}
+
+impl MemberWalker<'_> {
+    fn walk_member(
+        &mut self,
+        reader: &mut dyn Read,
+        info: &MemberInfo,
+        depth: usize,
+    ) -> Result<(), String> {
+        let mut header = Vec::with_capacity(SNIFF_LEN);
+        reader
+            .take(SNIFF_LEN as u64)
+            .read_to_end(&mut header)
+            .map_err(|err| format!("unable to read {}: {}", describe(info), err))?;
+        let kind = if depth < MAX_CONTAINER_DEPTH {
+            detect_container(&header)
+        } else {
+            None
+        };
+        let mut input = Cursor::new(header).chain(reader);
+
+        let Some(kind) = kind else {
+            let mut limited = LimitedReader {
+                inner: &mut input,
+                remaining: self.max_member_bytes,
+            };
+            return (self.visit)(info, &mut limited);
+        };
+        match kind {
+            ContainerKind::Gzip => {
+                let mut decoder = flate2::read::MultiGzDecoder::new(input);
+                self.walk_member(&mut decoder, &info.unwrapped(kind), depth + 1)
+            }
+            ContainerKind::Zstd => {
+                let mut decoder = ruzstd::streaming_decoder::StreamingDecoder::new(input)
+                    .map_err(|err| format!("invalid zstd stream in {}: {}", describe(info), err))?;
+                self.walk_member(&mut decoder, &info.unwrapped(kind), depth + 1)
+            }
+            ContainerKind::Xz => {
+                // lzma-rs only decodes into a writer, so the member is buffered.
+                let mut decoded = LimitedWriter {
+                    buffer: Vec::new(),
+                    remaining: self.max_member_bytes,
+                };
+                lzma_rs::xz_decompress(&mut std::io::BufReader::new(input), &mut decoded)
+                    .map_err(|err| format!("invalid xz stream in {}: {}", describe(info), err))?;
+                self.walk_member(
+                    &mut Cursor::new(decoded.buffer),
+                    &info.unwrapped(kind),
+                    depth + 1,
+                )
+            }
+            ContainerKind::Tar => {
+                let mut archive = tar::Archive::new(input);
+                let entries = archive
+                    .entries()
+                    .map_err(|err| format!("invalid tar archive in {}: {}", describe(info), err))?;
+                for entry in entries {
+                    let mut entry = entry.map_err(|err| {
+                        format!("invalid tar entry in {}: {}", describe(info), err)
+                    })?;
+                    if !entry.header().entry_type().is_file() {
+                        continue;
+                    }
+                    let name = entry
+                        .path()
+                        .map(|path| path.to_string_lossy().into_owned())
+                        .map_err(|err| {
+                            format!("invalid tar entry path in {}: {}", describe(info), err)
+                        })?;
+                    self.walk_member(&mut entry, &info.child(kind, &name), depth + 1)?;
+                }
+                Ok(())
+            }
+            ContainerKind::Zip => {
+                // The zip central directory sits at the end, so the archive is buffered.
+                let mut buffered = Vec::new();
+                LimitedReader {
+                    inner: &mut input,
+                    remaining: self.max_member_bytes,
+                }
+                .read_to_end(&mut buffered)
+                .map_err(|err| format!("unable to read {}: {}", describe(info), err))?;
+                let mut archive = zip::ZipArchive::new(Cursor::new(buffered))
+                    .map_err(|err| format!("invalid zip archive in {}: {}", describe(info), err))?;
+                for index in 0..archive.len() {
+                    let mut file = archive.by_index(index).map_err(|err| {
+                        format!("invalid zip entry in {}: {}", describe(info), err)
+                    })?;
+                    if !file.is_file() {
+                        continue;
+                    }
+                    let name = file.name().to_string();
+                    self.walk_member(&mut file, &info.child(kind, &name), depth + 1)?;
+                }
+                Ok(())
+            }
+        }
+    }
+}
+
+fn describe(info: &MemberInfo) -> String {
+    match &info.path {
+        Some(path) => format!("archive member {}", path),
+        None => "input".to_string(),
+    }
+}
+
+// Guards against decompression bombs: reads past the cap fail instead of truncating
+// silently.
+struct LimitedReader<'a> {
+    inner: &'a mut dyn Read,
+    remaining: u64,
+}
+
+impl Read for LimitedReader<'_> {
+    fn read(&mut self, buf: &mut [u8]) -> std::io::Result<usize> {
+        if self.remaining == 0 {
+            let mut probe = [0u8; 1];
+            return match self.inner.read(&mut probe)? {
+                0 => Ok(0),
+                _ => Err(size_limit_error()),
+            };
+        }
+        let len = buf.len().min(self.remaining as usize);
+        let read = self.inner.read(&mut buf[..len])?;
+        self.remaining -= read as u64;
+        Ok(read)
+    }
+}
+
+struct LimitedWriter {
+    buffer: Vec<u8>,
+    remaining: u64,
+}
+
+impl Write for LimitedWriter {
+    fn write(&mut self, buf: &[u8]) -> std::io::Result<usize> {
+        if buf.len() as u64 > self.remaining {
+            return Err(size_limit_error());
+        }
+        self.remaining -= buf.len() as u64;
+        self.buffer.extend_from_slice(buf);
+        Ok(buf.len())
+    }
+
+    fn flush(&mut self) -> std::io::Result<()> {
+        Ok(())
+    }
+}
+
+fn size_limit_error() -> std::io::Error {
+    std::io::Error::other("decompressed size limit exceeded")
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use flate2::write::GzEncoder;
+    use flate2::Compression;
+
+    fn collect(data: &[u8], max_member_bytes: u64) -> Result<Vec<(MemberInfo, Vec<u8>)>, String> {
+        let mut members = Vec::new();
+        visit_members(
+            &mut Cursor::new(data),
+            max_member_bytes,
+            &mut |info, reader| {
+                let mut body = Vec::new();
+                reader
+                    .read_to_end(&mut body)
+                    .map_err(|err| err.to_string())?;
+                members.push((info.clone(), body));
+                Ok(())
+            },
+        )?;
+        Ok(members)
+    }
+
+    fn gzip(data: &[u8]) -> Vec<u8> {
+        let mut encoder = GzEncoder::new(Vec::new(), Compression::default());
+        encoder.write_all(data).expect("gzip");
+        encoder.finish().expect("gzip")
+    }
+
+    fn tar(entries: &[(&str, &[u8])]) -> Vec<u8> {
+        let mut builder = tar::Builder::new(Vec::new());
+        for (name, body) in entries {
+            let mut header = tar::Header::new_gnu();
+            header.set_size(body.len() as u64);
+            header.set_mode(0o644);
+            header.set_cksum();
+            builder
+                .append_data(&mut header, name, *body)
+                .expect("tar entry");
+        }
+        builder.into_inner().expect("tar")
+    }
+
+    #[test]
+    fn test_plain_input_passes_through() {
+        let members = collect(b"plain text\n", 1024).expect("visit");
+        assert_eq!(
+            members,
+            vec![(MemberInfo::default(), b"plain text\n".to_vec())]
+        );
+    }
+
+    #[test]
+    fn test_tar_gz_members_carry_paths_and_containers() {
+        let bundle = gzip(&tar(&[
+            ("etc/passwd", b"root:x:0:0"),
+            ("bin/sh", b"\x7fELF"),
+        ]));
+        let members = collect(&bundle, 1024).expect("visit");
+        assert_eq!(members.len(), 2);
+        assert_eq!(
+            members[0].0.containers,
+            vec![ContainerKind::Gzip, ContainerKind::Tar]
+        );
+        assert_eq!(members[0].0.path.as_deref(), Some("etc/passwd"));
+        assert_eq!(members[0].1, b"root:x:0:0");
+        assert_eq!(members[1].0.path.as_deref(), Some("bin/sh"));
+    }
+
+    #[test]
+    fn test_nested_zip_member_paths_are_joined() {
+        let mut inner = zip::ZipWriter::new(Cursor::new(Vec::new()));
+        inner
+            .start_file("config.txt", zip::write::FileOptions::default())
+            .expect("zip entry");
+        inner.write_all(b"secret=1").expect("zip write");
+        let inner = inner.finish().expect("zip").into_inner();
+        let bundle = tar(&[("firmware/update.zip", &inner)]);
+
+        let members = collect(&bundle, 1024).expect("visit");
+        assert_eq!(members.len(), 1);
+        assert_eq!(
+            members[0].0.path.as_deref(),
+            Some("firmware/update.zip!/config.txt")
+        );
+        assert_eq!(
+            members[0].0.containers,
+            vec![ContainerKind::Tar, ContainerKind::Zip]
+        );
+        assert_eq!(members[0].1, b"secret=1");
+    }
+
+    #[test]
+    fn test_xz_and_zstd_streams_decode() {
+        let mut xz = Vec::new();
+        lzma_rs::xz_compress(&mut Cursor::new(b"xz payload".to_vec()), &mut xz).expect("xz");
+        let members = collect(&xz, 1024).expect("visit xz");
+        assert_eq!(members[0].0.containers, vec![ContainerKind::Xz]);
+        assert_eq!(members[0].1, b"xz payload");
+
+        // `zstd -c` output for "zstd payload\n".
+        let zstd = [
+            0x28, 0xb5, 0x2f, 0xfd, 0x04, 0x58, 0x69, 0x00, 0x00, 0x7a, 0x73, 0x74, 0x64, 0x20,
+            0x70, 0x61, 0x79, 0x6c, 0x6f, 0x61, 0x64, 0x0a, 0x3d, 0x01, 0xbf, 0xdd,
+        ];
+        let members = collect(&zstd, 1024).expect("visit zstd");
+        assert_eq!(members[0].0.containers, vec![ContainerKind::Zstd]);
+        assert_eq!(members[0].1, b"zstd payload\n");
+    }
+
+    #[test]
+    fn test_member_size_limit_rejects_bombs() {
+        let bomb = gzip(&vec![b'A'; 4096]);
+        assert!(collect(&bomb, 1024).is_err());
+    }
+}
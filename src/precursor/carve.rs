@@ -0,0 +1,236 @@
+#[derive(Clone, Copy, Debug, Eq, PartialEq)]
+pub struct CarvedObject {
+    pub signature: &'static str,
+    pub offset: usize,
+    pub length: usize,
+}
+
+struct Signature {
+    name: &'static str,
+    magic: &'static [u8],
+    // Validates the header at the match and returns the object length when the format
+    // records one; `Err` rejects the candidate as a false positive.
+    length: fn(&[u8]) -> Result<Option<usize>, ()>,
+}
+
+const SIGNATURES: &[Signature] = &[
+    Signature {
+        name: "elf",
+        magic: b"\x7fELF",
+        length: elf_length,
+    },
+    Signature {
+        name: "pe",
+        magic: b"MZ",
+        length: pe_length,
+    },
+    Signature {
+        name: "gzip",
+        magic: b"\x1f\x8b\x08",
+        length: gzip_length,
+    },
+    Signature {
+        name: "zip",
+        magic: b"PK\x03\x04",
+        length: unknown_length,
+    },
+    Signature {
+        name: "uimage",
+        magic: b"\x27\x05\x19\x56",
+        length: uimage_length,
+    },
+    Signature {
+        name: "squashfs",
+        magic: b"hsqs",
+        length: squashfs_length,
+    },
+    Signature {
+        name: "cramfs",
+        magic: b"\x45\x3d\xcd\x28",
+        length: cramfs_length,
+    },
+    Signature {
+        name: "romfs",
+        magic: b"-rom1fs-",
+        length: romfs_length,
+    },
+    Signature {
+        name: "xz",
+        magic: b"\xfd7zXZ\x00",
+        length: unknown_length,
+    },
+    Signature {
+        name: "zstd",
+        magic: b"\x28\xb5\x2f\xfd",
+        length: unknown_length,
+    },
+];
+
+// Scans every offset for signatures. Objects without a recorded length extend to the
+// next carved offset or the end of the blob. A match at offset 0 that spans the
+// whole blob is the blob itself and is not reported.
+pub fn carve_objects(blob: &[u8], max_objects: usize) -> Vec<CarvedObject> {
+    let mut found: Vec<(usize, &'static str, Option<usize>)> = Vec::new();
+    for offset in 0..blob.len() {
+        if found.len() == max_objects {
+            break;
+        }
+        let window = &blob[offset..];
+        let Some(signature) = SIGNATURES
+            .iter()
+            .find(|signature| window.starts_with(signature.magic))
+        else {
+            continue;
+        };
+        if let Ok(length) = (signature.length)(window) {
+            found.push((offset, signature.name, length));
+        }
+    }
+
+    let mut objects = Vec::with_capacity(found.len());
+    for (index, (offset, signature, length)) in found.iter().enumerate() {
+        let available = blob.len() - offset;
+        let length = match length {
+            Some(length) => (*length).min(available),
+            None => found
+                .get(index + 1)
+                .map_or(available, |(next_offset, _, _)| next_offset - offset),
+        };
+        if length == 0 || (*offset == 0 && length == blob.len()) {
+            continue;
+        }
+        objects.push(CarvedObject {
+            signature,
+            offset: *offset,
+            length,
+        });
+    }
+    objects
+}
+
+fn unknown_length(_: &[u8]) -> Result<Option<usize>, ()> {
+    Ok(None)
+}
+
+fn read_u32(window: &[u8], offset: usize, big_endian: bool) -> Result<u32, ()> {
+    let bytes: [u8; 4] = window
+        .get(offset..offset + 4)
+        .and_then(|bytes| bytes.try_into().ok())
+        .ok_or(())?;
+    Ok(if big_endian {
+        u32::from_be_bytes(bytes)
+    } else {
+        u32::from_le_bytes(bytes)
+    })
+}
+
+fn elf_length(window: &[u8]) -> Result<Option<usize>, ()> {
+    // EI_CLASS must be 32- or 64-bit and EI_DATA little- or big-endian.
+    match (window.get(4), window.get(5)) {
+        (Some(1 | 2), Some(1 | 2)) => Ok(None),
+        _ => Err(()),
+    }
+}
+
+fn pe_length(window: &[u8]) -> Result<Option<usize>, ()> {
+    let pe_offset = read_u32(window, 0x3c, false)? as usize;
+    match window.get(pe_offset..pe_offset + 4) {
+        Some(b"PE\0\0") => Ok(None),
+        _ => Err(()),
+    }
+}
+
+fn gzip_length(window: &[u8]) -> Result<Option<usize>, ()> {
+    // Reserved flag bits must be clear.
+    match window.get(3) {
+        Some(flags) if flags & 0xe0 == 0 => Ok(None),
+        _ => Err(()),
+    }
+}
+
+fn uimage_length(window: &[u8]) -> Result<Option<usize>, ()> {
+    // 64-byte header followed by `ih_size` bytes of image data.
+    let data_size = read_u32(window, 12, true)? as usize;
+    Ok(Some(64 + data_size))
+}
+
+fn squashfs_length(window: &[u8]) -> Result<Option<usize>, ()> {
+    let bytes: [u8; 8] = window
+        .get(40..48)
+        .and_then(|bytes| bytes.try_into().ok())
+        .ok_or(())?;
+    let bytes_used = u64::from_le_bytes(bytes);
+    usize::try_from(bytes_used).map(Some).map_err(|_| ())
+}
+
+fn cramfs_length(window: &[u8]) -> Result<Option<usize>, ()> {
+    Ok(Some(read_u32(window, 4, false)? as usize))
+}
+
+fn romfs_length(window: &[u8]) -> Result<Option<usize>, ()> {
+    Ok(Some(read_u32(window, 8, true)? as usize))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn uimage(data: &[u8]) -> Vec<u8> {
+        let mut image = vec![0u8; 64];
+        image[..4].copy_from_slice(b"\x27\x05\x19\x56");
+        image[12..16].copy_from_slice(&(data.len() as u32).to_be_bytes());
+        image.extend_from_slice(data);
+        image
+    }
+
+    #[test]
+    fn test_carves_embedded_objects_with_lengths() {
+        let mut blob = b"bootloader padding".to_vec();
+        let uimage_offset = blob.len();
+        blob.extend(uimage(b"kernel!!"));
+        let elf_offset = blob.len();
+        blob.extend_from_slice(b"\x7fELF\x02\x01\x01\x00rest-of-elf");
+
+        let objects = carve_objects(&blob, 16);
+        assert_eq!(
+            objects,
+            vec![
+                CarvedObject {
+                    signature: "uimage",
+                    offset: uimage_offset,
+                    length: 72,
+                },
+                CarvedObject {
+                    signature: "elf",
+                    offset: elf_offset,
+                    length: blob.len() - elf_offset,
+                },
+            ]
+        );
+    }
+
+    #[test]
+    fn test_unknown_lengths_stop_at_next_object() {
+        let mut blob = b"PK\x03\x04zipdata".to_vec();
+        blob.extend_from_slice(b"\x1f\x8b\x08\x00gzipdata");
+        let objects = carve_objects(&blob, 16);
+        assert_eq!(objects.len(), 2);
+        assert_eq!((objects[0].offset, objects[0].length), (0, 11));
+        assert_eq!(objects[1].signature, "gzip");
+        assert_eq!(objects[1].offset + objects[1].length, blob.len());
+    }
+
+    #[test]
+    fn test_false_positives_and_whole_blob_are_skipped() {
+        // "MZ" without a PE header and an ELF with an invalid class are ignored.
+        assert!(carve_objects(b"xxMZ not a pe xx\x7fELF\x09\x09", 16).is_empty());
+        // An object that is the entire blob is the parent, not a child.
+        assert!(carve_objects(b"\x7fELF\x01\x01\x01\x00body", 16).is_empty());
+    }
+
+    #[test]
+    fn test_object_cap() {
+        let blob = b"\x7fELF\x01\x01".repeat(10);
+        assert_eq!(carve_objects(&blob, 3).len(), 3);
+    }
+}
@@ -0,0 +1,194 @@
+#[derive(Clone, Copy, Debug, Eq, PartialEq)]
+pub enum ChunkMode {
+    Fixed,
+    Cdc,
+}
+
+impl ChunkMode {
+    pub fn from_str(value: &str) -> Result<Self, String> {
+        match value {
+            "fixed" => Ok(Self::Fixed),
+            "cdc" => Ok(Self::Cdc),
+            _ => Err(format!("Unsupported chunk mode '{}'", value)),
+        }
+    }
+
+    pub fn as_str(&self) -> &'static str {
+        match self {
+            Self::Fixed => "fixed",
+            Self::Cdc => "cdc",
+        }
+    }
+}
+
+#[derive(Clone, Copy, Debug)]
+pub struct ChunkConfig {
+    pub mode: ChunkMode,
+    // Window length for `fixed`, average target length for `cdc`.
+    pub size: usize,
+    // Bytes shared by consecutive `fixed` windows.
+    pub overlap: usize,
+}
+
+impl ChunkConfig {
+    pub fn validate(&self) -> Result<(), String> {
+        if self.size == 0 {
+            return Err("chunk size must be greater than zero".to_string());
+        }
+        if self.overlap >= self.size {
+            return Err(format!(
+                "chunk overlap {} must be smaller than chunk size {}",
+                self.overlap, self.size
+            ));
+        }
+        Ok(())
+    }
+}
+
+// Returns `(start, end)` byte ranges covering `data`.
+pub fn chunk_ranges(data: &[u8], config: &ChunkConfig) -> Vec<(usize, usize)> {
+    match config.mode {
+        ChunkMode::Fixed => fixed_ranges(data.len(), config.size, config.overlap),
+        ChunkMode::Cdc => cdc_ranges(data, config.size),
+    }
+}
+
+fn fixed_ranges(len: usize, size: usize, overlap: usize) -> Vec<(usize, usize)> {
+    let step = size - overlap;
+    let mut ranges = Vec::new();
+    let mut start = 0;
+    while start < len {
+        let end = (start + size).min(len);
+        ranges.push((start, end));
+        if end == len {
+            break;
+        }
+        start += step;
+    }
+    ranges
+}
+
+// Gear-hash content-defined chunking in the style of FastCDC: boundaries depend on
+// content, so an insertion only shifts the chunks around it and the rest keep their
+// hashes. Chunks stay between a quarter and four times the target size.
+fn cdc_ranges(data: &[u8], target: usize) -> Vec<(usize, usize)> {
+    let min_size = (target / 4).max(1);
+    let max_size = target.saturating_mul(4);
+    let bits = usize::BITS - target.max(2).leading_zeros() - 1;
+    // Boundary when the top `bits` bits of the rolling hash are zero.
+    let mask = if bits == 0 {
+        0
+    } else {
+        u64::MAX << (64 - bits)
+    };
+
+    let mut ranges = Vec::new();
+    let mut start = 0;
+    while start < data.len() {
+        let limit = (start + max_size).min(data.len());
+        let mut end = limit;
+        let mut hash = 0u64;
+        let mut position = start;
+        while position < limit {
+            hash = (hash << 1).wrapping_add(GEAR[data[position] as usize]);
+            position += 1;
+            if position - start >= min_size && hash & mask == 0 {
+                end = position;
+                break;
+            }
+        }
+        ranges.push((start, end));
+        start = end;
+    }
+    ranges
+}
+
+// splitmix64-derived table so the boundaries are stable across builds and platforms.
+const GEAR: [u64; 256] = gear_table();
+
+const fn gear_table() -> [u64; 256] {
+    let mut table = [0u64; 256];
+    let mut state = 0x9e37_79b9_7f4a_7c15u64;
+    let mut index = 0;
+    while index < 256 {
+        state = state.wrapping_add(0x9e37_79b9_7f4a_7c15);
+        let mut value = state;
+        value = (value ^ (value >> 30)).wrapping_mul(0xbf58_476d_1ce4_e5b9);
+        value = (value ^ (value >> 27)).wrapping_mul(0x94d0_49bb_1331_11eb);
+        table[index] = value ^ (value >> 31);
+        index += 1;
+    }
+    table
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn pseudo_random(len: usize, seed: u64) -> Vec<u8> {
+        let mut state = seed;
+        (0..len)
+            .map(|_| {
+                state ^= state << 13;
+                state ^= state >> 7;
+                state ^= state << 17;
+                state as u8
+            })
+            .collect()
+    }
+
+    #[test]
+    fn test_fixed_windows_overlap_and_cover_tail() {
+        let config = ChunkConfig {
+            mode: ChunkMode::Fixed,
+            size: 4,
+            overlap: 1,
+        };
+        assert_eq!(
+            chunk_ranges(&[0u8; 10], &config),
+            vec![(0, 4), (3, 7), (6, 10)]
+        );
+        assert!(chunk_ranges(&[], &config).is_empty());
+    }
+
+    #[test]
+    fn test_cdc_boundaries_resync_after_insertion() {
+        let config = ChunkConfig {
+            mode: ChunkMode::Cdc,
+            size: 256,
+            overlap: 0,
+        };
+        let original = pseudo_random(16 * 1024, 7);
+        let mut shifted = b"inserted-prefix".to_vec();
+        shifted.extend_from_slice(&original);
+
+        let original_ranges = chunk_ranges(&original, &config);
+        assert_eq!(
+            original_ranges.last().map(|range| range.1),
+            Some(original.len())
+        );
+        assert!(original_ranges
+            .iter()
+            .all(|(start, end)| end - start <= 1024));
+
+        let original_chunks: Vec<&[u8]> = original_ranges
+            .iter()
+            .map(|(start, end)| &original[*start..*end])
+            .collect();
+        let shared = chunk_ranges(&shifted, &config)
+            .iter()
+            .filter(|(start, end)| original_chunks.contains(&&shifted[*start..*end]))
+            .count();
+        assert!(shared + 2 >= original_chunks.len());
+    }
+
+    #[test]
+    fn test_config_validation() {
+        let config = ChunkConfig {
+            mode: ChunkMode::Fixed,
+            size: 4,
+            overlap: 4,
+        };
+        assert!(config.validate().is_err());
+    }
+}
@@ -0,0 +1,383 @@
+use crate::precursor::pattern_pack::load_pattern_pack;
+use std::collections::HashSet;
+use std::path::PathBuf;
+
+#[derive(Clone, Debug, Eq, PartialEq)]
+enum Token {
+    NamedGroup { name: String, start: usize },
+    Define,
+    Group,
+    Close { end: usize },
+    Reference(String),
+}
+
+// Group structure and subroutine references of a PCRE2 pattern. Escapes, character
+// classes, `\Q...\E` quoting and `(?#...)` comments are skipped so their parentheses do
+// not count.
+fn tokenize(pattern: &str) -> Vec<Token> {
+    let bytes = pattern.as_bytes();
+    let mut tokens = Vec::new();
+    let mut index = 0;
+    while index < bytes.len() {
+        match bytes[index] {
+            b'\\' => {
+                let rest = &pattern[index + 1..];
+                if let Some(quoted) = rest.strip_prefix('Q') {
+                    index = quoted
+                        .find("\\E")
+                        .map_or(bytes.len(), |end| pattern.len() - quoted.len() + end + 2);
+                    continue;
+                }
+                if let Some(name) = rest
+                    .strip_prefix("g<")
+                    .and_then(|name| name.split_once('>'))
+                    .or_else(|| {
+                        rest.strip_prefix("g'")
+                            .and_then(|name| name.split_once('\''))
+                    })
+                    .map(|(name, _)| name)
+                    .filter(|name| is_group_name(name))
+                {
+                    tokens.push(Token::Reference(name.to_string()));
+                }
+                index += 2;
+            }
+            b'[' => index = class_end(bytes, index),
+            b'(' => {
+                let rest = &pattern[index..];
+                if rest.starts_with("(?#") {
+                    index = rest.find(')').map_or(bytes.len(), |end| index + end + 1);
+                    continue;
+                }
+                let reference = rest
+                    .strip_prefix("(?&")
+                    .or_else(|| rest.strip_prefix("(?P>"))
+                    .and_then(|name| name.split_once(')'))
+                    .map(|(name, _)| name);
+                if let Some(name) = reference {
+                    tokens.push(Token::Reference(name.to_string()));
+                    index += rest.find(')').map_or(rest.len(), |end| end + 1);
+                    continue;
+                }
+                let named = ["(?<", "(?P<", "(?'"].iter().find_map(|prefix| {
+                    let name = rest.strip_prefix(prefix)?;
+                    let end = name.find(['>', '\''])?;
+                    is_group_name(&name[..end]).then(|| name[..end].to_string())
+                });
+                if let Some(name) = named {
+                    tokens.push(Token::NamedGroup { name, start: index });
+                } else if rest.starts_with("(?(DEFINE)") {
+                    // The `(DEFINE)` condition closes itself, so it is consumed whole.
+                    tokens.push(Token::Define);
+                    index += "(?(DEFINE)".len();
+                    continue;
+                } else {
+                    tokens.push(Token::Group);
+                }
+                index += 1;
+            }
+            b')' => {
+                tokens.push(Token::Close { end: index + 1 });
+                index += 1;
+            }
+            _ => index += 1,
+        }
+    }
+    tokens
+}
+
+fn is_group_name(name: &str) -> bool {
+    name.bytes()
+        .next()
+        .is_some_and(|first| first.is_ascii_alphabetic() || first == b'_')
+        && name.bytes().all(|b| b.is_ascii_alphanumeric() || b == b'_')
+}
+
+// Index just past the character class starting at `start`; a `]` right after `[` or
+// `[^` is literal, and POSIX classes such as `[:alpha:]` nest.
+fn class_end(bytes: &[u8], start: usize) -> usize {
+    let mut index = start + 1;
+    if bytes.get(index) == Some(&b'^') {
+        index += 1;
+    }
+    if bytes.get(index) == Some(&b']') {
+        index += 1;
+    }
+    while index < bytes.len() {
+        match bytes[index] {
+            b'\\' => index += 2,
+            b'[' if bytes.get(index + 1) == Some(&b':') => {
+                index = bytes[index + 2..]
+                    .windows(2)
+                    .position(|pair| pair == b":]")
+                    .map_or(index + 1, |end| index + 2 + end + 2);
+            }
+            b']' => return index + 1,
+            _ => index += 1,
+        }
+    }
+    bytes.len()
+}
+
+// A named group inside a DEFINE block, with its span in the pattern.
+struct DefinedGroup {
+    name: String,
+    start: usize,
+    end: usize,
+    // True for groups directly inside the block rather than nested in another group.
+    top_level: bool,
+}
+
+fn defined_groups(pattern: &str) -> Vec<DefinedGroup> {
+    // Each open group: its named-group index in `groups` and whether it is a DEFINE block.
+    let mut open: Vec<(Option<usize>, bool)> = Vec::new();
+    let mut groups: Vec<DefinedGroup> = Vec::new();
+    for token in tokenize(pattern) {
+        let inside_define = open.iter().any(|(_, define)| *define);
+        match token {
+            Token::Define => open.push((None, true)),
+            Token::NamedGroup { name, start } if inside_define => {
+                groups.push(DefinedGroup {
+                    name,
+                    start,
+                    end: start,
+                    top_level: open.last().is_some_and(|(_, define)| *define),
+                });
+                open.push((Some(groups.len() - 1), false));
+            }
+            Token::NamedGroup { .. } | Token::Group => open.push((None, false)),
+            Token::Close { end } => {
+                if let Some((Some(group), _)) = open.pop() {
+                    groups[group].end = end;
+                }
+            }
+            Token::Reference(_) => {}
+        }
+    }
+    groups
+}
+
+fn references(pattern: &str) -> Vec<String> {
+    tokenize(pattern)
+        .into_iter()
+        .filter_map(|token| match token {
+            Token::Reference(name) => Some(name),
+            _ => None,
+        })
+        .collect()
+}
+
+// Names of groups that only exist to be called as subroutines. They never match on their
+// own, so they are not tags.
+pub fn define_group_names(pattern: &str) -> HashSet<String> {
+    defined_groups(pattern)
+        .into_iter()
+        .map(|group| group.name)
+        .collect()
+}
+
+struct Definition {
+    name: String,
+    // The `(?<NAME>...)` group as written in the library.
+    group: String,
+    references: Vec<String>,
+}
+
+// Shared `(?(DEFINE)(?<NAME>...))` blocks from --define-library files. Patterns that call
+// a definition with `(?&NAME)` get it, and every definition it calls in turn, prepended
+// in their own DEFINE block.
+#[derive(Default)]
+pub struct DefineLibrary {
+    definitions: Vec<Definition>,
+}
+
+impl DefineLibrary {
+    // Libraries are read as pattern packs, so comments, blank lines and `@include` work,
+    // but every pattern line must be a DEFINE block.
+    pub fn load(paths: &[PathBuf]) -> Result<Self, String> {
+        let mut library = Self::default();
+        for path in paths {
+            for entry in load_pattern_pack(path)? {
+                library.add(&entry.pattern).map_err(|err| {
+                    format!("Invalid definition library {}: {}", path.display(), err)
+                })?;
+            }
+        }
+        Ok(library)
+    }
+
+    fn add(&mut self, block: &str) -> Result<(), String> {
+        let block = block.trim();
+        let tokens = tokenize(block);
+        // The block has to close exactly at the end of the line.
+        let is_single_block = block.starts_with("(?(DEFINE)")
+            && tokens.iter().try_fold(0usize, |depth, token| match token {
+                Token::Close { end } if depth == 1 => (*end == block.len()).then_some(0),
+                Token::Close { .. } => depth.checked_sub(1),
+                Token::Reference(_) => Some(depth),
+                _ => Some(depth + 1),
+            }) == Some(0);
+        if !is_single_block {
+            return Err(format!(
+                "expected a (?(DEFINE)(?<NAME>...)) block, got '{}'",
+                block
+            ));
+        }
+        let groups: Vec<DefinedGroup> = defined_groups(block)
+            .into_iter()
+            .filter(|group| group.top_level)
+            .collect();
+        if groups.is_empty() {
+            return Err(format!("DEFINE block '{}' names no groups", block));
+        }
+        for group in groups {
+            if self.get(&group.name).is_some() {
+                return Err(format!("'{}' is defined more than once", group.name));
+            }
+            let text = &block[group.start..group.end];
+            self.definitions.push(Definition {
+                name: group.name,
+                group: text.to_string(),
+                references: references(text),
+            });
+        }
+        Ok(())
+    }
+
+    fn get(&self, name: &str) -> Option<&Definition> {
+        self.definitions
+            .iter()
+            .find(|definition| definition.name == name)
+    }
+
+    pub fn len(&self) -> usize {
+        self.definitions.len()
+    }
+
+    // The pattern with the library definitions it calls (directly or through other
+    // definitions) prepended, in library order. Groups the pattern names itself win,
+    // and leading `(*...)` start items stay in front.
+    pub fn compose(&self, pattern: &str) -> String {
+        let local: HashSet<String> = tokenize(pattern)
+            .into_iter()
+            .filter_map(|token| match token {
+                Token::NamedGroup { name, .. } => Some(name),
+                _ => None,
+            })
+            .collect();
+        let mut used: HashSet<&str> = HashSet::new();
+        let mut pending = references(pattern);
+        while let Some(name) = pending.pop() {
+            if local.contains(&name) || used.contains(name.as_str()) {
+                continue;
+            }
+            if let Some(definition) = self.get(&name) {
+                used.insert(&definition.name);
+                pending.extend(definition.references.iter().cloned());
+            }
+        }
+        if used.is_empty() {
+            return pattern.to_string();
+        }
+        let groups: String = self
+            .definitions
+            .iter()
+            .filter(|definition| used.contains(definition.name.as_str()))
+            .map(|definition| definition.group.as_str())
+            .collect();
+        let mut start_items = 0;
+        while pattern[start_items..].starts_with("(*") {
+            match pattern[start_items..].find(')') {
+                Some(end) => start_items += end + 1,
+                None => break,
+            }
+        }
+        format!(
+            "{}(?(DEFINE){}){}",
+            &pattern[..start_items],
+            groups,
+            &pattern[start_items..]
+        )
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn library(blocks: &[&str]) -> DefineLibrary {
+        let mut library = DefineLibrary::default();
+        for block in blocks {
+            library.add(block).expect("add definition");
+        }
+        library
+    }
+
+    #[test]
+    fn compose_prepends_only_the_definitions_a_pattern_calls() {
+        let library = library(&[
+            r"(?(DEFINE)(?<USERNAME>(?:[a-zA-Z0-9._-]+)))",
+            r"(?(DEFINE)(?<USER>(?:(?&USERNAME))))",
+            r"(?(DEFINE)(?<INT>(?:[+-]?(?:[0-9]+))))",
+            r"(?(DEFINE)(?<IPV4>(?<![0-9])(?:(?:[0-1]?[0-9]{1,2}|2[0-4][0-9]|25[0-5])[.]){3}(?:[0-1]?[0-9]{1,2}|2[0-4][0-9]|25[0-5])(?![0-9])))",
+        ]);
+        assert_eq!(library.len(), 4);
+        assert_eq!(
+            library.compose(r"(?<login>user=(?&USER))"),
+            r"(?(DEFINE)(?<USERNAME>(?:[a-zA-Z0-9._-]+))(?<USER>(?:(?&USERNAME))))(?<login>user=(?&USER))"
+        );
+        assert_eq!(library.compose(r"(?<plain>GET /)"), r"(?<plain>GET /)");
+        assert_eq!(
+            library.compose(r"(?<INT>\d+)-(?&INT)"),
+            r"(?<INT>\d+)-(?&INT)"
+        );
+        assert_eq!(
+            library.compose(r"(*LIMIT_MATCH=10)(?<n>port=(?P>INT))"),
+            r"(*LIMIT_MATCH=10)(?(DEFINE)(?<INT>(?:[+-]?(?:[0-9]+))))(?<n>port=(?P>INT))"
+        );
+        // Local groups shadow the library, and escaped or quoted text is not a call.
+        assert_eq!(
+            library
+                .compose(r"(?(DEFINE)(?<INT>\d+))(?<n>(?&INT) \(?&USER\) [(?&USER)] \Q(?&USER)\E)"),
+            r"(?(DEFINE)(?<INT>\d+))(?<n>(?&INT) \(?&USER\) [(?&USER)] \Q(?&USER)\E)"
+        );
+        let composed = library.compose(r"(?<src>from (?&IPV4))");
+        let regex = crate::precursor::util::build_regex(&composed).expect("compile composed");
+        let caps = regex
+            .captures(b"ssh from 10.0.0.7 port 22")
+            .expect("match")
+            .expect("captures");
+        assert_eq!(
+            caps.name("src").map(|m| m.as_bytes()),
+            Some(&b"from 10.0.0.7"[..])
+        );
+        assert!(caps.name("IPV4").is_none());
+    }
+
+    #[test]
+    fn define_group_names_cover_nested_helpers_but_not_tags() {
+        let names = define_group_names(
+            r"(?(DEFINE)(?<HOST>(?<LABEL>[a-z]+)(?:\.(?&LABEL))*))(?<host>(?&HOST))(?<port>\d+)",
+        );
+        let mut names: Vec<&str> = names.iter().map(String::as_str).collect();
+        names.sort();
+        assert_eq!(names, vec!["HOST", "LABEL"]);
+        assert!(define_group_names(r"(?<a>[(?(DEFINE)(?<X>x))])").is_empty());
+    }
+
+    #[test]
+    fn library_entries_must_be_single_define_blocks() {
+        let mut library = DefineLibrary::default();
+        library
+            .add(r"(?(DEFINE)(?<A>a)(?<B>(?&A)b))")
+            .expect("two groups in one block");
+        assert_eq!(library.len(), 2);
+        assert!(library.add(r"(?<tag>x)").is_err());
+        assert!(library.add(r"(?(DEFINE)(?<C>c))(?<tag>x)").is_err());
+        assert!(library.add(r"(?(DEFINE)(?:c))").is_err());
+        let err = library
+            .add(r"(?(DEFINE)(?<A>again))")
+            .expect_err("duplicate");
+        assert!(err.contains("'A' is defined more than once"), "{}", err);
+    }
+}
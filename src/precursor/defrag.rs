@@ -0,0 +1,305 @@
+use crate::precursor::packet::{Fragmentation, IpDatagram};
+use std::collections::HashMap;
+use std::net::IpAddr;
+
+const MAX_DATAGRAM_LEN: usize = 65_535;
+const MAX_PENDING_DATAGRAMS: usize = 8_192;
+
+// Target-based overlap policies as described by Novak ("Target-Based Fragmentation
+// Reassembly"): `first` favours earlier fragments, `last` later ones, `bsd` lets a
+// later fragment win only when it starts before the one it overlaps, and `linux`
+// also lets it win when both start at the same offset.
+#[derive(Clone, Copy, Debug, Eq, PartialEq)]
+pub enum FragmentPolicy {
+    First,
+    Last,
+    Bsd,
+    Linux,
+}
+
+impl FragmentPolicy {
+    pub fn from_str(value: &str) -> Result<Self, String> {
+        match value {
+            "first" => Ok(Self::First),
+            "last" => Ok(Self::Last),
+            "bsd" => Ok(Self::Bsd),
+            "linux" => Ok(Self::Linux),
+            _ => Err(format!("Unsupported IP fragment policy '{}'", value)),
+        }
+    }
+
+    fn later_wins(&self, later_offset: usize, earlier_offset: usize) -> bool {
+        match self {
+            Self::First => false,
+            Self::Last => true,
+            Self::Bsd => later_offset < earlier_offset,
+            Self::Linux => later_offset <= earlier_offset,
+        }
+    }
+}
+
+#[derive(Clone, Copy, Debug)]
+pub struct DefragConfig {
+    pub policy: FragmentPolicy,
+    pub timeout_nanos: u64,
+}
+
+#[derive(Clone, Copy, Debug, Eq, Hash, PartialEq)]
+struct FragmentKey {
+    src_ip: IpAddr,
+    dst_ip: IpAddr,
+    protocol: u8,
+    id: u32,
+}
+
+struct PendingDatagram {
+    vlan_ids: Vec<u16>,
+    first_seen_nanos: u64,
+    total_len: Option<usize>,
+    data: Vec<u8>,
+    // Offset of the fragment that currently owns each byte.
+    owners: Vec<Option<usize>>,
+    fragmentation: Fragmentation,
+}
+
+impl PendingDatagram {
+    fn complete(&self) -> bool {
+        match self.total_len {
+            Some(total_len) => {
+                self.owners.len() >= total_len
+                    && self.owners[..total_len].iter().all(Option::is_some)
+            }
+            None => false,
+        }
+    }
+}
+
+pub struct Defragmenter {
+    config: DefragConfig,
+    pending: HashMap<FragmentKey, PendingDatagram>,
+    expired: u64,
+}
+
+impl Defragmenter {
+    pub fn new(config: DefragConfig) -> Self {
+        Defragmenter {
+            config,
+            pending: HashMap::new(),
+            expired: 0,
+        }
+    }
+
+    // Datagrams that timed out or are still waiting for fragments.
+    pub fn incomplete(&self) -> u64 {
+        self.expired + self.pending.len() as u64
+    }
+
+    // Unfragmented datagrams pass straight through. Fragments are buffered until the
+    // datagram is complete, at which point the reassembled datagram is returned.
+    pub fn push(
+        &mut self,
+        datagram: IpDatagram,
+        timestamp_nanos: u64,
+    ) -> Result<Option<IpDatagram>, String> {
+        self.expire(timestamp_nanos);
+        let Some(fragment) = datagram.fragment else {
+            return Ok(Some(datagram));
+        };
+        let fragment_end = fragment.offset + datagram.body.len();
+        if fragment_end > MAX_DATAGRAM_LEN {
+            return Err(format!(
+                "IP fragment ends at {} beyond maximum datagram size",
+                fragment_end
+            ));
+        }
+
+        let key = FragmentKey {
+            src_ip: datagram.src_ip,
+            dst_ip: datagram.dst_ip,
+            protocol: datagram.protocol,
+            id: fragment.id,
+        };
+        if !self.pending.contains_key(&key) && self.pending.len() >= MAX_PENDING_DATAGRAMS {
+            return Err("too many incomplete fragmented datagrams".to_string());
+        }
+        let pending = self.pending.entry(key).or_insert_with(|| PendingDatagram {
+            vlan_ids: datagram.vlan_ids.clone(),
+            first_seen_nanos: timestamp_nanos,
+            total_len: None,
+            data: Vec::new(),
+            owners: Vec::new(),
+            fragmentation: Fragmentation::default(),
+        });
+
+        pending.fragmentation.fragment_count += 1;
+        if !fragment.more_fragments && pending.total_len.is_none() {
+            pending.total_len = Some(fragment_end);
+        }
+        if pending.data.len() < fragment_end {
+            pending.data.resize(fragment_end, 0);
+            pending.owners.resize(fragment_end, None);
+        }
+        for (index, byte) in datagram.body.iter().enumerate() {
+            let position = fragment.offset + index;
+            match pending.owners[position] {
+                None => {
+                    pending.data[position] = *byte;
+                    pending.owners[position] = Some(fragment.offset);
+                }
+                Some(owner_offset) => {
+                    pending.fragmentation.overlap_bytes += 1;
+                    if pending.data[position] != *byte {
+                        pending.fragmentation.conflicting_bytes += 1;
+                    }
+                    if self.config.policy.later_wins(fragment.offset, owner_offset) {
+                        pending.data[position] = *byte;
+                        pending.owners[position] = Some(fragment.offset);
+                    }
+                }
+            }
+        }
+
+        if !pending.complete() {
+            return Ok(None);
+        }
+        let Some(mut pending) = self.pending.remove(&key) else {
+            return Ok(None);
+        };
+        pending
+            .data
+            .truncate(pending.total_len.unwrap_or(pending.data.len()));
+        Ok(Some(IpDatagram {
+            vlan_ids: pending.vlan_ids,
+            src_ip: key.src_ip,
+            dst_ip: key.dst_ip,
+            protocol: key.protocol,
+            fragment: None,
+            fragmentation: Some(pending.fragmentation),
+            body: pending.data,
+        }))
+    }
+
+    fn expire(&mut self, now_nanos: u64) {
+        let timeout_nanos = self.config.timeout_nanos;
+        let before = self.pending.len();
+        self.pending.retain(|_, pending| {
+            now_nanos.saturating_sub(pending.first_seen_nanos) <= timeout_nanos
+        });
+        self.expired += (before - self.pending.len()) as u64;
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::precursor::packet::IpFragment;
+    use std::net::Ipv4Addr;
+
+    const SECOND: u64 = 1_000_000_000;
+
+    fn fragment(id: u32, offset: usize, more_fragments: bool, body: &[u8]) -> IpDatagram {
+        IpDatagram {
+            vlan_ids: Vec::new(),
+            src_ip: IpAddr::V4(Ipv4Addr::new(10, 0, 0, 1)),
+            dst_ip: IpAddr::V4(Ipv4Addr::new(10, 0, 0, 2)),
+            protocol: 17,
+            fragment: Some(IpFragment {
+                id,
+                offset,
+                more_fragments,
+            }),
+            fragmentation: None,
+            body: body.to_vec(),
+        }
+    }
+
+    fn defragmenter(policy: FragmentPolicy) -> Defragmenter {
+        Defragmenter::new(DefragConfig {
+            policy,
+            timeout_nanos: 30 * SECOND,
+        })
+    }
+
+    #[test]
+    fn test_out_of_order_fragments_reassemble() {
+        let mut defrag = defragmenter(FragmentPolicy::Bsd);
+        assert!(defrag
+            .push(fragment(7, 8, false, b"tail"), 0)
+            .expect("push tail")
+            .is_none());
+        let datagram = defrag
+            .push(fragment(7, 0, true, b"head----"), 1)
+            .expect("push head")
+            .expect("complete datagram");
+        assert_eq!(datagram.body, b"head----tail");
+        assert!(datagram.fragment.is_none());
+        let fragmentation = datagram.fragmentation.expect("fragmentation summary");
+        assert_eq!(fragmentation.fragment_count, 2);
+        assert_eq!(fragmentation.overlap_bytes, 0);
+    }
+
+    #[test]
+    fn test_overlap_policies_pick_expected_bytes() {
+        // Original fragments at 0 and 8, then overlapping rewrites starting at 0 and 8.
+        let cases = [
+            (FragmentPolicy::First, b"AAAAAAAABBBBBBBB".to_vec()),
+            (FragmentPolicy::Last, b"XXXXXXXXYYYYYYYY".to_vec()),
+            (FragmentPolicy::Bsd, b"AAAAAAAAXXXXXXXX".to_vec()),
+            (FragmentPolicy::Linux, b"XXXXXXXXXXXXXXXX".to_vec()),
+        ];
+        for (policy, expected) in cases {
+            let mut defrag = defragmenter(policy);
+            defrag
+                .push(fragment(1, 8, true, b"BBBBBBBB"), 0)
+                .expect("push");
+            defrag
+                .push(fragment(1, 0, true, b"AAAAAAAA"), 0)
+                .expect("push");
+            defrag
+                .push(fragment(1, 0, true, b"XXXXXXXXXXXXXXXX"), 0)
+                .expect("push");
+            let datagram = defrag
+                .push(fragment(1, 8, false, b"YYYYYYYY"), 0)
+                .expect("push")
+                .expect("complete datagram");
+            assert_eq!(datagram.body, expected, "policy {:?}", policy);
+            let fragmentation = datagram.fragmentation.expect("fragmentation summary");
+            assert_eq!(fragmentation.overlap_bytes, 24);
+            assert!(fragmentation.conflicting_bytes > 0);
+        }
+    }
+
+    #[test]
+    fn test_incomplete_datagrams_expire_after_timeout() {
+        let mut defrag = defragmenter(FragmentPolicy::First);
+        defrag
+            .push(fragment(3, 0, true, b"orphaned"), 0)
+            .expect("push");
+        let datagram = defrag
+            .push(fragment(3, 8, false, b"late"), 31 * SECOND)
+            .expect("push");
+        assert!(datagram.is_none());
+        assert_eq!(defrag.expired, 1);
+        assert_eq!(defrag.incomplete(), 2);
+    }
+
+    #[test]
+    fn test_unfragmented_datagrams_pass_through() {
+        let mut defrag = defragmenter(FragmentPolicy::First);
+        let mut datagram = fragment(0, 0, false, b"whole");
+        datagram.fragment = None;
+        let passed = defrag
+            .push(datagram.clone(), 0)
+            .expect("push")
+            .expect("datagram");
+        assert_eq!(passed, datagram);
+    }
+
+    #[test]
+    fn test_oversized_fragment_is_rejected() {
+        let mut defrag = defragmenter(FragmentPolicy::First);
+        assert!(defrag
+            .push(fragment(9, 65_528, false, b"0123456789"), 0)
+            .is_err());
+    }
+}
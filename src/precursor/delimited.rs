@@ -0,0 +1,442 @@
+use serde_json::{json, Map, Value};
+use std::collections::HashSet;
+
+// Guards against an unbalanced quote swallowing the rest of the source.
+const MAX_QUOTED_RECORD_BYTES: usize = 16 * 1024 * 1024;
+const MAX_HEADER_CELL_CHARS: usize = 64;
+
+#[derive(Clone, Copy, Debug, Eq, PartialEq)]
+pub enum Dialect {
+    // RFC 4180: comma separated, `"`-quoted cells may hold commas, quotes (`""`) and newlines.
+    Csv,
+    // IANA text/tab-separated-values: no quoting, `\t`, `\n`, `\r` and `\\` escapes.
+    Tsv,
+}
+
+impl Dialect {
+    pub fn from_str(value: &str) -> Result<Self, String> {
+        match value {
+            "csv" => Ok(Self::Csv),
+            "tsv" => Ok(Self::Tsv),
+            _ => Err(format!("Unsupported delimited format '{}'", value)),
+        }
+    }
+}
+
+#[derive(Clone, Copy, Debug, Eq, PartialEq)]
+pub enum HeaderMode {
+    Auto,
+    Present,
+    Absent,
+}
+
+impl HeaderMode {
+    pub fn from_str(value: &str) -> Result<Self, String> {
+        match value {
+            "auto" => Ok(Self::Auto),
+            "present" => Ok(Self::Present),
+            "absent" => Ok(Self::Absent),
+            _ => Err(format!("Unsupported header mode '{}'", value)),
+        }
+    }
+}
+
+#[derive(Clone, Debug, Eq, PartialEq)]
+pub enum ColumnSelector {
+    Name(String),
+    // 1-based, as in `cut -f`.
+    Number(usize),
+}
+
+impl ColumnSelector {
+    pub fn parse(value: &str) -> Result<Self, String> {
+        if value.is_empty() {
+            return Err("payload column must not be empty".to_string());
+        }
+        if !value.bytes().all(|byte| byte.is_ascii_digit()) {
+            return Ok(Self::Name(value.to_string()));
+        }
+        match value.parse::<usize>() {
+            Ok(number) if number > 0 => Ok(Self::Number(number)),
+            _ => Err(format!(
+                "payload column number {:?} must be 1 or greater",
+                value
+            )),
+        }
+    }
+}
+
+#[derive(Clone, Debug)]
+pub struct DelimitedConfig {
+    pub dialect: Dialect,
+    pub header: HeaderMode,
+    pub payload_column: ColumnSelector,
+}
+
+impl DelimitedConfig {
+    pub fn validate(&self) -> Result<(), String> {
+        if let (ColumnSelector::Name(name), HeaderMode::Absent) =
+            (&self.payload_column, self.header)
+        {
+            return Err(format!(
+                "payload column {:?} is selected by name but the header is absent",
+                name
+            ));
+        }
+        Ok(())
+    }
+}
+
+// Turns the rows of one delimited source into JSON records of the form
+// `{"payload": <cell>, "columns": {<name>: <cell>, ...}}`, where `columns` holds every
+// other cell keyed by its header name (or `column_N` without a header). Header rows,
+// blank lines and the continuation lines of multi-line quoted cells yield `None`.
+pub struct DelimitedReader {
+    config: DelimitedConfig,
+    header: Option<Vec<String>>,
+    header_resolved: bool,
+    failed: bool,
+    pending: Option<String>,
+}
+
+impl DelimitedReader {
+    pub fn new(config: DelimitedConfig) -> Self {
+        let header_resolved = config.header == HeaderMode::Absent;
+        Self {
+            config,
+            header: None,
+            header_resolved,
+            failed: false,
+            pending: None,
+        }
+    }
+
+    // True while a quoted CSV cell is still open across lines.
+    pub fn in_quoted_cell(&self) -> bool {
+        self.pending.is_some()
+    }
+
+    // Reports a quoted cell left open at the end of the source.
+    pub fn finish(&mut self) -> Result<(), String> {
+        match self.pending.take() {
+            Some(_) => Err("quoted cell is not closed before the end of input".to_string()),
+            None => Ok(()),
+        }
+    }
+
+    pub fn normalize_line(&mut self, line: &str) -> Result<Option<String>, String> {
+        if self.failed {
+            return Ok(None);
+        }
+        let line = if self.header_resolved || self.pending.is_some() {
+            line
+        } else {
+            line.trim_start_matches('\u{feff}')
+        };
+        let record = match self.pending.take() {
+            Some(mut pending) => {
+                pending.push('\n');
+                pending.push_str(line);
+                pending
+            }
+            None if line.trim().is_empty() => return Ok(None),
+            None => line.to_string(),
+        };
+        let cells = match self.config.dialect {
+            Dialect::Csv => match split_csv(&record) {
+                Some(cells) => cells,
+                None if record.len() > MAX_QUOTED_RECORD_BYTES => {
+                    return Err(format!(
+                        "quoted cell exceeds {} bytes without a closing quote",
+                        MAX_QUOTED_RECORD_BYTES
+                    ));
+                }
+                None => {
+                    self.pending = Some(record);
+                    return Ok(None);
+                }
+            },
+            Dialect::Tsv => record.split('\t').map(unescape_tsv).collect(),
+        };
+        if !self.header_resolved {
+            self.header_resolved = true;
+            if self.is_header(&cells)? {
+                self.header = Some(unique_names(cells));
+                return Ok(None);
+            }
+        }
+        self.build_record(cells)
+            .map(|record| Some(record.to_string()))
+    }
+
+    fn is_header(&mut self, cells: &[String]) -> Result<bool, String> {
+        match (&self.config.payload_column, self.config.header) {
+            (ColumnSelector::Name(name), _) => {
+                if cells.iter().any(|cell| cell == name) {
+                    return Ok(true);
+                }
+                self.failed = true;
+                Err(format!(
+                    "payload column {:?} not found in header row; skipping source",
+                    name
+                ))
+            }
+            (ColumnSelector::Number(_), HeaderMode::Present) => Ok(true),
+            (ColumnSelector::Number(_), HeaderMode::Absent) => Ok(false),
+            (ColumnSelector::Number(_), HeaderMode::Auto) => Ok(looks_like_header(cells)),
+        }
+    }
+
+    fn build_record(&self, cells: Vec<String>) -> Result<Value, String> {
+        let payload_index = match &self.config.payload_column {
+            ColumnSelector::Number(number) => number - 1,
+            ColumnSelector::Name(name) => self
+                .header
+                .as_ref()
+                .and_then(|header| header.iter().position(|column| column == name))
+                .unwrap_or(usize::MAX),
+        };
+        if payload_index >= cells.len() {
+            return Err(format!(
+                "row has {} columns; payload column {} is missing",
+                cells.len(),
+                payload_index.saturating_add(1)
+            ));
+        }
+        let mut payload = String::new();
+        let mut columns = Map::new();
+        for (index, cell) in cells.into_iter().enumerate() {
+            if index == payload_index {
+                payload = cell;
+                continue;
+            }
+            let name = self
+                .header
+                .as_ref()
+                .and_then(|header| header.get(index).cloned())
+                .unwrap_or_else(|| format!("column_{}", index + 1));
+            columns.insert(name, Value::String(cell));
+        }
+        Ok(json!({ "payload": payload, "columns": columns }))
+    }
+}
+
+// Splits one CSV record, or returns `None` while a quoted cell is still open. Stray
+// quotes inside unquoted cells and text after a closing quote are kept literally.
+fn split_csv(record: &str) -> Option<Vec<String>> {
+    let mut cells = Vec::new();
+    let mut cell = String::new();
+    let mut chars = record.chars().peekable();
+    let mut at_cell_start = true;
+    let mut in_quotes = false;
+    while let Some(ch) = chars.next() {
+        if in_quotes {
+            if ch == '"' {
+                if chars.peek() == Some(&'"') {
+                    chars.next();
+                    cell.push('"');
+                } else {
+                    in_quotes = false;
+                }
+            } else {
+                cell.push(ch);
+            }
+            continue;
+        }
+        match ch {
+            '"' if at_cell_start => {
+                in_quotes = true;
+                at_cell_start = false;
+            }
+            ',' => {
+                cells.push(std::mem::take(&mut cell));
+                at_cell_start = true;
+            }
+            _ => {
+                cell.push(ch);
+                at_cell_start = false;
+            }
+        }
+    }
+    if in_quotes {
+        return None;
+    }
+    cells.push(cell);
+    Some(cells)
+}
+
+fn unescape_tsv(cell: &str) -> String {
+    if !cell.contains('\\') {
+        return cell.to_string();
+    }
+    let mut unescaped = String::with_capacity(cell.len());
+    let mut chars = cell.chars();
+    while let Some(ch) = chars.next() {
+        if ch != '\\' {
+            unescaped.push(ch);
+            continue;
+        }
+        match chars.next() {
+            Some('t') => unescaped.push('\t'),
+            Some('n') => unescaped.push('\n'),
+            Some('r') => unescaped.push('\r'),
+            Some('\\') => unescaped.push('\\'),
+            Some(other) => {
+                unescaped.push('\\');
+                unescaped.push(other);
+            }
+            None => unescaped.push('\\'),
+        }
+    }
+    unescaped
+}
+
+// A first row counts as a header when every cell is a short, distinct label: starting
+// with a letter or `_` and made of letters, digits, spaces and `_-.()` (so data such
+// as addresses, numbers, URLs or encoded payloads is not mistaken for one).
+fn looks_like_header(cells: &[String]) -> bool {
+    let mut seen = HashSet::new();
+    cells.iter().all(|cell| {
+        let label = cell.trim();
+        label.chars().count() <= MAX_HEADER_CELL_CHARS
+            && label
+                .chars()
+                .next()
+                .is_some_and(|first| first.is_alphabetic() || first == '_')
+            && label
+                .chars()
+                .all(|ch| ch.is_alphanumeric() || matches!(ch, ' ' | '_' | '-' | '.' | '(' | ')'))
+            && seen.insert(label)
+    })
+}
+
+// Empty names become `column_N` and repeated names get a `_N` suffix so no cell is lost.
+fn unique_names(cells: Vec<String>) -> Vec<String> {
+    let mut seen = HashSet::new();
+    cells
+        .into_iter()
+        .enumerate()
+        .map(|(index, cell)| {
+            let base = if cell.trim().is_empty() {
+                format!("column_{}", index + 1)
+            } else {
+                cell
+            };
+            let mut name = base.clone();
+            let mut suffix = 2;
+            while !seen.insert(name.clone()) {
+                name = format!("{}_{}", base, suffix);
+                suffix += 1;
+            }
+            name
+        })
+        .collect()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn records(config: DelimitedConfig, text: &str) -> Vec<Value> {
+        let mut reader = DelimitedReader::new(config);
+        let records = text
+            .lines()
+            .filter_map(|line| reader.normalize_line(line).expect("normalize"))
+            .map(|record| serde_json::from_str(&record).expect("json"))
+            .collect();
+        reader.finish().expect("finish");
+        records
+    }
+
+    fn csv(header: HeaderMode, column: &str) -> DelimitedConfig {
+        DelimitedConfig {
+            dialect: Dialect::Csv,
+            header,
+            payload_column: ColumnSelector::parse(column).expect("column"),
+        }
+    }
+
+    #[test]
+    fn test_csv_quoting_and_named_payload_column() {
+        let text = "\u{feff}time,host,cmdline,note\n\
+2026-03-01,ws1,\"powershell -enc SQBFAFgA\",\"said \"\"hi\"\", twice\"\n\
+2026-03-02,ws2,\"cmd /c \"\"echo\n\
+multi\"\"\",\n";
+        let records = records(csv(HeaderMode::Auto, "cmdline"), text);
+        assert_eq!(records.len(), 2);
+        assert_eq!(records[0]["payload"], json!("powershell -enc SQBFAFgA"));
+        assert_eq!(
+            records[0]["columns"],
+            json!({"time": "2026-03-01", "host": "ws1", "note": "said \"hi\", twice"})
+        );
+        assert_eq!(records[1]["payload"], json!("cmd /c \"echo\nmulti\""));
+        assert_eq!(records[1]["columns"]["note"], json!(""));
+
+        let mut reader = DelimitedReader::new(csv(HeaderMode::Auto, "missing"));
+        assert!(reader.normalize_line("a,b").is_err());
+        assert_eq!(reader.normalize_line("1,2").expect("skipped"), None);
+
+        let mut reader = DelimitedReader::new(csv(HeaderMode::Auto, "1"));
+        assert_eq!(reader.normalize_line("\"open").expect("pending"), None);
+        assert!(reader.in_quoted_cell());
+        assert!(reader.finish().is_err());
+    }
+
+    #[test]
+    fn test_header_detection_for_numbered_columns() {
+        let with_header = records(
+            csv(HeaderMode::Auto, "2"),
+            "id,payload,host\n1,R0VU,x\n2,UE9TVA==,y\n",
+        );
+        assert_eq!(with_header.len(), 2);
+        assert_eq!(with_header[0]["payload"], json!("R0VU"));
+        assert_eq!(with_header[0]["columns"], json!({"id": "1", "host": "x"}));
+
+        let duplicates = records(
+            csv(HeaderMode::Present, "2"),
+            "id,payload,id,\n1,R0VU,x,y\n",
+        );
+        assert_eq!(
+            duplicates[0]["columns"],
+            json!({"id": "1", "id_2": "x", "column_4": "y"})
+        );
+
+        let without_header = records(csv(HeaderMode::Auto, "2"), "1,R0VU\n2,UE9TVA==\n");
+        assert!(!looks_like_header(&[
+            "10.0.0.8".to_string(),
+            "curl/8.0".to_string()
+        ]));
+        assert!(looks_like_header(&[
+            "Source IP".to_string(),
+            "event.action".to_string()
+        ]));
+        assert_eq!(without_header.len(), 2);
+        assert_eq!(without_header[0]["columns"], json!({"column_1": "1"}));
+
+        let forced = records(csv(HeaderMode::Absent, "1"), "name,value\n");
+        assert_eq!(forced[0]["payload"], json!("name"));
+        assert!(csv(HeaderMode::Absent, "value").validate().is_err());
+        assert!(ColumnSelector::parse("0").is_err());
+
+        let mut reader = DelimitedReader::new(csv(HeaderMode::Absent, "3"));
+        assert!(reader.normalize_line("a,b").is_err());
+    }
+
+    #[test]
+    fn test_tsv_escapes_and_extra_cells() {
+        let config = DelimitedConfig {
+            dialect: Dialect::Tsv,
+            header: HeaderMode::Present,
+            payload_column: ColumnSelector::parse("body").expect("column"),
+        };
+        let records = records(
+            config,
+            "src\tbody\n10.0.0.1\tGET /\\tx\\\\y\\n\t\"extra\"\n",
+        );
+        assert_eq!(records[0]["payload"], json!("GET /\tx\\y\n"));
+        assert_eq!(
+            records[0]["columns"],
+            json!({"src": "10.0.0.1", "column_3": "\"extra\""})
+        );
+    }
+}
@@ -0,0 +1,266 @@
+use base64::engine::{general_purpose::STANDARD, Engine};
+use serde_json::{Map, Value};
+
+#[derive(Clone, Copy, Debug, Eq, PartialEq)]
+enum FieldEncoding {
+    Base64,
+    Text,
+}
+
+struct PayloadField {
+    // Dotted path into the event; `[]` fans out over every array element.
+    path: &'static [&'static str],
+    encoding: FieldEncoding,
+    // `*_printable` twin carrying the same bytes with non-printable bytes replaced;
+    // only read when the base64 field itself is missing.
+    printable: Option<&'static [&'static str]>,
+}
+
+// Payload-bearing fields across alert, http, fileinfo, dns and tls events, in report order.
+const PAYLOAD_FIELDS: &[PayloadField] = &[
+    PayloadField {
+        path: &["payload"],
+        encoding: FieldEncoding::Base64,
+        printable: Some(&["payload_printable"]),
+    },
+    PayloadField {
+        path: &["packet"],
+        encoding: FieldEncoding::Base64,
+        printable: None,
+    },
+    PayloadField {
+        path: &["http", "http_request_body"],
+        encoding: FieldEncoding::Base64,
+        printable: Some(&["http", "http_request_body_printable"]),
+    },
+    PayloadField {
+        path: &["http", "http_response_body"],
+        encoding: FieldEncoding::Base64,
+        printable: Some(&["http", "http_response_body_printable"]),
+    },
+    PayloadField {
+        path: &["http", "url"],
+        encoding: FieldEncoding::Text,
+        printable: None,
+    },
+    PayloadField {
+        path: &["fileinfo", "filename"],
+        encoding: FieldEncoding::Text,
+        printable: None,
+    },
+    PayloadField {
+        path: &["files", "[]", "filename"],
+        encoding: FieldEncoding::Text,
+        printable: None,
+    },
+    PayloadField {
+        path: &["dns", "rrname"],
+        encoding: FieldEncoding::Text,
+        printable: None,
+    },
+    PayloadField {
+        path: &["dns", "queries", "[]", "rrname"],
+        encoding: FieldEncoding::Text,
+        printable: None,
+    },
+    PayloadField {
+        path: &["tls", "sni"],
+        encoding: FieldEncoding::Text,
+        printable: None,
+    },
+];
+
+const FLOW_FIELDS: &[&str] = &[
+    "event_type",
+    "timestamp",
+    "flow_id",
+    "in_iface",
+    "src_ip",
+    "src_port",
+    "dest_ip",
+    "dest_port",
+    "proto",
+    "app_proto",
+];
+
+const ALERT_FIELDS: &[&str] = &[
+    "signature_id",
+    "signature",
+    "category",
+    "severity",
+    "gid",
+    "rev",
+];
+
+#[derive(Clone, Debug, Eq, PartialEq)]
+pub struct EvePayload {
+    // Field path the payload came from, e.g. `http.http_request_body` or `files[1].filename`.
+    pub field: String,
+    pub payload: Vec<u8>,
+}
+
+pub fn parse_eve_record(line: &str) -> Result<Value, String> {
+    let record: Value = serde_json::from_str(line)
+        .map_err(|err| format!("Unable to parse EVE record as JSON: {}", err))?;
+    if !record.is_object() {
+        return Err("EVE record must be a JSON object".to_string());
+    }
+    Ok(record)
+}
+
+// Returns every decoded payload field present in an EVE event. Events without payload
+// fields (flow, stats, netflow, ...) yield nothing.
+pub fn eve_payloads(record: &Value) -> Result<Vec<EvePayload>, String> {
+    let mut payloads = Vec::new();
+    for field in PAYLOAD_FIELDS {
+        let mut values = Vec::new();
+        collect_values(record, field.path, String::new(), &mut values);
+        let mut encoding = field.encoding;
+        if values.is_empty() {
+            if let Some(printable) = field.printable {
+                collect_values(record, printable, String::new(), &mut values);
+                encoding = FieldEncoding::Text;
+            }
+        }
+        for (label, value) in values {
+            let Some(text) = value.as_str().filter(|text| !text.is_empty()) else {
+                continue;
+            };
+            let payload = match encoding {
+                FieldEncoding::Base64 => STANDARD.decode(text).map_err(|err| {
+                    format!("Unable to decode EVE field {} as base64: {}", label, err)
+                })?,
+                FieldEncoding::Text => text.as_bytes().to_vec(),
+            };
+            payloads.push(EvePayload {
+                field: label,
+                payload,
+            });
+        }
+    }
+    Ok(payloads)
+}
+
+// Flow and alert metadata copied into every report derived from the event.
+pub fn eve_context(record: &Value) -> Map<String, Value> {
+    let mut context = Map::new();
+    for name in FLOW_FIELDS {
+        if let Some(value) = record.get(*name) {
+            context.insert(name.to_string(), value.clone());
+        }
+    }
+    if let Some(alert) = record.get("alert") {
+        for name in ALERT_FIELDS {
+            if let Some(value) = alert.get(*name) {
+                context.insert(name.to_string(), value.clone());
+            }
+        }
+    }
+    context
+}
+
+fn collect_values<'a>(
+    value: &'a Value,
+    path: &[&str],
+    label: String,
+    out: &mut Vec<(String, &'a Value)>,
+) {
+    let Some((segment, rest)) = path.split_first() else {
+        out.push((label, value));
+        return;
+    };
+    if *segment == "[]" {
+        if let Some(items) = value.as_array() {
+            for (index, item) in items.iter().enumerate() {
+                collect_values(item, rest, format!("{}[{}]", label, index), out);
+            }
+        }
+        return;
+    }
+    if let Some(child) = value.get(*segment) {
+        let label = if label.is_empty() {
+            segment.to_string()
+        } else {
+            format!("{}.{}", label, segment)
+        };
+        collect_values(child, rest, label, out);
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use serde_json::json;
+
+    fn fields(payloads: &[EvePayload]) -> Vec<&str> {
+        payloads
+            .iter()
+            .map(|payload| payload.field.as_str())
+            .collect()
+    }
+
+    #[test]
+    fn test_alert_payload_packet_and_http_body_are_decoded() {
+        let record = json!({
+            "event_type": "alert",
+            "payload": STANDARD.encode("GET /shell.php HTTP/1.1"),
+            "payload_printable": "GET /shell.php HTTP/1.1",
+            "packet": STANDARD.encode(b"\x45\x00\x00\x28"),
+            "http": {
+                "url": "/shell.php",
+                "http_request_body": STANDARD.encode("cmd=id"),
+            },
+        });
+        let payloads = eve_payloads(&record).expect("payloads");
+        assert_eq!(
+            fields(&payloads),
+            vec!["payload", "packet", "http.http_request_body", "http.url"]
+        );
+        assert_eq!(payloads[0].payload, b"GET /shell.php HTTP/1.1");
+        assert_eq!(payloads[1].payload, b"\x45\x00\x00\x28");
+        assert_eq!(payloads[2].payload, b"cmd=id");
+    }
+
+    #[test]
+    fn test_printable_fallback_and_file_arrays() {
+        let record = json!({
+            "event_type": "http",
+            "payload_printable": "USER anonymous",
+            "files": [{"filename": "/a.exe"}, {"filename": "/b.sh"}],
+        });
+        let payloads = eve_payloads(&record).expect("payloads");
+        assert_eq!(
+            fields(&payloads),
+            vec![
+                "payload_printable",
+                "files[0].filename",
+                "files[1].filename"
+            ]
+        );
+        assert_eq!(payloads[2].payload, b"/b.sh");
+
+        assert!(eve_payloads(&json!({"event_type": "flow"}))
+            .expect("payloads")
+            .is_empty());
+        assert!(eve_payloads(&json!({"payload": "not base64!"})).is_err());
+    }
+
+    #[test]
+    fn test_context_copies_flow_and_alert_metadata() {
+        let record = json!({
+            "event_type": "alert",
+            "src_ip": "10.0.0.5",
+            "dest_port": 80,
+            "app_proto": "http",
+            "payload": "",
+            "alert": {"signature_id": 2024897, "signature": "ET EXPLOIT", "action": "allowed"},
+        });
+        let context = eve_context(&record);
+        assert_eq!(context["src_ip"], json!("10.0.0.5"));
+        assert_eq!(context["dest_port"], json!(80));
+        assert_eq!(context["app_proto"], json!("http"));
+        assert_eq!(context["signature_id"], json!(2024897));
+        assert!(!context.contains_key("payload"));
+        assert!(!context.contains_key("action"));
+    }
+}
@@ -0,0 +1,417 @@
+use serde_json::{json, Value};
+use std::fs::{File, Metadata};
+use std::io::{BufRead, BufReader, Seek, SeekFrom};
+use std::path::{Path, PathBuf};
+
+#[derive(Clone, Copy, Debug, Eq, PartialEq)]
+pub enum FollowStart {
+    Beginning,
+    End,
+}
+
+impl FollowStart {
+    pub fn from_str(value: &str) -> Result<Self, String> {
+        match value {
+            "start" => Ok(Self::Beginning),
+            "end" => Ok(Self::End),
+            _ => Err(format!("Unsupported follow start position '{}'", value)),
+        }
+    }
+}
+
+#[derive(Clone, Debug)]
+pub struct FollowConfig {
+    pub path: PathBuf,
+    // Checkpoint file for the read offset; a matching checkpoint wins over `start`.
+    pub state_path: Option<PathBuf>,
+    pub start: FollowStart,
+}
+
+// Distinguishes a rotated-in replacement from the file being read.
+#[derive(Clone, Copy, Debug, Default, Eq, PartialEq)]
+struct FileIdentity {
+    device: u64,
+    inode: u64,
+}
+
+impl FileIdentity {
+    #[cfg(unix)]
+    fn of(metadata: &Metadata) -> Self {
+        use std::os::unix::fs::MetadataExt;
+        Self {
+            device: metadata.dev(),
+            inode: metadata.ino(),
+        }
+    }
+
+    #[cfg(not(unix))]
+    fn of(_metadata: &Metadata) -> Self {
+        Self::default()
+    }
+}
+
+#[cfg(unix)]
+fn is_fifo(metadata: &Metadata) -> bool {
+    use std::os::unix::fs::FileTypeExt;
+    metadata.file_type().is_fifo()
+}
+
+#[cfg(not(unix))]
+fn is_fifo(_metadata: &Metadata) -> bool {
+    false
+}
+
+#[derive(Debug)]
+pub enum FollowEvent {
+    Line {
+        line: String,
+        line_number: u64,
+        offset: u64,
+    },
+    // The source was replaced, truncated, or (for a FIFO) its writers went away;
+    // line numbers and offsets restart from the beginning of the new content.
+    Reopened {
+        reason: &'static str,
+    },
+}
+
+struct Checkpoint {
+    identity: FileIdentity,
+    offset: u64,
+    line_number: u64,
+}
+
+// Tails a growing file or FIFO. Only complete lines are handed out; a trailing partial
+// line waits for its newline. Rotation (the path now names another file) and truncation
+// (the file shrank below the read offset) both restart reading from the beginning.
+pub struct LogFollower {
+    config: FollowConfig,
+    reader: Option<BufReader<File>>,
+    identity: FileIdentity,
+    fifo: bool,
+    // Byte offset just past the last complete line handed out.
+    offset: u64,
+    line_number: u64,
+    partial: Vec<u8>,
+    reopens: u64,
+    checkpoint: Option<Checkpoint>,
+    first_open: bool,
+}
+
+impl LogFollower {
+    pub fn new(config: FollowConfig) -> Result<Self, String> {
+        let checkpoint = match &config.state_path {
+            Some(state_path) => load_checkpoint(state_path, &config.path)?,
+            None => None,
+        };
+        Ok(Self {
+            config,
+            reader: None,
+            identity: FileIdentity::default(),
+            fifo: false,
+            offset: 0,
+            line_number: 0,
+            partial: Vec::new(),
+            reopens: 0,
+            checkpoint,
+            first_open: true,
+        })
+    }
+
+    // Hands every newly completed line to `on_event` and returns whether any bytes
+    // arrived. Opening a FIFO blocks until a writer connects.
+    pub fn poll(
+        &mut self,
+        mut on_event: impl FnMut(FollowEvent) -> Result<(), String>,
+    ) -> Result<bool, String> {
+        if self.reader.is_none() && !self.open()? {
+            return Ok(false);
+        }
+        let mut progressed = false;
+        while let Some(reader) = self.reader.as_mut() {
+            let read = reader
+                .read_until(b'\n', &mut self.partial)
+                .map_err(|err| format!("Unable to read {}: {}", self.config.path.display(), err))?;
+            if read == 0 {
+                break;
+            }
+            progressed = true;
+            if self.partial.last() != Some(&b'\n') {
+                break;
+            }
+            self.emit_partial(&mut on_event)?;
+        }
+
+        if self.fifo {
+            if !progressed {
+                // EOF on a FIFO: every writer closed; wait for the next one.
+                self.emit_partial(&mut on_event)?;
+                self.restart();
+                on_event(FollowEvent::Reopened {
+                    reason: "fifo writers closed",
+                })?;
+            }
+            return Ok(progressed);
+        }
+        let Ok(metadata) = std::fs::metadata(&self.config.path) else {
+            // Rotated away and not recreated yet; keep the old handle until it is.
+            return Ok(progressed);
+        };
+        if FileIdentity::of(&metadata) != self.identity {
+            self.emit_partial(&mut on_event)?;
+            self.restart();
+            on_event(FollowEvent::Reopened { reason: "rotated" })?;
+            return Ok(true);
+        }
+        if metadata.len() < self.offset + self.partial.len() as u64 {
+            self.restart();
+            on_event(FollowEvent::Reopened {
+                reason: "truncated",
+            })?;
+            return Ok(true);
+        }
+        Ok(progressed)
+    }
+
+    // Writes the checkpoint atomically (temp file + rename).
+    pub fn save_state(&self) -> Result<(), String> {
+        let Some(state_path) = &self.config.state_path else {
+            return Ok(());
+        };
+        let state = json!({
+            "path": self.config.path.to_string_lossy(),
+            "device": self.identity.device,
+            "inode": self.identity.inode,
+            "offset": self.offset,
+            "line": self.line_number,
+        });
+        let temp_path = state_path.with_extension("tmp");
+        std::fs::write(&temp_path, state.to_string())
+            .and_then(|_| std::fs::rename(&temp_path, state_path))
+            .map_err(|err| {
+                format!(
+                    "Unable to write follow state {}: {}",
+                    state_path.display(),
+                    err
+                )
+            })
+    }
+
+    pub fn status(&self) -> Value {
+        json!({
+            "Path": self.config.path.to_string_lossy(),
+            "Offset": self.offset,
+            "Lines": self.line_number,
+            "Reopens": self.reopens,
+        })
+    }
+
+    fn open(&mut self) -> Result<bool, String> {
+        let mut file = match File::open(&self.config.path) {
+            Ok(file) => file,
+            Err(err) if err.kind() == std::io::ErrorKind::NotFound => return Ok(false),
+            Err(err) => {
+                return Err(format!(
+                    "Unable to open {}: {}",
+                    self.config.path.display(),
+                    err
+                ))
+            }
+        };
+        let metadata = file
+            .metadata()
+            .map_err(|err| format!("Unable to stat {}: {}", self.config.path.display(), err))?;
+        self.identity = FileIdentity::of(&metadata);
+        self.fifo = is_fifo(&metadata);
+        self.offset = 0;
+        self.line_number = 0;
+        if std::mem::take(&mut self.first_open) && !self.fifo {
+            match self.checkpoint.take() {
+                Some(checkpoint)
+                    if checkpoint.identity == self.identity
+                        && checkpoint.offset <= metadata.len() =>
+                {
+                    self.offset = checkpoint.offset;
+                    self.line_number = checkpoint.line_number;
+                }
+                _ if self.config.start == FollowStart::End => self.offset = metadata.len(),
+                _ => {}
+            }
+            file.seek(SeekFrom::Start(self.offset))
+                .map_err(|err| format!("Unable to seek {}: {}", self.config.path.display(), err))?;
+        }
+        self.reader = Some(BufReader::new(file));
+        Ok(true)
+    }
+
+    fn restart(&mut self) {
+        self.reader = None;
+        self.partial.clear();
+        self.offset = 0;
+        self.line_number = 0;
+        self.reopens += 1;
+    }
+
+    fn emit_partial(
+        &mut self,
+        on_event: &mut impl FnMut(FollowEvent) -> Result<(), String>,
+    ) -> Result<(), String> {
+        if self.partial.is_empty() {
+            return Ok(());
+        }
+        let mut raw = std::mem::take(&mut self.partial);
+        let offset = self.offset;
+        self.offset += raw.len() as u64;
+        self.line_number += 1;
+        if raw.last() == Some(&b'\n') {
+            raw.pop();
+            if raw.last() == Some(&b'\r') {
+                raw.pop();
+            }
+        }
+        match String::from_utf8(raw) {
+            Ok(line) => on_event(FollowEvent::Line {
+                line,
+                line_number: self.line_number,
+                offset,
+            }),
+            Err(err) => {
+                eprintln!(
+                    "Unable to read line {} from {}: {}",
+                    self.line_number,
+                    self.config.path.display(),
+                    err.utf8_error()
+                );
+                Ok(())
+            }
+        }
+    }
+}
+
+// A checkpoint written for another path is ignored rather than applied.
+fn load_checkpoint(state_path: &Path, path: &Path) -> Result<Option<Checkpoint>, String> {
+    let text = match std::fs::read_to_string(state_path) {
+        Ok(text) => text,
+        Err(err) if err.kind() == std::io::ErrorKind::NotFound => return Ok(None),
+        Err(err) => {
+            return Err(format!(
+                "Unable to read follow state {}: {}",
+                state_path.display(),
+                err
+            ))
+        }
+    };
+    let state: Value = serde_json::from_str(&text).map_err(|err| {
+        format!(
+            "Unable to parse follow state {}: {}",
+            state_path.display(),
+            err
+        )
+    })?;
+    if state["path"].as_str() != Some(path.to_string_lossy().as_ref()) {
+        return Ok(None);
+    }
+    Ok(Some(Checkpoint {
+        identity: FileIdentity {
+            device: state["device"].as_u64().unwrap_or_default(),
+            inode: state["inode"].as_u64().unwrap_or_default(),
+        },
+        offset: state["offset"].as_u64().unwrap_or_default(),
+        line_number: state["line"].as_u64().unwrap_or_default(),
+    }))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::io::Write;
+
+    fn temp_path(name: &str) -> PathBuf {
+        std::env::temp_dir().join(format!("precursor-follow-{}-{}", std::process::id(), name))
+    }
+
+    fn drain(follower: &mut LogFollower) -> Vec<String> {
+        let mut events = Vec::new();
+        follower
+            .poll(|event| {
+                events.push(match event {
+                    FollowEvent::Line {
+                        line, line_number, ..
+                    } => format!("{}:{}", line_number, line),
+                    FollowEvent::Reopened { reason } => format!("reopened:{}", reason),
+                });
+                Ok(())
+            })
+            .expect("poll");
+        events
+    }
+
+    #[test]
+    fn test_partial_lines_truncation_and_rotation() {
+        let path = temp_path("rotate.log");
+        let rotated = temp_path("rotate.log.1");
+        std::fs::write(&path, "one\ntw").expect("write");
+        let mut follower = LogFollower::new(FollowConfig {
+            path: path.clone(),
+            state_path: None,
+            start: FollowStart::Beginning,
+        })
+        .expect("follower");
+        assert_eq!(drain(&mut follower), vec!["1:one"]);
+
+        let mut file = std::fs::OpenOptions::new()
+            .append(true)
+            .open(&path)
+            .expect("append");
+        file.write_all(b"o\r\nthree\n").expect("append");
+        assert_eq!(drain(&mut follower), vec!["2:two", "3:three"]);
+
+        std::fs::write(&path, "x\n").expect("truncate");
+        assert_eq!(drain(&mut follower), vec!["reopened:truncated"]);
+        assert_eq!(drain(&mut follower), vec!["1:x"]);
+
+        std::fs::rename(&path, &rotated).expect("rotate");
+        std::fs::write(&path, "fresh\n").expect("recreate");
+        let mut events = drain(&mut follower);
+        events.extend(drain(&mut follower));
+        assert_eq!(events, vec!["reopened:rotated", "1:fresh"]);
+
+        let _ = std::fs::remove_file(&path);
+        let _ = std::fs::remove_file(&rotated);
+    }
+
+    #[test]
+    fn test_checkpoint_resumes_and_start_end_skips_backlog() {
+        let path = temp_path("resume.log");
+        let state_path = temp_path("resume.state");
+        std::fs::write(&path, "a\nb\n").expect("write");
+        let config = FollowConfig {
+            path: path.clone(),
+            state_path: Some(state_path.clone()),
+            start: FollowStart::Beginning,
+        };
+        let mut follower = LogFollower::new(config.clone()).expect("follower");
+        assert_eq!(drain(&mut follower), vec!["1:a", "2:b"]);
+        follower.save_state().expect("save");
+        assert_eq!(follower.status()["Offset"], json!(4));
+
+        std::fs::OpenOptions::new()
+            .append(true)
+            .open(&path)
+            .and_then(|mut file| file.write_all(b"c\n"))
+            .expect("append");
+        let mut resumed = LogFollower::new(config).expect("resume");
+        assert_eq!(drain(&mut resumed), vec!["3:c"]);
+
+        let mut from_end = LogFollower::new(FollowConfig {
+            path: path.clone(),
+            state_path: None,
+            start: FollowStart::End,
+        })
+        .expect("follower");
+        assert!(drain(&mut from_end).is_empty());
+
+        let _ = std::fs::remove_file(&path);
+        let _ = std::fs::remove_file(&state_path);
+    }
+}
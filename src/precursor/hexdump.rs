@@ -0,0 +1,347 @@
+use serde_json::{json, Map, Value};
+
+// `*` lines in `hexdump -C` output repeat the previous row up to the next offset; cap
+// the expansion so a bogus offset cannot allocate without bound.
+const MAX_REPEAT_FILL: usize = 16 * 1024 * 1024;
+// Offset-less hex lines only start a dump when they carry at least this many bytes,
+// so hex-looking words in surrounding ticket text are not taken for data.
+const MIN_PLAIN_LINE_BYTES: usize = 8;
+
+#[derive(Clone, Copy, Debug, Eq, PartialEq)]
+pub enum HexDumpFormat {
+    // `00000000: 4865 6c6c 6f0a  Hello.`
+    Xxd,
+    // `xxd -p` and other bare hex lines.
+    Plain,
+    // `00000000  48 65 6c 6c 6f 0a  |Hello.|`
+    HexdumpCanonical,
+    // `\t0x0000:  4500 003c 1c46 4000  E..<.F@.`
+    Tcpdump,
+    // `0000   45 00 00 3c 1c 46 40 00   E..<.F@.`
+    Wireshark,
+}
+
+impl HexDumpFormat {
+    pub fn as_str(&self) -> &'static str {
+        match self {
+            Self::Xxd => "xxd",
+            Self::Plain => "plain",
+            Self::HexdumpCanonical => "hexdump_c",
+            Self::Tcpdump => "tcpdump",
+            Self::Wireshark => "wireshark",
+        }
+    }
+}
+
+#[derive(Clone, Debug, Eq, PartialEq)]
+pub struct HexDump {
+    pub format: HexDumpFormat,
+    pub bytes: Vec<u8>,
+    // Offset printed on the first row, when the format has offsets.
+    pub start_offset: Option<u64>,
+    // 1-based source lines spanned by the dump.
+    pub first_line: u64,
+    pub last_line: u64,
+}
+
+impl HexDump {
+    pub fn metadata(&self, dump_index: usize) -> Map<String, Value> {
+        let mut metadata = Map::new();
+        metadata.insert("format".to_string(), json!(self.format.as_str()));
+        metadata.insert("dump_index".to_string(), json!(dump_index));
+        metadata.insert("first_line".to_string(), json!(self.first_line));
+        metadata.insert("last_line".to_string(), json!(self.last_line));
+        if let Some(start_offset) = self.start_offset {
+            metadata.insert("start_offset".to_string(), json!(start_offset));
+        }
+        metadata
+    }
+}
+
+enum DumpLine {
+    Data {
+        format: HexDumpFormat,
+        offset: Option<u64>,
+        bytes: Vec<u8>,
+    },
+    Repeat,
+    End(u64),
+}
+
+struct DumpBuilder {
+    dump: HexDump,
+    // Start of the most recent row within `dump.bytes`.
+    row_start: usize,
+    repeat_pending: bool,
+}
+
+impl DumpBuilder {
+    // Lines up the next row with the dump: fills `*` repeats, trims gutter characters
+    // that were read as hex on the previous row, and refuses rows that restart or jump.
+    fn align_to(&mut self, offset: Option<u64>) -> bool {
+        let (Some(offset), Some(base)) = (offset, self.dump.start_offset) else {
+            return offset.is_none() && self.dump.start_offset.is_none();
+        };
+        let Some(relative) = offset
+            .checked_sub(base)
+            .and_then(|relative| usize::try_from(relative).ok())
+        else {
+            return false;
+        };
+        let len = self.dump.bytes.len();
+        if std::mem::take(&mut self.repeat_pending) {
+            if relative < len || relative - len > MAX_REPEAT_FILL || self.row_start == len {
+                return false;
+            }
+            let row = self.dump.bytes[self.row_start..].to_vec();
+            self.dump
+                .bytes
+                .extend(row.iter().cycle().take(relative - len).copied());
+            return true;
+        }
+        if relative == len {
+            return true;
+        }
+        if relative > self.row_start && relative < len {
+            self.dump.bytes.truncate(relative);
+            return true;
+        }
+        false
+    }
+
+    fn push_row(&mut self, bytes: Vec<u8>, line_number: u64) {
+        self.row_start = self.dump.bytes.len();
+        self.dump.bytes.extend(bytes);
+        self.dump.last_line = line_number;
+    }
+}
+
+// Finds every hex dump in `text`. A dump ends at a blank or non-dump line, a change
+// of layout, or an offset that does not continue the previous row, so several dumps
+// pasted into one ticket (or tcpdump packets between header lines) come out separately.
+pub fn parse_hexdumps(text: &str) -> Vec<HexDump> {
+    let mut dumps = Vec::new();
+    let mut current: Option<DumpBuilder> = None;
+    for (index, line) in text.lines().enumerate() {
+        let line_number = index as u64 + 1;
+        let current_format = current.as_ref().map(|builder| builder.dump.format);
+        match classify_line(line.trim_end(), current_format) {
+            Some(DumpLine::Data {
+                format,
+                offset,
+                bytes,
+            }) => {
+                let continues = current.as_mut().is_some_and(|builder| {
+                    builder.dump.format == format && builder.align_to(offset)
+                });
+                if !continues {
+                    dumps.extend(current.take().map(|builder| builder.dump));
+                    if format == HexDumpFormat::Plain && bytes.len() < MIN_PLAIN_LINE_BYTES {
+                        continue;
+                    }
+                }
+                let builder = current.get_or_insert_with(|| DumpBuilder {
+                    dump: HexDump {
+                        format,
+                        bytes: Vec::new(),
+                        start_offset: offset,
+                        first_line: line_number,
+                        last_line: line_number,
+                    },
+                    row_start: 0,
+                    repeat_pending: false,
+                });
+                builder.push_row(bytes, line_number);
+            }
+            Some(DumpLine::Repeat) => {
+                if let Some(builder) = current.as_mut() {
+                    builder.repeat_pending = true;
+                    builder.dump.last_line = line_number;
+                }
+            }
+            Some(DumpLine::End(offset)) => {
+                if let Some(mut builder) = current.take() {
+                    builder.align_to(Some(offset));
+                    builder.dump.last_line = line_number;
+                    dumps.push(builder.dump);
+                }
+            }
+            None => dumps.extend(current.take().map(|builder| builder.dump)),
+        }
+    }
+    dumps.extend(current.map(|builder| builder.dump));
+    dumps
+}
+
+fn classify_line(line: &str, current: Option<HexDumpFormat>) -> Option<DumpLine> {
+    let trimmed = line.trim_start();
+    if trimmed.is_empty() {
+        return None;
+    }
+    if trimmed == "*" {
+        return (current == Some(HexDumpFormat::HexdumpCanonical)).then_some(DumpLine::Repeat);
+    }
+    if let Some(rest) = trimmed.strip_prefix("0x") {
+        let (offset, rest) = split_offset(rest)?;
+        let bytes = decode_hex_area(rest.strip_prefix(':')?.trim_start(), 2)?;
+        return Some(DumpLine::Data {
+            format: HexDumpFormat::Tcpdump,
+            offset: Some(offset),
+            bytes,
+        });
+    }
+    if let Some((offset, rest)) = split_offset(trimmed) {
+        if let Some(area) = rest.strip_prefix(": ") {
+            return Some(DumpLine::Data {
+                format: HexDumpFormat::Xxd,
+                offset: Some(offset),
+                bytes: decode_hex_area(area, 2)?,
+            });
+        }
+        if rest.is_empty() && current == Some(HexDumpFormat::HexdumpCanonical) {
+            return Some(DumpLine::End(offset));
+        }
+        if rest.starts_with("  ") {
+            if let Some(gutter) = rest.find('|').filter(|_| rest.ends_with('|')) {
+                return Some(DumpLine::Data {
+                    format: HexDumpFormat::HexdumpCanonical,
+                    offset: Some(offset),
+                    bytes: decode_hex_area(&rest[..gutter], usize::MAX)?,
+                });
+            }
+            return Some(DumpLine::Data {
+                format: HexDumpFormat::Wireshark,
+                offset: Some(offset),
+                bytes: decode_hex_area(rest.trim_start(), 3)?,
+            });
+        }
+    }
+    let bytes = decode_hex_area(trimmed, usize::MAX)?;
+    let fully_hex = trimmed
+        .split_whitespace()
+        .all(|token| token.len() % 2 == 0 && token.bytes().all(|byte| byte.is_ascii_hexdigit()));
+    fully_hex.then_some(DumpLine::Data {
+        format: HexDumpFormat::Plain,
+        offset: None,
+        bytes,
+    })
+}
+
+// Leading offset of at least four hex digits.
+fn split_offset(text: &str) -> Option<(u64, &str)> {
+    let digits = text
+        .find(|ch: char| !ch.is_ascii_hexdigit())
+        .unwrap_or(text.len());
+    if !(4..=16).contains(&digits) {
+        return None;
+    }
+    let offset = u64::from_str_radix(&text[..digits], 16).ok()?;
+    Some((offset, &text[digits..]))
+}
+
+// Decodes whitespace-separated hex groups up to the first non-hex token, tab, or run
+// of `stop_gap` spaces (the separator in front of the ASCII gutter).
+fn decode_hex_area(area: &str, stop_gap: usize) -> Option<Vec<u8>> {
+    let mut bytes = Vec::new();
+    let mut rest = area;
+    loop {
+        let token_start = rest.trim_start_matches(' ');
+        let gap = rest.len() - token_start.len();
+        if token_start.is_empty()
+            || token_start.starts_with('\t')
+            || (!bytes.is_empty() && gap >= stop_gap)
+        {
+            break;
+        }
+        let token_len = token_start
+            .find(char::is_whitespace)
+            .unwrap_or(token_start.len());
+        match hex::decode(&token_start[..token_len]) {
+            Ok(decoded) => bytes.extend(decoded),
+            Err(_) => break,
+        }
+        rest = &token_start[token_len..];
+    }
+    (!bytes.is_empty()).then_some(bytes)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    const HELLO: &[u8] = b"Hello, hexdump world!\n";
+
+    fn single(text: &str) -> HexDump {
+        let mut dumps = parse_hexdumps(text);
+        assert_eq!(dumps.len(), 1, "{:?}", dumps);
+        dumps.remove(0)
+    }
+
+    #[test]
+    fn test_common_layouts_reassemble_the_same_bytes() {
+        let xxd = "00000000: 4865 6c6c 6f2c 2068 6578 6475 6d70 2077  Hello, hexdump w\n\
+00000010: 6f72 6c64 210a                           orld!.\n";
+        let hexdump_c =
+            "00000000  48 65 6c 6c 6f 2c 20 68  65 78 64 75 6d 70 20 77  |Hello, hexdump w|\n\
+00000010  6f 72 6c 64 21 0a                                 |orld!.|\n\
+00000016\n";
+        let tcpdump = "\t0x0000:  4865 6c6c 6f2c 2068 6578 6475 6d70 2077  Hello,.hexdump.w\n\
+\t0x0010:  6f72 6c64 210a                           orld!.\n";
+        let wireshark =
+            "0000   48 65 6c 6c 6f 2c 20 68 65 78 64 75 6d 70 20 77   Hello, hexdump w\n\
+0010   6f 72 6c 64 21 0a                                 orld!.\n";
+        let plain = "48656c6c6f2c206865786475\n6d7020776f726c64210a\n";
+        for (text, format) in [
+            (xxd, HexDumpFormat::Xxd),
+            (hexdump_c, HexDumpFormat::HexdumpCanonical),
+            (tcpdump, HexDumpFormat::Tcpdump),
+            (wireshark, HexDumpFormat::Wireshark),
+            (plain, HexDumpFormat::Plain),
+        ] {
+            let dump = single(text);
+            assert_eq!(dump.format, format);
+            assert_eq!(dump.bytes, HELLO, "{}", format.as_str());
+        }
+    }
+
+    #[test]
+    fn test_repeats_and_gutters_that_look_like_hex() {
+        let repeated =
+            "00000000  41 41 41 41 41 41 41 41  41 41 41 41 41 41 41 41  |AAAAAAAAAAAAAAAA|\n\
+*\n\
+00000030  42 0a                                             |B.|\n\
+00000032\n";
+        let dump = single(repeated);
+        assert_eq!(dump.bytes.len(), 0x32);
+        assert_eq!(&dump.bytes[0x2e..], b"AAB\n");
+        assert_eq!(dump.last_line, 4);
+
+        // The gutter `ab cd` is hex-like; the next row's offset trims it back off.
+        let wireshark = "0000  61 62 20 63 64  ab cd\n0005  65 66   ef\n";
+        assert_eq!(single(wireshark).bytes, b"ab cdef");
+    }
+
+    #[test]
+    fn test_multiple_dumps_and_surrounding_text() {
+        let ticket = "Seen on the edge proxy:\n\
+12:00:00.000000 IP 10.0.0.1.4444 > 10.0.0.2.80: Flags [P.]\n\
+\t0x0000:  4745 5420 2f20 4854 5450 2f31 2e31 0d0a  GET./.HTTP/1.1..\n\
+12:00:01.000000 IP 10.0.0.1.4445 > 10.0.0.2.80: Flags [P.]\n\
+\t0x0000:  5052 4f50 4649 4e44 202f 2048 5454 500a  PROPFIND./.HTTP.\n\
+\n\
+and the dropper, dead beef:\n\
+00000000: 7f45 4c46 0201 0100                      .ELF....\n";
+        let dumps = parse_hexdumps(ticket);
+        let summary: Vec<(&str, u64, usize)> = dumps
+            .iter()
+            .map(|dump| (dump.format.as_str(), dump.first_line, dump.bytes.len()))
+            .collect();
+        assert_eq!(
+            summary,
+            vec![("tcpdump", 3, 16), ("tcpdump", 5, 16), ("xxd", 8, 8)]
+        );
+        assert!(dumps[1].bytes.starts_with(b"PROPFIND"));
+        assert_eq!(dumps[2].metadata(2)["start_offset"], json!(0));
+        assert!(parse_hexdumps("dead beef\ncafe\n").is_empty());
+    }
+}
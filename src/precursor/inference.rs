@@ -73,6 +73,8 @@ pub fn infer_protocol_candidates(
     neighbor_count: usize,
     top_k: usize,
     abstain_threshold: f64,
+    transport_evidence: &[String],
+    app_proto: Option<&str>,
 ) -> ProtocolInference {
     let mut scores: HashMap<String, (f64, Vec<String>)> = HashMap::new();
     let lower_payload = lowercase_payload(payload);
@@ -230,6 +232,28 @@ pub fn infer_protocol_candidates(
         }
     }
 
+    // Application protocol already identified by an upstream sensor (Suricata `app_proto`).
+    if let Some(app_proto) = app_proto
+        .map(str::to_ascii_lowercase)
+        .filter(|app_proto| !matches!(app_proto.as_str(), "" | "failed" | "unknown"))
+    {
+        let protocol = match app_proto.as_str() {
+            "http" | "http1" | "http2" => "http",
+            "tls" => "tls",
+            "ssh" => "ssh",
+            "smtp" => "smtp",
+            "ftp" | "ftp-data" | "pop3" => "pop3_or_ftp",
+            "dns" => "dns_or_domain_payload",
+            other => other,
+        };
+        add_score(
+            &mut scores,
+            protocol,
+            0.7,
+            &format!("sensor app_proto evidence: {}", app_proto),
+        );
+    }
+
     let neighbor_boost = (neighbor_count as f64).ln_1p() * 0.08;
     if neighbor_boost > 0.0 {
         for (_protocol, (score, evidence)) in scores.iter_mut() {
@@ -241,6 +265,12 @@ pub fn infer_protocol_candidates(
         }
     }
 
+    // Transport anomalies (fragmentation, overlaps) do not change scores but are
+    // attached to every candidate so downstream review sees them alongside the verdict.
+    for (_protocol, (_score, evidence)) in scores.iter_mut() {
+        evidence.extend(transport_evidence.iter().cloned());
+    }
+
     let mut candidates: Vec<ProtocolCandidate> = scores
         .into_iter()
         .map(|(protocol, (score, evidence))| ProtocolCandidate {
@@ -265,7 +295,9 @@ pub fn infer_protocol_candidates(
             candidates: vec![ProtocolCandidate {
                 protocol: "unknown".to_string(),
                 score: 0.0,
-                evidence: vec!["no protocol heuristics matched".to_string()],
+                evidence: std::iter::once("no protocol heuristics matched".to_string())
+                    .chain(transport_evidence.iter().cloned())
+                    .collect(),
             }],
         };
     }
@@ -293,7 +325,7 @@ mod tests {
     #[test]
     fn test_http_candidate() {
         let payload = b"GET /index.html HTTP/1.1\r\nHost: example.org\r\n\r\n";
-        let inference = infer_protocol_candidates(payload, &[], 0, 3, 0.6);
+        let inference = infer_protocol_candidates(payload, &[], 0, 3, 0.6, &[], None);
         assert_eq!(inference.label, "http");
         assert!(!inference.abstained);
     }
@@ -301,7 +333,7 @@ mod tests {
     #[test]
     fn test_tls_candidate() {
         let payload = vec![0x16, 0x03, 0x03, 0x00, 0x2f, 0x01, 0x00, 0x00, 0x2b];
-        let inference = infer_protocol_candidates(&payload, &[], 0, 3, 0.6);
+        let inference = infer_protocol_candidates(&payload, &[], 0, 3, 0.6, &[], None);
         assert_eq!(inference.label, "tls");
         assert!(!inference.abstained);
     }
@@ -309,14 +341,14 @@ mod tests {
     #[test]
     fn test_firmware_magic_candidate() {
         let payload = b"\x7fELF\x02\x01\x01\x00\x00\x00\x00\x00\x00\x00\x00\x00";
-        let inference = infer_protocol_candidates(payload, &[], 0, 3, 0.6);
+        let inference = infer_protocol_candidates(payload, &[], 0, 3, 0.6, &[], None);
         assert_eq!(inference.label, "firmware_binary");
     }
 
     #[test]
     fn test_abstain_on_ambiguous_payload() {
         let payload = b"abc";
-        let inference = infer_protocol_candidates(payload, &[], 0, 3, 0.8);
+        let inference = infer_protocol_candidates(payload, &[], 0, 3, 0.8, &[], None);
         assert_eq!(inference.label, "unknown");
         assert!(inference.abstained);
     }
@@ -324,8 +356,41 @@ mod tests {
     #[test]
     fn test_neighbor_boost_changes_confidence() {
         let payload = b"GET / HTTP/1.1\r\nHost: x\r\n\r\n";
-        let without_neighbors = infer_protocol_candidates(payload, &[], 0, 3, 0.95);
-        let with_neighbors = infer_protocol_candidates(payload, &[], 20, 3, 0.95);
+        let without_neighbors = infer_protocol_candidates(payload, &[], 0, 3, 0.95, &[], None);
+        let with_neighbors = infer_protocol_candidates(payload, &[], 20, 3, 0.95, &[], None);
         assert!(with_neighbors.confidence > without_neighbors.confidence);
     }
+
+    #[test]
+    fn test_transport_evidence_is_attached_without_scoring() {
+        let payload = b"GET /index.html HTTP/1.1\r\nHost: example.org\r\n\r\n";
+        let baseline = infer_protocol_candidates(payload, &[], 0, 3, 0.6, &[], None);
+        let evidence = vec!["reassembled from 3 IP fragments".to_string()];
+        let inference = infer_protocol_candidates(payload, &[], 0, 3, 0.6, &evidence, None);
+        assert_eq!(inference.confidence, baseline.confidence);
+        assert!(inference.candidates[0]
+            .evidence
+            .contains(&"reassembled from 3 IP fragments".to_string()));
+
+        let unknown = infer_protocol_candidates(b"", &[], 0, 3, 0.6, &evidence, None);
+        assert_eq!(unknown.candidates[0].evidence.len(), 2);
+    }
+
+    #[test]
+    fn test_app_proto_evidence_scores_sensor_protocol() {
+        let payload = b"\x00\x00\x00\x85\xffSMBr";
+        let baseline = infer_protocol_candidates(payload, &[], 0, 3, 0.65, &[], None);
+        assert_eq!(baseline.label, "unknown");
+
+        let inference = infer_protocol_candidates(payload, &[], 0, 3, 0.65, &[], Some("smb"));
+        assert_eq!(inference.label, "smb");
+        assert!(inference.candidates[0]
+            .evidence
+            .contains(&"sensor app_proto evidence: smb".to_string()));
+
+        let http = infer_protocol_candidates(b"abc", &[], 0, 3, 0.65, &[], Some("http2"));
+        assert_eq!(http.label, "http");
+        let failed = infer_protocol_candidates(b"abc", &[], 0, 3, 0.65, &[], Some("failed"));
+        assert!(failed.abstained);
+    }
 }
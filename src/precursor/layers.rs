@@ -0,0 +1,192 @@
+use base64::engine::general_purpose::{STANDARD, STANDARD_NO_PAD, URL_SAFE, URL_SAFE_NO_PAD};
+use base64::engine::Engine;
+use std::io::Read;
+
+// Decoded layers larger than this are not peeled further.
+const MAX_LAYER_BYTES: u64 = 16 * 1024 * 1024;
+// Shorter base64/hex candidates are too likely to be ordinary words or numbers.
+const MIN_TEXT_ENCODED_LEN: usize = 8;
+const MIN_PRINTABLE_RATIO: f64 = 0.9;
+
+#[derive(Clone, Debug, Eq, PartialEq)]
+pub struct DecodedLayer {
+    // Encodings peeled from the original payload to reach this layer, outermost first.
+    pub chain: Vec<&'static str>,
+    pub payload: Vec<u8>,
+}
+
+// Repeatedly detects and peels one encoding off `payload`, returning every decoded
+// layer (the original payload itself is not included). Stops at `max_depth`, when no
+// encoding is detected, or when a decode makes no progress.
+pub fn peel_layers(payload: &[u8], max_depth: usize) -> Vec<DecodedLayer> {
+    let mut layers: Vec<DecodedLayer> = Vec::new();
+    let mut chain = Vec::new();
+    let mut current = payload.to_vec();
+    while layers.len() < max_depth {
+        let Some((encoding, decoded)) = decode_layer(&current) else {
+            break;
+        };
+        if decoded.is_empty() || decoded == current {
+            break;
+        }
+        chain.push(encoding);
+        layers.push(DecodedLayer {
+            chain: chain.clone(),
+            payload: decoded.clone(),
+        });
+        current = decoded;
+    }
+    layers
+}
+
+// Detection order runs from the most to the least specific signal: compression
+// magic, percent escapes, then hex (every hex string is also valid base64).
+fn decode_layer(payload: &[u8]) -> Option<(&'static str, Vec<u8>)> {
+    if payload.starts_with(&[0x1f, 0x8b]) {
+        return inflate(flate2::read::MultiGzDecoder::new(payload))
+            .map(|decoded| ("gzip", decoded));
+    }
+    if is_zlib_header(payload) {
+        return inflate(flate2::read::ZlibDecoder::new(payload)).map(|decoded| ("zlib", decoded));
+    }
+    if let Some(decoded) = decode_url(payload) {
+        return Some(("url", decoded));
+    }
+    let trimmed = trim_encoded(payload);
+    if let Some(decoded) = decode_hex(trimmed) {
+        return Some(("hex", decoded));
+    }
+    decode_base64(trimmed).map(|decoded| ("base64", decoded))
+}
+
+fn inflate(decoder: impl Read) -> Option<Vec<u8>> {
+    let mut decoded = Vec::new();
+    let read = decoder
+        .take(MAX_LAYER_BYTES + 1)
+        .read_to_end(&mut decoded)
+        .ok()?;
+    (read as u64 <= MAX_LAYER_BYTES).then_some(decoded)
+}
+
+fn is_zlib_header(payload: &[u8]) -> bool {
+    payload.len() > 2
+        && payload[0] & 0x0f == 8
+        && payload[0] >> 4 <= 7
+        && (u16::from(payload[0]) << 8 | u16::from(payload[1])) % 31 == 0
+}
+
+fn decode_url(payload: &[u8]) -> Option<Vec<u8>> {
+    let mut decoded = Vec::with_capacity(payload.len());
+    let mut escapes = 0;
+    let mut index = 0;
+    while index < payload.len() {
+        if payload[index] == b'%' {
+            if let Some(byte) = payload
+                .get(index + 1..index + 3)
+                .and_then(|digits| std::str::from_utf8(digits).ok())
+                .and_then(|digits| u8::from_str_radix(digits, 16).ok())
+            {
+                decoded.push(byte);
+                escapes += 1;
+                index += 3;
+                continue;
+            }
+        }
+        decoded.push(payload[index]);
+        index += 1;
+    }
+    (escapes > 0).then_some(decoded)
+}
+
+fn trim_encoded(payload: &[u8]) -> &[u8] {
+    let trimmed = payload.trim_ascii();
+    match (trimmed.first(), trimmed.last()) {
+        (Some(b'"'), Some(b'"')) | (Some(b'\''), Some(b'\'')) if trimmed.len() >= 2 => {
+            &trimmed[1..trimmed.len() - 1]
+        }
+        _ => trimmed,
+    }
+}
+
+fn decode_hex(payload: &[u8]) -> Option<Vec<u8>> {
+    if payload.len() < MIN_TEXT_ENCODED_LEN || !payload.iter().all(u8::is_ascii_hexdigit) {
+        return None;
+    }
+    hex::decode(payload)
+        .ok()
+        .filter(|decoded| looks_decoded(decoded))
+}
+
+fn decode_base64(payload: &[u8]) -> Option<Vec<u8>> {
+    if payload.len() < MIN_TEXT_ENCODED_LEN {
+        return None;
+    }
+    [STANDARD, URL_SAFE, STANDARD_NO_PAD, URL_SAFE_NO_PAD]
+        .iter()
+        .find_map(|engine| engine.decode(payload).ok())
+        .filter(|decoded| looks_decoded(decoded))
+}
+
+// Base64 and hex decode almost any short token, so only accept results that are
+// mostly printable text or start with compression magic we can peel next.
+fn looks_decoded(decoded: &[u8]) -> bool {
+    if decoded.starts_with(&[0x1f, 0x8b]) || is_zlib_header(decoded) {
+        return true;
+    }
+    if decoded.is_empty() {
+        return false;
+    }
+    let printable = decoded
+        .iter()
+        .filter(|byte| byte.is_ascii_graphic() || byte.is_ascii_whitespace())
+        .count();
+    printable as f64 / decoded.len() as f64 >= MIN_PRINTABLE_RATIO
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use flate2::write::GzEncoder;
+    use flate2::Compression;
+    use std::io::Write;
+
+    #[test]
+    fn test_peels_base64_gzip_url_chain() {
+        let mut encoder = GzEncoder::new(Vec::new(), Compression::default());
+        encoder
+            .write_all(b"q=%24%7Bjndi%3Aldap%3A%2F%2Fx%7D")
+            .expect("gzip");
+        let payload = STANDARD.encode(encoder.finish().expect("gzip"));
+
+        let layers = peel_layers(payload.as_bytes(), 5);
+        let chains: Vec<Vec<&str>> = layers.iter().map(|layer| layer.chain.clone()).collect();
+        assert_eq!(
+            chains,
+            vec![
+                vec!["base64"],
+                vec!["base64", "gzip"],
+                vec!["base64", "gzip", "url"],
+            ]
+        );
+        assert_eq!(layers[2].payload, b"q=${jndi:ldap://x}");
+    }
+
+    #[test]
+    fn test_depth_limit_and_plain_payloads() {
+        let payload = STANDARD.encode(hex::encode("nested hex text"));
+        let layers = peel_layers(payload.as_bytes(), 1);
+        assert_eq!(layers.len(), 1);
+        assert_eq!(layers[0].chain, vec!["base64"]);
+
+        assert!(peel_layers(b"GET /index.html HTTP/1.1", 5).is_empty());
+        // Valid base64 alphabet, but decodes to binary noise.
+        assert!(peel_layers(b"password", 5).is_empty());
+    }
+
+    #[test]
+    fn test_hex_is_preferred_over_base64() {
+        let (encoding, decoded) = decode_layer(b"6964203b20756e616d65").expect("decode");
+        assert_eq!(encoding, "hex");
+        assert_eq!(decoded, b"id ; uname");
+    }
+}
@@ -0,0 +1,170 @@
+use serde_json::{json, Value};
+use std::sync::atomic::{AtomicBool, AtomicU64, Ordering};
+
+// PCRE2 match error codes (pcre2.h) for the resource limits a pattern can trip.
+const PCRE2_ERROR_MATCHLIMIT: i32 = -47;
+const PCRE2_ERROR_DEPTHLIMIT: i32 = -53;
+const PCRE2_ERROR_HEAPLIMIT: i32 = -63;
+
+#[derive(Clone, Copy, Debug, Default, Eq, PartialEq)]
+pub struct PatternLimits {
+    pub match_limit: Option<u32>,
+    pub depth_limit: Option<u32>,
+}
+
+impl PatternLimits {
+    // Limits are applied with PCRE2 start-of-pattern items, which hold per pattern and can
+    // only lower the library defaults.
+    pub fn apply(&self, pattern: &str) -> String {
+        let mut limited = String::new();
+        if let Some(limit) = self.match_limit {
+            limited.push_str(&format!("(*LIMIT_MATCH={})", limit));
+        }
+        if let Some(limit) = self.depth_limit {
+            limited.push_str(&format!("(*LIMIT_DEPTH={})", limit));
+        }
+        limited.push_str(pattern);
+        limited
+    }
+}
+
+pub fn match_error_kind(code: i32) -> &'static str {
+    match code {
+        PCRE2_ERROR_MATCHLIMIT => "match_limit",
+        PCRE2_ERROR_DEPTHLIMIT => "depth_limit",
+        PCRE2_ERROR_HEAPLIMIT => "heap_limit",
+        _ => "match_error",
+    }
+}
+
+#[derive(Clone, Copy, Debug, Eq, PartialEq)]
+pub enum ErrorOutcome {
+    // The pattern's first error, worth one warning.
+    First,
+    Repeated,
+    // This error reached the threshold and the pattern is now skipped.
+    Disabled,
+}
+
+// Shared per-pattern error tally. Patterns that keep failing (usually catastrophic
+// backtracking against hostile payloads) are disabled after `max_errors` errors so they
+// stop pinning workers; 0 keeps every pattern enabled.
+pub struct PatternHealth {
+    errors: Vec<AtomicU64>,
+    disabled: Vec<AtomicBool>,
+    max_errors: u64,
+    budget_exceeded: AtomicU64,
+}
+
+impl PatternHealth {
+    pub fn new(pattern_count: usize, max_errors: u64) -> Self {
+        Self {
+            errors: (0..pattern_count).map(|_| AtomicU64::new(0)).collect(),
+            disabled: (0..pattern_count).map(|_| AtomicBool::new(false)).collect(),
+            max_errors,
+            budget_exceeded: AtomicU64::new(0),
+        }
+    }
+
+    pub fn is_disabled(&self, index: usize) -> bool {
+        self.disabled[index].load(Ordering::Relaxed)
+    }
+
+    pub fn record_error(&self, index: usize) -> ErrorOutcome {
+        let count = self.errors[index].fetch_add(1, Ordering::Relaxed) + 1;
+        if self.max_errors > 0
+            && count >= self.max_errors
+            && !self.disabled[index].swap(true, Ordering::Relaxed)
+        {
+            ErrorOutcome::Disabled
+        } else if count == 1 {
+            ErrorOutcome::First
+        } else {
+            ErrorOutcome::Repeated
+        }
+    }
+
+    pub fn record_budget_exceeded(&self) {
+        self.budget_exceeded.fetch_add(1, Ordering::Relaxed);
+    }
+
+    // Only patterns that errored are listed, labelled by `describe(index)`.
+    pub fn stats(&self, describe: impl Fn(usize) -> String) -> Value {
+        let patterns: Vec<Value> = self
+            .errors
+            .iter()
+            .enumerate()
+            .filter_map(|(index, errors)| {
+                let errors = errors.load(Ordering::Relaxed);
+                (errors > 0).then(|| {
+                    json!({
+                        "Index": index,
+                        "Pattern": describe(index),
+                        "Errors": errors,
+                        "Disabled": self.is_disabled(index),
+                    })
+                })
+            })
+            .collect();
+        json!({
+            "Total": self.errors.iter().map(|errors| errors.load(Ordering::Relaxed)).sum::<u64>(),
+            "BudgetExceeded": self.budget_exceeded.load(Ordering::Relaxed),
+            "Patterns": patterns,
+        })
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn limits_are_prepended_as_pcre2_start_items() {
+        let limits = PatternLimits {
+            match_limit: Some(1000),
+            depth_limit: Some(50),
+        };
+        let limited = limits.apply("(?<redos>(a+)+$)");
+        assert_eq!(
+            limited,
+            "(*LIMIT_MATCH=1000)(*LIMIT_DEPTH=50)(?<redos>(a+)+$)"
+        );
+        assert_eq!(PatternLimits::default().apply("(?<x>x)"), "(?<x>x)");
+
+        let regex = pcre2::bytes::RegexBuilder::new()
+            .build(&limited)
+            .expect("compile limited pattern");
+        let err = regex
+            .captures_iter(b"aaaaaaaaaaaaaaaaaaaaaaaaaaaaaaaa!")
+            .find_map(Result::err)
+            .expect("limit error");
+        assert!(["match_limit", "depth_limit"].contains(&match_error_kind(err.code())));
+        assert!(regex.is_match(b"aaaa").expect("short payload"));
+    }
+
+    #[test]
+    fn patterns_are_disabled_after_the_error_threshold() {
+        let health = PatternHealth::new(2, 3);
+        assert_eq!(health.record_error(1), ErrorOutcome::First);
+        assert_eq!(health.record_error(1), ErrorOutcome::Repeated);
+        assert!(!health.is_disabled(1));
+        assert_eq!(health.record_error(1), ErrorOutcome::Disabled);
+        assert_eq!(health.record_error(1), ErrorOutcome::Repeated);
+        assert!(health.is_disabled(1));
+        assert!(!health.is_disabled(0));
+        health.record_budget_exceeded();
+        let stats = health.stats(|index| format!("p{}", index));
+        assert_eq!(stats["Total"], json!(4));
+        assert_eq!(stats["BudgetExceeded"], json!(1));
+        assert_eq!(
+            stats["Patterns"],
+            json!([{"Index": 1, "Pattern": "p1", "Errors": 4, "Disabled": true}])
+        );
+
+        let unlimited = PatternHealth::new(1, 0);
+        for _ in 0..5 {
+            unlimited.record_error(0);
+        }
+        assert!(!unlimited.is_disabled(0));
+    }
+}
@@ -0,0 +1,395 @@
+use std::io::{ErrorKind, Read, Write};
+use std::net::{Shutdown, TcpListener, TcpStream, UdpSocket};
+#[cfg(unix)]
+use std::os::unix::net::{UnixListener, UnixStream};
+use std::path::{Path, PathBuf};
+
+// Upper bound on one length-prefixed frame, so a corrupt prefix cannot allocate gigabytes.
+pub const MAX_FRAME_BYTES: usize = 16 * 1024 * 1024;
+// The same bound for one line with line framing.
+pub const MAX_LINE_BYTES: usize = MAX_FRAME_BYTES;
+
+#[derive(Clone, Debug, Eq, PartialEq)]
+pub enum ListenAddress {
+    Tcp(String),
+    Udp(String),
+    Unix(PathBuf),
+}
+
+impl ListenAddress {
+    // Accepts `tcp://HOST:PORT`, `udp://HOST:PORT`, and `unix:PATH` (or `unix://PATH`).
+    pub fn parse(spec: &str) -> Result<Self, String> {
+        if let Some(address) = spec.strip_prefix("tcp://") {
+            Self::host_port(spec, address).map(Self::Tcp)
+        } else if let Some(address) = spec.strip_prefix("udp://") {
+            Self::host_port(spec, address).map(Self::Udp)
+        } else if let Some(path) = spec
+            .strip_prefix("unix://")
+            .or_else(|| spec.strip_prefix("unix:"))
+        {
+            if path.is_empty() {
+                Err(format!("Missing socket path in listen address '{}'", spec))
+            } else {
+                Ok(Self::Unix(PathBuf::from(path)))
+            }
+        } else {
+            Err(format!(
+                "Unsupported listen address '{}': expected tcp://HOST:PORT, udp://HOST:PORT, or unix:PATH",
+                spec
+            ))
+        }
+    }
+
+    fn host_port(spec: &str, address: &str) -> Result<String, String> {
+        match address.rsplit_once(':') {
+            Some((host, port)) if !host.is_empty() && port.parse::<u16>().is_ok() => {
+                Ok(address.to_string())
+            }
+            _ => Err(format!(
+                "Invalid listen address '{}': expected HOST:PORT",
+                spec
+            )),
+        }
+    }
+
+    pub fn is_datagram(&self) -> bool {
+        matches!(self, Self::Udp(_))
+    }
+}
+
+#[derive(Clone, Copy, Debug, Eq, PartialEq)]
+pub enum Framing {
+    // Newline-delimited records, as on stdin.
+    Line,
+    // Each record is preceded by its length as a 4-byte big-endian integer.
+    Length,
+}
+
+impl Framing {
+    pub fn from_str(value: &str) -> Result<Self, String> {
+        match value {
+            "line" => Ok(Self::Line),
+            "length" => Ok(Self::Length),
+            _ => Err(format!("Unsupported listen framing '{}'", value)),
+        }
+    }
+}
+
+// Reads one length-prefixed frame. A clean end of stream between frames is Ok(None);
+// ending inside a frame, or a prefix above `max_bytes`, is an error.
+pub fn read_length_frame<R: Read>(
+    reader: &mut R,
+    max_bytes: usize,
+) -> Result<Option<Vec<u8>>, String> {
+    let mut prefix = [0u8; 4];
+    let mut filled = 0;
+    while filled < prefix.len() {
+        match reader.read(&mut prefix[filled..]) {
+            Ok(0) if filled == 0 => return Ok(None),
+            Ok(0) => return Err("Connection closed inside a frame length prefix".to_string()),
+            Ok(read) => filled += read,
+            Err(err) if err.kind() == ErrorKind::Interrupted => {}
+            Err(err) => return Err(format!("Unable to read frame length: {}", err)),
+        }
+    }
+    let length = u32::from_be_bytes(prefix) as usize;
+    if length > max_bytes {
+        return Err(format!(
+            "Frame of {} bytes exceeds the {} byte limit",
+            length, max_bytes
+        ));
+    }
+    let mut frame = vec![0u8; length];
+    reader
+        .read_exact(&mut frame)
+        .map_err(|err| format!("Connection closed inside a {} byte frame: {}", length, err))?;
+    Ok(Some(frame))
+}
+
+// Fails the stream once a line runs past `max_bytes` without a newline, so a client
+// cannot make the line reader buffer without bound.
+pub struct LineLimit<R> {
+    inner: R,
+    max_bytes: usize,
+    line_bytes: usize,
+}
+
+impl<R> LineLimit<R> {
+    pub fn new(inner: R, max_bytes: usize) -> Self {
+        Self {
+            inner,
+            max_bytes,
+            line_bytes: 0,
+        }
+    }
+}
+
+impl<R: Read> Read for LineLimit<R> {
+    fn read(&mut self, buf: &mut [u8]) -> std::io::Result<usize> {
+        let read = self.inner.read(buf)?;
+        for byte in &buf[..read] {
+            if *byte == b'\n' {
+                self.line_bytes = 0;
+                continue;
+            }
+            self.line_bytes += 1;
+            if self.line_bytes > self.max_bytes {
+                return Err(std::io::Error::new(
+                    ErrorKind::InvalidData,
+                    format!("Line exceeds the {} byte limit", self.max_bytes),
+                ));
+            }
+        }
+        Ok(read)
+    }
+}
+
+pub enum Listener {
+    Tcp(TcpListener),
+    Udp(UdpSocket),
+    #[cfg(unix)]
+    Unix {
+        listener: UnixListener,
+        path: PathBuf,
+        accepted: u64,
+    },
+}
+
+impl Listener {
+    pub fn bind(address: &ListenAddress) -> Result<Self, String> {
+        match address {
+            ListenAddress::Tcp(address) => TcpListener::bind(address)
+                .map(Self::Tcp)
+                .map_err(|err| format!("Unable to listen on tcp://{}: {}", address, err)),
+            ListenAddress::Udp(address) => UdpSocket::bind(address)
+                .map(Self::Udp)
+                .map_err(|err| format!("Unable to listen on udp://{}: {}", address, err)),
+            ListenAddress::Unix(path) => Self::bind_unix(path),
+        }
+    }
+
+    #[cfg(unix)]
+    fn bind_unix(path: &Path) -> Result<Self, String> {
+        use std::os::unix::fs::FileTypeExt;
+        // A socket file left behind by a previous run is replaced, but a live one is not.
+        if let Ok(metadata) = std::fs::symlink_metadata(path) {
+            if metadata.file_type().is_socket() && UnixStream::connect(path).is_err() {
+                let _ = std::fs::remove_file(path);
+            }
+        }
+        UnixListener::bind(path)
+            .map(|listener| Self::Unix {
+                listener,
+                path: path.to_path_buf(),
+                accepted: 0,
+            })
+            .map_err(|err| format!("Unable to listen on unix:{}: {}", path.display(), err))
+    }
+
+    #[cfg(not(unix))]
+    fn bind_unix(path: &Path) -> Result<Self, String> {
+        Err(format!(
+            "Unable to listen on unix:{}: Unix domain sockets are not supported on this platform",
+            path.display()
+        ))
+    }
+
+    // The bound address, with the actual port when port 0 was requested.
+    pub fn label(&self) -> String {
+        match self {
+            Self::Tcp(listener) => listener
+                .local_addr()
+                .map(|address| format!("tcp://{}", address))
+                .unwrap_or_else(|_| "tcp://?".to_string()),
+            Self::Udp(socket) => socket
+                .local_addr()
+                .map(|address| format!("udp://{}", address))
+                .unwrap_or_else(|_| "udp://?".to_string()),
+            #[cfg(unix)]
+            Self::Unix { path, .. } => format!("unix:{}", path.display()),
+        }
+    }
+
+    // Waits for the next stream client and returns it with a label naming the peer.
+    // Unix peers are unnamed, so they are numbered in accept order instead.
+    pub fn accept(&mut self) -> Result<(Connection, String), String> {
+        match self {
+            Self::Tcp(listener) => listener
+                .accept()
+                .map(|(stream, peer)| (Connection::Tcp(stream), format!("tcp://{}", peer)))
+                .map_err(|err| format!("Unable to accept TCP client: {}", err)),
+            Self::Udp(_) => Err("UDP sockets do not accept connections".to_string()),
+            #[cfg(unix)]
+            Self::Unix {
+                listener,
+                path,
+                accepted,
+            } => {
+                let (stream, _) = listener
+                    .accept()
+                    .map_err(|err| format!("Unable to accept Unix socket client: {}", err))?;
+                *accepted += 1;
+                Ok((
+                    Connection::Unix(stream),
+                    format!("unix:{}#{}", path.display(), accepted),
+                ))
+            }
+        }
+    }
+}
+
+#[derive(Debug)]
+pub enum Connection {
+    Tcp(TcpStream),
+    #[cfg(unix)]
+    Unix(UnixStream),
+}
+
+impl Connection {
+    pub fn try_clone(&self) -> std::io::Result<Self> {
+        match self {
+            Self::Tcp(stream) => stream.try_clone().map(Self::Tcp),
+            #[cfg(unix)]
+            Self::Unix(stream) => stream.try_clone().map(Self::Unix),
+        }
+    }
+
+    // Signals end of replies to the client while leaving the read side untouched.
+    pub fn shutdown_write(&self) {
+        let _ = match self {
+            Self::Tcp(stream) => stream.shutdown(Shutdown::Write),
+            #[cfg(unix)]
+            Self::Unix(stream) => stream.shutdown(Shutdown::Write),
+        };
+    }
+
+    // Drops the client: pending reads end and pending writes fail.
+    pub fn shutdown(&self) {
+        let _ = match self {
+            Self::Tcp(stream) => stream.shutdown(Shutdown::Both),
+            #[cfg(unix)]
+            Self::Unix(stream) => stream.shutdown(Shutdown::Both),
+        };
+    }
+}
+
+impl Read for Connection {
+    fn read(&mut self, buf: &mut [u8]) -> std::io::Result<usize> {
+        match self {
+            Self::Tcp(stream) => stream.read(buf),
+            #[cfg(unix)]
+            Self::Unix(stream) => stream.read(buf),
+        }
+    }
+}
+
+impl Write for Connection {
+    fn write(&mut self, buf: &[u8]) -> std::io::Result<usize> {
+        match self {
+            Self::Tcp(stream) => stream.write(buf),
+            #[cfg(unix)]
+            Self::Unix(stream) => stream.write(buf),
+        }
+    }
+
+    fn flush(&mut self) -> std::io::Result<()> {
+        match self {
+            Self::Tcp(stream) => stream.flush(),
+            #[cfg(unix)]
+            Self::Unix(stream) => stream.flush(),
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::io::Cursor;
+
+    #[test]
+    fn listen_addresses_parse_by_scheme() {
+        assert_eq!(
+            ListenAddress::parse("tcp://127.0.0.1:9000"),
+            Ok(ListenAddress::Tcp("127.0.0.1:9000".to_string()))
+        );
+        assert_eq!(
+            ListenAddress::parse("udp://[::1]:514"),
+            Ok(ListenAddress::Udp("[::1]:514".to_string()))
+        );
+        assert_eq!(
+            ListenAddress::parse("unix:///run/precursor.sock"),
+            Ok(ListenAddress::Unix(PathBuf::from("/run/precursor.sock")))
+        );
+        assert_eq!(
+            ListenAddress::parse("unix:precursor.sock"),
+            Ok(ListenAddress::Unix(PathBuf::from("precursor.sock")))
+        );
+        assert!(ListenAddress::parse("tcp://localhost").is_err());
+        assert!(ListenAddress::parse("tcp://:9000").is_err());
+        assert!(ListenAddress::parse("127.0.0.1:9000").is_err());
+        assert!(ListenAddress::parse("unix:").is_err());
+    }
+
+    #[test]
+    fn length_frames_split_on_prefix_and_reject_truncation_and_oversize() {
+        let mut wire = Vec::new();
+        for frame in [&b"GET / HTTP/1.1"[..], b"", b"\x00\xff"] {
+            wire.extend_from_slice(&(frame.len() as u32).to_be_bytes());
+            wire.extend_from_slice(frame);
+        }
+        let mut reader = Cursor::new(wire.clone());
+        assert_eq!(
+            read_length_frame(&mut reader, 64),
+            Ok(Some(b"GET / HTTP/1.1".to_vec()))
+        );
+        assert_eq!(read_length_frame(&mut reader, 64), Ok(Some(Vec::new())));
+        assert_eq!(
+            read_length_frame(&mut reader, 64),
+            Ok(Some(b"\x00\xff".to_vec()))
+        );
+        assert_eq!(read_length_frame(&mut reader, 64), Ok(None));
+
+        let mut truncated = Cursor::new(wire[..10].to_vec());
+        assert!(read_length_frame(&mut truncated, 64).is_err());
+        let mut short_prefix = Cursor::new(vec![0u8, 0]);
+        assert!(read_length_frame(&mut short_prefix, 64).is_err());
+        let mut oversized = Cursor::new(wire);
+        assert!(read_length_frame(&mut oversized, 4).is_err());
+    }
+
+    #[test]
+    fn line_limit_rejects_lines_longer_than_the_cap() {
+        let mut lines = String::new();
+        LineLimit::new(Cursor::new(b"abcd\nefgh\n".to_vec()), 4)
+            .read_to_string(&mut lines)
+            .expect("lines within the cap");
+        assert_eq!(lines, "abcd\nefgh\n");
+
+        let mut reader = std::io::BufReader::with_capacity(2, {
+            LineLimit::new(Cursor::new(b"ab\nabcde\n".to_vec()), 4)
+        });
+        let mut line = Vec::new();
+        std::io::BufRead::read_until(&mut reader, b'\n', &mut line).expect("first line");
+        assert_eq!(line, b"ab\n");
+        let err = std::io::BufRead::read_until(&mut reader, b'\n', &mut line)
+            .expect_err("oversized line");
+        assert_eq!(err.kind(), ErrorKind::InvalidData);
+    }
+
+    #[test]
+    fn tcp_listener_reports_bound_port_and_peer() {
+        let mut listener =
+            Listener::bind(&ListenAddress::parse("tcp://127.0.0.1:0").expect("address"))
+                .expect("bind");
+        let label = listener.label();
+        let address = label.strip_prefix("tcp://").expect("tcp label");
+        assert!(!address.ends_with(":0"));
+        let mut client = TcpStream::connect(address).expect("connect");
+        client.write_all(b"ping\n").expect("write");
+        let (mut connection, peer) = listener.accept().expect("accept");
+        assert!(peer.starts_with("tcp://127.0.0.1:"));
+        let mut received = [0u8; 5];
+        connection.read_exact(&mut received).expect("read");
+        assert_eq!(&received, b"ping\n");
+    }
+}
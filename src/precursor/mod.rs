@@ -1,10 +1,30 @@
+pub mod archive;
+pub mod carve;
+pub mod chunking;
+pub mod defines;
+pub mod defrag;
+pub mod delimited;
+pub mod eve;
 pub mod fbhash;
+pub mod follow;
+pub mod hexdump;
 pub mod inference;
+pub mod layers;
+pub mod limits;
+pub mod listen;
 pub mod lzjd;
 pub mod mrshv2;
+pub mod packet;
+pub mod pattern_pack;
+pub mod pcap;
+pub mod prefilter;
+pub mod reassembly;
 pub mod regex_engine;
 pub mod sigma;
 pub mod similarity;
+pub mod syslog;
 pub mod tlsh;
+pub mod walk;
+pub mod zeek;
 
 pub mod util;
@@ -0,0 +1,528 @@
+use serde_json::{json, Map, Value};
+use std::net::{IpAddr, Ipv4Addr, Ipv6Addr};
+
+pub const LINKTYPE_NULL: u32 = 0;
+pub const LINKTYPE_ETHERNET: u32 = 1;
+pub const LINKTYPE_RAW: u32 = 101;
+pub const LINKTYPE_LOOP: u32 = 108;
+pub const LINKTYPE_LINUX_SLL: u32 = 113;
+pub const LINKTYPE_IPV4: u32 = 228;
+pub const LINKTYPE_IPV6: u32 = 229;
+
+const ETHERTYPE_IPV4: u16 = 0x0800;
+const ETHERTYPE_IPV6: u16 = 0x86dd;
+const ETHERTYPE_VLAN: u16 = 0x8100;
+const ETHERTYPE_QINQ: u16 = 0x88a8;
+const ETHERTYPE_VLAN_LEGACY: u16 = 0x9100;
+
+const IP_PROTO_TCP: u8 = 6;
+const IP_PROTO_UDP: u8 = 17;
+
+#[derive(Clone, Copy, Debug, Eq, PartialEq)]
+pub enum Transport {
+    Tcp,
+    Udp,
+}
+
+impl Transport {
+    pub fn as_str(&self) -> &'static str {
+        match self {
+            Self::Tcp => "tcp",
+            Self::Udp => "udp",
+        }
+    }
+}
+
+#[derive(Clone, Copy, Debug, Default, Eq, PartialEq)]
+pub struct TcpFlags {
+    pub syn: bool,
+    pub ack: bool,
+    pub fin: bool,
+    pub rst: bool,
+    pub psh: bool,
+}
+
+#[derive(Clone, Copy, Debug, Eq, PartialEq)]
+pub struct IpFragment {
+    pub id: u32,
+    pub offset: usize,
+    pub more_fragments: bool,
+}
+
+#[derive(Clone, Copy, Debug, Default, Eq, PartialEq)]
+pub struct Fragmentation {
+    pub fragment_count: usize,
+    pub overlap_bytes: usize,
+    pub conflicting_bytes: usize,
+}
+
+#[derive(Clone, Debug, Eq, PartialEq)]
+pub struct IpDatagram {
+    pub vlan_ids: Vec<u16>,
+    pub src_ip: IpAddr,
+    pub dst_ip: IpAddr,
+    pub protocol: u8,
+    pub fragment: Option<IpFragment>,
+    pub fragmentation: Option<Fragmentation>,
+    pub body: Vec<u8>,
+}
+
+#[derive(Clone, Debug, Eq, PartialEq)]
+pub struct DecodedPacket {
+    pub vlan_ids: Vec<u16>,
+    pub src_ip: IpAddr,
+    pub dst_ip: IpAddr,
+    pub transport: Transport,
+    pub src_port: u16,
+    pub dst_port: u16,
+    pub tcp_seq: Option<u32>,
+    pub tcp_flags: Option<TcpFlags>,
+    pub fragmentation: Option<Fragmentation>,
+    pub payload: Vec<u8>,
+}
+
+impl DecodedPacket {
+    pub fn metadata(&self) -> Map<String, Value> {
+        let mut metadata = Map::new();
+        metadata.insert("transport".to_string(), json!(self.transport.as_str()));
+        metadata.insert("src_ip".to_string(), json!(self.src_ip.to_string()));
+        metadata.insert("src_port".to_string(), json!(self.src_port));
+        metadata.insert("dst_ip".to_string(), json!(self.dst_ip.to_string()));
+        metadata.insert("dst_port".to_string(), json!(self.dst_port));
+        if !self.vlan_ids.is_empty() {
+            metadata.insert("vlan_ids".to_string(), json!(self.vlan_ids));
+        }
+        if let Some(fragmentation) = self.fragmentation {
+            metadata.insert("fragmented".to_string(), json!(true));
+            metadata.insert(
+                "fragment_count".to_string(),
+                json!(fragmentation.fragment_count),
+            );
+            metadata.insert(
+                "fragment_overlap_bytes".to_string(),
+                json!(fragmentation.overlap_bytes),
+            );
+            metadata.insert(
+                "fragment_conflicting_bytes".to_string(),
+                json!(fragmentation.conflicting_bytes),
+            );
+        }
+        metadata
+    }
+}
+
+fn be_u16(bytes: &[u8], offset: usize) -> u16 {
+    u16::from_be_bytes([bytes[offset], bytes[offset + 1]])
+}
+
+fn be_u32(bytes: &[u8], offset: usize) -> u32 {
+    u32::from_be_bytes([
+        bytes[offset],
+        bytes[offset + 1],
+        bytes[offset + 2],
+        bytes[offset + 3],
+    ])
+}
+
+// Returns Ok(None) for frames that are well-formed but carry no IP header
+// (ARP, LLDP, ...). Fragments are returned as-is for the defragmenter.
+pub fn decode_ip_datagram(link_type: u32, frame: &[u8]) -> Result<Option<IpDatagram>, String> {
+    let mut vlan_ids = Vec::new();
+    let (ethertype, network) = match link_type {
+        LINKTYPE_ETHERNET => decode_ethernet(frame, &mut vlan_ids)?,
+        LINKTYPE_LINUX_SLL => {
+            if frame.len() < 16 {
+                return Err("truncated Linux cooked capture header".to_string());
+            }
+            (be_u16(frame, 14), &frame[16..])
+        }
+        LINKTYPE_NULL | LINKTYPE_LOOP => {
+            if frame.len() < 4 {
+                return Err("truncated loopback header".to_string());
+            }
+            (ip_ethertype_from_version(&frame[4..]), &frame[4..])
+        }
+        LINKTYPE_RAW => (ip_ethertype_from_version(frame), frame),
+        LINKTYPE_IPV4 => (ETHERTYPE_IPV4, frame),
+        LINKTYPE_IPV6 => (ETHERTYPE_IPV6, frame),
+        _ => return Err(format!("unsupported capture link type {}", link_type)),
+    };
+
+    match ethertype {
+        ETHERTYPE_IPV4 => decode_ipv4(network, vlan_ids).map(Some),
+        ETHERTYPE_IPV6 => decode_ipv6(network, vlan_ids).map(Some),
+        _ => Ok(None),
+    }
+}
+
+// Returns Ok(None) for datagrams that carry no TCP/UDP header (ICMP, ...) and
+// for fragments that have not been through the defragmenter.
+pub fn decode_datagram_transport(datagram: IpDatagram) -> Result<Option<DecodedPacket>, String> {
+    if datagram.fragment.is_some() {
+        return Ok(None);
+    }
+    let (protocol, body) = match datagram.src_ip {
+        IpAddr::V6(_) => {
+            let (protocol, offset, fragment) =
+                walk_ipv6_extensions(datagram.protocol, &datagram.body)?;
+            if fragment.is_some() {
+                return Err("nested IPv6 fragment header".to_string());
+            }
+            (protocol, &datagram.body[offset..])
+        }
+        IpAddr::V4(_) => (datagram.protocol, datagram.body.as_slice()),
+    };
+    match protocol {
+        IP_PROTO_TCP => {
+            if body.len() < 20 {
+                return Err("truncated TCP header".to_string());
+            }
+            let data_offset = ((body[12] >> 4) as usize) * 4;
+            if data_offset < 20 || body.len() < data_offset {
+                return Err(format!("invalid TCP data offset {}", data_offset));
+            }
+            let flags = body[13];
+            Ok(Some(DecodedPacket {
+                vlan_ids: datagram.vlan_ids,
+                src_ip: datagram.src_ip,
+                dst_ip: datagram.dst_ip,
+                transport: Transport::Tcp,
+                src_port: be_u16(body, 0),
+                dst_port: be_u16(body, 2),
+                tcp_seq: Some(be_u32(body, 4)),
+                tcp_flags: Some(TcpFlags {
+                    fin: flags & 0x01 != 0,
+                    syn: flags & 0x02 != 0,
+                    rst: flags & 0x04 != 0,
+                    psh: flags & 0x08 != 0,
+                    ack: flags & 0x10 != 0,
+                }),
+                fragmentation: datagram.fragmentation,
+                payload: body[data_offset..].to_vec(),
+            }))
+        }
+        IP_PROTO_UDP => {
+            if body.len() < 8 {
+                return Err("truncated UDP header".to_string());
+            }
+            let udp_len = (be_u16(body, 4) as usize).clamp(8, body.len());
+            Ok(Some(DecodedPacket {
+                vlan_ids: datagram.vlan_ids,
+                src_ip: datagram.src_ip,
+                dst_ip: datagram.dst_ip,
+                transport: Transport::Udp,
+                src_port: be_u16(body, 0),
+                dst_port: be_u16(body, 2),
+                tcp_seq: None,
+                tcp_flags: None,
+                fragmentation: datagram.fragmentation,
+                payload: body[8..udp_len].to_vec(),
+            }))
+        }
+        _ => Ok(None),
+    }
+}
+
+fn ip_ethertype_from_version(network: &[u8]) -> u16 {
+    match network.first().map(|byte| byte >> 4) {
+        Some(4) => ETHERTYPE_IPV4,
+        Some(6) => ETHERTYPE_IPV6,
+        _ => 0,
+    }
+}
+
+fn decode_ethernet<'a>(
+    frame: &'a [u8],
+    vlan_ids: &mut Vec<u16>,
+) -> Result<(u16, &'a [u8]), String> {
+    if frame.len() < 14 {
+        return Err("truncated Ethernet header".to_string());
+    }
+    let mut ethertype = be_u16(frame, 12);
+    let mut offset = 14;
+    while matches!(
+        ethertype,
+        ETHERTYPE_VLAN | ETHERTYPE_QINQ | ETHERTYPE_VLAN_LEGACY
+    ) {
+        if frame.len() < offset + 4 {
+            return Err("truncated 802.1Q VLAN tag".to_string());
+        }
+        vlan_ids.push(be_u16(frame, offset) & 0x0fff);
+        ethertype = be_u16(frame, offset + 2);
+        offset += 4;
+    }
+    Ok((ethertype, &frame[offset..]))
+}
+
+fn decode_ipv4(packet: &[u8], vlan_ids: Vec<u16>) -> Result<IpDatagram, String> {
+    if packet.len() < 20 {
+        return Err("truncated IPv4 header".to_string());
+    }
+    let header_len = ((packet[0] & 0x0f) as usize) * 4;
+    if header_len < 20 || packet.len() < header_len {
+        return Err(format!("invalid IPv4 header length {}", header_len));
+    }
+    // Trim Ethernet padding while tolerating snaplen-truncated captures.
+    let total_len = (be_u16(packet, 2) as usize).clamp(header_len, packet.len());
+    let flags_and_offset = be_u16(packet, 6);
+    let more_fragments = flags_and_offset & 0x2000 != 0;
+    let fragment_offset = ((flags_and_offset & 0x1fff) as usize) * 8;
+    let fragment = (more_fragments || fragment_offset != 0).then(|| IpFragment {
+        id: be_u16(packet, 4) as u32,
+        offset: fragment_offset,
+        more_fragments,
+    });
+    let src_ip = Ipv4Addr::new(packet[12], packet[13], packet[14], packet[15]);
+    let dst_ip = Ipv4Addr::new(packet[16], packet[17], packet[18], packet[19]);
+    Ok(IpDatagram {
+        vlan_ids,
+        src_ip: IpAddr::V4(src_ip),
+        dst_ip: IpAddr::V4(dst_ip),
+        protocol: packet[9],
+        fragment,
+        fragmentation: None,
+        body: packet[header_len..total_len].to_vec(),
+    })
+}
+
+fn decode_ipv6(packet: &[u8], vlan_ids: Vec<u16>) -> Result<IpDatagram, String> {
+    if packet.len() < 40 {
+        return Err("truncated IPv6 header".to_string());
+    }
+    let payload_len = be_u16(packet, 4) as usize;
+    let end = (40 + payload_len).min(packet.len());
+    let mut src = [0u8; 16];
+    let mut dst = [0u8; 16];
+    src.copy_from_slice(&packet[8..24]);
+    dst.copy_from_slice(&packet[24..40]);
+
+    let (protocol, offset, fragment) = walk_ipv6_extensions(packet[6], &packet[40..end])?;
+    Ok(IpDatagram {
+        vlan_ids,
+        src_ip: IpAddr::V6(Ipv6Addr::from(src)),
+        dst_ip: IpAddr::V6(Ipv6Addr::from(dst)),
+        protocol,
+        fragment,
+        fragmentation: None,
+        body: packet[40 + offset..end].to_vec(),
+    })
+}
+
+// Walks extension headers until an upper-layer protocol or a non-atomic
+// fragment header; everything after a fragment header is the fragmentable part.
+fn walk_ipv6_extensions(
+    mut next_header: u8,
+    body: &[u8],
+) -> Result<(u8, usize, Option<IpFragment>), String> {
+    let mut offset = 0;
+    loop {
+        match next_header {
+            // Hop-by-hop, routing, and destination options share a length layout.
+            0 | 43 | 60 => {
+                if body.len() < offset + 8 {
+                    return Err("truncated IPv6 extension header".to_string());
+                }
+                next_header = body[offset];
+                offset += (body[offset + 1] as usize + 1) * 8;
+            }
+            44 => {
+                if body.len() < offset + 8 {
+                    return Err("truncated IPv6 fragment header".to_string());
+                }
+                let offset_and_flags = be_u16(body, offset + 2);
+                let fragment = IpFragment {
+                    id: be_u32(body, offset + 4),
+                    offset: ((offset_and_flags >> 3) as usize) * 8,
+                    more_fragments: offset_and_flags & 0x0001 != 0,
+                };
+                next_header = body[offset];
+                offset += 8;
+                if fragment.offset != 0 || fragment.more_fragments {
+                    return Ok((next_header, offset, Some(fragment)));
+                }
+            }
+            51 => {
+                if body.len() < offset + 8 {
+                    return Err("truncated IPv6 authentication header".to_string());
+                }
+                next_header = body[offset];
+                offset += (body[offset + 1] as usize + 2) * 4;
+            }
+            _ => break,
+        }
+        if offset > body.len() {
+            return Err("IPv6 extension headers exceed packet length".to_string());
+        }
+    }
+    Ok((next_header, offset, None))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn decode_transport_payload(
+        link_type: u32,
+        frame: &[u8],
+    ) -> Result<Option<DecodedPacket>, String> {
+        match decode_ip_datagram(link_type, frame)? {
+            Some(datagram) => decode_datagram_transport(datagram),
+            None => Ok(None),
+        }
+    }
+
+    fn ipv4_header(protocol: u8, body_len: usize, id: u16, frag: u16) -> Vec<u8> {
+        let total_len = (20 + body_len) as u16;
+        let mut header = vec![0x45, 0x00];
+        header.extend_from_slice(&total_len.to_be_bytes());
+        header.extend_from_slice(&id.to_be_bytes());
+        header.extend_from_slice(&frag.to_be_bytes());
+        header.extend_from_slice(&[64, protocol, 0, 0]);
+        header.extend_from_slice(&[10, 0, 0, 1]);
+        header.extend_from_slice(&[10, 0, 0, 2]);
+        header
+    }
+
+    fn tcp_segment(src_port: u16, dst_port: u16, seq: u32, flags: u8, data: &[u8]) -> Vec<u8> {
+        let mut segment = Vec::new();
+        segment.extend_from_slice(&src_port.to_be_bytes());
+        segment.extend_from_slice(&dst_port.to_be_bytes());
+        segment.extend_from_slice(&seq.to_be_bytes());
+        segment.extend_from_slice(&0u32.to_be_bytes());
+        segment.extend_from_slice(&[0x50, flags, 0xff, 0xff, 0, 0, 0, 0]);
+        segment.extend_from_slice(data);
+        segment
+    }
+
+    fn udp_datagram(src_port: u16, dst_port: u16, data: &[u8]) -> Vec<u8> {
+        let mut datagram = Vec::new();
+        datagram.extend_from_slice(&src_port.to_be_bytes());
+        datagram.extend_from_slice(&dst_port.to_be_bytes());
+        datagram.extend_from_slice(&((8 + data.len()) as u16).to_be_bytes());
+        datagram.extend_from_slice(&[0, 0]);
+        datagram.extend_from_slice(data);
+        datagram
+    }
+
+    fn ethernet_frame(ethertype: u16, vlan: Option<u16>, network: &[u8]) -> Vec<u8> {
+        let mut frame = vec![0u8; 12];
+        if let Some(vlan_id) = vlan {
+            frame.extend_from_slice(&ETHERTYPE_VLAN.to_be_bytes());
+            frame.extend_from_slice(&vlan_id.to_be_bytes());
+        }
+        frame.extend_from_slice(&ethertype.to_be_bytes());
+        frame.extend_from_slice(network);
+        frame
+    }
+
+    #[test]
+    fn test_decode_ethernet_ipv4_tcp_payload() {
+        let tcp = tcp_segment(49152, 80, 1000, 0x18, b"GET / HTTP/1.1\r\n");
+        let mut ip = ipv4_header(IP_PROTO_TCP, tcp.len(), 1, 0x4000);
+        ip.extend_from_slice(&tcp);
+        let mut frame = ethernet_frame(ETHERTYPE_IPV4, None, &ip);
+        frame.extend_from_slice(&[0u8; 6]);
+
+        let packet = decode_transport_payload(LINKTYPE_ETHERNET, &frame)
+            .expect("decode")
+            .expect("payload");
+        assert_eq!(packet.transport, Transport::Tcp);
+        assert_eq!(packet.src_ip.to_string(), "10.0.0.1");
+        assert_eq!(packet.dst_port, 80);
+        assert_eq!(packet.tcp_seq, Some(1000));
+        assert_eq!(packet.payload, b"GET / HTTP/1.1\r\n");
+    }
+
+    #[test]
+    fn test_decode_vlan_tagged_udp_payload() {
+        let udp = udp_datagram(5353, 53, b"\x12\x34query");
+        let mut ip = ipv4_header(IP_PROTO_UDP, udp.len(), 2, 0);
+        ip.extend_from_slice(&udp);
+        let frame = ethernet_frame(ETHERTYPE_IPV4, Some(42), &ip);
+
+        let packet = decode_transport_payload(LINKTYPE_ETHERNET, &frame)
+            .expect("decode")
+            .expect("payload");
+        assert_eq!(packet.transport, Transport::Udp);
+        assert_eq!(packet.vlan_ids, vec![42]);
+        assert_eq!(packet.payload, b"\x12\x34query");
+        assert_eq!(packet.metadata()["vlan_ids"], json!([42]));
+    }
+
+    #[test]
+    fn test_decode_ipv6_with_extension_header() {
+        let udp = udp_datagram(1000, 2000, b"v6");
+        let mut ip = vec![0x60, 0, 0, 0];
+        ip.extend_from_slice(&((8 + udp.len()) as u16).to_be_bytes());
+        ip.extend_from_slice(&[60, 64]);
+        ip.extend_from_slice(&Ipv6Addr::LOCALHOST.octets());
+        ip.extend_from_slice(&Ipv6Addr::LOCALHOST.octets());
+        ip.extend_from_slice(&[IP_PROTO_UDP, 0, 0, 0, 0, 0, 0, 0]);
+        ip.extend_from_slice(&udp);
+
+        let packet = decode_transport_payload(LINKTYPE_RAW, &ip)
+            .expect("decode")
+            .expect("payload");
+        assert_eq!(packet.dst_ip.to_string(), "::1");
+        assert_eq!(packet.payload, b"v6");
+    }
+
+    #[test]
+    fn test_non_ip_and_non_first_fragments_are_skipped() {
+        let arp = ethernet_frame(0x0806, None, &[0u8; 28]);
+        assert!(decode_transport_payload(LINKTYPE_ETHERNET, &arp)
+            .expect("decode arp")
+            .is_none());
+
+        let mut fragment = ipv4_header(IP_PROTO_UDP, 8, 3, 0x0001);
+        fragment.extend_from_slice(&[0u8; 8]);
+        assert!(decode_transport_payload(LINKTYPE_IPV4, &fragment)
+            .expect("decode fragment")
+            .is_none());
+    }
+
+    #[test]
+    fn test_fragments_expose_offsets_for_defragmentation() {
+        let mut ip = ipv4_header(IP_PROTO_UDP, 8, 9, 0x2002);
+        ip.extend_from_slice(&[0u8; 8]);
+        let datagram = decode_ip_datagram(LINKTYPE_IPV4, &ip)
+            .expect("decode")
+            .expect("datagram");
+        assert_eq!(
+            datagram.fragment,
+            Some(IpFragment {
+                id: 9,
+                offset: 16,
+                more_fragments: true,
+            })
+        );
+
+        let mut ip6 = vec![0x60, 0, 0, 0, 0, 12, 44, 64];
+        ip6.extend_from_slice(&Ipv6Addr::LOCALHOST.octets());
+        ip6.extend_from_slice(&Ipv6Addr::LOCALHOST.octets());
+        ip6.extend_from_slice(&[IP_PROTO_UDP, 0, 0, 0x19, 0, 0, 0, 5]);
+        ip6.extend_from_slice(b"frag");
+        let datagram = decode_ip_datagram(LINKTYPE_RAW, &ip6)
+            .expect("decode")
+            .expect("datagram");
+        assert_eq!(datagram.protocol, IP_PROTO_UDP);
+        assert_eq!(datagram.body, b"frag");
+        assert_eq!(
+            datagram.fragment,
+            Some(IpFragment {
+                id: 5,
+                offset: 24,
+                more_fragments: true,
+            })
+        );
+    }
+
+    #[test]
+    fn test_truncated_headers_are_errors() {
+        assert!(decode_transport_payload(LINKTYPE_ETHERNET, &[0u8; 10]).is_err());
+        let ip = ipv4_header(IP_PROTO_TCP, 4, 1, 0);
+        let mut packet = ip.clone();
+        packet.extend_from_slice(&[0u8; 4]);
+        assert!(decode_transport_payload(LINKTYPE_IPV4, &packet).is_err());
+    }
+}
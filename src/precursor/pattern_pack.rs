@@ -0,0 +1,350 @@
+use serde_json::{Map, Value};
+use std::collections::HashSet;
+use std::path::{Path, PathBuf};
+
+const SEVERITIES: [&str; 5] = ["info", "low", "medium", "high", "critical"];
+// Nested includes deeper than this are almost certainly a mistake.
+const MAX_INCLUDE_DEPTH: usize = 16;
+
+// One pattern from a pattern pack, with the metadata declared above it.
+#[derive(Clone, Debug, PartialEq)]
+pub struct PatternEntry {
+    pub pattern: String,
+    // JSON object of the declared metadata, None when the pattern has none.
+    pub metadata: Option<Value>,
+}
+
+// Pattern files keep the one-pattern-per-line format and add:
+// - blank lines and `#` comment lines, which are skipped
+// - `@key value` directives describing the next pattern: `@id`, `@description`,
+//   `@severity`, `@tags`, `@attack`, `@reference` (repeatable), `@owner`, `@enabled`
+// - `@include PATH`, resolved relative to the including file
+// A pattern that starts with a literal `#` or `@` can escape it as `\#` or `\@`.
+pub fn load_pattern_pack(path: &Path) -> Result<Vec<PatternEntry>, String> {
+    let mut loader = PackLoader::default();
+    loader.load(path, 0)?;
+    Ok(loader.entries)
+}
+
+#[derive(Default)]
+struct PackLoader {
+    entries: Vec<PatternEntry>,
+    // Files currently being read, to reject include cycles.
+    stack: Vec<PathBuf>,
+    ids: HashSet<String>,
+}
+
+#[derive(Default)]
+struct PendingMetadata {
+    fields: Map<String, Value>,
+    enabled: bool,
+    // Line of the first directive, for dangling-metadata errors.
+    line: Option<usize>,
+}
+
+impl PackLoader {
+    fn load(&mut self, path: &Path, depth: usize) -> Result<(), String> {
+        if depth > MAX_INCLUDE_DEPTH {
+            return Err(format!(
+                "Pattern includes nest deeper than {} files at {}",
+                MAX_INCLUDE_DEPTH,
+                path.display()
+            ));
+        }
+        let canonical = std::fs::canonicalize(path)
+            .map_err(|err| format!("Unable to read pattern file {}: {}", path.display(), err))?;
+        if self.stack.contains(&canonical) {
+            return Err(format!("Pattern file {} includes itself", path.display()));
+        }
+        let contents = std::fs::read_to_string(path)
+            .map_err(|err| format!("Unable to read pattern file {}: {}", path.display(), err))?;
+        self.stack.push(canonical);
+
+        let mut pending = PendingMetadata::new();
+        for (index, line) in contents.lines().enumerate() {
+            let line_number = index + 1;
+            let location = format!("{}:{}", path.display(), line_number);
+            let trimmed = line.trim();
+            if trimmed.is_empty() || trimmed.starts_with('#') {
+                continue;
+            }
+            let Some(directive) = trimmed.strip_prefix('@') else {
+                let pending = std::mem::replace(&mut pending, PendingMetadata::new());
+                if pending.enabled {
+                    self.entries.push(PatternEntry {
+                        pattern: line.to_string(),
+                        metadata: (!pending.fields.is_empty())
+                            .then_some(Value::Object(pending.fields)),
+                    });
+                }
+                continue;
+            };
+            let (key, value) = directive
+                .split_once(char::is_whitespace)
+                .map_or((directive, ""), |(key, value)| (key, value.trim()));
+            if key == "include" {
+                if let Some(line) = pending.line {
+                    return Err(format!(
+                        "{}:{}: metadata is not followed by a pattern",
+                        path.display(),
+                        line
+                    ));
+                }
+                if value.is_empty() {
+                    return Err(format!("{}: @include needs a path", location));
+                }
+                let base = path.parent().unwrap_or_else(|| Path::new(""));
+                self.load(&base.join(value), depth + 1)
+                    .map_err(|err| format!("{}: {}", location, err))?;
+                continue;
+            }
+            pending
+                .set(key, value, &mut self.ids)
+                .map_err(|err| format!("{}: {}", location, err))?;
+            pending.line.get_or_insert(line_number);
+        }
+        if let Some(line) = pending.line {
+            return Err(format!(
+                "{}:{}: metadata is not followed by a pattern",
+                path.display(),
+                line
+            ));
+        }
+        self.stack.pop();
+        Ok(())
+    }
+}
+
+impl PendingMetadata {
+    fn new() -> Self {
+        Self {
+            enabled: true,
+            ..Self::default()
+        }
+    }
+
+    fn set(&mut self, key: &str, value: &str, ids: &mut HashSet<String>) -> Result<(), String> {
+        if value.is_empty() {
+            return Err(format!("@{} needs a value", key));
+        }
+        match key {
+            "id" => {
+                if !ids.insert(value.to_string()) {
+                    return Err(format!("duplicate pattern id '{}'", value));
+                }
+                self.single(key, Value::String(value.to_string()))
+            }
+            "description" | "owner" => self.single(key, Value::String(value.to_string())),
+            "severity" => {
+                let severity = value.to_ascii_lowercase();
+                if !SEVERITIES.contains(&severity.as_str()) {
+                    return Err(format!(
+                        "unknown severity '{}': expected one of {}",
+                        value,
+                        SEVERITIES.join(", ")
+                    ));
+                }
+                self.single(key, Value::String(severity))
+            }
+            "tags" => self.single(key, list(value)),
+            "attack" => {
+                let techniques = list(value);
+                for technique in techniques.as_array().into_iter().flatten() {
+                    let technique = technique.as_str().unwrap_or_default();
+                    if !is_attack_technique(technique) {
+                        return Err(format!(
+                            "invalid ATT&CK technique '{}': expected e.g. T1059 or T1059.004",
+                            technique
+                        ));
+                    }
+                }
+                self.single(key, techniques)
+            }
+            "reference" => {
+                let references = self
+                    .fields
+                    .entry("references")
+                    .or_insert_with(|| Value::Array(Vec::new()));
+                if let Value::Array(references) = references {
+                    references.push(Value::String(value.to_string()));
+                }
+                Ok(())
+            }
+            "enabled" => {
+                self.enabled = match value {
+                    "true" | "yes" => true,
+                    "false" | "no" => false,
+                    _ => return Err(format!("@enabled expects true or false, got '{}'", value)),
+                };
+                Ok(())
+            }
+            _ => Err(format!("unknown pattern directive '@{}'", key)),
+        }
+    }
+
+    fn single(&mut self, key: &str, value: Value) -> Result<(), String> {
+        if self.fields.insert(key.to_string(), value).is_some() {
+            return Err(format!("@{} is set twice for one pattern", key));
+        }
+        Ok(())
+    }
+}
+
+// Comma-separated values, as used by @tags and @attack.
+fn list(value: &str) -> Value {
+    Value::Array(
+        value
+            .split(',')
+            .map(str::trim)
+            .filter(|item| !item.is_empty())
+            .map(|item| Value::String(item.to_string()))
+            .collect(),
+    )
+}
+
+fn is_attack_technique(value: &str) -> bool {
+    let Some(id) = value.strip_prefix('T') else {
+        return false;
+    };
+    let (technique, sub_technique) = id.split_once('.').map_or((id, None), |(t, s)| (t, Some(s)));
+    technique.len() == 4
+        && technique.bytes().all(|b| b.is_ascii_digit())
+        && sub_technique.is_none_or(|s| s.len() == 3 && s.bytes().all(|b| b.is_ascii_digit()))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use serde_json::json;
+    use std::time::{SystemTime, UNIX_EPOCH};
+
+    struct TempDirGuard {
+        path: PathBuf,
+    }
+
+    impl Drop for TempDirGuard {
+        fn drop(&mut self) {
+            let _ = std::fs::remove_dir_all(&self.path);
+        }
+    }
+
+    fn temp_dir(stem: &str) -> TempDirGuard {
+        let nanos = SystemTime::now()
+            .duration_since(UNIX_EPOCH)
+            .expect("clock")
+            .as_nanos();
+        let path = std::env::temp_dir().join(format!("{}-{}-{}", stem, std::process::id(), nanos));
+        std::fs::create_dir_all(path.join("shared")).expect("create temp dir");
+        TempDirGuard { path }
+    }
+
+    #[test]
+    fn packs_skip_comments_and_blank_lines_and_attach_metadata() {
+        let dir = temp_dir("precursor-pack");
+        let pack = dir.path.join("web.pcre");
+        std::fs::write(
+            &pack,
+            "# Web probes\n\
+             \n\
+             (?<legacy>legacy)\n\
+             @id web-fgt-lang\n\
+             @description FortiOS path traversal probe\n\
+             @severity HIGH\n\
+             @tags fortinet, traversal\n\
+             @attack T1190, T1083\n\
+             @reference https://example.org/a\n\
+             @reference https://example.org/b\n\
+             @owner netsec\n\
+             (?<fgt_lang>/remote/fgt_lang\\?lang=)\n\
+             \n\
+             @enabled false\n\
+             (?<retired>retired)\n\
+             \\#(?<hash>x)\n",
+        )
+        .expect("write pack");
+        let entries = load_pattern_pack(&pack).expect("load pack");
+        let patterns: Vec<&str> = entries.iter().map(|entry| entry.pattern.as_str()).collect();
+        assert_eq!(
+            patterns,
+            vec![
+                "(?<legacy>legacy)",
+                "(?<fgt_lang>/remote/fgt_lang\\?lang=)",
+                "\\#(?<hash>x)"
+            ]
+        );
+        assert_eq!(entries[0].metadata, None);
+        assert_eq!(
+            entries[1].metadata,
+            Some(json!({
+                "id": "web-fgt-lang",
+                "description": "FortiOS path traversal probe",
+                "severity": "high",
+                "tags": ["fortinet", "traversal"],
+                "attack": ["T1190", "T1083"],
+                "references": ["https://example.org/a", "https://example.org/b"],
+                "owner": "netsec",
+            }))
+        );
+        assert_eq!(entries[2].metadata, None);
+    }
+
+    #[test]
+    fn includes_resolve_relative_to_the_including_file_and_reject_cycles() {
+        let dir = temp_dir("precursor-pack-include");
+        std::fs::write(
+            dir.path.join("shared/common.pcre"),
+            "@id common-ssh\n(?<ssh>SSH-2\\.0)\n",
+        )
+        .expect("write include");
+        let pack = dir.path.join("main.pcre");
+        std::fs::write(
+            &pack,
+            "(?<first>a)\n@include shared/common.pcre\n(?<last>b)\n",
+        )
+        .expect("write pack");
+        let entries = load_pattern_pack(&pack).expect("load pack");
+        let patterns: Vec<&str> = entries.iter().map(|entry| entry.pattern.as_str()).collect();
+        assert_eq!(
+            patterns,
+            vec!["(?<first>a)", "(?<ssh>SSH-2\\.0)", "(?<last>b)"]
+        );
+        assert_eq!(entries[1].metadata, Some(json!({"id": "common-ssh"})));
+
+        std::fs::write(dir.path.join("shared/loop.pcre"), "@include ../main.pcre\n")
+            .expect("write loop");
+        std::fs::write(&pack, "@include shared/loop.pcre\n").expect("write pack");
+        let err = load_pattern_pack(&pack).expect_err("include cycle");
+        assert!(err.contains("includes itself"), "{}", err);
+    }
+
+    #[test]
+    fn malformed_directives_are_rejected_with_their_location() {
+        let dir = temp_dir("precursor-pack-errors");
+        let pack = dir.path.join("bad.pcre");
+        for (contents, expected) in [
+            (
+                "@sevrity high\n(?<a>a)\n",
+                "bad.pcre:1: unknown pattern directive '@sevrity'",
+            ),
+            (
+                "(?<a>a)\n@severity urgent\n(?<b>b)\n",
+                "bad.pcre:2: unknown severity",
+            ),
+            ("@attack T10\n(?<a>a)\n", "invalid ATT&CK technique 'T10'"),
+            (
+                "@id x\n(?<a>a)\n@id x\n(?<b>b)\n",
+                "bad.pcre:3: duplicate pattern id 'x'",
+            ),
+            ("@id x\n@id y\n(?<a>a)\n", "@id is set twice"),
+            (
+                "(?<a>a)\n@owner soc\n",
+                "bad.pcre:2: metadata is not followed by a pattern",
+            ),
+            ("@include missing.pcre\n", "Unable to read pattern file"),
+        ] {
+            std::fs::write(&pack, contents).expect("write pack");
+            let err = load_pattern_pack(&pack).expect_err(contents);
+            assert!(err.contains(expected), "{:?}: {}", contents, err);
+        }
+    }
+}
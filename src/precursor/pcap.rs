@@ -0,0 +1,521 @@
+use std::io::{ErrorKind, Read};
+
+const PCAP_MAGIC_MICROS: u32 = 0xa1b2c3d4;
+const PCAP_MAGIC_NANOS: u32 = 0xa1b23c4d;
+const PCAPNG_SECTION_HEADER: u32 = 0x0a0d0d0a;
+const PCAPNG_BYTE_ORDER_MAGIC: u32 = 0x1a2b3c4d;
+const PCAPNG_INTERFACE_DESCRIPTION: u32 = 0x00000001;
+const PCAPNG_OBSOLETE_PACKET: u32 = 0x00000002;
+const PCAPNG_SIMPLE_PACKET: u32 = 0x00000003;
+const PCAPNG_ENHANCED_PACKET: u32 = 0x00000006;
+const PCAPNG_OPTION_END: u16 = 0;
+const PCAPNG_OPTION_IF_TSRESOL: u16 = 9;
+const MAX_RECORD_LEN: usize = 256 * 1024 * 1024;
+
+#[derive(Clone, Copy, Debug, Eq, PartialEq)]
+pub enum CaptureFormat {
+    Pcap,
+    PcapNg,
+}
+
+impl CaptureFormat {
+    pub fn as_str(&self) -> &'static str {
+        match self {
+            Self::Pcap => "pcap",
+            Self::PcapNg => "pcapng",
+        }
+    }
+}
+
+#[derive(Clone, Debug)]
+pub struct CapturedFrame {
+    pub frame_number: u64,
+    pub timestamp_nanos: u64,
+    pub link_type: u32,
+    pub original_len: u32,
+    pub data: Vec<u8>,
+}
+
+impl CapturedFrame {
+    pub fn timestamp_seconds(&self) -> f64 {
+        self.timestamp_nanos as f64 / 1_000_000_000.0
+    }
+}
+
+#[derive(Clone, Copy, Debug)]
+struct InterfaceDescription {
+    link_type: u32,
+    nanos_per_unit: u64,
+    units_per_second: u64,
+}
+
+#[derive(Clone, Copy, Debug)]
+enum ReaderState {
+    Pcap {
+        big_endian: bool,
+        link_type: u32,
+        nanosecond_resolution: bool,
+    },
+    PcapNg {
+        big_endian: bool,
+    },
+}
+
+pub struct CaptureReader<R: Read> {
+    reader: R,
+    state: ReaderState,
+    interfaces: Vec<InterfaceDescription>,
+    frame_number: u64,
+}
+
+fn read_u16(bytes: &[u8], big_endian: bool) -> u16 {
+    let raw = [bytes[0], bytes[1]];
+    if big_endian {
+        u16::from_be_bytes(raw)
+    } else {
+        u16::from_le_bytes(raw)
+    }
+}
+
+fn read_u32(bytes: &[u8], big_endian: bool) -> u32 {
+    let raw = [bytes[0], bytes[1], bytes[2], bytes[3]];
+    if big_endian {
+        u32::from_be_bytes(raw)
+    } else {
+        u32::from_le_bytes(raw)
+    }
+}
+
+// Returns Ok(false) on a clean EOF before any byte was read.
+fn read_exact_or_eof<R: Read>(reader: &mut R, buf: &mut [u8]) -> Result<bool, String> {
+    let mut filled = 0;
+    while filled < buf.len() {
+        match reader.read(&mut buf[filled..]) {
+            Ok(0) if filled == 0 => return Ok(false),
+            Ok(0) => {
+                return Err(format!(
+                    "truncated capture record: expected {} bytes, got {}",
+                    buf.len(),
+                    filled
+                ))
+            }
+            Ok(read) => filled += read,
+            Err(err) if err.kind() == ErrorKind::Interrupted => continue,
+            Err(err) => return Err(format!("unable to read capture: {}", err)),
+        }
+    }
+    Ok(true)
+}
+
+fn read_record_body<R: Read>(reader: &mut R, len: usize) -> Result<Vec<u8>, String> {
+    if len > MAX_RECORD_LEN {
+        return Err(format!(
+            "capture record length {} exceeds the {} byte limit",
+            len, MAX_RECORD_LEN
+        ));
+    }
+    let mut body = vec![0u8; len];
+    if !read_exact_or_eof(reader, body.as_mut_slice())? && len > 0 {
+        return Err("truncated capture record body".to_string());
+    }
+    Ok(body)
+}
+
+fn parse_pcapng_section_byte_order(header_rest: &[u8]) -> Result<bool, String> {
+    let magic_le = u32::from_le_bytes([
+        header_rest[0],
+        header_rest[1],
+        header_rest[2],
+        header_rest[3],
+    ]);
+    if magic_le == PCAPNG_BYTE_ORDER_MAGIC {
+        Ok(false)
+    } else if magic_le.swap_bytes() == PCAPNG_BYTE_ORDER_MAGIC {
+        Ok(true)
+    } else {
+        Err("invalid pcapng byte-order magic".to_string())
+    }
+}
+
+fn parse_interface_description(
+    body: &[u8],
+    big_endian: bool,
+) -> Result<InterfaceDescription, String> {
+    if body.len() < 8 {
+        return Err("pcapng interface description block is too short".to_string());
+    }
+    let link_type = read_u16(&body[0..2], big_endian) as u32;
+    let mut description = InterfaceDescription {
+        link_type,
+        nanos_per_unit: 1_000,
+        units_per_second: 1_000_000,
+    };
+    let mut offset = 8;
+    while offset + 4 <= body.len() {
+        let code = read_u16(&body[offset..offset + 2], big_endian);
+        let len = read_u16(&body[offset + 2..offset + 4], big_endian) as usize;
+        offset += 4;
+        if code == PCAPNG_OPTION_END || offset + len > body.len() {
+            break;
+        }
+        if code == PCAPNG_OPTION_IF_TSRESOL && len >= 1 {
+            let resolution = body[offset];
+            let exponent = (resolution & 0x7f) as u32;
+            let units_per_second = if resolution & 0x80 != 0 {
+                2u64.checked_pow(exponent)
+            } else {
+                10u64.checked_pow(exponent)
+            };
+            if let Some(units_per_second) = units_per_second.filter(|units| *units > 0) {
+                description.units_per_second = units_per_second;
+                description.nanos_per_unit = if 1_000_000_000 % units_per_second == 0 {
+                    1_000_000_000 / units_per_second
+                } else {
+                    0
+                };
+            }
+        }
+        offset += (len + 3) & !3;
+    }
+    Ok(description)
+}
+
+fn pcapng_timestamp_nanos(interface: &InterfaceDescription, high: u32, low: u32) -> u64 {
+    let units = ((high as u64) << 32) | low as u64;
+    if interface.nanos_per_unit > 0 {
+        units.saturating_mul(interface.nanos_per_unit)
+    } else {
+        let seconds = units / interface.units_per_second;
+        let remainder = units % interface.units_per_second;
+        seconds.saturating_mul(1_000_000_000)
+            + remainder.saturating_mul(1_000_000_000) / interface.units_per_second
+    }
+}
+
+impl<R: Read> CaptureReader<R> {
+    pub fn new(mut reader: R) -> Result<Self, String> {
+        let mut magic = [0u8; 4];
+        if !read_exact_or_eof(&mut reader, &mut magic)? {
+            return Err("capture input is empty".to_string());
+        }
+        let magic_le = u32::from_le_bytes(magic);
+        let magic_be = u32::from_be_bytes(magic);
+
+        if magic_le == PCAPNG_SECTION_HEADER {
+            let mut capture = CaptureReader {
+                reader,
+                state: ReaderState::PcapNg { big_endian: false },
+                interfaces: Vec::new(),
+                frame_number: 0,
+            };
+            capture.read_section_header_after_type()?;
+            return Ok(capture);
+        }
+
+        let (big_endian, nanosecond_resolution) = match (magic_le, magic_be) {
+            (PCAP_MAGIC_MICROS, _) => (false, false),
+            (PCAP_MAGIC_NANOS, _) => (false, true),
+            (_, PCAP_MAGIC_MICROS) => (true, false),
+            (_, PCAP_MAGIC_NANOS) => (true, true),
+            _ => {
+                return Err(format!(
+                    "unrecognized capture magic {:02x}{:02x}{:02x}{:02x}; expected pcap or pcapng",
+                    magic[0], magic[1], magic[2], magic[3]
+                ))
+            }
+        };
+        let mut header = [0u8; 20];
+        if !read_exact_or_eof(&mut reader, &mut header)? {
+            return Err("truncated pcap global header".to_string());
+        }
+        let link_type = read_u32(&header[16..20], big_endian) & 0x0fff_ffff;
+        Ok(CaptureReader {
+            reader,
+            state: ReaderState::Pcap {
+                big_endian,
+                link_type,
+                nanosecond_resolution,
+            },
+            interfaces: Vec::new(),
+            frame_number: 0,
+        })
+    }
+
+    pub fn format(&self) -> CaptureFormat {
+        match self.state {
+            ReaderState::Pcap { .. } => CaptureFormat::Pcap,
+            ReaderState::PcapNg { .. } => CaptureFormat::PcapNg,
+        }
+    }
+
+    // Reads the rest of a section header block once its block type has been consumed.
+    fn read_section_header_after_type(&mut self) -> Result<(), String> {
+        let mut prefix = [0u8; 8];
+        if !read_exact_or_eof(&mut self.reader, &mut prefix)? {
+            return Err("truncated pcapng section header".to_string());
+        }
+        let big_endian = parse_pcapng_section_byte_order(&prefix[4..8])?;
+        let total_len = read_u32(&prefix[0..4], big_endian) as usize;
+        if total_len < 28 || total_len % 4 != 0 {
+            return Err(format!(
+                "invalid pcapng section header length {}",
+                total_len
+            ));
+        }
+        read_record_body(&mut self.reader, total_len - 12)?;
+        self.state = ReaderState::PcapNg { big_endian };
+        self.interfaces.clear();
+        Ok(())
+    }
+
+    pub fn next_frame(&mut self) -> Result<Option<CapturedFrame>, String> {
+        match self.state {
+            ReaderState::Pcap {
+                big_endian,
+                link_type,
+                nanosecond_resolution,
+            } => self.next_pcap_frame(big_endian, link_type, nanosecond_resolution),
+            ReaderState::PcapNg { .. } => self.next_pcapng_frame(),
+        }
+    }
+
+    fn next_pcap_frame(
+        &mut self,
+        big_endian: bool,
+        link_type: u32,
+        nanosecond_resolution: bool,
+    ) -> Result<Option<CapturedFrame>, String> {
+        let mut header = [0u8; 16];
+        if !read_exact_or_eof(&mut self.reader, &mut header)? {
+            return Ok(None);
+        }
+        let ts_sec = read_u32(&header[0..4], big_endian) as u64;
+        let ts_frac = read_u32(&header[4..8], big_endian) as u64;
+        let captured_len = read_u32(&header[8..12], big_endian) as usize;
+        let original_len = read_u32(&header[12..16], big_endian);
+        let data = read_record_body(&mut self.reader, captured_len)?;
+        let frac_nanos = if nanosecond_resolution {
+            ts_frac
+        } else {
+            ts_frac.saturating_mul(1_000)
+        };
+        self.frame_number += 1;
+        Ok(Some(CapturedFrame {
+            frame_number: self.frame_number,
+            timestamp_nanos: ts_sec.saturating_mul(1_000_000_000) + frac_nanos,
+            link_type,
+            original_len,
+            data,
+        }))
+    }
+
+    fn next_pcapng_frame(&mut self) -> Result<Option<CapturedFrame>, String> {
+        loop {
+            let mut block_type_raw = [0u8; 4];
+            if !read_exact_or_eof(&mut self.reader, &mut block_type_raw)? {
+                return Ok(None);
+            }
+            if u32::from_le_bytes(block_type_raw) == PCAPNG_SECTION_HEADER {
+                self.read_section_header_after_type()?;
+                continue;
+            }
+
+            let ReaderState::PcapNg { big_endian } = self.state else {
+                return Err("pcapng block read outside of a pcapng section".to_string());
+            };
+            let block_type = read_u32(&block_type_raw, big_endian);
+            let mut len_raw = [0u8; 4];
+            if !read_exact_or_eof(&mut self.reader, &mut len_raw)? {
+                return Err("truncated pcapng block header".to_string());
+            }
+            let total_len = read_u32(&len_raw, big_endian) as usize;
+            if total_len < 12 || total_len % 4 != 0 {
+                return Err(format!("invalid pcapng block length {}", total_len));
+            }
+            // Body plus the trailing duplicate of the block length.
+            let mut body = read_record_body(&mut self.reader, total_len - 8)?;
+            body.truncate(total_len - 12);
+
+            match block_type {
+                PCAPNG_INTERFACE_DESCRIPTION => {
+                    let interface = parse_interface_description(body.as_slice(), big_endian)?;
+                    self.interfaces.push(interface);
+                }
+                PCAPNG_ENHANCED_PACKET | PCAPNG_OBSOLETE_PACKET => {
+                    if body.len() < 20 {
+                        return Err("pcapng packet block is too short".to_string());
+                    }
+                    let interface_id = if block_type == PCAPNG_ENHANCED_PACKET {
+                        read_u32(&body[0..4], big_endian) as usize
+                    } else {
+                        read_u16(&body[0..2], big_endian) as usize
+                    };
+                    let Some(interface) = self.interfaces.get(interface_id).copied() else {
+                        return Err(format!(
+                            "pcapng packet references unknown interface {}",
+                            interface_id
+                        ));
+                    };
+                    let ts_high = read_u32(&body[4..8], big_endian);
+                    let ts_low = read_u32(&body[8..12], big_endian);
+                    let captured_len = read_u32(&body[12..16], big_endian) as usize;
+                    let original_len = read_u32(&body[16..20], big_endian);
+                    let data_end = 20usize.saturating_add(captured_len);
+                    if data_end > body.len() {
+                        return Err("pcapng packet data exceeds block length".to_string());
+                    }
+                    self.frame_number += 1;
+                    return Ok(Some(CapturedFrame {
+                        frame_number: self.frame_number,
+                        timestamp_nanos: pcapng_timestamp_nanos(&interface, ts_high, ts_low),
+                        link_type: interface.link_type,
+                        original_len,
+                        data: body[20..data_end].to_vec(),
+                    }));
+                }
+                PCAPNG_SIMPLE_PACKET => {
+                    if body.len() < 4 {
+                        return Err("pcapng simple packet block is too short".to_string());
+                    }
+                    let Some(interface) = self.interfaces.first().copied() else {
+                        return Err("pcapng simple packet without interface".to_string());
+                    };
+                    let original_len = read_u32(&body[0..4], big_endian);
+                    let data_end = (4 + original_len as usize).min(body.len());
+                    self.frame_number += 1;
+                    return Ok(Some(CapturedFrame {
+                        frame_number: self.frame_number,
+                        timestamp_nanos: 0,
+                        link_type: interface.link_type,
+                        original_len,
+                        data: body[4..data_end].to_vec(),
+                    }));
+                }
+                _ => continue,
+            }
+        }
+    }
+}
+
+impl<R: Read> Iterator for CaptureReader<R> {
+    type Item = Result<CapturedFrame, String>;
+
+    fn next(&mut self) -> Option<Self::Item> {
+        self.next_frame().transpose()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn pcap_bytes(frames: &[(u32, u32, &[u8])]) -> Vec<u8> {
+        let mut bytes = Vec::new();
+        bytes.extend_from_slice(&PCAP_MAGIC_MICROS.to_le_bytes());
+        bytes.extend_from_slice(&2u16.to_le_bytes());
+        bytes.extend_from_slice(&4u16.to_le_bytes());
+        bytes.extend_from_slice(&0u32.to_le_bytes());
+        bytes.extend_from_slice(&0u32.to_le_bytes());
+        bytes.extend_from_slice(&65535u32.to_le_bytes());
+        bytes.extend_from_slice(&1u32.to_le_bytes());
+        for (ts_sec, ts_usec, data) in frames {
+            bytes.extend_from_slice(&ts_sec.to_le_bytes());
+            bytes.extend_from_slice(&ts_usec.to_le_bytes());
+            bytes.extend_from_slice(&(data.len() as u32).to_le_bytes());
+            bytes.extend_from_slice(&(data.len() as u32).to_le_bytes());
+            bytes.extend_from_slice(data);
+        }
+        bytes
+    }
+
+    fn pcapng_block(block_type: u32, body: &[u8]) -> Vec<u8> {
+        let padded_len = (body.len() + 3) & !3;
+        let total_len = (12 + padded_len) as u32;
+        let mut block = Vec::new();
+        block.extend_from_slice(&block_type.to_le_bytes());
+        block.extend_from_slice(&total_len.to_le_bytes());
+        block.extend_from_slice(body);
+        block.resize(8 + padded_len, 0);
+        block.extend_from_slice(&total_len.to_le_bytes());
+        block
+    }
+
+    fn pcapng_bytes(tsresol: Option<u8>, timestamp_units: u64, data: &[u8]) -> Vec<u8> {
+        let mut shb = Vec::new();
+        shb.extend_from_slice(&PCAPNG_BYTE_ORDER_MAGIC.to_le_bytes());
+        shb.extend_from_slice(&1u16.to_le_bytes());
+        shb.extend_from_slice(&0u16.to_le_bytes());
+        shb.extend_from_slice(&u64::MAX.to_le_bytes());
+
+        let mut idb = Vec::new();
+        idb.extend_from_slice(&1u16.to_le_bytes());
+        idb.extend_from_slice(&0u16.to_le_bytes());
+        idb.extend_from_slice(&65535u32.to_le_bytes());
+        if let Some(resolution) = tsresol {
+            idb.extend_from_slice(&PCAPNG_OPTION_IF_TSRESOL.to_le_bytes());
+            idb.extend_from_slice(&1u16.to_le_bytes());
+            idb.extend_from_slice(&[resolution, 0, 0, 0]);
+            idb.extend_from_slice(&[0, 0, 0, 0]);
+        }
+
+        let mut epb = Vec::new();
+        epb.extend_from_slice(&0u32.to_le_bytes());
+        epb.extend_from_slice(&((timestamp_units >> 32) as u32).to_le_bytes());
+        epb.extend_from_slice(&(timestamp_units as u32).to_le_bytes());
+        epb.extend_from_slice(&(data.len() as u32).to_le_bytes());
+        epb.extend_from_slice(&(data.len() as u32).to_le_bytes());
+        epb.extend_from_slice(data);
+
+        let mut bytes = pcapng_block(PCAPNG_SECTION_HEADER, &shb);
+        bytes.extend(pcapng_block(PCAPNG_INTERFACE_DESCRIPTION, &idb));
+        bytes.extend(pcapng_block(0x0000_0005, &[0u8; 8]));
+        bytes.extend(pcapng_block(PCAPNG_ENHANCED_PACKET, &epb));
+        bytes
+    }
+
+    #[test]
+    fn test_pcap_reader_yields_numbered_frames() {
+        let bytes = pcap_bytes(&[(10, 500_000, b"first"), (11, 0, b"second")]);
+        let reader = CaptureReader::new(bytes.as_slice()).expect("pcap header");
+        assert_eq!(reader.format(), CaptureFormat::Pcap);
+        let frames: Vec<CapturedFrame> = reader.map(|frame| frame.expect("frame")).collect();
+        assert_eq!(frames.len(), 2);
+        assert_eq!(frames[0].frame_number, 1);
+        assert_eq!(frames[0].link_type, 1);
+        assert_eq!(frames[0].data, b"first");
+        assert!((frames[0].timestamp_seconds() - 10.5).abs() < 1e-9);
+        assert_eq!(frames[1].frame_number, 2);
+    }
+
+    #[test]
+    fn test_pcapng_reader_skips_unknown_blocks_and_applies_tsresol() {
+        let bytes = pcapng_bytes(Some(9), 1_500_000_000, b"payload");
+        let mut reader = CaptureReader::new(bytes.as_slice()).expect("pcapng header");
+        assert_eq!(reader.format(), CaptureFormat::PcapNg);
+        let frame = reader.next_frame().expect("frame").expect("some frame");
+        assert_eq!(frame.data, b"payload");
+        assert_eq!(frame.timestamp_nanos, 1_500_000_000);
+        assert!(reader.next_frame().expect("eof").is_none());
+    }
+
+    #[test]
+    fn test_pcapng_default_resolution_is_microseconds() {
+        let bytes = pcapng_bytes(None, 2_000_001, b"x");
+        let mut reader = CaptureReader::new(bytes.as_slice()).expect("pcapng header");
+        let frame = reader.next_frame().expect("frame").expect("some frame");
+        assert_eq!(frame.timestamp_nanos, 2_000_001_000);
+    }
+
+    #[test]
+    fn test_truncated_capture_is_an_error() {
+        let mut bytes = pcap_bytes(&[(1, 0, b"abcdef")]);
+        bytes.truncate(bytes.len() - 3);
+        let mut reader = CaptureReader::new(bytes.as_slice()).expect("pcap header");
+        assert!(reader.next_frame().is_err());
+    }
+
+    #[test]
+    fn test_unknown_magic_is_rejected() {
+        assert!(CaptureReader::new(&b"GET / HTTP/1.1\r\n\r\n"[..]).is_err());
+    }
+}
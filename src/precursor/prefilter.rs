@@ -0,0 +1,382 @@
+use aho_corasick::{AhoCorasick, MatchKind};
+use regex_syntax::hir::literal::{ExtractKind, Extractor};
+use regex_syntax::hir::{Hir, HirKind};
+use regex_syntax::ParserBuilder;
+use serde_json::{json, Value};
+use std::collections::HashMap;
+use std::sync::atomic::{AtomicU64, Ordering};
+
+// Shorter literals occur in nearly every payload, so they would only add scan cost.
+const MIN_LITERAL_LEN: usize = 2;
+// Caps the case-folded and alternation expansions kept for a single pattern.
+const MAX_LITERALS_PER_PATTERN: usize = 64;
+const SELECTIVE_LITERAL_LEN: usize = 4;
+
+// Literals of which at least one must occur in every match of `pattern`, or None when
+// no useful set exists (or the pattern uses PCRE syntax outside the regex-syntax subset,
+// in which case it always runs through PCRE2).
+pub fn required_literals(pattern: &str) -> Option<Vec<Vec<u8>>> {
+    if has_class_set_operations(pattern) || has_divergent_flags_or_escapes(pattern) {
+        return None;
+    }
+    // Mirrors util::build_regex: byte-oriented, ASCII classes, multi-line anchors.
+    let hir = ParserBuilder::new()
+        .unicode(false)
+        .utf8(false)
+        .multi_line(true)
+        .build()
+        .parse(pattern)
+        .ok()?;
+    required(&hir)
+}
+
+// Inside a class regex-syntax reads `&&`, `--`, `~~` and nested `[...]` as set
+// operations, where PCRE2 takes them literally, so such patterns parse differently.
+// POSIX classes like `[:digit:]` mean the same to both.
+fn has_class_set_operations(pattern: &str) -> bool {
+    let bytes = pattern.as_bytes();
+    let mut in_class = false;
+    let mut index = 0;
+    while index < bytes.len() {
+        match bytes[index] {
+            b'\\' => {
+                index += 2;
+                continue;
+            }
+            b'[' if !in_class => {
+                in_class = true;
+                index += 1;
+                if bytes.get(index) == Some(&b'^') {
+                    index += 1;
+                }
+                // A leading `]` is a literal member in both dialects.
+                if bytes.get(index) == Some(&b']') {
+                    index += 1;
+                }
+                continue;
+            }
+            b'[' if bytes.get(index + 1) == Some(&b':') => match pattern[index + 2..].find(":]") {
+                Some(end) => {
+                    index += end + 4;
+                    continue;
+                }
+                None => return true,
+            },
+            b'[' => return true,
+            b']' if in_class => in_class = false,
+            b'&' | b'-' | b'~' if in_class && bytes.get(index + 1) == Some(&bytes[index]) => {
+                return true
+            }
+            _ => {}
+        }
+        index += 1;
+    }
+    false
+}
+
+// Extended mode (`x`/`xx`) drops whitespace inside classes in regex-syntax but not in
+// PCRE2, and `\v` is a vertical tab to regex-syntax but any vertical space to PCRE2.
+fn has_divergent_flags_or_escapes(pattern: &str) -> bool {
+    let bytes = pattern.as_bytes();
+    let mut index = 0;
+    while index < bytes.len() {
+        match bytes[index] {
+            b'\\' if bytes.get(index + 1) == Some(&b'v') => return true,
+            b'\\' => {
+                index += 2;
+                continue;
+            }
+            b'(' if bytes.get(index + 1) == Some(&b'?') => {
+                let flags = &bytes[index + 2..];
+                let enabled = flags
+                    .iter()
+                    .take_while(|flag| flag.is_ascii_alphabetic())
+                    .count();
+                let is_flag_group = matches!(flags.get(enabled), Some(b'-' | b':' | b')'));
+                if is_flag_group && flags[..enabled].contains(&b'x') {
+                    return true;
+                }
+            }
+            _ => {}
+        }
+        index += 1;
+    }
+    false
+}
+
+fn required(hir: &Hir) -> Option<Vec<Vec<u8>>> {
+    match hir.kind() {
+        HirKind::Capture(capture) => required(&capture.sub),
+        HirKind::Repetition(repetition) if repetition.min > 0 => required(&repetition.sub),
+        // Every part of a concatenation occurs in each match, so the best part will do.
+        HirKind::Concat(parts) => parts.iter().map(required).fold(extract(hir), better),
+        // Each branch needs its own literals; the union then covers the alternation.
+        HirKind::Alternation(branches) => {
+            let mut union: Vec<Vec<u8>> = Vec::new();
+            for branch in branches {
+                let Some(literals) = required(branch) else {
+                    return extract(hir);
+                };
+                for literal in literals {
+                    if !union.contains(&literal) {
+                        union.push(literal);
+                    }
+                }
+            }
+            let union = Some(union).filter(|union| quality(union).is_some());
+            better(extract(hir), union)
+        }
+        _ => extract(hir),
+    }
+}
+
+// Finite prefix or suffix sets from regex-syntax, which expand small classes and
+// case-insensitive literals; any match starts (or ends) with one of them.
+fn extract(hir: &Hir) -> Option<Vec<Vec<u8>>> {
+    [ExtractKind::Prefix, ExtractKind::Suffix]
+        .into_iter()
+        .map(|kind| {
+            let seq = Extractor::new().kind(kind).extract(hir);
+            let mut literals: Vec<Vec<u8>> = Vec::new();
+            for literal in seq.literals()? {
+                let bytes = literal.as_bytes().to_vec();
+                if !literals.contains(&bytes) {
+                    literals.push(bytes);
+                }
+            }
+            quality(&literals).map(|_| literals)
+        })
+        .fold(None, better)
+}
+
+// Longer shortest literals filter better up to SELECTIVE_LITERAL_LEN; past that, fewer
+// literals win, then longer ones.
+fn quality(literals: &[Vec<u8>]) -> Option<(usize, std::cmp::Reverse<usize>, usize)> {
+    let shortest = literals.iter().map(Vec::len).min()?;
+    (shortest >= MIN_LITERAL_LEN && literals.len() <= MAX_LITERALS_PER_PATTERN).then_some((
+        shortest.min(SELECTIVE_LITERAL_LEN),
+        std::cmp::Reverse(literals.len()),
+        shortest,
+    ))
+}
+
+fn better(left: Option<Vec<Vec<u8>>>, right: Option<Vec<Vec<u8>>>) -> Option<Vec<Vec<u8>>> {
+    match (left, right) {
+        (Some(left), Some(right)) => {
+            if quality(&right) > quality(&left) {
+                Some(right)
+            } else {
+                Some(left)
+            }
+        }
+        (left, right) => left.or(right),
+    }
+}
+
+// One Aho-Corasick pass over the required literals of every filterable pattern decides
+// which patterns can possibly match; the rest are skipped before PCRE2 runs.
+pub struct Prefilter {
+    automaton: AhoCorasick,
+    // Pattern indexes to mark for each literal the automaton reports.
+    literal_patterns: Vec<Vec<usize>>,
+    filtered: Vec<bool>,
+    filtered_count: usize,
+    scans: AtomicU64,
+    candidates: AtomicU64,
+}
+
+impl Prefilter {
+    // Builds over `(pattern_index, expression)` pairs out of `pattern_count` patterns;
+    // None when no pattern has usable literals.
+    pub fn build(patterns: &[(usize, &str)], pattern_count: usize) -> Result<Option<Self>, String> {
+        let mut literal_ids: HashMap<Vec<u8>, usize> = HashMap::new();
+        let mut literals: Vec<Vec<u8>> = Vec::new();
+        let mut literal_patterns: Vec<Vec<usize>> = Vec::new();
+        let mut filtered = vec![false; pattern_count];
+        for (index, pattern) in patterns {
+            let Some(required) = required_literals(pattern) else {
+                continue;
+            };
+            for literal in required {
+                let id = *literal_ids.entry(literal.clone()).or_insert_with(|| {
+                    literals.push(literal);
+                    literal_patterns.push(Vec::new());
+                    literal_patterns.len() - 1
+                });
+                if !literal_patterns[id].contains(index) {
+                    literal_patterns[id].push(*index);
+                }
+            }
+            filtered[*index] = true;
+        }
+        let filtered_count = filtered.iter().filter(|filtered| **filtered).count();
+        if filtered_count == 0 {
+            return Ok(None);
+        }
+        let automaton = AhoCorasick::builder()
+            .match_kind(MatchKind::Standard)
+            .build(&literals)
+            .map_err(|err| format!("Unable to build literal prefilter: {}", err))?;
+        Ok(Some(Self {
+            automaton,
+            literal_patterns,
+            filtered,
+            filtered_count,
+            scans: AtomicU64::new(0),
+            candidates: AtomicU64::new(0),
+        }))
+    }
+
+    // True for every pattern that may match the payload: all unfiltered patterns, plus
+    // the filtered ones whose required literals occur in it.
+    pub fn candidates(&self, payload: &[u8]) -> Vec<bool> {
+        let mut candidates: Vec<bool> = self.filtered.iter().map(|filtered| !filtered).collect();
+        let mut hits = 0;
+        for found in self.automaton.find_overlapping_iter(payload) {
+            for index in &self.literal_patterns[found.pattern().as_usize()] {
+                if !candidates[*index] {
+                    candidates[*index] = true;
+                    hits += 1;
+                }
+            }
+            if hits == self.filtered_count {
+                break;
+            }
+        }
+        self.scans.fetch_add(1, Ordering::Relaxed);
+        self.candidates.fetch_add(hits as u64, Ordering::Relaxed);
+        candidates
+    }
+
+    // `HitRatio` is the share of filtered pattern checks that still went on to PCRE2.
+    pub fn stats(&self) -> Value {
+        let scans = self.scans.load(Ordering::Relaxed);
+        let candidates = self.candidates.load(Ordering::Relaxed);
+        let checks = scans * self.filtered_count as u64;
+        json!({
+            "Patterns": self.filtered_count,
+            "Literals": self.literal_patterns.len(),
+            "Scans": scans,
+            "Candidates": candidates,
+            "Skipped": checks - candidates,
+            "HitRatio": if checks == 0 { 0.0 } else { candidates as f64 / checks as f64 },
+        })
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn literals(pattern: &str) -> Option<Vec<String>> {
+        required_literals(pattern).map(|literals| {
+            let mut literals: Vec<String> = literals
+                .into_iter()
+                .map(|literal| String::from_utf8_lossy(&literal).into_owned())
+                .collect();
+            literals.sort();
+            literals
+        })
+    }
+
+    #[test]
+    fn required_literals_follow_concatenations_alternations_and_case_folding() {
+        assert_eq!(
+            literals(r"(?<http_get>^GET /)"),
+            Some(vec!["GET /".to_string()])
+        );
+        assert_eq!(
+            literals(r"(?s)(?<wget>wget \S+ - http\S+ \| sh)"),
+            Some(vec![" - http".to_string()])
+        );
+        assert_eq!(
+            literals(r"(?<port>\d+)/tcp open"),
+            Some(vec!["/tcp open".to_string()])
+        );
+        assert_eq!(
+            literals(r"(?<verb>^(?:GET|POST) )|(?<ssh>SSH-2\.0)"),
+            Some(vec![
+                "GET ".to_string(),
+                "POST ".to_string(),
+                "SSH-2.0".to_string()
+            ])
+        );
+        assert_eq!(literals(r"(?i)(?<ua>curl/)").map(|set| set.len()), Some(16));
+        assert_eq!(
+            literals(r"(?<magic>\x7fELF)"),
+            Some(vec!["\u{7f}ELF".to_string()])
+        );
+    }
+
+    #[test]
+    fn patterns_without_required_literals_are_not_filtered() {
+        assert_eq!(literals(r"(?<digits>\d+)"), None);
+        assert_eq!(literals(r"(?<maybe>(?:GET)?x)"), None);
+        assert_eq!(literals(r"(?<either>GET|x)"), None);
+        assert_eq!(literals(r"(?<admin>(?<=user=)admin)"), None);
+        assert_eq!(literals(r"(?<echo>(\w+) \1)"), None);
+    }
+
+    #[test]
+    fn class_set_operations_are_not_filtered() {
+        assert_eq!(literals(r"(?<and>GET [a&&b]x)"), None);
+        assert_eq!(literals(r"(?<diff>GET [a--b]x)"), None);
+        assert_eq!(literals(r"(?<xor>GET [^a~~b]x)"), None);
+        assert_eq!(literals(r"(?<nested>GET [[ab]c]x)"), None);
+        assert_eq!(literals(r"(?<bracket>GET []&&]x)"), None);
+        assert_eq!(
+            literals(r"(?<posix>GET [[:digit:]&]x)"),
+            Some(vec!["GET ".to_string()])
+        );
+        assert_eq!(
+            literals(r"(?<escaped>GET \[a&&b\]x)"),
+            Some(vec!["GET [a&&b]x".to_string()])
+        );
+        assert_eq!(
+            literals(r"(?<range>GET [a-z-]x)"),
+            Some(vec!["GET ".to_string()])
+        );
+    }
+
+    #[test]
+    fn extended_mode_and_vertical_space_escapes_are_not_filtered() {
+        assert_eq!(literals(r"(?x)(?<t>GET[ /]admin)"), None);
+        assert_eq!(literals(r"(?xx)(?<t>GET[ /]admin)"), None);
+        assert_eq!(literals(r"(?<t>(?ix:GET[ /]admin))"), None);
+        assert_eq!(literals(r"(?<t>GET\vadmin)"), None);
+        assert_eq!(
+            literals(r"(?s-x)(?<t>GET[ /]admin)"),
+            Some(vec!["admin".to_string()])
+        );
+        assert_eq!(
+            literals(r"(?<t>GET \\vx)"),
+            Some(vec!["GET \\vx".to_string()])
+        );
+    }
+
+    #[test]
+    fn prefilter_marks_candidates_and_counts_hits() {
+        let patterns = [
+            (0, r"(?<http_get>^GET /)"),
+            (1, r"(?<digits>\d+)"),
+            (2, r"(?<ssh>SSH-2\.0)"),
+        ];
+        let prefilter = Prefilter::build(&patterns, 3)
+            .expect("build")
+            .expect("prefilter");
+        assert_eq!(prefilter.stats()["Patterns"], json!(2));
+        assert_eq!(
+            prefilter.candidates(b"GET / HTTP/1.1"),
+            vec![true, true, false]
+        );
+        assert_eq!(prefilter.candidates(b"hello"), vec![false, true, false]);
+        let stats = prefilter.stats();
+        assert_eq!(stats["Scans"], json!(2));
+        assert_eq!(stats["Candidates"], json!(1));
+        assert_eq!(stats["Skipped"], json!(3));
+        assert_eq!(stats["HitRatio"], json!(0.25));
+        assert!(Prefilter::build(&patterns[1..2], 3)
+            .expect("build")
+            .is_none());
+    }
+}
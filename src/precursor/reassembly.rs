@@ -0,0 +1,621 @@
+use crate::precursor::packet::{DecodedPacket, Transport};
+use serde_json::{json, Map, Value};
+use std::collections::{BTreeMap, BTreeSet, HashMap};
+use std::net::IpAddr;
+use xxhash_rust::xxh3::xxh3_64;
+
+#[derive(Clone, Copy, Debug, Eq, PartialEq)]
+pub enum ReassemblyMode {
+    Stream,
+    Message,
+}
+
+impl ReassemblyMode {
+    pub fn from_str(value: &str) -> Result<Self, String> {
+        match value {
+            "stream" => Ok(Self::Stream),
+            "message" => Ok(Self::Message),
+            _ => Err(format!("Unsupported TCP reassembly mode '{}'", value)),
+        }
+    }
+}
+
+#[derive(Clone, Copy, Debug, Eq, PartialEq)]
+pub enum OverlapPolicy {
+    First,
+    Last,
+}
+
+impl OverlapPolicy {
+    pub fn from_str(value: &str) -> Result<Self, String> {
+        match value {
+            "first" => Ok(Self::First),
+            "last" => Ok(Self::Last),
+            _ => Err(format!("Unsupported TCP overlap policy '{}'", value)),
+        }
+    }
+}
+
+#[derive(Clone, Copy, Debug)]
+pub struct ReassemblyConfig {
+    pub mode: ReassemblyMode,
+    pub overlap_policy: OverlapPolicy,
+    pub max_stream_bytes: u64,
+}
+
+#[derive(Clone, Copy, Debug, Eq, Hash, PartialEq)]
+pub enum StreamDirection {
+    ToServer,
+    ToClient,
+}
+
+impl StreamDirection {
+    pub fn as_str(&self) -> &'static str {
+        match self {
+            Self::ToServer => "to_server",
+            Self::ToClient => "to_client",
+        }
+    }
+
+    fn index(&self) -> usize {
+        match self {
+            Self::ToServer => 0,
+            Self::ToClient => 1,
+        }
+    }
+}
+
+#[derive(Clone, Copy, Debug, Default, Eq, PartialEq)]
+pub struct DirectionAnomalies {
+    pub retransmitted_bytes: u64,
+    pub overlap_conflicts: u64,
+    pub gap_count: u64,
+    pub fragmented_segments: u64,
+    pub truncated: bool,
+}
+
+#[derive(Clone, Debug)]
+pub struct StreamChunk {
+    pub flow_id: String,
+    pub direction: StreamDirection,
+    pub src_ip: IpAddr,
+    pub src_port: u16,
+    pub dst_ip: IpAddr,
+    pub dst_port: u16,
+    pub start_offset: u64,
+    pub end_offset: u64,
+    pub first_frame: u64,
+    pub last_frame: u64,
+    pub first_timestamp_nanos: u64,
+    pub anomalies: DirectionAnomalies,
+    pub payload: Vec<u8>,
+}
+
+impl StreamChunk {
+    pub fn metadata(&self) -> Map<String, Value> {
+        let mut metadata = Map::new();
+        metadata.insert("flow_id".to_string(), json!(self.flow_id));
+        metadata.insert("direction".to_string(), json!(self.direction.as_str()));
+        metadata.insert("transport".to_string(), json!(Transport::Tcp.as_str()));
+        metadata.insert("src_ip".to_string(), json!(self.src_ip.to_string()));
+        metadata.insert("src_port".to_string(), json!(self.src_port));
+        metadata.insert("dst_ip".to_string(), json!(self.dst_ip.to_string()));
+        metadata.insert("dst_port".to_string(), json!(self.dst_port));
+        metadata.insert("start_offset".to_string(), json!(self.start_offset));
+        metadata.insert("end_offset".to_string(), json!(self.end_offset));
+        metadata.insert("first_frame".to_string(), json!(self.first_frame));
+        metadata.insert("last_frame".to_string(), json!(self.last_frame));
+        metadata.insert(
+            "first_timestamp".to_string(),
+            serde_json::Number::from_f64(self.first_timestamp_nanos as f64 / 1_000_000_000.0)
+                .map(Value::Number)
+                .unwrap_or(Value::Null),
+        );
+        metadata.insert(
+            "retransmitted_bytes".to_string(),
+            json!(self.anomalies.retransmitted_bytes),
+        );
+        metadata.insert(
+            "overlap_conflicts".to_string(),
+            json!(self.anomalies.overlap_conflicts),
+        );
+        metadata.insert("gap_count".to_string(), json!(self.anomalies.gap_count));
+        metadata.insert(
+            "fragmented_segments".to_string(),
+            json!(self.anomalies.fragmented_segments),
+        );
+        metadata.insert("truncated".to_string(), json!(self.anomalies.truncated));
+        metadata
+    }
+}
+
+#[derive(Clone, Copy, Debug, Eq, Hash, PartialEq)]
+struct Endpoint {
+    ip: IpAddr,
+    port: u16,
+}
+
+#[derive(Clone, Copy, Debug, Eq, Hash, PartialEq)]
+struct FlowKey {
+    low: Endpoint,
+    high: Endpoint,
+}
+
+impl FlowKey {
+    fn new(left: Endpoint, right: Endpoint) -> Self {
+        if (left.ip, left.port) <= (right.ip, right.port) {
+            FlowKey {
+                low: left,
+                high: right,
+            }
+        } else {
+            FlowKey {
+                low: right,
+                high: left,
+            }
+        }
+    }
+}
+
+#[derive(Clone, Debug)]
+struct Segment {
+    data: Vec<u8>,
+    frame: u64,
+    timestamp_nanos: u64,
+}
+
+#[derive(Debug, Default)]
+struct DirectionState {
+    isn: Option<u32>,
+    segments: BTreeMap<u64, Segment>,
+    message_starts: BTreeSet<u64>,
+    stored_bytes: u64,
+    fin: bool,
+    anomalies: DirectionAnomalies,
+}
+
+impl DirectionState {
+    fn insert(&mut self, offset: u64, segment: Segment, config: &ReassemblyConfig) {
+        let mut segment = segment;
+        if offset >= config.max_stream_bytes {
+            self.anomalies.truncated = true;
+            return;
+        }
+        let allowed = (config.max_stream_bytes - offset) as usize;
+        if segment.data.len() > allowed {
+            segment.data.truncate(allowed);
+            self.anomalies.truncated = true;
+        }
+        let end = offset + segment.data.len() as u64;
+
+        let overlapping: Vec<u64> = self
+            .segments
+            .range(..end)
+            .rev()
+            .take_while(|(start, existing)| **start + existing.data.len() as u64 > offset)
+            .map(|(start, _)| *start)
+            .collect::<Vec<u64>>()
+            .into_iter()
+            .rev()
+            .collect();
+
+        for start in overlapping.iter() {
+            let existing = &self.segments[start];
+            let overlap_start = (*start).max(offset);
+            let overlap_end = (*start + existing.data.len() as u64).min(end);
+            let existing_bytes =
+                &existing.data[(overlap_start - start) as usize..(overlap_end - start) as usize];
+            let new_bytes =
+                &segment.data[(overlap_start - offset) as usize..(overlap_end - offset) as usize];
+            if existing_bytes == new_bytes {
+                self.anomalies.retransmitted_bytes += overlap_end - overlap_start;
+            } else {
+                self.anomalies.overlap_conflicts += 1;
+            }
+        }
+
+        match config.overlap_policy {
+            OverlapPolicy::First => {
+                let mut cursor = offset;
+                for start in overlapping.iter() {
+                    let existing_end = *start + self.segments[start].data.len() as u64;
+                    if *start > cursor {
+                        self.store(cursor, slice_segment(&segment, offset, cursor, *start));
+                    }
+                    cursor = cursor.max(existing_end);
+                }
+                if cursor < end {
+                    self.store(cursor, slice_segment(&segment, offset, cursor, end));
+                }
+            }
+            OverlapPolicy::Last => {
+                for start in overlapping.iter() {
+                    let Some(existing) = self.segments.remove(start) else {
+                        continue;
+                    };
+                    self.stored_bytes -= existing.data.len() as u64;
+                    let existing_end = *start + existing.data.len() as u64;
+                    if *start < offset {
+                        self.store(*start, slice_segment(&existing, *start, *start, offset));
+                    }
+                    if existing_end > end {
+                        self.store(end, slice_segment(&existing, *start, end, existing_end));
+                    }
+                }
+                self.store(offset, segment);
+            }
+        }
+    }
+
+    fn store(&mut self, offset: u64, segment: Segment) {
+        if segment.data.is_empty() {
+            return;
+        }
+        self.stored_bytes += segment.data.len() as u64;
+        self.segments.insert(offset, segment);
+    }
+
+    fn drain_chunks(&mut self, mode: ReassemblyMode) -> Vec<(u64, Vec<Segment>)> {
+        let mut runs: Vec<(u64, Vec<Segment>)> = Vec::new();
+        let mut run_end = 0u64;
+        let segments = std::mem::take(&mut self.segments);
+        self.stored_bytes = 0;
+        for (start, segment) in segments {
+            let segment_end = start + segment.data.len() as u64;
+            let split_here = mode == ReassemblyMode::Message
+                && self.message_starts.contains(&start)
+                && start == run_end;
+            match runs.last_mut() {
+                Some((_, run)) if start == run_end && !split_here => run.push(segment),
+                _ => {
+                    if !runs.is_empty() && start != run_end {
+                        self.anomalies.gap_count += 1;
+                    }
+                    runs.push((start, vec![segment]));
+                }
+            }
+            run_end = segment_end;
+        }
+        runs
+    }
+}
+
+fn slice_segment(segment: &Segment, segment_offset: u64, start: u64, end: u64) -> Segment {
+    Segment {
+        data: segment.data[(start - segment_offset) as usize..(end - segment_offset) as usize]
+            .to_vec(),
+        frame: segment.frame,
+        timestamp_nanos: segment.timestamp_nanos,
+    }
+}
+
+#[derive(Debug)]
+struct FlowState {
+    client: Endpoint,
+    server: Endpoint,
+    directions: [DirectionState; 2],
+    last_data_direction: Option<StreamDirection>,
+    reset: bool,
+}
+
+impl FlowState {
+    fn flow_id(&self) -> String {
+        let canonical = format!(
+            "tcp|{}|{}|{}|{}",
+            self.client.ip, self.client.port, self.server.ip, self.server.port
+        );
+        format!("{:016x}", xxh3_64(canonical.as_bytes()))
+    }
+
+    fn finished(&self) -> bool {
+        self.reset || (self.directions[0].fin && self.directions[1].fin)
+    }
+}
+
+pub struct TcpReassembler {
+    config: ReassemblyConfig,
+    flows: HashMap<FlowKey, FlowState>,
+}
+
+impl TcpReassembler {
+    pub fn new(config: ReassemblyConfig) -> Self {
+        TcpReassembler {
+            config,
+            flows: HashMap::new(),
+        }
+    }
+
+    // Returns chunks for flows that were closed by this segment (FIN from both sides or RST).
+    pub fn push(
+        &mut self,
+        packet: DecodedPacket,
+        frame_number: u64,
+        timestamp_nanos: u64,
+    ) -> Vec<StreamChunk> {
+        let (Some(seq), Some(flags)) = (packet.tcp_seq, packet.tcp_flags) else {
+            return Vec::new();
+        };
+        let source = Endpoint {
+            ip: packet.src_ip,
+            port: packet.src_port,
+        };
+        let destination = Endpoint {
+            ip: packet.dst_ip,
+            port: packet.dst_port,
+        };
+        let key = FlowKey::new(source, destination);
+        let flow = self.flows.entry(key).or_insert_with(|| {
+            // A bare SYN-ACK means we missed the SYN; its sender is the server.
+            let (client, server) = if flags.syn && flags.ack {
+                (destination, source)
+            } else {
+                (source, destination)
+            };
+            FlowState {
+                client,
+                server,
+                directions: [DirectionState::default(), DirectionState::default()],
+                last_data_direction: None,
+                reset: false,
+            }
+        });
+
+        let direction = if source == flow.client {
+            StreamDirection::ToServer
+        } else {
+            StreamDirection::ToClient
+        };
+        let state = &mut flow.directions[direction.index()];
+        if flags.syn {
+            state.isn.get_or_insert(seq.wrapping_add(1));
+        }
+        if packet.fragmentation.is_some() {
+            state.anomalies.fragmented_segments += 1;
+        }
+        if !packet.payload.is_empty() {
+            let isn = *state.isn.get_or_insert(seq);
+            let relative = seq.wrapping_sub(isn);
+            // Segments from before the first observed sequence number cannot be placed.
+            if relative < 0x8000_0000 {
+                let offset = relative as u64;
+                if flow.last_data_direction != Some(direction) {
+                    state.message_starts.insert(offset);
+                    flow.last_data_direction = Some(direction);
+                }
+                state.insert(
+                    offset,
+                    Segment {
+                        data: packet.payload,
+                        frame: frame_number,
+                        timestamp_nanos,
+                    },
+                    &self.config,
+                );
+            } else {
+                state.anomalies.retransmitted_bytes += packet.payload.len() as u64;
+            }
+        }
+        if flags.fin {
+            state.fin = true;
+        }
+        if flags.rst {
+            flow.reset = true;
+        }
+
+        if flow.finished() {
+            if let Some(flow) = self.flows.remove(&key) {
+                return self.flow_chunks(flow);
+            }
+        }
+        Vec::new()
+    }
+
+    pub fn flush(&mut self) -> Vec<StreamChunk> {
+        let mut flows: Vec<FlowState> = self.flows.drain().map(|(_, flow)| flow).collect();
+        flows.sort_by_key(|flow| {
+            flow.directions
+                .iter()
+                .flat_map(|direction| direction.segments.values().map(|segment| segment.frame))
+                .min()
+                .unwrap_or(u64::MAX)
+        });
+        flows
+            .into_iter()
+            .flat_map(|flow| self.flow_chunks(flow))
+            .collect()
+    }
+
+    fn flow_chunks(&self, mut flow: FlowState) -> Vec<StreamChunk> {
+        let flow_id = flow.flow_id();
+        let mut chunks = Vec::new();
+        for direction in [StreamDirection::ToServer, StreamDirection::ToClient] {
+            let (src, dst) = match direction {
+                StreamDirection::ToServer => (flow.client, flow.server),
+                StreamDirection::ToClient => (flow.server, flow.client),
+            };
+            let state = &mut flow.directions[direction.index()];
+            let runs = state.drain_chunks(self.config.mode);
+            let anomalies = state.anomalies;
+            for (start_offset, segments) in runs {
+                let mut payload = Vec::new();
+                let mut first_frame = u64::MAX;
+                let mut last_frame = 0;
+                let mut first_timestamp_nanos = u64::MAX;
+                for segment in segments {
+                    first_frame = first_frame.min(segment.frame);
+                    last_frame = last_frame.max(segment.frame);
+                    first_timestamp_nanos = first_timestamp_nanos.min(segment.timestamp_nanos);
+                    payload.extend_from_slice(&segment.data);
+                }
+                chunks.push(StreamChunk {
+                    flow_id: flow_id.clone(),
+                    direction,
+                    src_ip: src.ip,
+                    src_port: src.port,
+                    dst_ip: dst.ip,
+                    dst_port: dst.port,
+                    start_offset,
+                    end_offset: start_offset + payload.len() as u64,
+                    first_frame,
+                    last_frame,
+                    first_timestamp_nanos,
+                    anomalies,
+                    payload,
+                });
+            }
+        }
+        chunks.sort_by_key(|chunk| chunk.first_frame);
+        chunks
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::precursor::packet::TcpFlags;
+    use std::net::Ipv4Addr;
+
+    const CLIENT: [u8; 4] = [10, 0, 0, 1];
+    const SERVER: [u8; 4] = [10, 0, 0, 2];
+
+    fn segment(to_server: bool, seq: u32, flags: TcpFlags, data: &[u8]) -> DecodedPacket {
+        let (src, dst, src_port, dst_port) = if to_server {
+            (CLIENT, SERVER, 40000, 80)
+        } else {
+            (SERVER, CLIENT, 80, 40000)
+        };
+        DecodedPacket {
+            vlan_ids: Vec::new(),
+            src_ip: IpAddr::V4(Ipv4Addr::from(src)),
+            dst_ip: IpAddr::V4(Ipv4Addr::from(dst)),
+            transport: Transport::Tcp,
+            src_port,
+            dst_port,
+            tcp_seq: Some(seq),
+            tcp_flags: Some(flags),
+            fragmentation: None,
+            payload: data.to_vec(),
+        }
+    }
+
+    fn data_flags() -> TcpFlags {
+        TcpFlags {
+            ack: true,
+            psh: true,
+            ..TcpFlags::default()
+        }
+    }
+
+    fn syn() -> TcpFlags {
+        TcpFlags {
+            syn: true,
+            ..TcpFlags::default()
+        }
+    }
+
+    fn fin() -> TcpFlags {
+        TcpFlags {
+            fin: true,
+            ack: true,
+            ..TcpFlags::default()
+        }
+    }
+
+    fn config(mode: ReassemblyMode, overlap_policy: OverlapPolicy) -> ReassemblyConfig {
+        ReassemblyConfig {
+            mode,
+            overlap_policy,
+            max_stream_bytes: 1 << 20,
+        }
+    }
+
+    #[test]
+    fn test_out_of_order_segments_are_reassembled_in_sequence() {
+        let mut reassembler =
+            TcpReassembler::new(config(ReassemblyMode::Stream, OverlapPolicy::First));
+        assert!(reassembler
+            .push(segment(true, 99, syn(), b""), 1, 0)
+            .is_empty());
+        reassembler.push(segment(true, 109, data_flags(), b"{jndi:ldap}"), 3, 0);
+        reassembler.push(segment(true, 100, data_flags(), b"GET /?q=$"), 2, 0);
+        reassembler.push(segment(true, 100, data_flags(), b"GET /?q=$"), 4, 0);
+        reassembler.push(segment(true, 120, fin(), b""), 5, 0);
+        let chunks = reassembler.push(segment(false, 500, fin(), b""), 6, 0);
+
+        assert_eq!(chunks.len(), 1);
+        assert_eq!(chunks[0].payload, b"GET /?q=${jndi:ldap}");
+        assert_eq!(chunks[0].direction, StreamDirection::ToServer);
+        assert_eq!(chunks[0].start_offset, 0);
+        assert_eq!(chunks[0].end_offset, 20);
+        assert_eq!(chunks[0].first_frame, 2);
+        assert_eq!(chunks[0].last_frame, 3);
+        assert_eq!(chunks[0].anomalies.retransmitted_bytes, 9);
+    }
+
+    #[test]
+    fn test_overlap_policy_selects_first_or_last_bytes() {
+        for (policy, expected) in [
+            (OverlapPolicy::First, b"AAAAXB".to_vec()),
+            (OverlapPolicy::Last, b"AAXXBB".to_vec()),
+        ] {
+            let mut reassembler = TcpReassembler::new(config(ReassemblyMode::Stream, policy));
+            reassembler.push(segment(true, 0, data_flags(), b"AAAA"), 1, 0);
+            reassembler.push(segment(true, 2, data_flags(), b"XXX"), 2, 0);
+            reassembler.push(segment(true, 4, data_flags(), b"BB"), 3, 0);
+            let chunks = reassembler.flush();
+            assert_eq!(chunks.len(), 1);
+            assert_eq!(chunks[0].payload, expected);
+            assert_eq!(chunks[0].anomalies.overlap_conflicts, 2);
+        }
+    }
+
+    #[test]
+    fn test_message_mode_splits_on_direction_turns() {
+        let mut reassembler =
+            TcpReassembler::new(config(ReassemblyMode::Message, OverlapPolicy::First));
+        reassembler.push(segment(true, 0, data_flags(), b"REQ1-"), 1, 0);
+        reassembler.push(segment(true, 5, data_flags(), b"part2"), 2, 0);
+        reassembler.push(segment(false, 0, data_flags(), b"RESP1"), 3, 0);
+        reassembler.push(segment(true, 10, data_flags(), b"REQ2"), 4, 0);
+        let chunks = reassembler.flush();
+
+        let payloads: Vec<&[u8]> = chunks
+            .iter()
+            .map(|chunk| chunk.payload.as_slice())
+            .collect();
+        assert_eq!(
+            payloads,
+            vec![&b"REQ1-part2"[..], &b"RESP1"[..], &b"REQ2"[..]]
+        );
+        assert_eq!(chunks[2].start_offset, 10);
+        assert!(chunks
+            .iter()
+            .all(|chunk| chunk.flow_id == chunks[0].flow_id));
+    }
+
+    #[test]
+    fn test_gaps_split_stream_runs_and_are_counted() {
+        let mut reassembler =
+            TcpReassembler::new(config(ReassemblyMode::Stream, OverlapPolicy::First));
+        reassembler.push(segment(true, 0, data_flags(), b"head"), 1, 0);
+        reassembler.push(segment(true, 10, data_flags(), b"tail"), 2, 0);
+        let chunks = reassembler.flush();
+        assert_eq!(chunks.len(), 2);
+        assert_eq!(chunks[1].start_offset, 10);
+        assert_eq!(chunks[1].anomalies.gap_count, 1);
+    }
+
+    #[test]
+    fn test_max_stream_bytes_truncates_direction() {
+        let mut reassembler = TcpReassembler::new(ReassemblyConfig {
+            mode: ReassemblyMode::Stream,
+            overlap_policy: OverlapPolicy::First,
+            max_stream_bytes: 6,
+        });
+        reassembler.push(segment(true, 0, data_flags(), b"0123"), 1, 0);
+        reassembler.push(segment(true, 4, data_flags(), b"4567"), 2, 0);
+        let chunks = reassembler.flush();
+        assert_eq!(chunks[0].payload, b"012345");
+        assert!(chunks[0].anomalies.truncated);
+    }
+}
@@ -26,6 +26,9 @@ pub fn vectorscan_compatibility_issues(pattern: &str) -> Vec<&'static str> {
     if pattern.contains("(?<=") || pattern.contains("(?<!") {
         issues.push("lookbehind assertions are not supported");
     }
+    if pattern.contains("(?=") || pattern.contains("(?!") {
+        issues.push("lookahead assertions are not supported");
+    }
     if pattern.contains("\\1")
         || pattern.contains("\\2")
         || pattern.contains("\\3")
@@ -35,10 +38,13 @@ pub fn vectorscan_compatibility_issues(pattern: &str) -> Vec<&'static str> {
         || pattern.contains("\\7")
         || pattern.contains("\\8")
         || pattern.contains("\\9")
+        || pattern.contains("\\k<")
+        || pattern.contains("\\g")
+        || pattern.contains("(?P=")
     {
         issues.push("backreferences are not supported");
     }
-    if pattern.contains("(?R") || pattern.contains("(?&") {
+    if pattern.contains("(?R") || pattern.contains("(?&") || pattern.contains("(?P>") {
         issues.push("recursive/subroutine constructs are not supported");
     }
     if pattern.contains("(?>") {
@@ -53,6 +59,300 @@ pub fn vectorscan_compatibility_issues(pattern: &str) -> Vec<&'static str> {
     issues
 }
 
+// `(pattern_index, reason)` for every expression left to PCRE2 alone.
+pub type RejectedExpressions = Vec<(usize, String)>;
+
+// Vectorscan only reports which expressions matched, so it is used as a multi-pattern
+// prefilter: a pattern it did not flag cannot match, and PCRE2 still runs the flagged
+// ones to extract named groups, which keeps tags identical to the PCRE2 engine.
+pub struct VectorscanSet {
+    #[cfg(feature = "regex-vectorscan")]
+    database: native::Database,
+    #[cfg(not(feature = "regex-vectorscan"))]
+    unavailable: std::convert::Infallible,
+}
+
+impl VectorscanSet {
+    // Compiles `(pattern_index, expression)` pairs into one block-mode database. Expressions
+    // Vectorscan rejects are returned with its message instead of failing the whole set;
+    // the set is None when nothing compiled.
+    #[cfg(feature = "regex-vectorscan")]
+    pub fn compile(
+        expressions: &[(usize, &str)],
+    ) -> Result<(Option<Self>, RejectedExpressions), String> {
+        let (database, rejected) = native::Database::compile(expressions)?;
+        Ok((database.map(|database| Self { database }), rejected))
+    }
+
+    #[cfg(not(feature = "regex-vectorscan"))]
+    pub fn compile(
+        _expressions: &[(usize, &str)],
+    ) -> Result<(Option<Self>, RejectedExpressions), String> {
+        Err(
+            "this build does not include Vectorscan; rebuild with `--features regex-vectorscan`"
+                .to_string(),
+        )
+    }
+
+    // Sets `candidates[pattern_index]` for every expression that matched the payload.
+    #[cfg(feature = "regex-vectorscan")]
+    pub fn scan(&self, payload: &[u8], candidates: &mut [bool]) -> Result<(), String> {
+        self.database.scan(payload, candidates)
+    }
+
+    #[cfg(not(feature = "regex-vectorscan"))]
+    pub fn scan(&self, _payload: &[u8], _candidates: &mut [bool]) -> Result<(), String> {
+        match self.unavailable {}
+    }
+}
+
+#[cfg(feature = "regex-vectorscan")]
+mod native {
+    use super::RejectedExpressions;
+    use std::ffi::{CStr, CString};
+    use std::os::raw::{c_char, c_int, c_uint, c_ulonglong, c_void};
+    use std::sync::Mutex;
+
+    #[repr(C)]
+    struct HsDatabase {
+        _private: [u8; 0],
+    }
+
+    #[repr(C)]
+    struct HsScratch {
+        _private: [u8; 0],
+    }
+
+    #[repr(C)]
+    struct HsCompileError {
+        message: *mut c_char,
+        expression: c_int,
+    }
+
+    type MatchEventHandler = unsafe extern "C" fn(
+        id: c_uint,
+        from: c_ulonglong,
+        to: c_ulonglong,
+        flags: c_uint,
+        context: *mut c_void,
+    ) -> c_int;
+
+    extern "C" {
+        fn hs_compile_multi(
+            expressions: *const *const c_char,
+            flags: *const c_uint,
+            ids: *const c_uint,
+            elements: c_uint,
+            mode: c_uint,
+            platform: *const c_void,
+            database: *mut *mut HsDatabase,
+            error: *mut *mut HsCompileError,
+        ) -> c_int;
+        fn hs_free_compile_error(error: *mut HsCompileError) -> c_int;
+        fn hs_free_database(database: *mut HsDatabase) -> c_int;
+        fn hs_alloc_scratch(database: *const HsDatabase, scratch: *mut *mut HsScratch) -> c_int;
+        fn hs_clone_scratch(source: *const HsScratch, scratch: *mut *mut HsScratch) -> c_int;
+        fn hs_free_scratch(scratch: *mut HsScratch) -> c_int;
+        fn hs_scan(
+            database: *const HsDatabase,
+            data: *const c_char,
+            length: c_uint,
+            flags: c_uint,
+            scratch: *mut HsScratch,
+            on_event: MatchEventHandler,
+            context: *mut c_void,
+        ) -> c_int;
+    }
+
+    const HS_SUCCESS: c_int = 0;
+    const HS_MODE_BLOCK: c_uint = 1;
+    // Mirrors util::build_regex, which compiles PCRE2 patterns in multi-line mode.
+    const HS_FLAG_MULTILINE: c_uint = 4;
+    const HS_FLAG_SINGLEMATCH: c_uint = 8;
+    const HS_FLAG_ALLOWEMPTY: c_uint = 16;
+    // Prefilter mode guarantees a superset of the exact matches: where Vectorscan's
+    // semantics differ from PCRE2 it over-reports, and PCRE2 has the final say.
+    const HS_FLAG_PREFILTER: c_uint = 128;
+
+    struct Scratch(*mut HsScratch);
+
+    // The compiled database is immutable and safe to share; each scan takes a scratch
+    // space from the pool (cloning the prototype when the pool is empty) and returns it.
+    pub struct Database {
+        database: *mut HsDatabase,
+        prototype: *mut HsScratch,
+        pool: Mutex<Vec<Scratch>>,
+    }
+
+    unsafe impl Send for Database {}
+    unsafe impl Sync for Database {}
+
+    unsafe extern "C" fn on_match(
+        id: c_uint,
+        _from: c_ulonglong,
+        _to: c_ulonglong,
+        _flags: c_uint,
+        context: *mut c_void,
+    ) -> c_int {
+        let candidates = &mut *(context as *mut &mut [bool]);
+        if let Some(candidate) = candidates.get_mut(id as usize) {
+            *candidate = true;
+        }
+        0
+    }
+
+    impl Database {
+        pub fn compile(
+            expressions: &[(usize, &str)],
+        ) -> Result<(Option<Self>, RejectedExpressions), String> {
+            let mut rejected = Vec::new();
+            let mut remaining = Vec::with_capacity(expressions.len());
+            for (index, expression) in expressions {
+                match CString::new(*expression) {
+                    Ok(expression) => remaining.push((*index, expression)),
+                    Err(_) => rejected.push((*index, "embedded NUL byte".to_string())),
+                }
+            }
+            // Vectorscan names the first expression it cannot compile, so drop that one
+            // and retry until the rest compile together.
+            let database = loop {
+                if remaining.is_empty() {
+                    return Ok((None, rejected));
+                }
+                let pointers: Vec<*const c_char> = remaining
+                    .iter()
+                    .map(|(_, expression)| expression.as_ptr())
+                    .collect();
+                let ids: Vec<c_uint> = remaining
+                    .iter()
+                    .map(|(index, _)| *index as c_uint)
+                    .collect();
+                let flags = vec![
+                    HS_FLAG_MULTILINE
+                        | HS_FLAG_SINGLEMATCH
+                        | HS_FLAG_ALLOWEMPTY
+                        | HS_FLAG_PREFILTER;
+                    ids.len()
+                ];
+                let mut database = std::ptr::null_mut();
+                let mut error = std::ptr::null_mut();
+                let rc = unsafe {
+                    hs_compile_multi(
+                        pointers.as_ptr(),
+                        flags.as_ptr(),
+                        ids.as_ptr(),
+                        ids.len() as c_uint,
+                        HS_MODE_BLOCK,
+                        std::ptr::null(),
+                        &mut database,
+                        &mut error,
+                    )
+                };
+                if rc == HS_SUCCESS {
+                    break database;
+                }
+                let (message, expression) = if error.is_null() {
+                    (
+                        "Vectorscan failed to compile the pattern set".to_string(),
+                        -1,
+                    )
+                } else {
+                    unsafe {
+                        let message = if (*error).message.is_null() {
+                            "unknown compile error".to_string()
+                        } else {
+                            CStr::from_ptr((*error).message)
+                                .to_string_lossy()
+                                .into_owned()
+                        };
+                        let expression = (*error).expression;
+                        hs_free_compile_error(error);
+                        (message, expression)
+                    }
+                };
+                match usize::try_from(expression) {
+                    Ok(position) if position < remaining.len() => {
+                        let (index, _) = remaining.remove(position);
+                        rejected.push((index, message));
+                    }
+                    _ => return Err(format!("Unable to build Vectorscan database: {}", message)),
+                }
+            };
+            let mut prototype = std::ptr::null_mut();
+            if unsafe { hs_alloc_scratch(database, &mut prototype) } != HS_SUCCESS {
+                unsafe {
+                    hs_free_database(database);
+                }
+                return Err("Unable to allocate Vectorscan scratch space".to_string());
+            }
+            Ok((
+                Some(Self {
+                    database,
+                    prototype,
+                    pool: Mutex::new(Vec::new()),
+                }),
+                rejected,
+            ))
+        }
+
+        pub fn scan(&self, payload: &[u8], mut candidates: &mut [bool]) -> Result<(), String> {
+            let length = c_uint::try_from(payload.len())
+                .map_err(|_| "payload is too large for a single Vectorscan scan".to_string())?;
+            let pooled = self
+                .pool
+                .lock()
+                .map_err(|_| "Vectorscan scratch pool lock is poisoned".to_string())?
+                .pop();
+            let scratch = match pooled {
+                Some(scratch) => scratch,
+                None => {
+                    let mut scratch = std::ptr::null_mut();
+                    if unsafe { hs_clone_scratch(self.prototype, &mut scratch) } != HS_SUCCESS {
+                        return Err("Unable to clone Vectorscan scratch space".to_string());
+                    }
+                    Scratch(scratch)
+                }
+            };
+            let rc = unsafe {
+                hs_scan(
+                    self.database,
+                    payload.as_ptr() as *const c_char,
+                    length,
+                    0,
+                    scratch.0,
+                    on_match,
+                    &mut candidates as *mut &mut [bool] as *mut c_void,
+                )
+            };
+            match self.pool.lock() {
+                Ok(mut pool) => pool.push(scratch),
+                Err(_) => unsafe {
+                    hs_free_scratch(scratch.0);
+                },
+            }
+            if rc == HS_SUCCESS {
+                Ok(())
+            } else {
+                Err(format!("Vectorscan scan failed with error {}", rc))
+            }
+        }
+    }
+
+    impl Drop for Database {
+        fn drop(&mut self) {
+            unsafe {
+                if let Ok(pool) = self.pool.get_mut() {
+                    for scratch in pool.drain(..) {
+                        hs_free_scratch(scratch.0);
+                    }
+                }
+                hs_free_scratch(self.prototype);
+                hs_free_database(self.database);
+            }
+        }
+    }
+}
+
 #[cfg(test)]
 mod tests {
     use super::*;
@@ -80,5 +380,41 @@ mod tests {
         let issues = vectorscan_compatibility_issues(r"(?<=abc)(foo)\1");
         assert!(issues.iter().any(|issue| issue.contains("lookbehind")));
         assert!(issues.iter().any(|issue| issue.contains("backreferences")));
+        let issues = vectorscan_compatibility_issues(r"(?<word>\w+) (?!\k<word>)");
+        assert!(issues.iter().any(|issue| issue.contains("lookahead")));
+        assert!(issues.iter().any(|issue| issue.contains("backreferences")));
+        assert!(vectorscan_compatibility_issues(r"(?<http_get>^GET /)").is_empty());
+    }
+
+    #[cfg(not(feature = "regex-vectorscan"))]
+    #[test]
+    fn vectorscan_set_reports_missing_feature() {
+        let err = VectorscanSet::compile(&[(0, "(?<x>x)")])
+            .err()
+            .expect("compile without the feature");
+        assert!(err.contains("regex-vectorscan"));
+    }
+
+    #[cfg(feature = "regex-vectorscan")]
+    #[test]
+    fn vectorscan_set_flags_matching_patterns_and_rejects_unsupported_ones() {
+        let (set, rejected) = VectorscanSet::compile(&[
+            (0, "(?<http_get>^GET /)"),
+            (1, "(?<=user=)admin"),
+            (2, "(?<ssh>^SSH-2\\.0)"),
+        ])
+        .expect("compile");
+        let set = set.expect("database");
+        assert_eq!(
+            rejected.iter().map(|(index, _)| *index).collect::<Vec<_>>(),
+            vec![1]
+        );
+        let mut candidates = vec![false; 3];
+        set.scan(b"junk\nGET / HTTP/1.1", &mut candidates)
+            .expect("scan");
+        assert_eq!(candidates, vec![true, false, false]);
+        let mut candidates = vec![false; 3];
+        set.scan(b"HTTP/1.1 200 OK", &mut candidates).expect("scan");
+        assert_eq!(candidates, vec![false, false, false]);
     }
 }
@@ -81,7 +81,7 @@ pub fn calculate_similarity_hash(
     tlsh_algorithm: &str,
 ) -> Result<SimilarityHash, SimilarityError> {
     match mode {
-        SimilarityMode::Tlsh => calculate_tlsh_hash(payload, &tlsh_algorithm.to_string())
+        SimilarityMode::Tlsh => calculate_tlsh_hash(payload, tlsh_algorithm)
             .map(SimilarityHash::Tlsh)
             .map_err(|err| SimilarityError::new(err.to_string())),
         SimilarityMode::Lzjd => calculate_lzjd_hash(payload)
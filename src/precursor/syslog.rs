@@ -0,0 +1,345 @@
+use serde_json::{json, Map, Value};
+
+const FACILITY_NAMES: [&str; 24] = [
+    "kern",
+    "user",
+    "mail",
+    "daemon",
+    "auth",
+    "syslog",
+    "lpr",
+    "news",
+    "uucp",
+    "cron",
+    "authpriv",
+    "ftp",
+    "ntp",
+    "security",
+    "console",
+    "solaris-cron",
+    "local0",
+    "local1",
+    "local2",
+    "local3",
+    "local4",
+    "local5",
+    "local6",
+    "local7",
+];
+const SEVERITY_NAMES: [&str; 8] = [
+    "emerg", "alert", "crit", "err", "warning", "notice", "info", "debug",
+];
+const MONTHS: [&str; 12] = [
+    "Jan", "Feb", "Mar", "Apr", "May", "Jun", "Jul", "Aug", "Sep", "Oct", "Nov", "Dec",
+];
+const NILVALUE: &str = "-";
+
+// Splits a syslog line into its parsed header and the MSG body patterns should run
+// against. RFC 5424 is recognised by its version digit after PRI; everything else is
+// parsed leniently as RFC 3164, where only PRI or a timestamp is required and a
+// missing HOSTNAME/TAG leaves the rest of the line as MSG (Fortinet `key=value`
+// bodies, Cisco `%ASA-...` messages).
+pub fn parse_syslog(line: &str) -> Result<(Map<String, Value>, &str), String> {
+    let mut header = Map::new();
+    let rest = match parse_pri(line) {
+        Some((pri, rest)) => {
+            header.insert("pri".to_string(), json!(pri));
+            header.insert("facility".to_string(), json!(pri >> 3));
+            header.insert(
+                "facility_name".to_string(),
+                json!(FACILITY_NAMES[(pri >> 3) as usize]),
+            );
+            header.insert("severity".to_string(), json!(pri & 7));
+            header.insert(
+                "severity_name".to_string(),
+                json!(SEVERITY_NAMES[(pri & 7) as usize]),
+            );
+            rest
+        }
+        None => line,
+    };
+    let has_pri = !header.is_empty();
+
+    if has_pri {
+        if let Some(rest) = rest.strip_prefix("1 ") {
+            header.insert("format".to_string(), json!("rfc5424"));
+            header.insert("version".to_string(), json!(1));
+            let msg = parse_rfc5424(rest, &mut header)?;
+            return Ok((header, msg));
+        }
+    }
+
+    let (timestamp, rest) = match split_rfc3164_timestamp(rest) {
+        Some(split) => split,
+        None if has_pri => {
+            header.insert("format".to_string(), json!("rfc3164"));
+            return Ok((header, rest.trim_start()));
+        }
+        None => return Err("line has neither a syslog PRI nor a timestamp".to_string()),
+    };
+    header.insert("format".to_string(), json!("rfc3164"));
+    header.insert("timestamp".to_string(), json!(timestamp));
+
+    let body = rest.trim_start();
+    let (hostname, rest) = split_token(body);
+    if hostname.is_empty() || hostname.ends_with(':') || hostname.starts_with('%') {
+        // No HOSTNAME, e.g. `Mar 1 10:00:00 sshd[1]: ...` or `... %ASA-6-302013: ...`.
+        let msg = parse_tag(body, &mut header);
+        return Ok((header, msg));
+    }
+    header.insert("hostname".to_string(), json!(hostname));
+    let msg = parse_tag(rest, &mut header);
+    Ok((header, msg))
+}
+
+fn parse_pri(line: &str) -> Option<(u8, &str)> {
+    let rest = line.strip_prefix('<')?;
+    let close = rest.find('>')?;
+    if close == 0 || close > 3 {
+        return None;
+    }
+    let pri = rest[..close].parse::<u8>().ok().filter(|pri| *pri <= 191)?;
+    Some((pri, &rest[close + 1..]))
+}
+
+// `Mmm dd hh:mm:ss`, optionally with a year (Cisco) or fractional seconds, or an
+// RFC 3339 timestamp as written by rsyslog's high-precision template.
+fn split_rfc3164_timestamp(rest: &str) -> Option<(&str, &str)> {
+    let trimmed = rest.trim_start_matches('*');
+    if MONTHS.iter().any(|month| trimmed.starts_with(month)) {
+        let mut fields = 0;
+        let mut end = 0;
+        for (index, token) in trimmed.split(' ').enumerate() {
+            if token.is_empty() {
+                // Day numbers are space-padded: `Mar  1`.
+                end += 1;
+                continue;
+            }
+            let expected = match fields {
+                0 => token.len() == 3,
+                1 => token.len() <= 2 && token.bytes().all(|byte| byte.is_ascii_digit()),
+                2 if token.len() == 4 && token.bytes().all(|byte| byte.is_ascii_digit()) => {
+                    // Cisco's optional year; the time follows.
+                    end += token.len() + usize::from(index > 0);
+                    continue;
+                }
+                2 => is_clock(token.trim_end_matches(':')),
+                _ => false,
+            };
+            if !expected {
+                return None;
+            }
+            end += token.len() + usize::from(index > 0);
+            fields += 1;
+            if fields == 3 {
+                let end = end + (rest.len() - trimmed.len());
+                let timestamp = rest[..end].trim_end_matches(':');
+                return Some((timestamp, &rest[end..]));
+            }
+        }
+        return None;
+    }
+    let (token, remainder) = split_token(rest);
+    let is_rfc3339 = token.len() >= 19
+        && token.as_bytes()[4] == b'-'
+        && token.as_bytes()[10] == b'T'
+        && token[..4].bytes().all(|byte| byte.is_ascii_digit());
+    is_rfc3339.then_some((token, remainder))
+}
+
+fn is_clock(token: &str) -> bool {
+    let clock = token.split('.').next().unwrap_or_default();
+    let parts: Vec<&str> = clock.split(':').collect();
+    parts.len() == 3
+        && parts
+            .iter()
+            .all(|part| part.len() == 2 && part.bytes().all(|byte| byte.is_ascii_digit()))
+}
+
+// `TAG[PID]: MSG`; without a well-formed tag the whole remainder is MSG.
+fn parse_tag<'a>(rest: &'a str, header: &mut Map<String, Value>) -> &'a str {
+    let tag_len = rest
+        .find(|ch: char| !(ch.is_ascii_alphanumeric() || matches!(ch, '-' | '_' | '.' | '/')))
+        .unwrap_or(rest.len());
+    let (tag, after_tag) = rest.split_at(tag_len);
+    let (procid, after_procid) = match after_tag.strip_prefix('[') {
+        Some(bracketed) => match bracketed.split_once(']') {
+            Some((procid, after)) => (Some(procid), after),
+            None => return rest,
+        },
+        None => (None, after_tag),
+    };
+    let Some(msg) = after_procid.strip_prefix(':') else {
+        return rest;
+    };
+    if tag.is_empty() {
+        return msg.trim_start();
+    }
+    header.insert("app_name".to_string(), json!(tag));
+    if let Some(procid) = procid {
+        header.insert("procid".to_string(), json!(procid));
+    }
+    msg.strip_prefix(' ').unwrap_or(msg)
+}
+
+fn parse_rfc5424<'a>(rest: &'a str, header: &mut Map<String, Value>) -> Result<&'a str, String> {
+    let mut rest = rest;
+    for name in ["timestamp", "hostname", "app_name", "procid", "msgid"] {
+        let (token, remainder) = split_token(rest);
+        if token.is_empty() {
+            return Err(format!("RFC 5424 header is missing {}", name));
+        }
+        if token != NILVALUE {
+            header.insert(name.to_string(), json!(token));
+        }
+        rest = remainder;
+    }
+    let (structured_data, msg) = parse_structured_data(rest)?;
+    if !structured_data.is_empty() {
+        header.insert(
+            "structured_data".to_string(),
+            Value::Object(structured_data),
+        );
+    }
+    let msg = msg.strip_prefix(' ').unwrap_or(msg);
+    Ok(msg.strip_prefix('\u{feff}').unwrap_or(msg))
+}
+
+// `[id name="value" ...][id2 ...]` into `{id: {name: value}}`; `\"`, `\\` and `\]`
+// are unescaped in values.
+fn parse_structured_data(rest: &str) -> Result<(Map<String, Value>, &str), String> {
+    let mut elements = Map::new();
+    if let Some(msg) = rest.strip_prefix(NILVALUE) {
+        return Ok((elements, msg));
+    }
+    let mut remaining = rest;
+    while let Some(element) = remaining.strip_prefix('[') {
+        let id_end = element
+            .find([' ', ']'])
+            .ok_or("unterminated structured data element")?;
+        let id = &element[..id_end];
+        let mut params = Map::new();
+        let mut cursor = &element[id_end..];
+        loop {
+            cursor = cursor.trim_start_matches(' ');
+            if let Some(after) = cursor.strip_prefix(']') {
+                cursor = after;
+                break;
+            }
+            let (name, after_name) = cursor
+                .split_once("=\"")
+                .ok_or("malformed structured data parameter")?;
+            let mut value = String::new();
+            let mut chars = after_name.char_indices();
+            let mut consumed = None;
+            while let Some((index, ch)) = chars.next() {
+                match ch {
+                    '\\' => match chars.next() {
+                        Some((_, escaped @ ('"' | '\\' | ']'))) => value.push(escaped),
+                        Some((_, other)) => {
+                            value.push('\\');
+                            value.push(other);
+                        }
+                        None => break,
+                    },
+                    '"' => {
+                        consumed = Some(index + 1);
+                        break;
+                    }
+                    _ => value.push(ch),
+                }
+            }
+            let consumed = consumed.ok_or("unterminated structured data value")?;
+            params.insert(name.to_string(), Value::String(value));
+            cursor = &after_name[consumed..];
+        }
+        elements.insert(id.to_string(), Value::Object(params));
+        remaining = cursor;
+    }
+    if elements.is_empty() {
+        return Err("RFC 5424 header is missing STRUCTURED-DATA".to_string());
+    }
+    Ok((elements, remaining))
+}
+
+fn split_token(text: &str) -> (&str, &str) {
+    match text.split_once(' ') {
+        Some((token, rest)) => (token, rest),
+        None => (text, ""),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_rfc5424_header_and_structured_data() {
+        let line = "<165>1 2026-03-01T10:00:00.003Z fw01 sshd 4123 ID47 [exampleSDID@32473 iut=\"3\" eventSource=\"App\\\"lication\"][meta seq=\"9\"] \u{feff}Accepted password for root";
+        let (header, msg) = parse_syslog(line).expect("parse");
+        assert_eq!(msg, "Accepted password for root");
+        assert_eq!(header["format"], json!("rfc5424"));
+        assert_eq!(header["facility"], json!(20));
+        assert_eq!(header["facility_name"], json!("local4"));
+        assert_eq!(header["severity_name"], json!("notice"));
+        assert_eq!(header["timestamp"], json!("2026-03-01T10:00:00.003Z"));
+        assert_eq!(header["hostname"], json!("fw01"));
+        assert_eq!(header["app_name"], json!("sshd"));
+        assert_eq!(header["procid"], json!("4123"));
+        assert_eq!(header["msgid"], json!("ID47"));
+        assert_eq!(
+            header["structured_data"],
+            json!({
+                "exampleSDID@32473": {"iut": "3", "eventSource": "App\"lication"},
+                "meta": {"seq": "9"},
+            })
+        );
+
+        let (header, msg) = parse_syslog("<14>1 - - - - - -").expect("nil header");
+        assert_eq!(msg, "");
+        assert!(!header.contains_key("hostname"));
+        assert!(parse_syslog("<14>1 2026-03-01T10:00:00Z host app").is_err());
+    }
+
+    #[test]
+    fn test_rfc3164_with_and_without_pri() {
+        let (header, msg) =
+            parse_syslog("<34>Oct 11 22:14:15 mymachine su[230]: 'su root' failed").expect("parse");
+        assert_eq!(header["format"], json!("rfc3164"));
+        assert_eq!(header["severity"], json!(2));
+        assert_eq!(header["timestamp"], json!("Oct 11 22:14:15"));
+        assert_eq!(header["hostname"], json!("mymachine"));
+        assert_eq!(header["app_name"], json!("su"));
+        assert_eq!(header["procid"], json!("230"));
+        assert_eq!(msg, "'su root' failed");
+
+        let (header, msg) =
+            parse_syslog("Mar  1 09:00:01 gw CRON[77]: (root) CMD (run-parts)").expect("parse");
+        assert!(!header.contains_key("pri"));
+        assert_eq!(header["timestamp"], json!("Mar  1 09:00:01"));
+        assert_eq!(header["app_name"], json!("CRON"));
+        assert_eq!(msg, "(root) CMD (run-parts)");
+
+        assert!(parse_syslog("plain text line").is_err());
+    }
+
+    #[test]
+    fn test_vendor_variants_keep_body_as_msg() {
+        let line = "<189>date=2026-03-01 time=10:00:00 devname=\"FGT60F\" type=\"event\" msg=\"SSL VPN login fail\"";
+        let (header, msg) = parse_syslog(line).expect("fortinet");
+        assert_eq!(header["facility_name"], json!("local7"));
+        assert!(!header.contains_key("timestamp"));
+        assert!(msg.starts_with("date=2026-03-01 time=10:00:00 devname="));
+
+        let line = "<166>Mar 01 2026 10:00:00 asa01 : %ASA-6-302013: Built inbound TCP connection";
+        let (header, msg) = parse_syslog(line).expect("cisco");
+        assert_eq!(header["timestamp"], json!("Mar 01 2026 10:00:00"));
+        assert_eq!(header["hostname"], json!("asa01"));
+        assert_eq!(msg, "%ASA-6-302013: Built inbound TCP connection");
+
+        let line = "<166>Mar 01 2026 10:00:00: %ASA-4-106023: Deny tcp src outside";
+        let (header, msg) = parse_syslog(line).expect("cisco without host");
+        assert!(!header.contains_key("hostname"));
+        assert_eq!(msg, "%ASA-4-106023: Deny tcp src outside");
+    }
+}
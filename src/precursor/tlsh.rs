@@ -97,14 +97,14 @@ impl Error for TlshCalculationError {}
 
 pub fn calculate_tlsh_hash(
     payload: &[u8],
-    tlsh_algorithm: &String,
+    tlsh_algorithm: &str,
 ) -> Result<TlshHashInstance, TlshCalculationError> {
     if payload.len() < 49 {
         return Err(TlshCalculationError {
             message: "Payload must be at least 48 bytes".to_owned(),
         });
     }
-    let mut builder: TlshBuilderInstance = match tlsh_algorithm.as_str() {
+    let mut builder: TlshBuilderInstance = match tlsh_algorithm {
         "48_1" => TlshBuilderInstance::Tlsh48_1(tlsh2::TlshBuilder48_1::new()),
         "128_1" => TlshBuilderInstance::Tlsh128_1(tlsh2::TlshBuilder128_1::new()),
         "128_3" => TlshBuilderInstance::Tlsh128_3(tlsh2::TlshBuilder128_3::new()),
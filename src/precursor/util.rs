@@ -1,6 +1,5 @@
 use base64::engine::{general_purpose::STANDARD, Engine};
 use pcre2::bytes::{Regex, RegexBuilder};
-use std::path::PathBuf;
 use xxhash_rust::xxh3::xxh3_64;
 
 pub fn xxh3_64_hex(input: Vec<u8>) -> (u64, String) {
@@ -10,8 +9,8 @@ pub fn xxh3_64_hex(input: Vec<u8>) -> (u64, String) {
 
 pub fn remove_wrapped_quotes(input: &str) -> &str {
     input
-        .trim_start_matches(|c| c == '"' || c == '\'')
-        .trim_end_matches(|c| c == '"' || c == '\'')
+        .trim_start_matches(['"', '\''])
+        .trim_end_matches(['"', '\''])
 }
 
 fn remove_wrapped_quotes_bytes(input: &[u8]) -> &[u8] {
@@ -63,6 +62,16 @@ pub fn get_payload_from_blob(blob: &[u8], input_mode: &str) -> Result<Vec<u8>, S
     }
 }
 
+// Returns the capture as UTF-8 when possible, otherwise encoded with `binary_encoding`
+// ("hex" or "base64"), along with the encoding name that was used.
+pub fn encode_capture_value(value: &[u8], binary_encoding: &str) -> (String, &'static str) {
+    match std::str::from_utf8(value) {
+        Ok(text) => (text.to_string(), "utf8"),
+        Err(_) if binary_encoding == "hex" => (hex::encode(value), "hex"),
+        Err(_) => (STANDARD.encode(value), "base64"),
+    }
+}
+
 pub fn format_size(size: i64) -> String {
     const KILOBYTE: i64 = 1024;
     const MEGABYTE: i64 = KILOBYTE * 1024;
@@ -82,17 +91,6 @@ pub fn format_size(size: i64) -> String {
     }
 }
 
-pub fn read_patterns(pattern_file: Option<&PathBuf>) -> Result<Vec<String>, std::io::Error> {
-    let mut patterns = Vec::new();
-    if let Some(path) = pattern_file {
-        let file_contents = std::fs::read_to_string(path)?;
-        for line in file_contents.lines() {
-            patterns.push(line.to_owned());
-        }
-    }
-    Ok(patterns)
-}
-
 pub fn build_regex(pattern: &str) -> Result<Regex, Box<dyn std::error::Error>> {
     let re = RegexBuilder::new()
         // NOTE: We should only enable JIT if we're going to compile all patterns into one large PCRE2 statement
@@ -106,33 +104,6 @@ pub fn build_regex(pattern: &str) -> Result<Regex, Box<dyn std::error::Error>> {
 #[cfg(test)]
 mod tests {
     use super::*;
-    use std::fs::File;
-    use std::io::Write;
-    use std::path::PathBuf;
-    use std::time::{SystemTime, UNIX_EPOCH};
-
-    struct TempFileGuard {
-        path: PathBuf,
-    }
-
-    impl Drop for TempFileGuard {
-        fn drop(&mut self) {
-            let _ = std::fs::remove_file(&self.path);
-        }
-    }
-
-    fn temp_file_path(stem: &str) -> (PathBuf, TempFileGuard) {
-        let nanos = SystemTime::now()
-            .duration_since(UNIX_EPOCH)
-            .expect("clock")
-            .as_nanos();
-        let path =
-            std::env::temp_dir().join(format!("{}-{}-{}.txt", stem, std::process::id(), nanos));
-        let guard = TempFileGuard {
-            path: path.to_path_buf(),
-        };
-        (path, guard)
-    }
 
     #[test]
     fn test_xxh3_64_hex() {
@@ -142,6 +113,22 @@ mod tests {
         assert_eq!(hex, format!("{:x}", hash));
     }
 
+    #[test]
+    fn test_encode_capture_value() {
+        assert_eq!(
+            encode_capture_value(b"ldap://x", "base64"),
+            ("ldap://x".to_string(), "utf8")
+        );
+        assert_eq!(
+            encode_capture_value(&[0xff, 0x00], "hex"),
+            ("ff00".to_string(), "hex")
+        );
+        assert_eq!(
+            encode_capture_value(&[0xff, 0x00], "base64"),
+            ("/wA=".to_string(), "base64")
+        );
+    }
+
     #[test]
     fn test_remove_wrapped_quotes() {
         assert_eq!(remove_wrapped_quotes("Hello"), "Hello");
@@ -210,16 +197,6 @@ mod tests {
         assert_eq!(format_size(1649267441664), "1.50TB");
     }
 
-    #[test]
-    fn test_read_patterns() {
-        let (path, _guard) = temp_file_path("precursor-patterns");
-        let mut file = File::create(&path).expect("create temp patterns file");
-        write!(file, "pattern1\npattern2\n").expect("write patterns");
-
-        let patterns = read_patterns(Some(&path)).expect("read patterns");
-        assert_eq!(patterns, vec!["pattern1", "pattern2"]);
-    }
-
     #[test]
     fn test_build_regex() {
         assert!(build_regex("\\d+").is_ok());
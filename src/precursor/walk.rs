@@ -0,0 +1,360 @@
+use std::collections::HashSet;
+use std::path::{Path, PathBuf};
+
+#[derive(Clone, Debug, Default)]
+pub struct WalkConfig {
+    pub recursive: bool,
+    // Root entries are depth 1; `None` means unlimited when recursive.
+    pub max_depth: Option<usize>,
+    pub include: Vec<String>,
+    pub exclude: Vec<String>,
+    pub follow_symlinks: bool,
+    pub max_file_size: Option<u64>,
+}
+
+#[derive(Clone, Debug, Eq, PartialEq)]
+pub struct WalkedFile {
+    pub path: PathBuf,
+    // Relative to the walk root, always `/`-separated.
+    pub relative_path: String,
+}
+
+#[derive(Debug, Default)]
+pub struct FolderWalk {
+    pub files: Vec<WalkedFile>,
+    pub skipped_oversize: u64,
+    pub skipped_symlinks: u64,
+    pub errors: Vec<String>,
+}
+
+// Walks `root` in sorted order so sequential ingest assigns stable input indexes.
+pub fn walk_folder(root: &Path, config: &WalkConfig) -> Result<FolderWalk, String> {
+    if !root.is_dir() {
+        return Err(format!("-f path must be a folder: {}", root.display()));
+    }
+    let mut walk = FolderWalk::default();
+    let mut visited = HashSet::new();
+    if let Ok(canonical) = root.canonicalize() {
+        visited.insert(canonical);
+    }
+    walk_dir(root, "", 1, config, &mut visited, &mut walk);
+    Ok(walk)
+}
+
+fn walk_dir(
+    dir: &Path,
+    relative_dir: &str,
+    depth: usize,
+    config: &WalkConfig,
+    visited: &mut HashSet<PathBuf>,
+    walk: &mut FolderWalk,
+) {
+    let entries = match std::fs::read_dir(dir) {
+        Ok(entries) => entries,
+        Err(err) => {
+            walk.errors.push(format!(
+                "Unable to read directory {}: {}",
+                dir.display(),
+                err
+            ));
+            return;
+        }
+    };
+    let mut entries: Vec<_> = entries
+        .filter_map(|entry| match entry {
+            Ok(entry) => Some(entry),
+            Err(err) => {
+                walk.errors
+                    .push(format!("Unable to read directory entry: {}", err));
+                None
+            }
+        })
+        .collect();
+    entries.sort_by_key(|entry| entry.file_name());
+
+    for entry in entries {
+        let path = entry.path();
+        let name = entry.file_name().to_string_lossy().into_owned();
+        let relative_path = if relative_dir.is_empty() {
+            name
+        } else {
+            format!("{}/{}", relative_dir, name)
+        };
+        if matches_any(&config.exclude, &relative_path) {
+            continue;
+        }
+
+        let mut file_type = match entry.file_type() {
+            Ok(file_type) => file_type,
+            Err(err) => {
+                walk.errors
+                    .push(format!("Unable to stat {}: {}", path.display(), err));
+                continue;
+            }
+        };
+        if file_type.is_symlink() {
+            if !config.follow_symlinks {
+                walk.skipped_symlinks += 1;
+                continue;
+            }
+            file_type = match std::fs::metadata(&path) {
+                Ok(metadata) => metadata.file_type(),
+                Err(err) => {
+                    walk.errors.push(format!(
+                        "Unable to follow symlink {}: {}",
+                        path.display(),
+                        err
+                    ));
+                    continue;
+                }
+            };
+        }
+
+        if file_type.is_dir() {
+            let within_depth = config.max_depth.is_none_or(|max_depth| depth < max_depth);
+            if !config.recursive || !within_depth {
+                continue;
+            }
+            // Followed symlinks can loop back to an ancestor.
+            if let Ok(canonical) = path.canonicalize() {
+                if !visited.insert(canonical) {
+                    continue;
+                }
+            }
+            walk_dir(&path, &relative_path, depth + 1, config, visited, walk);
+            continue;
+        }
+        if !file_type.is_file() {
+            continue;
+        }
+        if !config.include.is_empty() && !matches_any(&config.include, &relative_path) {
+            continue;
+        }
+        if let Some(max_file_size) = config.max_file_size {
+            match std::fs::metadata(&path) {
+                Ok(metadata) if metadata.len() > max_file_size => {
+                    walk.skipped_oversize += 1;
+                    continue;
+                }
+                Ok(_) => {}
+                Err(err) => {
+                    walk.errors
+                        .push(format!("Unable to stat {}: {}", path.display(), err));
+                    continue;
+                }
+            }
+        }
+        walk.files.push(WalkedFile {
+            path,
+            relative_path,
+        });
+    }
+}
+
+// Globs without a `/` match the entry name at any depth; globs with one match the
+// whole relative path.
+fn matches_any(globs: &[String], relative_path: &str) -> bool {
+    let name = relative_path.rsplit('/').next().unwrap_or(relative_path);
+    globs.iter().any(|glob| {
+        let target = if glob.contains('/') {
+            relative_path
+        } else {
+            name
+        };
+        glob_match(glob.as_bytes(), target.as_bytes())
+    })
+}
+
+// Supports `*` and `?` (not crossing `/`), `**` (any depth), and `[...]` classes.
+fn glob_match(pattern: &[u8], text: &[u8]) -> bool {
+    match pattern.first() {
+        None => text.is_empty(),
+        Some(b'*') if pattern.get(1) == Some(&b'*') => {
+            let rest = &pattern[2..];
+            if let Some(after_slash) = rest.strip_prefix(b"/") {
+                glob_match(after_slash, text)
+                    || text.iter().enumerate().any(|(index, byte)| {
+                        *byte == b'/' && glob_match(after_slash, &text[index + 1..])
+                    })
+            } else {
+                (0..=text.len()).any(|index| glob_match(rest, &text[index..]))
+            }
+        }
+        Some(b'*') => {
+            let rest = &pattern[1..];
+            for index in 0..=text.len() {
+                if glob_match(rest, &text[index..]) {
+                    return true;
+                }
+                if text.get(index) == Some(&b'/') {
+                    break;
+                }
+            }
+            false
+        }
+        Some(b'?') => match text.first() {
+            Some(byte) if *byte != b'/' => glob_match(&pattern[1..], &text[1..]),
+            _ => false,
+        },
+        Some(b'[') => match (parse_class(pattern), text.first()) {
+            (Some((matches, consumed)), Some(byte)) => {
+                *byte != b'/' && matches(*byte) && glob_match(&pattern[consumed..], &text[1..])
+            }
+            (Some(_), None) => false,
+            (None, _) => text.first() == Some(&b'[') && glob_match(&pattern[1..], &text[1..]),
+        },
+        Some(literal) => text.first() == Some(literal) && glob_match(&pattern[1..], &text[1..]),
+    }
+}
+
+// Returns a matcher for a `[...]` class and the number of pattern bytes it spans,
+// or `None` when the bracket is unterminated and should be treated literally.
+fn parse_class(pattern: &[u8]) -> Option<(impl Fn(u8) -> bool + '_, usize)> {
+    let mut index = 1;
+    let negated = matches!(pattern.get(index), Some(b'!') | Some(b'^'));
+    if negated {
+        index += 1;
+    }
+    let start = index;
+    // A `]` right after the opening bracket is a literal member.
+    if pattern.get(index) == Some(&b']') {
+        index += 1;
+    }
+    while pattern.get(index)? != &b']' {
+        index += 1;
+    }
+    let members = &pattern[start..index];
+    let matcher = move |byte: u8| {
+        let mut position = 0;
+        let mut found = false;
+        while position < members.len() {
+            if position + 2 < members.len() && members[position + 1] == b'-' {
+                found |= (members[position]..=members[position + 2]).contains(&byte);
+                position += 3;
+            } else {
+                found |= members[position] == byte;
+                position += 1;
+            }
+        }
+        found != negated
+    };
+    Some((matcher, index + 1))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::time::{SystemTime, UNIX_EPOCH};
+
+    struct TempDirGuard {
+        path: PathBuf,
+    }
+
+    impl Drop for TempDirGuard {
+        fn drop(&mut self) {
+            let _ = std::fs::remove_dir_all(&self.path);
+        }
+    }
+
+    fn temp_tree() -> TempDirGuard {
+        let nanos = SystemTime::now()
+            .duration_since(UNIX_EPOCH)
+            .expect("clock")
+            .as_nanos();
+        let path =
+            std::env::temp_dir().join(format!("precursor-walk-{}-{}", std::process::id(), nanos));
+        std::fs::create_dir_all(path.join("etc/init.d")).expect("create tree");
+        std::fs::create_dir_all(path.join("bin")).expect("create tree");
+        std::fs::write(path.join("root.bin"), b"root").expect("write file");
+        std::fs::write(path.join("etc/passwd"), b"root:x:0:0").expect("write file");
+        std::fs::write(path.join("etc/init.d/rcS"), b"#!/bin/sh").expect("write file");
+        std::fs::write(path.join("bin/busybox"), vec![0u8; 64]).expect("write file");
+        TempDirGuard { path }
+    }
+
+    fn relative_paths(walk: &FolderWalk) -> Vec<&str> {
+        walk.files
+            .iter()
+            .map(|file| file.relative_path.as_str())
+            .collect()
+    }
+
+    #[test]
+    fn test_glob_match() {
+        assert!(glob_match(b"*.bin", b"root.bin"));
+        assert!(!glob_match(b"*.bin", b"dir/root.bin"));
+        assert!(glob_match(b"**/*.bin", b"root.bin"));
+        assert!(glob_match(b"**/*.bin", b"a/b/root.bin"));
+        assert!(glob_match(b"etc/**", b"etc/init.d/rcS"));
+        assert!(glob_match(b"rc[A-Z]", b"rcS"));
+        assert!(!glob_match(b"rc[!A-Z]", b"rcS"));
+        assert!(glob_match(b"pass?d", b"passwd"));
+        assert!(glob_match(b"[abc", b"[abc"));
+    }
+
+    #[test]
+    fn test_flat_walk_skips_subdirectories() {
+        let tree = temp_tree();
+        let walk = walk_folder(&tree.path, &WalkConfig::default()).expect("walk");
+        assert_eq!(relative_paths(&walk), vec!["root.bin"]);
+    }
+
+    #[test]
+    fn test_recursive_walk_honours_depth_globs_and_size() {
+        let tree = temp_tree();
+        let config = WalkConfig {
+            recursive: true,
+            ..WalkConfig::default()
+        };
+        let walk = walk_folder(&tree.path, &config).expect("walk");
+        assert_eq!(
+            relative_paths(&walk),
+            vec!["bin/busybox", "etc/init.d/rcS", "etc/passwd", "root.bin"]
+        );
+
+        let config = WalkConfig {
+            recursive: true,
+            max_depth: Some(2),
+            exclude: vec!["bin".to_string()],
+            max_file_size: Some(8),
+            ..WalkConfig::default()
+        };
+        let walk = walk_folder(&tree.path, &config).expect("walk");
+        assert_eq!(relative_paths(&walk), vec!["root.bin"]);
+        assert_eq!(walk.skipped_oversize, 1);
+
+        let config = WalkConfig {
+            recursive: true,
+            include: vec!["etc/**".to_string()],
+            ..WalkConfig::default()
+        };
+        let walk = walk_folder(&tree.path, &config).expect("walk");
+        assert_eq!(relative_paths(&walk), vec!["etc/init.d/rcS", "etc/passwd"]);
+    }
+
+    #[cfg(unix)]
+    #[test]
+    fn test_symlinks_are_skipped_unless_followed() {
+        let tree = temp_tree();
+        std::os::unix::fs::symlink(&tree.path, tree.path.join("etc/loop")).expect("symlink");
+        std::os::unix::fs::symlink(tree.path.join("etc/passwd"), tree.path.join("passwd.lnk"))
+            .expect("symlink");
+        let config = WalkConfig {
+            recursive: true,
+            ..WalkConfig::default()
+        };
+        let walk = walk_folder(&tree.path, &config).expect("walk");
+        assert_eq!(walk.files.len(), 4);
+        assert_eq!(walk.skipped_symlinks, 2);
+
+        let config = WalkConfig {
+            recursive: true,
+            follow_symlinks: true,
+            ..WalkConfig::default()
+        };
+        let walk = walk_folder(&tree.path, &config).expect("walk");
+        // The directory loop is visited once; the file symlink is ingested.
+        assert_eq!(walk.files.len(), 5);
+        assert!(relative_paths(&walk).contains(&"passwd.lnk"));
+    }
+}
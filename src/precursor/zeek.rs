@@ -0,0 +1,292 @@
+use serde_json::{Map, Number, Value};
+
+struct TsvHeader {
+    separator: String,
+    set_separator: String,
+    empty_field: String,
+    unset_field: String,
+    path: Option<String>,
+    fields: Vec<String>,
+    types: Vec<String>,
+}
+
+impl Default for TsvHeader {
+    fn default() -> Self {
+        Self {
+            separator: "\t".to_string(),
+            set_separator: ",".to_string(),
+            empty_field: "(empty)".to_string(),
+            unset_field: "-".to_string(),
+            path: None,
+            fields: Vec::new(),
+            types: Vec::new(),
+        }
+    }
+}
+
+// Tracks the `#`-directive header of Zeek TSV logs so data lines can be turned into
+// the same JSON records Zeek writes with `LogAscii::use_json`. JSON log lines pass
+// through unchanged, and a new header (e.g. concatenated or rotated logs) replaces the
+// previous one.
+#[derive(Default)]
+pub struct ZeekLogReader {
+    header: TsvHeader,
+}
+
+impl ZeekLogReader {
+    // Returns the JSON record text for a data line, or `None` for header directives
+    // and blank lines.
+    pub fn normalize_line(&mut self, line: &str) -> Result<Option<String>, String> {
+        if line.trim().is_empty() {
+            return Ok(None);
+        }
+        if line.trim_start().starts_with('{') {
+            return Ok(Some(line.to_string()));
+        }
+        if let Some(directive) = line.strip_prefix('#') {
+            self.apply_directive(directive);
+            return Ok(None);
+        }
+        self.parse_tsv_record(line)
+            .map(|record| Some(Value::Object(record).to_string()))
+    }
+
+    fn apply_directive(&mut self, directive: &str) {
+        if let Some(separator) = directive.strip_prefix("separator ") {
+            // Starts a new header; only the separator itself is written space-delimited.
+            self.header = TsvHeader {
+                separator: unescape(separator.trim()),
+                ..TsvHeader::default()
+            };
+            return;
+        }
+        let mut parts = directive.split(self.header.separator.as_str());
+        let name = parts.next().unwrap_or_default();
+        let values: Vec<String> = parts.map(str::to_string).collect();
+        match name {
+            "set_separator" => self.header.set_separator = unescape(&values.join("")),
+            "empty_field" => self.header.empty_field = values.join(""),
+            "unset_field" => self.header.unset_field = values.join(""),
+            "path" => self.header.path = values.first().cloned(),
+            "fields" => self.header.fields = values,
+            "types" => self.header.types = values,
+            _ => {}
+        }
+    }
+
+    fn parse_tsv_record(&self, line: &str) -> Result<Map<String, Value>, String> {
+        let header = &self.header;
+        if header.fields.is_empty() {
+            return Err("Zeek TSV data line found before a #fields header".to_string());
+        }
+        let columns: Vec<&str> = line.split(header.separator.as_str()).collect();
+        if columns.len() != header.fields.len() {
+            return Err(format!(
+                "Zeek TSV line has {} columns but #fields declares {}",
+                columns.len(),
+                header.fields.len()
+            ));
+        }
+        let mut record = Map::new();
+        if let Some(path) = &header.path {
+            record.insert("_path".to_string(), Value::String(path.clone()));
+        }
+        for (index, (field, column)) in header.fields.iter().zip(columns).enumerate() {
+            // Zeek's JSON writer omits unset fields rather than writing null.
+            if column == header.unset_field {
+                continue;
+            }
+            let zeek_type = header.types.get(index).map_or("string", String::as_str);
+            record.insert(field.clone(), convert_column(column, zeek_type, header));
+        }
+        Ok(record)
+    }
+}
+
+fn convert_column(column: &str, zeek_type: &str, header: &TsvHeader) -> Value {
+    if let Some(element_type) = zeek_type
+        .strip_prefix("set[")
+        .or_else(|| zeek_type.strip_prefix("vector["))
+        .and_then(|rest| rest.strip_suffix(']'))
+    {
+        if column == header.empty_field {
+            return Value::Array(Vec::new());
+        }
+        return Value::Array(
+            column
+                .split(header.set_separator.as_str())
+                .map(|element| convert_scalar(element, element_type))
+                .collect(),
+        );
+    }
+    if column == header.empty_field {
+        return Value::String(String::new());
+    }
+    convert_scalar(column, zeek_type)
+}
+
+fn convert_scalar(value: &str, zeek_type: &str) -> Value {
+    let number = match zeek_type {
+        "count" | "port" => value.parse::<u64>().ok().map(Number::from),
+        "int" => value.parse::<i64>().ok().map(Number::from),
+        "double" | "time" | "interval" => value.parse::<f64>().ok().and_then(Number::from_f64),
+        "bool" => return Value::Bool(value == "T"),
+        _ => None,
+    };
+    number.map_or_else(|| Value::String(unescape(value)), Value::Number)
+}
+
+// Zeek writes separators and non-printable bytes in TSV values as `\xNN`.
+fn unescape(value: &str) -> String {
+    if !value.contains("\\x") {
+        return value.to_string();
+    }
+    let bytes = value.as_bytes();
+    let mut decoded = Vec::with_capacity(bytes.len());
+    let mut index = 0;
+    while index < bytes.len() {
+        if bytes[index] == b'\\' && bytes.get(index + 1) == Some(&b'x') {
+            if let Some(byte) = bytes
+                .get(index + 2..index + 4)
+                .and_then(|digits| std::str::from_utf8(digits).ok())
+                .and_then(|digits| u8::from_str_radix(digits, 16).ok())
+            {
+                decoded.push(byte);
+                index += 4;
+                continue;
+            }
+        }
+        decoded.push(bytes[index]);
+        index += 1;
+    }
+    String::from_utf8_lossy(&decoded).into_owned()
+}
+
+#[derive(Clone, Debug, Eq, PartialEq)]
+pub struct ZeekPayload {
+    // Selected field name, with an `[index]` suffix for set/vector elements.
+    pub field: String,
+    pub payload: Vec<u8>,
+}
+
+// Field names are matched literally, so dotted Zeek names like `id.orig_h` work as-is.
+pub fn zeek_payloads(record: &Map<String, Value>, fields: &[String]) -> Vec<ZeekPayload> {
+    let mut payloads = Vec::new();
+    for field in fields {
+        match record.get(field) {
+            Some(Value::Array(items)) => {
+                for (index, item) in items.iter().enumerate() {
+                    if let Some(payload) = scalar_bytes(item) {
+                        payloads.push(ZeekPayload {
+                            field: format!("{}[{}]", field, index),
+                            payload,
+                        });
+                    }
+                }
+            }
+            Some(value) => {
+                if let Some(payload) = scalar_bytes(value) {
+                    payloads.push(ZeekPayload {
+                        field: field.clone(),
+                        payload,
+                    });
+                }
+            }
+            None => {}
+        }
+    }
+    payloads
+}
+
+// The record's remaining columns, kept as context on every report derived from it.
+pub fn zeek_context(record: &Map<String, Value>, fields: &[String]) -> Map<String, Value> {
+    record
+        .iter()
+        .filter(|(name, _)| !fields.contains(name))
+        .map(|(name, value)| (name.clone(), value.clone()))
+        .collect()
+}
+
+fn scalar_bytes(value: &Value) -> Option<Vec<u8>> {
+    match value {
+        Value::String(text) if !text.is_empty() => Some(text.as_bytes().to_vec()),
+        Value::Number(number) => Some(number.to_string().into_bytes()),
+        Value::Bool(flag) => Some(flag.to_string().into_bytes()),
+        _ => None,
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use serde_json::json;
+
+    const DNS_LOG: &str = "#separator \\x09
+#set_separator\t,
+#empty_field\t(empty)
+#unset_field\t-
+#path\tdns
+#open\t2025-04-29-08-57-58
+#fields\tts\tuid\tid.orig_h\tid.resp_p\tquery\tanswers\tAA\trtt
+#types\ttime\tstring\taddr\tport\tstring\tvector[string]\tbool\tinterval
+1745917078.683723\tCp5N8f2I6Bms4Kx111\t192.168.1.111\t53\tthis.yourtrap.com\t10.11.12.13,10.0.0.1\tF\t-
+1745917081.101010\tC2zeekDns\t192.168.1.112\t53\tpaste\\x09bin.top\t(empty)\tT\t0.25
+#close\t2025-04-29-09-00-00";
+
+    fn records(log: &str) -> Vec<Map<String, Value>> {
+        let mut reader = ZeekLogReader::default();
+        log.lines()
+            .filter_map(|line| reader.normalize_line(line).expect("normalize"))
+            .map(|text| serde_json::from_str(&text).expect("json"))
+            .collect()
+    }
+
+    #[test]
+    fn test_tsv_records_are_typed_like_json_logs() {
+        let records = records(DNS_LOG);
+        assert_eq!(records.len(), 2);
+        assert_eq!(records[0]["_path"], json!("dns"));
+        assert_eq!(records[0]["ts"], json!(1745917078.683723));
+        assert_eq!(records[0]["id.resp_p"], json!(53));
+        assert_eq!(records[0]["answers"], json!(["10.11.12.13", "10.0.0.1"]));
+        assert_eq!(records[0]["AA"], json!(false));
+        assert!(!records[0].contains_key("rtt"));
+        assert_eq!(records[1]["query"], json!("paste\tbin.top"));
+        assert_eq!(records[1]["answers"], json!([]));
+        assert_eq!(records[1]["rtt"], json!(0.25));
+    }
+
+    #[test]
+    fn test_json_lines_pass_through_and_bad_tsv_is_rejected() {
+        let mut reader = ZeekLogReader::default();
+        let line = r#"{"uid":"C1","query":"example.org"}"#;
+        assert_eq!(
+            reader.normalize_line(line).expect("json"),
+            Some(line.to_string())
+        );
+        assert!(reader.normalize_line("a\tb").is_err());
+        reader
+            .normalize_line("#fields\tuid\tquery")
+            .expect("directive");
+        assert!(reader.normalize_line("only-one-column").is_err());
+    }
+
+    #[test]
+    fn test_selected_fields_become_payloads_and_rest_is_context() {
+        let record = records(DNS_LOG).remove(0);
+        let fields = vec!["query".to_string(), "answers".to_string()];
+        let payloads = zeek_payloads(&record, &fields);
+        let labels: Vec<&str> = payloads
+            .iter()
+            .map(|payload| payload.field.as_str())
+            .collect();
+        assert_eq!(labels, vec!["query", "answers[0]", "answers[1]"]);
+        assert_eq!(payloads[0].payload, b"this.yourtrap.com");
+
+        let context = zeek_context(&record, &fields);
+        assert_eq!(context["uid"], json!("Cp5N8f2I6Bms4Kx111"));
+        assert_eq!(context["id.orig_h"], json!("192.168.1.111"));
+        assert!(!context.contains_key("query"));
+        assert!(!context.contains_key("answers"));
+    }
+}
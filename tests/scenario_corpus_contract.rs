@@ -4,6 +4,10 @@ use std::path::PathBuf;
 use std::process::{Command, Output, Stdio};
 
 fn run_precursor(args: &[&str], stdin_payload: &str) -> Output {
+    run_precursor_bytes(args, stdin_payload.as_bytes())
+}
+
+fn run_precursor_bytes(args: &[&str], stdin_payload: &[u8]) -> Output {
     let mut cmd = Command::new(env!("CARGO_BIN_EXE_precursor"));
     cmd.args(args)
         .stdin(Stdio::piped())
@@ -12,7 +16,7 @@ fn run_precursor(args: &[&str], stdin_payload: &str) -> Output {
     let mut child = cmd.spawn().expect("failed to spawn precursor");
     if let Some(stdin) = child.stdin.as_mut() {
         stdin
-            .write_all(stdin_payload.as_bytes())
+            .write_all(stdin_payload)
             .expect("failed to write stdin");
     }
     let output = child.wait_with_output().expect("failed to wait on process");
@@ -282,6 +286,48 @@ fn zeek_dns_log_scenario_extracts_query_field() {
     }));
 }
 
+#[test]
+fn zeek_dns_tsv_log_scenario_matches_json_log_tags() {
+    let scenario = scenario_root().join("public-zeek-dns-log-triage");
+    let pattern_file = scenario.join("patterns.pcre");
+    let pattern_path = pattern_file.to_str().expect("pattern path utf8");
+    let tsv = std::fs::read_to_string(scenario.join("dns.log")).expect("read dns.log");
+    let json = std::fs::read_to_string(scenario.join("payloads.jsonl")).expect("read payloads");
+
+    let tags_by_uid = |stdout: &[u8]| {
+        let mut tags: Vec<(String, Value)> = parse_ndjson(stdout)
+            .iter()
+            .map(|report| {
+                assert_eq!(report["zeek"]["payload_field"].as_str(), Some("query"));
+                assert!(report["zeek"].get("query").is_none());
+                (
+                    report["zeek"]["uid"].as_str().expect("uid").to_string(),
+                    report["tags"].clone(),
+                )
+            })
+            .collect();
+        tags.sort_by(|left, right| left.0.cmp(&right.0));
+        tags
+    };
+
+    let args = ["-p", pattern_path, "-m", "zeek", "--zeek-field", "query"];
+    let tsv_output = run_precursor(&args, tsv.as_str());
+    let json_output = run_precursor(&args, json.as_str());
+    let tsv_tags = tags_by_uid(&tsv_output.stdout);
+    assert_eq!(tsv_tags.len(), 4);
+    assert_eq!(tsv_tags, tags_by_uid(&json_output.stdout));
+
+    let reports = parse_ndjson(&tsv_output.stdout);
+    let first = reports
+        .iter()
+        .find(|report| report["zeek"]["uid"] == "Cp5N8f2I6Bms4Kx111")
+        .expect("first dns record");
+    assert_eq!(first["zeek"]["_path"].as_str(), Some("dns"));
+    assert_eq!(first["zeek"]["id.orig_h"].as_str(), Some("192.168.1.111"));
+    assert_eq!(first["zeek"]["id.resp_p"].as_u64(), Some(53));
+    assert_eq!(first["zeek"]["answers"][0].as_str(), Some("10.11.12.13"));
+}
+
 #[test]
 fn foxit_log4shell_pcap_scenario_emits_fbhash_and_jndi_tags() {
     let scenario = scenario_root().join("public-log4shell-foxit-pcap");
@@ -329,6 +375,49 @@ fn foxit_log4shell_pcap_scenario_emits_fbhash_and_jndi_tags() {
     }));
 }
 
+#[test]
+fn foxit_log4shell_pcap_scenario_ingests_capture_natively() {
+    let scenario = scenario_root().join("public-log4shell-foxit-pcap");
+    let pattern_file = scenario.join("patterns.pcre");
+    let capture = std::fs::read(scenario.join("ldap-uri-params-ev0.pcap")).expect("read pcap");
+
+    let output = run_precursor_bytes(
+        &[
+            "-p",
+            pattern_file.to_str().expect("pattern path utf8"),
+            "--input-mode",
+            "pcap",
+            "-P",
+        ],
+        capture.as_slice(),
+    );
+
+    let reports = parse_ndjson(&output.stdout);
+    assert!(
+        reports.len() >= 10,
+        "expected at least 10 reports, got {}",
+        reports.len()
+    );
+    assert!(reports.iter().all(|report| {
+        let packet = &report["packet"];
+        packet["transport"].as_str() == Some("tcp")
+            && packet["src_ip"].as_str().is_some()
+            && packet["dst_port"].as_u64().is_some()
+            && packet["frame"].as_u64().is_some()
+            && packet["timestamp"].as_f64().is_some()
+    }));
+    assert!(reports.iter().any(|report| {
+        report
+            .get("tags")
+            .and_then(Value::as_array)
+            .map(|tags| {
+                tags.iter()
+                    .any(|tag| tag.as_str() == Some("urlencoded_jndi"))
+            })
+            .unwrap_or(false)
+    }));
+}
+
 #[test]
 fn public_firmware_binwalk_scenario_tags_real_magic_headers() {
     let scenario = scenario_root().join("public-firmware-binwalk-magic");
@@ -418,3 +507,40 @@ fn foxit_pcap_extraction_script_matches_committed_payloads() {
         "regenerated payloads do not match committed payloads.string"
     );
 }
+
+#[test]
+fn foxit_log4shell_pcap_scenario_reassembles_tcp_messages() {
+    let scenario = scenario_root().join("public-log4shell-foxit-pcap");
+    let pattern_file = scenario.join("patterns.pcre");
+    let capture = std::fs::read(scenario.join("ldap-uri-params-ev0.pcap")).expect("read pcap");
+
+    let output = run_precursor_bytes(
+        &[
+            "-p",
+            pattern_file.to_str().expect("pattern path utf8"),
+            "--input-mode",
+            "pcap",
+            "--tcp-reassembly",
+            "message",
+        ],
+        capture.as_slice(),
+    );
+
+    let reports = parse_ndjson(&output.stdout);
+    assert!(
+        reports.len() >= 5,
+        "expected at least 5 reports, got {}",
+        reports.len()
+    );
+    assert!(reports.iter().all(|report| {
+        let stream = &report["stream"];
+        report.get("packet").is_none()
+            && stream["flow_id"].as_str().map(str::len) == Some(16)
+            && stream["direction"].as_str().is_some()
+            && stream["start_offset"].as_u64() < stream["end_offset"].as_u64()
+    }));
+    assert!(reports.iter().any(|report| {
+        report["stream"]["start_offset"].as_u64() > Some(0)
+            && report["stream"]["direction"].as_str() == Some("to_server")
+    }));
+}